@@ -2,6 +2,8 @@ import { Connection, LAMPORTS_PER_SOL } from '@solana/web3.js';
 import * as path from 'path';
 
 export const PDA_SEED = 'game_seed'; // Must match with the seed used in contract to derive PDA
+export const PLAYER_SEED = 'player'; // Followed by the holder pubkey, the player account is derived from both
+export const GAME_INFO_SEED = 'game_info'; // The program account, one per deployed program
 export const TOKEN_DECIMALS = (String(LAMPORTS_PER_SOL).match(/0/g) || []).length;
 export const RPC_URL = process.env.NODE_ENV === 'devnet' ? 'https://api.devnet.solana.com' : 'http://127.0.0.1:8899';
 export const connection = new Connection(RPC_URL, 'confirmed');
@@ -1,10 +1,10 @@
-import { Keypair } from '@solana/web3.js';
+import { Keypair, PublicKey } from '@solana/web3.js';
 import PromptSync from 'prompt-sync';
 import { TOKEN_DECIMALS } from './config';
 import { Player } from './model/player';
 import { AdminModule } from './module/admin';
 import { PlayerModule } from './module/player';
-import { createGameAccount, createNewToken, createTokenAccount, getDeployedProgramKeypairOrThrow, getPayerKeypair, initializeGame, mintToken } from './utils';
+import { createNewToken, createTokenAccount, getDeployedProgramKeypairOrThrow, getPayerKeypair, initializeGame, mintToken } from './utils';
 const prompt = PromptSync();
 
 let feePayerKeypair = getPayerKeypair();
@@ -12,7 +12,7 @@ let ownerKeypair = feePayerKeypair;
 let authorityKeypair = feePayerKeypair;
 let mintAccount: Keypair;
 let gameTokenAccount: Keypair;
-let gameAccount: Keypair;
+let gameAccount: PublicKey;
 const players: Player[] = [];
 const EXIT = 0;
 
@@ -49,8 +49,7 @@ async function initialize() {
 	mintAccount = await createNewToken(TOKEN_DECIMALS, authorityKeypair, feePayerKeypair);
 	gameTokenAccount = await createTokenAccount(mintAccount, ownerKeypair, feePayerKeypair);
 	await mintToken(1000 * Math.pow(10, TOKEN_DECIMALS), mintAccount, gameTokenAccount, authorityKeypair, feePayerKeypair);
-	gameAccount = await createGameAccount(feePayerKeypair);
-	await initializeGame(gameTokenAccount.publicKey, ownerKeypair, gameAccount.publicKey, feePayerKeypair);
+	gameAccount = await initializeGame(gameTokenAccount.publicKey, mintAccount.publicKey, ownerKeypair, feePayerKeypair);
 
 	const payoutModule = new AdminModule(feePayerKeypair, ownerKeypair, authorityKeypair, mintAccount, gameTokenAccount, gameAccount, players);
 	const playerModule = new PlayerModule(feePayerKeypair, ownerKeypair, authorityKeypair, mintAccount, gameTokenAccount, gameAccount, players);
@@ -1,10 +1,11 @@
-import { Keypair } from '@solana/web3.js';
+import { Keypair, PublicKey } from '@solana/web3.js';
 
 export class Player {
 	keypair: Keypair;
-	account: Keypair;
+	// PDA of the holder, created by the contract on register
+	account: PublicKey;
 	tokenAccount: Keypair;
-	constructor(keypair: Keypair, account: Keypair, tokenAccount: Keypair) {
+	constructor(keypair: Keypair, account: PublicKey, tokenAccount: Keypair) {
 		this.keypair = keypair;
 		this.account = account;
 		this.tokenAccount = tokenAccount;
@@ -1,9 +1,7 @@
-import { Keypair } from '@solana/web3.js';
+import { Keypair, PublicKey } from '@solana/web3.js';
 import PromptSync from 'prompt-sync';
 import { connection, TOKEN_DECIMALS } from '../config';
 import { Player } from '../model/player';
-import { SchemaBuilder } from '../schema/builder';
-import { fromSchemaDataToPlayerState, PlayerStateSchema } from '../schema/states';
 import { addReward, mintToken } from '../utils';
 import { BaseModule } from './base';
 
@@ -48,13 +46,13 @@ async function checkSufficientAmountToPayout(reward: number, gameTokenAccount: K
 	}
 }
 
-async function addRewardToPlayer(players: Player[], ownerKeypair: Keypair, gameAccount: Keypair, gameTokenAccount: Keypair) {
+async function addRewardToPlayer(players: Player[], ownerKeypair: Keypair, gameAccount: PublicKey, gameTokenAccount: Keypair) {
 	if (players.length > 0) {
 		try {
 			console.log('');
 			console.log('Players');
 			for (let i = 0; i < players.length; i++) {
-				console.log(`${i + 1}. ${players[i].account.publicKey.toBase58()}`);
+				console.log(`${i + 1}. ${players[i].account.toBase58()}`);
 			}
 			console.log('0 - To cancel');
 			const choice = Number(prompt('Select player to add reward: '));
@@ -63,14 +61,9 @@ async function addRewardToPlayer(players: Player[], ownerKeypair: Keypair, gameA
 				const amount = Number.parseInt(prompt('Enter reward amount: '));
 				if (amount > 0) {
 					await checkSufficientAmountToPayout(amount, gameTokenAccount);
-					const playerAccountInfo = await connection.getAccountInfo(player.account.publicKey);
+					const playerAccountInfo = await connection.getAccountInfo(player.account);
 					if (playerAccountInfo) {
-						const playerState = fromSchemaDataToPlayerState(SchemaBuilder.deserialize(PlayerStateSchema, playerAccountInfo.data));
-						if (playerState.has_upline && playerState.upline) {
-							await addReward(amount, ownerKeypair, ownerKeypair, gameAccount.publicKey, player.account.publicKey, playerState.upline);
-						} else {
-							await addReward(amount, ownerKeypair, ownerKeypair, gameAccount.publicKey, player.account.publicKey);
-						}
+						await addReward(amount, ownerKeypair, ownerKeypair, gameAccount, player.account);
 						console.log('Reward added');
 					} else {
 						console.log('Player not found');
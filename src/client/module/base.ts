@@ -1,4 +1,4 @@
-import { Keypair } from '@solana/web3.js';
+import { Keypair, PublicKey } from '@solana/web3.js';
 import { Player } from '../model/player';
 
 export class BaseModule {
@@ -7,7 +7,7 @@ export class BaseModule {
 	authorityKeypair: Keypair;
 	mintAccount: Keypair;
 	gameTokenAccount: Keypair;
-	gameAccount: Keypair;
+	gameAccount: PublicKey;
 	players: Player[];
 
 	constructor(
@@ -16,7 +16,7 @@ export class BaseModule {
 		authorityKeypair: Keypair,
 		mintAccount: Keypair,
 		gameTokenAccount: Keypair,
-		gameAccount: Keypair,
+		gameAccount: PublicKey,
 		players: Player[],
 	) {
 		this.feePayerKeypair = feePayerKeypair;
@@ -5,7 +5,7 @@ import { connection } from '../config';
 import { Player } from '../model/player';
 import { SchemaBuilder } from '../schema/builder';
 import { fromSchemaDataToPlayerState, PlayerStateSchema } from '../schema/states';
-import { claimReward, createPlayerAccount, createPlayerKeypair, createTokenAccount, registerPlayer } from '../utils';
+import { claimReward, createPlayerKeypair, createTokenAccount, registerPlayer } from '../utils';
 import { BaseModule } from './base';
 
 const prompt = PromptSync();
@@ -38,7 +38,7 @@ export class PlayerModule extends BaseModule {
 					}
 					break;
 				case 3:
-					await claim(this.players, this.gameAccount.publicKey, this.gameTokenAccount.publicKey);
+					await claim(this.players, this.gameAccount, this.gameTokenAccount.publicKey);
 					break;
 				case 0:
 					break;
@@ -55,7 +55,7 @@ async function showPlayerDetailsMenu(players: Player[]) {
 		console.log('');
 		console.log('Players');
 		for (let i = 0; i < players.length; i++) {
-			console.log(`${i + 1}. ${players[i].account.publicKey.toBase58()}`);
+			console.log(`${i + 1}. ${players[i].account.toBase58()}`);
 		}
 		console.log('0. Back');
 		choice = Number(prompt('Enter number to view player details: '));
@@ -71,7 +71,7 @@ async function printPlayerDetails(player: Player) {
 	console.log('');
 	const walletAccountInfo = await connection.getAccountInfo(player.keypair.publicKey);
 	const tokenAccountInfo = await connection.getTokenAccountBalance(player.tokenAccount.publicKey);
-	const playerAccountInfo = await connection.getAccountInfo(player.account.publicKey);
+	const playerAccountInfo = await connection.getAccountInfo(player.account);
 	if (walletAccountInfo) {
 		console.log(player.keypair.publicKey.toBase58(), `${walletAccountInfo.lamports / LAMPORTS_PER_SOL} SOL`);
 	}
@@ -81,7 +81,7 @@ async function printPlayerDetails(player: Player) {
 	if (playerAccountInfo) {
 		const playerState = fromSchemaDataToPlayerState(SchemaBuilder.deserialize(PlayerStateSchema, playerAccountInfo.data));
 		console.log('Player info');
-		console.log('Program account - ' + player.account.publicKey.toBase58());
+		console.log('Program account - ' + player.account.toBase58());
 		console.log('Has upline      - ' + playerState.has_upline);
 		if (playerState.upline) {
 			console.log('Upline          - ' + playerState.upline.toBase58());
@@ -97,7 +97,7 @@ async function selectUplineFromPlayerList(players: Player[]): Promise<PublicKey
 		console.log('');
 		console.log('Players');
 		for (let i = 0; i < players.length; i++) {
-			console.log(`${i + 1}. ${players[i].account.publicKey.toBase58()}`);
+			console.log(`${i + 1}. ${players[i].account.toBase58()}`);
 		}
 		console.log('0 - No upline');
 		const input: string = prompt('Select upline or manual enter account address: ');
@@ -106,20 +106,15 @@ async function selectUplineFromPlayerList(players: Player[]): Promise<PublicKey
 			return new PublicKey(input);
 		}
 		let choice = Number(input);
-		return choice ? players[choice - 1].account.publicKey : undefined;
+		return choice ? players[choice - 1].account : undefined;
 	}
 }
 
-async function registerNewPlayer(gameAccount: Keypair, mintAccount: Keypair, players: Player[]) {
+async function registerNewPlayer(gameAccount: PublicKey, mintAccount: Keypair, players: Player[]) {
 	try {
 		const playerKeypair = await createPlayerKeypair();
-		const playerAccount = await createPlayerAccount(playerKeypair);
 		const uplinePubkey = await selectUplineFromPlayerList(players);
-		if (uplinePubkey) {
-			await registerPlayer(playerKeypair, playerAccount.publicKey, gameAccount.publicKey, playerKeypair, uplinePubkey);
-		} else {
-			await registerPlayer(playerKeypair, playerAccount.publicKey, gameAccount.publicKey, playerKeypair);
-		}
+		const playerAccount = await registerPlayer(playerKeypair, gameAccount, playerKeypair, uplinePubkey);
 		const playerTokenAccount = await createTokenAccount(mintAccount, playerKeypair, playerKeypair);
 		console.log('Player created');
 		return new Player(playerKeypair, playerAccount, playerTokenAccount);
@@ -133,19 +128,19 @@ async function claim(players: Player[], gameAccountPubkey: PublicKey, gameTokenA
 		console.log('');
 		console.log('Players');
 		for (let i = 0; i < players.length; i++) {
-			console.log(`${i + 1}. ${players[i].account.publicKey.toBase58()}`);
+			console.log(`${i + 1}. ${players[i].account.toBase58()}`);
 		}
 		console.log('0 - Back');
 		const choice = Number(prompt('Select the player to claim reward: '));
 		if (choice >= 1 && choice <= players.length) {
 			const player = players[choice - 1];
-			const playerAccountInfo = await connection.getAccountInfo(player.account.publicKey);
+			const playerAccountInfo = await connection.getAccountInfo(player.account);
 			if (playerAccountInfo) {
 				const playerState = fromSchemaDataToPlayerState(SchemaBuilder.deserialize(PlayerStateSchema, playerAccountInfo.data));
 				if (playerState.reward_to_claim.eq(new BN(0))) {
 					console.log('No claimable reward');
 				} else {
-					await claimReward(player.keypair, gameAccountPubkey, player.account.publicKey, gameTokenAccountPubkey, player.tokenAccount.publicKey, player.keypair);
+					await claimReward(player.keypair, gameAccountPubkey, gameTokenAccountPubkey, player.tokenAccount.publicKey, player.keypair);
 					console.log(`Claimed ${playerState.reward_to_claim.div(new BN(LAMPORTS_PER_SOL)).toString()} SPL token to ${player.tokenAccount.publicKey.toBase58()}`);
 				}
 			}
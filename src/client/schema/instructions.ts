@@ -6,7 +6,11 @@ export const GameInitIxSchema = new Map([
 		SchemaData,
 		{
 			kind: 'struct',
-			fields: [['tag', 'u8']],
+			fields: [
+				['magic', 'u8'],
+				['tag', 'u8'],
+				['referral_bonus', 'u64'],
+			],
 		},
 	],
 ]);
@@ -17,6 +21,7 @@ export const AddRewardIxScheme = new Map([
 		{
 			kind: 'struct',
 			fields: [
+				['magic', 'u8'],
 				['tag', 'u8'],
 				['reward_amount', 'u64'],
 			],
@@ -24,10 +29,22 @@ export const AddRewardIxScheme = new Map([
 	],
 ]);
 
-export const ClaimRewardIxSchema = GameInitIxSchema;
-export const PlayerRegisterIxSchema = GameInitIxSchema;
+export const ClaimRewardIxSchema = new Map([
+	[
+		SchemaData,
+		{
+			kind: 'struct',
+			fields: [
+				['magic', 'u8'],
+				['tag', 'u8'],
+			],
+		},
+	],
+]);
+export const PlayerRegisterIxSchema = ClaimRewardIxSchema;
 
 interface BaseIx {
+	magic: number;
 	tag: Tag;
 }
 
@@ -35,6 +52,9 @@ export interface IAddRewardIx extends BaseIx {
 	reward_amount: number;
 }
 
-export interface IGameInitIx extends BaseIx {}
+// The rest of the game settings are left out, the contract use their default value
+export interface IGameInitIx extends BaseIx {
+	referral_bonus: number;
+}
 export interface IClaimRewardIx extends BaseIx {}
 export interface IPlayerRegisterIx extends BaseIx {}
@@ -2,6 +2,8 @@ import { PublicKey } from '@solana/web3.js';
 import BN from 'bn.js';
 import { SchemaData } from './builder';
 
+// The whole account must be described, borsh reject the bytes left after the last field
+// The unix timestamps are i64 in the contract, read as u64 since they are never negative
 export const PlayerStateSchema = new Map([
 	[
 		SchemaData,
@@ -14,6 +16,18 @@ export const PlayerStateSchema = new Map([
 				['program_account', ['u8', 32]],
 				['has_upline', ['u8', 4]],
 				['upline', ['u8', 32]],
+				['downline_count', 'u32'],
+				['registered_at', 'u64'],
+				['last_claim_at', 'u64'],
+				['total_earned', 'u64'],
+				['referral_code', ['u8', 8]],
+				['depth', 'u8'],
+				['last_credit_at', 'u64'],
+				['frozen', 'u8'],
+				['tier', 'u8'],
+				['vest_amount', 'u64'],
+				['vest_start', 'u64'],
+				['vest_duration', 'u64'],
 			],
 		},
 	],
@@ -26,6 +40,10 @@ export interface IPlayerState {
 	program_account: PublicKey;
 	has_upline: boolean;
 	upline?: PublicKey;
+	downline_count: number;
+	total_earned: BN;
+	depth: number;
+	frozen: boolean;
 }
 
 export const GameStateSchema = new Map([
@@ -34,9 +52,28 @@ export const GameStateSchema = new Map([
 		{
 			kind: 'struct',
 			fields: [
+				['version', 'u8'],
 				['is_initialized', 'u8'],
-				['admin', ['u8', 32]],
+				['admins', [['u8', 32], 3]],
 				['spl_token_account', ['u8', 32]],
+				['total_players', 'u64'],
+				['referral_bonus', 'u64'],
+				['paused', 'u8'],
+				['max_depth', 'u8'],
+				['mint', ['u8', 32]],
+				['min_claim', 'u64'],
+				['register_fee', 'u64'],
+				['max_reward_per_player', 'u64'],
+				['nonce', 'u64'],
+				['decay_bps_per_day', 'u64'],
+				['matching_pool', 'u64'],
+				['relayer', ['u8', 32]],
+				['vault_bump', 'u8'],
+				['level_bps', ['u16', 3]],
+				['dust', 'u64'],
+				['tier_thresholds', ['u64', 3]],
+				['vest_duration', 'u64'],
+				['reward_multiplier_bps', 'u16'],
 			],
 		},
 	],
@@ -44,8 +81,12 @@ export const GameStateSchema = new Map([
 
 export interface IGameState {
 	is_initialized: boolean;
-	admin: PublicKey;
+	// Unused admin slots are left out
+	admins: PublicKey[];
 	spl_token_account: PublicKey;
+	mint: PublicKey;
+	total_players: BN;
+	paused: boolean;
 }
 
 export function fromSchemaDataToPlayerState(playerStateSchema: any): IPlayerState {
@@ -55,6 +96,10 @@ export function fromSchemaDataToPlayerState(playerStateSchema: any): IPlayerStat
 		reward_to_claim: playerStateSchema.reward_to_claim,
 		owner: new PublicKey(playerStateSchema.owner),
 		program_account: new PublicKey(playerStateSchema.program_account),
+		downline_count: playerStateSchema.downline_count,
+		total_earned: playerStateSchema.total_earned,
+		depth: playerStateSchema.depth,
+		frozen: playerStateSchema.frozen === 1,
 	};
 	if (playerState.has_upline) {
 		playerState.upline = new PublicKey(playerStateSchema.upline);
@@ -65,10 +110,14 @@ export function fromSchemaDataToPlayerState(playerStateSchema: any): IPlayerStat
 export function fromSchemaDataToGameState(gameStateSchema: any): IGameState {
 	return {
 		is_initialized: gameStateSchema.is_initialized === 1,
-		admin: new PublicKey(gameStateSchema.admin),
+		admins: gameStateSchema.admins.map((admin: number[]) => new PublicKey(admin)).filter((admin: PublicKey) => !admin.equals(PublicKey.default)),
 		spl_token_account: new PublicKey(gameStateSchema.spl_token_account),
+		mint: new PublicKey(gameStateSchema.mint),
+		total_players: gameStateSchema.total_players,
+		paused: gameStateSchema.paused === 1,
 	};
 }
 
-export const PLAYER_STATE_BYTE = 109;
-export const GAME_STATE_BYTE = 65;
+// Must match with PLAYER_LEN and GAME_INFO_LEN of the contract
+export const PLAYER_STATE_BYTE = 180;
+export const GAME_STATE_BYTE = 309;
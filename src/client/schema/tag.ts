@@ -1,3 +1,6 @@
+// Must match with the constants of the contract, every instruction data starts with the magic, then the tag
+export const INSTRUCTION_MAGIC = 0x4c;
+
 export enum Tag {
 	Init,
 	Register,
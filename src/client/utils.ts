@@ -1,10 +1,10 @@
 import * as SplToken from '@solana/spl-token';
-import { AccountMeta, Keypair, LAMPORTS_PER_SOL, PublicKey, sendAndConfirmTransaction, SystemProgram, Transaction, TransactionInstruction } from '@solana/web3.js';
+import { AccountMeta, Keypair, LAMPORTS_PER_SOL, PublicKey, sendAndConfirmTransaction, SystemProgram, SYSVAR_CLOCK_PUBKEY, Transaction, TransactionInstruction } from '@solana/web3.js';
 import * as fs from 'fs';
 import os from 'os';
 import * as path from 'path';
 import yaml from 'yaml';
-import { connection, PDA_SEED, programKeypairPath } from './config';
+import { connection, GAME_INFO_SEED, PDA_SEED, PLAYER_SEED, programKeypairPath } from './config';
 import { SchemaBuilder, SchemaData } from './schema/builder';
 import {
 	AddRewardIxScheme,
@@ -16,8 +16,8 @@ import {
 	IPlayerRegisterIx,
 	PlayerRegisterIxSchema,
 } from './schema/instructions';
-import { GAME_STATE_BYTE, PLAYER_STATE_BYTE } from './schema/states';
-import { Tag } from './schema/tag';
+import { fromSchemaDataToPlayerState, GAME_STATE_BYTE, PLAYER_STATE_BYTE, PlayerStateSchema } from './schema/states';
+import { INSTRUCTION_MAGIC, Tag } from './schema/tag';
 
 export async function requestAirdropIfInsufficientBalance(feePayerKeypair: Keypair, signatureCount: number, bytes?: number[]) {
 	const feePayerBalance = await connection.getBalance(feePayerKeypair.publicKey);
@@ -45,16 +45,17 @@ export async function requestAirdropIfInsufficientBalance(feePayerKeypair: Keypa
 // 4-  []         - The PDA, owner (in term of token, not account owner) of token account
 // 5 - [writable] - The player token account
 // 6 - []         - The token program
+// 7 - []         - The clock sysvar
 export async function claimReward(
 	playerKeypair: Keypair,
 	programAccountPubkey: PublicKey,
-	playerAccountPubkey: PublicKey,
 	gameTokenAccountPubkey: PublicKey,
 	playerTokenAccountPubkey: PublicKey,
 	feePayerKeypair: Keypair,
 ) {
 	const programId = (await getDeployedProgramKeypairOrThrow()).publicKey;
 	const [PDA] = await PublicKey.findProgramAddress([Buffer.from(PDA_SEED)], programId);
+	const playerAccountPubkey = await findPlayerAccountAddress(programId, playerKeypair.publicKey);
 	const transaction = new Transaction().add(
 		new TransactionInstruction({
 			keys: [
@@ -65,11 +66,13 @@ export async function claimReward(
 				{ isSigner: false, isWritable: false, pubkey: PDA },
 				{ isSigner: false, isWritable: true, pubkey: playerTokenAccountPubkey },
 				{ isSigner: false, isWritable: false, pubkey: SplToken.TOKEN_PROGRAM_ID },
+				{ isSigner: false, isWritable: false, pubkey: SYSVAR_CLOCK_PUBKEY },
 			],
 			programId,
 			data: SchemaBuilder.serialize(
 				ClaimRewardIxSchema,
 				new SchemaData({
+					magic: INSTRUCTION_MAGIC,
 					tag: Tag.Claim,
 				} as IClaimRewardIx),
 			),
@@ -83,14 +86,15 @@ export async function claimReward(
 // 0 - [signer]   - The admin (holder) account
 // 1 - [writable] - Program account
 // 2 - [writable] - The player program account
-// 3 - [writable] - The player upline program account
+// 3 - [writable] - The player upline program account, if any
+// 4 - [writable] - The second level upline program account, if any
+// 5 - [writable] - The third level upline program account, if any
 export async function addReward(
 	rewardAmountInSol: number,
 	feePayerKeypair: Keypair,
 	adminKeypair: Keypair,
 	programAccountPubkey: PublicKey,
 	playerAccountPubkey: PublicKey,
-	playerUplineAccountPubkey?: PublicKey,
 ) {
 	rewardAmountInSol = rewardAmountInSol * LAMPORTS_PER_SOL;
 	const programId = (await getDeployedProgramKeypairOrThrow()).publicKey;
@@ -99,8 +103,9 @@ export async function addReward(
 		{ isSigner: false, isWritable: true, pubkey: programAccountPubkey },
 		{ isSigner: false, isWritable: true, pubkey: playerAccountPubkey },
 	];
-	if (playerUplineAccountPubkey) {
-		keys.push({ isSigner: false, isWritable: true, pubkey: playerUplineAccountPubkey });
+	// The contract walk the whole chain, every upline up to the third level must be passed
+	for (const uplinePubkey of await getUplineAccounts(playerAccountPubkey, REWARD_LEVELS)) {
+		keys.push({ isSigner: false, isWritable: true, pubkey: uplinePubkey });
 	}
 	const transaction = new Transaction().add(
 		new TransactionInstruction({
@@ -109,6 +114,7 @@ export async function addReward(
 			data: SchemaBuilder.serialize(
 				AddRewardIxScheme,
 				new SchemaData({
+					magic: INSTRUCTION_MAGIC,
 					tag: Tag.AddReward,
 					reward_amount: rewardAmountInSol,
 				} as IAddRewardIx),
@@ -120,50 +126,70 @@ export async function addReward(
 	console.log('Add reward instruction', transactionSignature);
 }
 
-// 0 - [signer]   - The admin (holder) account
-// 1 - [writable] - Program account
-// 2 - [writable] - An token account created by the admin, and pre-funded
-// 3 - []         - The token program
-export async function initializeGame(tokenAccountPublicKey: PublicKey, adminKeypair: Keypair, gameAccountPubkey: PublicKey, feePayerKeypair: Keypair) {
+// 0 - [signer, writable] - The admin (holder) account, which pay for the program account rent
+// 1 - [writable]         - Program account, created by the contract at the game info PDA
+// 2 - []                 - The system program
+// 3 - [writable]         - An token account created by the admin, and pre-funded
+// 4 - []                 - The mint of the token account
+// 5 - []                 - The token program
+export async function initializeGame(
+	tokenAccountPublicKey: PublicKey,
+	mintPubkey: PublicKey,
+	adminKeypair: Keypair,
+	feePayerKeypair: Keypair,
+	referralBonus: number = 0,
+): Promise<PublicKey> {
 	const programId = (await getDeployedProgramKeypairOrThrow()).publicKey;
+	const gameAccountPubkey = await findGameAccountAddress(programId);
 	const transaction = new Transaction().add(
 		new TransactionInstruction({
 			keys: [
-				{ isSigner: true, isWritable: false, pubkey: adminKeypair.publicKey },
+				{ isSigner: true, isWritable: true, pubkey: adminKeypair.publicKey },
 				{ isSigner: false, isWritable: true, pubkey: gameAccountPubkey },
+				{ isSigner: false, isWritable: false, pubkey: SystemProgram.programId },
 				{ isSigner: false, isWritable: true, pubkey: tokenAccountPublicKey },
+				{ isSigner: false, isWritable: false, pubkey: mintPubkey },
 				{ isSigner: false, isWritable: false, pubkey: SplToken.TOKEN_PROGRAM_ID },
 			],
 			programId,
 			data: SchemaBuilder.serialize(
 				GameInitIxSchema,
 				new SchemaData({
+					magic: INSTRUCTION_MAGIC,
 					tag: Tag.Init,
+					referral_bonus: referralBonus,
 				} as IGameInitIx),
 			),
 		}),
 	);
-	await requestAirdropIfInsufficientBalance(feePayerKeypair, 1);
+	await requestAirdropIfInsufficientBalance(feePayerKeypair, 1, [GAME_STATE_BYTE]);
 	const transactionSignature = await sendAndConfirmTransaction(connection, transaction, [feePayerKeypair, adminKeypair]);
 	console.log('Initialize game account instruction', transactionSignature);
+	return gameAccountPubkey;
 }
 
-// 0 - [signer]   - The player (holder) account
-// 1 - [writable] - The player account for the program
-// 2 - []         - The program account
-// 3 - []         - The upline player account for the program
-export async function registerPlayer(playerKeypair: Keypair, playerAccountPubkey: PublicKey, programAccountPubkey: PublicKey, feePayerKeypair: Keypair, uplinePubkey?: PublicKey) {
+// 0 - [signer, writable] - The player (holder) account, which pay for the player account rent
+// 1 - [writable]         - The player account for the program, created by the contract at the PDA of the holder
+// 2 - [writable]         - The program account
+// 3 - []                 - The system program
+// 4 - []                 - The clock sysvar
+// 5 - [writable]         - The upline player account for the program
+export async function registerPlayer(playerKeypair: Keypair, programAccountPubkey: PublicKey, feePayerKeypair: Keypair, uplinePubkey?: PublicKey): Promise<PublicKey> {
 	const programId = (await getDeployedProgramKeypairOrThrow()).publicKey;
+	const playerAccountPubkey = await findPlayerAccountAddress(programId, playerKeypair.publicKey);
 	const playerRegisterInstruction: IPlayerRegisterIx = {
+		magic: INSTRUCTION_MAGIC,
 		tag: Tag.Register,
 	};
 	const keys: AccountMeta[] = [
-		{ isSigner: true, isWritable: false, pubkey: playerKeypair.publicKey },
+		{ isSigner: true, isWritable: true, pubkey: playerKeypair.publicKey },
 		{ isSigner: false, isWritable: true, pubkey: playerAccountPubkey },
-		{ isSigner: false, isWritable: false, pubkey: programAccountPubkey },
+		{ isSigner: false, isWritable: true, pubkey: programAccountPubkey },
+		{ isSigner: false, isWritable: false, pubkey: SystemProgram.programId },
+		{ isSigner: false, isWritable: false, pubkey: SYSVAR_CLOCK_PUBKEY },
 	];
 	if (uplinePubkey) {
-		keys.push({ isSigner: false, isWritable: false, pubkey: uplinePubkey });
+		keys.push({ isSigner: false, isWritable: true, pubkey: uplinePubkey });
 	}
 	const transaction = new Transaction().add(
 		new TransactionInstruction({
@@ -172,9 +198,10 @@ export async function registerPlayer(playerKeypair: Keypair, playerAccountPubkey
 			data: SchemaBuilder.serialize(PlayerRegisterIxSchema, new SchemaData(playerRegisterInstruction)),
 		}),
 	);
-	await requestAirdropIfInsufficientBalance(feePayerKeypair, 1);
+	await requestAirdropIfInsufficientBalance(feePayerKeypair, 1, [PLAYER_STATE_BYTE]);
 	const transactionSignature = await sendAndConfirmTransaction(connection, transaction, [feePayerKeypair, playerKeypair]);
 	console.log('Register player instruction', transactionSignature);
+	return playerAccountPubkey;
 }
 
 export async function createPlayerKeypair(): Promise<Keypair> {
@@ -184,42 +211,38 @@ export async function createPlayerKeypair(): Promise<Keypair> {
 	return playerKeypair;
 }
 
-export async function createPlayerAccount(feePayerKeypair: Keypair): Promise<Keypair> {
-	const programId = (await getDeployedProgramKeypairOrThrow()).publicKey;
-	const playerAccount = Keypair.generate();
-	const lamportsForPlayerAccountRentExeption = await connection.getMinimumBalanceForRentExemption(PLAYER_STATE_BYTE);
-	const transaction = new Transaction().add(
-		SystemProgram.createAccount({
-			fromPubkey: feePayerKeypair.publicKey,
-			lamports: lamportsForPlayerAccountRentExeption,
-			newAccountPubkey: playerAccount.publicKey,
-			space: PLAYER_STATE_BYTE,
-			programId,
-		}),
-	);
-	await requestAirdropIfInsufficientBalance(feePayerKeypair, 1, [PLAYER_STATE_BYTE]);
-	const transactionSignature = await sendAndConfirmTransaction(connection, transaction, [feePayerKeypair, playerAccount]);
-	console.log('Player account created', transactionSignature);
-	return playerAccount;
+// Number of upline levels sharing a reward, must match with the level bps of the contract
+export const REWARD_LEVELS = 3;
+
+// The player account is no longer created by the client, the contract create it at this address on register
+export async function findPlayerAccountAddress(programId: PublicKey, holderPubkey: PublicKey): Promise<PublicKey> {
+	const [playerAccountPubkey] = await PublicKey.findProgramAddress([Buffer.from(PLAYER_SEED), holderPubkey.toBuffer()], programId);
+	return playerAccountPubkey;
 }
 
-export async function createGameAccount(feePayerKeypair: Keypair): Promise<Keypair> {
-	const programId = (await getDeployedProgramKeypairOrThrow()).publicKey;
-	const gameAccount = Keypair.generate();
-	const lamportsForGameAccountRentExeption = await connection.getMinimumBalanceForRentExemption(GAME_STATE_BYTE);
-	const transaction = new Transaction().add(
-		SystemProgram.createAccount({
-			fromPubkey: feePayerKeypair.publicKey,
-			lamports: lamportsForGameAccountRentExeption,
-			newAccountPubkey: gameAccount.publicKey,
-			space: GAME_STATE_BYTE,
-			programId,
-		}),
-	);
-	await requestAirdropIfInsufficientBalance(feePayerKeypair, 2, [GAME_STATE_BYTE]);
-	const transactionSignature = await sendAndConfirmTransaction(connection, transaction, [feePayerKeypair, gameAccount]);
-	console.log('Game account created', transactionSignature);
-	return gameAccount;
+// The program account is no longer created by the client, the contract create it at this address on init
+export async function findGameAccountAddress(programId: PublicKey): Promise<PublicKey> {
+	const [gameAccountPubkey] = await PublicKey.findProgramAddress([Buffer.from(GAME_INFO_SEED)], programId);
+	return gameAccountPubkey;
+}
+
+// Upline program accounts of the player, direct upline first, up to the levels or the top of the chain
+export async function getUplineAccounts(playerAccountPubkey: PublicKey, levels: number): Promise<PublicKey[]> {
+	const uplines: PublicKey[] = [];
+	let accountPubkey = playerAccountPubkey;
+	while (uplines.length < levels) {
+		const accountInfo = await connection.getAccountInfo(accountPubkey);
+		if (accountInfo === null) {
+			throw new Error(`Player account ${accountPubkey.toBase58()} not found`);
+		}
+		const playerState = fromSchemaDataToPlayerState(SchemaBuilder.deserialize(PlayerStateSchema, accountInfo.data));
+		if (!playerState.has_upline || !playerState.upline) {
+			break;
+		}
+		uplines.push(playerState.upline);
+		accountPubkey = playerState.upline;
+	}
+	return uplines;
 }
 
 export function toJSONStringAndBeautify(obj: any): string {
@@ -0,0 +1,812 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::error::GameError;
+
+// Named accounts of every instruction, read in the order the instruction builders pass them
+// The constructors only check the account count and the signer / writable flags,
+// owner and data checks need the program id and account data, so they stay in the processor
+
+// Reject an instruction with fewer accounts than it needs up front, instead of failing halfway through the accounts
+// Optional trailing accounts, like the register upline, are not part of the expected count
+fn check_account_count(accounts: &[AccountInfo], expected: usize) -> ProgramResult {
+    if accounts.len() < expected {
+        msg!(
+            "Instruction expects at least {} accounts, but got {}",
+            expected,
+            accounts.len()
+        );
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    Ok(())
+}
+
+fn check_signer(account: &AccountInfo, name: &str) -> ProgramResult {
+    if !account.is_signer {
+        msg!("{} account must be signed", name);
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Ok(())
+}
+
+fn check_writable(account: &AccountInfo, name: &str) -> ProgramResult {
+    if !account.is_writable {
+        msg!("{} account must be writable", name);
+        return Err(GameError::AccountNotWritable.into());
+    }
+    Ok(())
+}
+
+// Reject the same account passed in two slots of an instruction, the named accounts must all be different
+// Otherwise the second borrow of the account data fails halfway through, or one account silently plays two roles
+fn check_distinct(accounts: &[(&AccountInfo, &str)]) -> ProgramResult {
+    for (index, (account, name)) in accounts.iter().enumerate() {
+        if let Some((_, other_name)) = accounts[index + 1..]
+            .iter()
+            .find(|(other_account, _)| other_account.key == account.key)
+        {
+            msg!("{} and {} accounts must be different", name, other_name);
+            return Err(GameError::DuplicateAccount.into());
+        }
+    }
+    Ok(())
+}
+
+pub struct InitAccounts<'a, 'b> {
+    pub admin: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub token_account: &'a AccountInfo<'b>,
+    pub mint: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> InitAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 6)?;
+        let account_iter = &mut accounts.iter();
+        let init_accounts = Self {
+            admin: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+            system_program: next_account_info(account_iter)?,
+            token_account: next_account_info(account_iter)?,
+            mint: next_account_info(account_iter)?,
+            token_program: next_account_info(account_iter)?,
+        };
+        check_signer(init_accounts.admin, "Admin")?;
+        // The admin funds the program account when it does not exist yet
+        check_writable(init_accounts.admin, "Admin")?;
+        check_writable(init_accounts.game_info, "Program")?;
+        check_writable(init_accounts.token_account, "Token")?;
+        check_distinct(&[
+            (init_accounts.admin, "Admin"),
+            (init_accounts.game_info, "Program"),
+            (init_accounts.token_account, "Token"),
+            (init_accounts.mint, "Mint"),
+        ])?;
+        Ok(init_accounts)
+    }
+}
+
+pub struct RegisterAccounts<'a, 'b> {
+    pub holder: &'a AccountInfo<'b>,
+    pub player: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub clock: &'a AccountInfo<'b>,
+    pub upline: Option<&'a AccountInfo<'b>>,
+}
+
+impl<'a, 'b> RegisterAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 5)?;
+        let account_iter = &mut accounts.iter();
+        let register_accounts = Self {
+            holder: next_account_info(account_iter)?,
+            player: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+            system_program: next_account_info(account_iter)?,
+            clock: next_account_info(account_iter)?,
+            // Some clients pass the default pubkey as a placeholder instead of omitting the upline
+            upline: next_account_info(account_iter)
+                .ok()
+                .filter(|upline| *upline.key != Pubkey::default()),
+        };
+        // The holder only needs to be writable to pay for the player account or the register fee,
+        // which the processor checks once it knows
+        check_signer(register_accounts.holder, "Player holder")?;
+        check_writable(register_accounts.player, "Player program")?;
+        check_writable(register_accounts.game_info, "Program")?;
+        if let Some(upline) = register_accounts.upline {
+            check_writable(upline, "Upline player program")?;
+        }
+        check_distinct(&[
+            (register_accounts.holder, "Player holder"),
+            (register_accounts.player, "Player program"),
+            (register_accounts.game_info, "Program"),
+        ])?;
+        Ok(register_accounts)
+    }
+}
+
+pub struct BatchRegisterAccounts<'a, 'b> {
+    pub game_info: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub clock: &'a AccountInfo<'b>,
+    pub upline: &'a AccountInfo<'b>,
+    // (holder, player) account pairs
+    pub players: &'a [AccountInfo<'b>],
+}
+
+impl<'a, 'b> BatchRegisterAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 6)?;
+        let account_iter = &mut accounts.iter();
+        let batch_register_accounts = Self {
+            game_info: next_account_info(account_iter)?,
+            system_program: next_account_info(account_iter)?,
+            clock: next_account_info(account_iter)?,
+            upline: next_account_info(account_iter)?,
+            players: account_iter.as_slice(),
+        };
+        if batch_register_accounts.players.len() % 2 != 0 {
+            msg!("Batch register expects (holder, player) account pairs");
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        for pair in batch_register_accounts.players.chunks(2) {
+            check_signer(&pair[0], "Player holder")?;
+            check_writable(&pair[1], "Player program")?;
+        }
+        check_writable(batch_register_accounts.game_info, "Program")?;
+        check_writable(batch_register_accounts.upline, "Upline player program")?;
+        Ok(batch_register_accounts)
+    }
+}
+
+pub struct AddRewardAccounts<'a, 'b> {
+    pub admin: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+    pub player: &'a AccountInfo<'b>,
+    // The upline accounts, from the direct upline up, as many as the player chain has
+    pub uplines: &'a [AccountInfo<'b>],
+}
+
+impl<'a, 'b> AddRewardAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 3)?;
+        let account_iter = &mut accounts.iter();
+        let add_reward_accounts = Self {
+            admin: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+            player: next_account_info(account_iter)?,
+            uplines: account_iter.as_slice(),
+        };
+        check_signer(add_reward_accounts.admin, "Admin")?;
+        // The program account keeps the dust of the reward split
+        check_writable(add_reward_accounts.game_info, "Program")?;
+        check_writable(add_reward_accounts.player, "Player program")?;
+        for upline in add_reward_accounts.uplines {
+            check_writable(upline, "Upline player program")?;
+        }
+        check_distinct(&[
+            (add_reward_accounts.game_info, "Program"),
+            (add_reward_accounts.player, "Player program"),
+        ])?;
+        Ok(add_reward_accounts)
+    }
+}
+
+pub struct ClaimAccounts<'a, 'b> {
+    pub holder: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+    pub player: &'a AccountInfo<'b>,
+    pub vault_token_account: &'a AccountInfo<'b>,
+    pub vault_authority: &'a AccountInfo<'b>,
+    pub player_token_account: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+    pub clock: &'a AccountInfo<'b>,
+    // Passed only when the claim should be recorded with a memo
+    pub memo_program: Option<&'a AccountInfo<'b>>,
+}
+
+impl<'a, 'b> ClaimAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 8)?;
+        let account_iter = &mut accounts.iter();
+        let claim_accounts = Self {
+            holder: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+            player: next_account_info(account_iter)?,
+            vault_token_account: next_account_info(account_iter)?,
+            vault_authority: next_account_info(account_iter)?,
+            player_token_account: next_account_info(account_iter)?,
+            token_program: next_account_info(account_iter)?,
+            clock: next_account_info(account_iter)?,
+            memo_program: next_account_info(account_iter).ok(),
+        };
+        check_signer(claim_accounts.holder, "Player holder")?;
+        // The program account keeps the event nonce
+        check_writable(claim_accounts.game_info, "Program")?;
+        check_writable(claim_accounts.player, "Player program")?;
+        check_writable(claim_accounts.vault_token_account, "Program token")?;
+        check_writable(claim_accounts.player_token_account, "Player token")?;
+        check_distinct(&[
+            (claim_accounts.game_info, "Program"),
+            (claim_accounts.player, "Player program"),
+            (claim_accounts.vault_token_account, "Program token"),
+            (claim_accounts.player_token_account, "Player token"),
+        ])?;
+        Ok(claim_accounts)
+    }
+}
+
+pub struct CloseAccountAccounts<'a, 'b> {
+    pub holder: &'a AccountInfo<'b>,
+    pub player: &'a AccountInfo<'b>,
+    pub destination: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+    // The downline accounts to reassign, preceded by the player upline account when the player has one
+    pub reassigned: &'a [AccountInfo<'b>],
+}
+
+impl<'a, 'b> CloseAccountAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 4)?;
+        let account_iter = &mut accounts.iter();
+        let close_account_accounts = Self {
+            holder: next_account_info(account_iter)?,
+            player: next_account_info(account_iter)?,
+            destination: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+            reassigned: account_iter.as_slice(),
+        };
+        check_signer(close_account_accounts.holder, "Player holder")?;
+        check_writable(close_account_accounts.player, "Player program")?;
+        check_writable(close_account_accounts.destination, "Destination")?;
+        // The program account keeps the number of players
+        check_writable(close_account_accounts.game_info, "Program")?;
+        for reassigned in close_account_accounts.reassigned {
+            check_writable(reassigned, "Reassigned player program")?;
+        }
+        // The rent sent back to the player account would be lost with it
+        check_distinct(&[
+            (close_account_accounts.player, "Player program"),
+            (close_account_accounts.destination, "Destination"),
+            (close_account_accounts.game_info, "Program"),
+        ])?;
+        Ok(close_account_accounts)
+    }
+}
+
+pub struct SetAdminAccounts<'a, 'b> {
+    pub admin: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+    pub new_admin: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> SetAdminAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 3)?;
+        let account_iter = &mut accounts.iter();
+        let set_admin_accounts = Self {
+            admin: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+            new_admin: next_account_info(account_iter)?,
+        };
+        check_signer(set_admin_accounts.admin, "Admin")?;
+        check_writable(set_admin_accounts.game_info, "Program")?;
+        Ok(set_admin_accounts)
+    }
+}
+
+pub struct AddAdminAccounts<'a, 'b> {
+    pub admin: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+    pub new_admin: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> AddAdminAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 3)?;
+        let account_iter = &mut accounts.iter();
+        let add_admin_accounts = Self {
+            admin: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+            new_admin: next_account_info(account_iter)?,
+        };
+        check_signer(add_admin_accounts.admin, "Admin")?;
+        check_writable(add_admin_accounts.game_info, "Program")?;
+        Ok(add_admin_accounts)
+    }
+}
+
+pub struct RemoveAdminAccounts<'a, 'b> {
+    pub admin: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+    pub removed_admin: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> RemoveAdminAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 3)?;
+        let account_iter = &mut accounts.iter();
+        let remove_admin_accounts = Self {
+            admin: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+            removed_admin: next_account_info(account_iter)?,
+        };
+        check_signer(remove_admin_accounts.admin, "Admin")?;
+        check_writable(remove_admin_accounts.game_info, "Program")?;
+        Ok(remove_admin_accounts)
+    }
+}
+
+pub struct SetPausedAccounts<'a, 'b> {
+    pub admin: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> SetPausedAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 2)?;
+        let account_iter = &mut accounts.iter();
+        let set_paused_accounts = Self {
+            admin: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+        };
+        check_signer(set_paused_accounts.admin, "Admin")?;
+        check_writable(set_paused_accounts.game_info, "Program")?;
+        Ok(set_paused_accounts)
+    }
+}
+
+pub struct SetTierThresholdsAccounts<'a, 'b> {
+    pub admin: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> SetTierThresholdsAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 2)?;
+        let account_iter = &mut accounts.iter();
+        let set_tier_thresholds_accounts = Self {
+            admin: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+        };
+        check_signer(set_tier_thresholds_accounts.admin, "Admin")?;
+        check_writable(set_tier_thresholds_accounts.game_info, "Program")?;
+        Ok(set_tier_thresholds_accounts)
+    }
+}
+
+pub struct SetReferralBonusAccounts<'a, 'b> {
+    pub admin: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> SetReferralBonusAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 2)?;
+        let account_iter = &mut accounts.iter();
+        let set_referral_bonus_accounts = Self {
+            admin: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+        };
+        check_signer(set_referral_bonus_accounts.admin, "Admin")?;
+        check_writable(set_referral_bonus_accounts.game_info, "Program")?;
+        Ok(set_referral_bonus_accounts)
+    }
+}
+
+pub struct SetVestDurationAccounts<'a, 'b> {
+    pub admin: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> SetVestDurationAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 2)?;
+        let account_iter = &mut accounts.iter();
+        let set_vest_duration_accounts = Self {
+            admin: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+        };
+        check_signer(set_vest_duration_accounts.admin, "Admin")?;
+        check_writable(set_vest_duration_accounts.game_info, "Program")?;
+        Ok(set_vest_duration_accounts)
+    }
+}
+
+pub struct SetRewardMultiplierAccounts<'a, 'b> {
+    pub admin: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> SetRewardMultiplierAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 2)?;
+        let account_iter = &mut accounts.iter();
+        let set_reward_multiplier_accounts = Self {
+            admin: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+        };
+        check_signer(set_reward_multiplier_accounts.admin, "Admin")?;
+        check_writable(set_reward_multiplier_accounts.game_info, "Program")?;
+        Ok(set_reward_multiplier_accounts)
+    }
+}
+
+pub struct SweepFeesAccounts<'a, 'b> {
+    pub admin: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+    pub destination: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> SweepFeesAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 3)?;
+        let account_iter = &mut accounts.iter();
+        let sweep_fees_accounts = Self {
+            admin: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+            destination: next_account_info(account_iter)?,
+        };
+        check_signer(sweep_fees_accounts.admin, "Admin")?;
+        check_writable(sweep_fees_accounts.game_info, "Program")?;
+        check_writable(sweep_fees_accounts.destination, "Destination")?;
+        check_distinct(&[
+            (sweep_fees_accounts.game_info, "Program"),
+            (sweep_fees_accounts.destination, "Destination"),
+        ])?;
+        Ok(sweep_fees_accounts)
+    }
+}
+
+pub struct SetVaultAuthorityAccounts<'a, 'b> {
+    pub admin: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+    pub vault_token_account: &'a AccountInfo<'b>,
+    pub vault_authority: &'a AccountInfo<'b>,
+    pub new_authority: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> SetVaultAuthorityAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 6)?;
+        let account_iter = &mut accounts.iter();
+        let set_vault_authority_accounts = Self {
+            admin: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+            vault_token_account: next_account_info(account_iter)?,
+            vault_authority: next_account_info(account_iter)?,
+            new_authority: next_account_info(account_iter)?,
+            token_program: next_account_info(account_iter)?,
+        };
+        check_signer(set_vault_authority_accounts.admin, "Admin")?;
+        check_writable(set_vault_authority_accounts.game_info, "Program")?;
+        check_writable(
+            set_vault_authority_accounts.vault_token_account,
+            "Program token",
+        )?;
+        check_distinct(&[
+            (set_vault_authority_accounts.game_info, "Program"),
+            (
+                set_vault_authority_accounts.vault_token_account,
+                "Program token",
+            ),
+        ])?;
+        Ok(set_vault_authority_accounts)
+    }
+}
+
+pub struct SweepDustAccounts<'a, 'b> {
+    pub admin: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+    pub vault_token_account: &'a AccountInfo<'b>,
+    pub vault_authority: &'a AccountInfo<'b>,
+    pub destination: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> SweepDustAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 6)?;
+        let account_iter = &mut accounts.iter();
+        let sweep_dust_accounts = Self {
+            admin: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+            vault_token_account: next_account_info(account_iter)?,
+            vault_authority: next_account_info(account_iter)?,
+            destination: next_account_info(account_iter)?,
+            token_program: next_account_info(account_iter)?,
+        };
+        check_signer(sweep_dust_accounts.admin, "Admin")?;
+        check_writable(sweep_dust_accounts.game_info, "Program")?;
+        check_writable(sweep_dust_accounts.vault_token_account, "Program token")?;
+        check_writable(sweep_dust_accounts.destination, "Destination token")?;
+        check_distinct(&[
+            (sweep_dust_accounts.game_info, "Program"),
+            (sweep_dust_accounts.vault_token_account, "Program token"),
+            (sweep_dust_accounts.destination, "Destination token"),
+        ])?;
+        Ok(sweep_dust_accounts)
+    }
+}
+
+pub struct AdminClaimBatchAccounts<'a, 'b> {
+    pub admin: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+    pub vault_token_account: &'a AccountInfo<'b>,
+    pub vault_authority: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+    pub clock: &'a AccountInfo<'b>,
+    // (player, destination token) account pairs
+    pub players: &'a [AccountInfo<'b>],
+}
+
+impl<'a, 'b> AdminClaimBatchAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 8)?;
+        let account_iter = &mut accounts.iter();
+        let admin_claim_batch_accounts = Self {
+            admin: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+            vault_token_account: next_account_info(account_iter)?,
+            vault_authority: next_account_info(account_iter)?,
+            token_program: next_account_info(account_iter)?,
+            clock: next_account_info(account_iter)?,
+            players: account_iter.as_slice(),
+        };
+        if admin_claim_batch_accounts.players.len() % 2 != 0 {
+            msg!("Admin claim batch expects (player, destination token) account pairs");
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        check_signer(admin_claim_batch_accounts.admin, "Admin")?;
+        // The program account keeps the event nonce
+        check_writable(admin_claim_batch_accounts.game_info, "Program")?;
+        check_writable(
+            admin_claim_batch_accounts.vault_token_account,
+            "Program token",
+        )?;
+        for pair in admin_claim_batch_accounts.players.chunks(2) {
+            check_writable(&pair[0], "Player program")?;
+            check_writable(&pair[1], "Destination token")?;
+            check_distinct(&[
+                (admin_claim_batch_accounts.game_info, "Program"),
+                (
+                    admin_claim_batch_accounts.vault_token_account,
+                    "Program token",
+                ),
+                (&pair[0], "Player program"),
+                (&pair[1], "Destination token"),
+            ])?;
+        }
+        Ok(admin_claim_batch_accounts)
+    }
+}
+
+pub struct AdminSetRewardAccounts<'a, 'b> {
+    pub admin: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+    pub player: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> AdminSetRewardAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 3)?;
+        let account_iter = &mut accounts.iter();
+        let admin_set_reward_accounts = Self {
+            admin: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+            player: next_account_info(account_iter)?,
+        };
+        check_signer(admin_set_reward_accounts.admin, "Admin")?;
+        check_writable(admin_set_reward_accounts.game_info, "Program")?;
+        check_writable(admin_set_reward_accounts.player, "Player program")?;
+        Ok(admin_set_reward_accounts)
+    }
+}
+
+pub struct FreezePlayerAccounts<'a, 'b> {
+    pub admin: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+    pub player: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> FreezePlayerAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 3)?;
+        let account_iter = &mut accounts.iter();
+        let freeze_player_accounts = Self {
+            admin: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+            player: next_account_info(account_iter)?,
+        };
+        check_signer(freeze_player_accounts.admin, "Admin")?;
+        check_writable(freeze_player_accounts.game_info, "Program")?;
+        check_writable(freeze_player_accounts.player, "Player program")?;
+        Ok(freeze_player_accounts)
+    }
+}
+
+pub struct SetRelayerAccounts<'a, 'b> {
+    pub admin: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+    pub relayer: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> SetRelayerAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 3)?;
+        let account_iter = &mut accounts.iter();
+        let set_relayer_accounts = Self {
+            admin: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+            relayer: next_account_info(account_iter)?,
+        };
+        check_signer(set_relayer_accounts.admin, "Admin")?;
+        check_writable(set_relayer_accounts.game_info, "Program")?;
+        Ok(set_relayer_accounts)
+    }
+}
+
+pub struct FundPoolAccounts<'a, 'b> {
+    pub admin: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> FundPoolAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 2)?;
+        let account_iter = &mut accounts.iter();
+        let fund_pool_accounts = Self {
+            admin: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+        };
+        check_signer(fund_pool_accounts.admin, "Admin")?;
+        check_writable(fund_pool_accounts.game_info, "Program")?;
+        Ok(fund_pool_accounts)
+    }
+}
+
+pub struct GetRewardAccounts<'a, 'b> {
+    pub player: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> GetRewardAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 1)?;
+        let account_iter = &mut accounts.iter();
+        Ok(Self {
+            player: next_account_info(account_iter)?,
+        })
+    }
+}
+
+pub struct ExportPlayerAccounts<'a, 'b> {
+    pub player: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> ExportPlayerAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 1)?;
+        let account_iter = &mut accounts.iter();
+        Ok(Self {
+            player: next_account_info(account_iter)?,
+        })
+    }
+}
+
+pub struct SlashRewardAccounts<'a, 'b> {
+    pub admin: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+    pub player: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> SlashRewardAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 3)?;
+        let account_iter = &mut accounts.iter();
+        let slash_reward_accounts = Self {
+            admin: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+            player: next_account_info(account_iter)?,
+        };
+        check_signer(slash_reward_accounts.admin, "Admin")?;
+        // The program account keeps the event nonce
+        check_writable(slash_reward_accounts.game_info, "Program")?;
+        check_writable(slash_reward_accounts.player, "Player program")?;
+        Ok(slash_reward_accounts)
+    }
+}
+
+pub struct SetUplineAccounts<'a, 'b> {
+    pub holder: &'a AccountInfo<'b>,
+    pub player: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+    pub upline: &'a AccountInfo<'b>,
+    // The player accounts above the upline, from its own upline up, as many as the client passes
+    pub ancestors: &'a [AccountInfo<'b>],
+}
+
+impl<'a, 'b> SetUplineAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 4)?;
+        let account_iter = &mut accounts.iter();
+        let set_upline_accounts = Self {
+            holder: next_account_info(account_iter)?,
+            player: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+            upline: next_account_info(account_iter)?,
+            ancestors: account_iter.as_slice(),
+        };
+        check_signer(set_upline_accounts.holder, "Player holder")?;
+        check_writable(set_upline_accounts.player, "Player program")?;
+        check_writable(set_upline_accounts.game_info, "Program")?;
+        check_writable(set_upline_accounts.upline, "Upline player program")?;
+        Ok(set_upline_accounts)
+    }
+}
+
+pub struct TransferRewardAccounts<'a, 'b> {
+    pub holder: &'a AccountInfo<'b>,
+    pub player: &'a AccountInfo<'b>,
+    pub destination_player: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> TransferRewardAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 4)?;
+        let account_iter = &mut accounts.iter();
+        let transfer_reward_accounts = Self {
+            holder: next_account_info(account_iter)?,
+            player: next_account_info(account_iter)?,
+            destination_player: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+        };
+        check_signer(transfer_reward_accounts.holder, "Player holder")?;
+        check_writable(transfer_reward_accounts.player, "Player program")?;
+        check_writable(transfer_reward_accounts.game_info, "Program")?;
+        check_writable(
+            transfer_reward_accounts.destination_player,
+            "Destination player program",
+        )?;
+        Ok(transfer_reward_accounts)
+    }
+}
+
+pub struct MigratePlayerAccounts<'a, 'b> {
+    pub holder: &'a AccountInfo<'b>,
+    pub player: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub game_info: &'a AccountInfo<'b>,
+    // Passed only when the player has an upline and its layout predates the depth
+    pub upline: Option<&'a AccountInfo<'b>>,
+}
+
+impl<'a, 'b> MigratePlayerAccounts<'a, 'b> {
+    pub fn from_account_iter(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        check_account_count(accounts, 4)?;
+        let account_iter = &mut accounts.iter();
+        let migrate_player_accounts = Self {
+            holder: next_account_info(account_iter)?,
+            player: next_account_info(account_iter)?,
+            system_program: next_account_info(account_iter)?,
+            game_info: next_account_info(account_iter)?,
+            upline: next_account_info(account_iter).ok(),
+        };
+        check_signer(migrate_player_accounts.holder, "Player holder")?;
+        // The holder tops up the rent of the bigger player account
+        check_writable(migrate_player_accounts.holder, "Player holder")?;
+        check_writable(migrate_player_accounts.player, "Player program")?;
+        check_writable(migrate_player_accounts.game_info, "Program")?;
+        Ok(migrate_player_accounts)
+    }
+}
@@ -0,0 +1,191 @@
+use solana_program::{
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack},
+    pubkey::Pubkey,
+    rent::Rent,
+};
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+use crate::{
+    error::GameError,
+    processor::{check_register, player_pda, register_under_upline},
+    state::{GameInfo, Player, LEGACY_PLAYER_LENS},
+};
+
+// Errors returned when decoding the program accounts off-chain
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum ClientError {
+    // The account data size does not match the account layout
+    #[error("Invalid {account} account data size, expected {expected} bytes but got {actual}")]
+    InvalidDataSize {
+        account: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+
+    // The account data has the right size but could not be decoded
+    #[error("Invalid {account} account data")]
+    InvalidData { account: &'static str },
+
+    // The account has been allocated but not initialized by the program yet
+    #[error("{account} account is not initialized")]
+    NotInitialized { account: &'static str },
+}
+
+// Render a player referral code as a base58 string, to be shared by the player
+pub fn render_referral_code(referral_code: &[u8; 8]) -> String {
+    bs58::encode(referral_code).into_string()
+}
+
+// Player account address and bump of each holder, in the order of the holders, without any RPC call
+pub fn player_pdas(program_id: &Pubkey, holders: &[Pubkey]) -> Vec<(Pubkey, u8)> {
+    holders
+        .iter()
+        .map(|holder| player_pda(program_id, holder))
+        .collect()
+}
+
+// Sum the reward to claim of the root player and every player below it in the referral tree
+// Players are given with their player account, the tree is rebuilt from the upline links
+pub fn subtree_reward_to_claim(players: &[(Pubkey, Player)], root: &Pubkey) -> u64 {
+    let mut downlines: HashMap<Pubkey, Vec<&(Pubkey, Player)>> = HashMap::new();
+    for entry in players {
+        if let Some(upline) = Option::<Pubkey>::from(entry.1.upline) {
+            downlines.entry(upline).or_default().push(entry);
+        }
+    }
+
+    let mut total = players
+        .iter()
+        .find(|(account, _)| account == root)
+        .map_or(0, |(_, player)| player.reward_to_claim);
+    // The program never links a cycle, but the slice may come from anywhere, so visit each player once
+    let mut visited = HashSet::from([*root]);
+    let mut pending = vec![*root];
+    while let Some(account) = pending.pop() {
+        for (downline, player) in downlines.get(&account).into_iter().flatten() {
+            if visited.insert(*downline) {
+                total = total.saturating_add(player.reward_to_claim);
+                pending.push(*downline);
+            }
+        }
+    }
+    total
+}
+
+// Minimum balance of the program token account to pay every player claiming their whole reward
+// Takes the data of every player account, closed or not yet registered accounts owe nothing
+pub fn vault_balance_requirement(player_datas: &[&[u8]]) -> Result<u64, ClientError> {
+    let mut requirement: u64 = 0;
+    for data in player_datas {
+        let player = match decode_player(data) {
+            Ok(player) => player,
+            Err(ClientError::NotInitialized { .. }) => continue,
+            Err(error) => return Err(error),
+        };
+        requirement = requirement.saturating_add(player.reward_to_claim);
+    }
+    Ok(requirement)
+}
+
+// On-chain state of an account as fetched by the client, an account which does not exist yet is owned by the system program
+// with no lamports and no data
+pub struct ClientAccount<'a> {
+    pub key: Pubkey,
+    pub owner: Pubkey,
+    pub lamports: u64,
+    pub data: &'a [u8],
+}
+
+// Dry run of the register checks of the processor against the fetched state, in the same order
+// Returns the error the register transaction would fail with, or Ok when it would go through
+// Only the state is checked, the signer and writable flags are up to the transaction built by the client
+// The signer is the holder itself, or the relayer registering the holder
+pub fn validate_register(
+    program_id: &Pubkey,
+    signer: &Pubkey,
+    holder: &Pubkey,
+    player_account: &ClientAccount,
+    program_account: &ClientAccount,
+    upline_account: Option<&ClientAccount>,
+    idempotent: bool,
+) -> Result<(), ProgramError> {
+    if player_account.key != player_pda(program_id, holder).0 {
+        return Err(GameError::InvalidPlayerAccount.into());
+    }
+
+    if program_account.owner != *program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mut program_account_data = GameInfo::unpack_unchecked(program_account.data)?;
+    check_register(&program_account_data, signer, holder)?;
+
+    // Otherwise the player account is created by the register, with the right size and balance
+    if player_account.owner == *program_id {
+        // The cluster rent is not fetched, the default rent is the one of every public cluster
+        if !Rent::default().is_exempt(player_account.lamports, player_account.data.len()) {
+            return Err(GameError::NotRentExempt.into());
+        }
+        let player_data = Player::unpack_unchecked(player_account.data)?;
+        if player_data.is_initialized {
+            if idempotent {
+                return Ok(());
+            }
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+    }
+
+    // Same as the processor, the default pubkey placeholder is no upline
+    let upline_account = upline_account.filter(|upline| upline.key != Pubkey::default());
+    if let Some(upline_account) = upline_account {
+        if upline_account.key == player_account.key {
+            return Err(GameError::SelfRecursiveUpline.into());
+        }
+        if upline_account.owner != *program_id {
+            return Err(GameError::UplineNotRegistered.into());
+        }
+        let mut upline_data = Player::unpack_unchecked(upline_account.data)?;
+        // The player is registered from a zeroed account, and the time of the credit does not change the outcome
+        let mut player_data = Player::unpack_unchecked(&[0; Player::LEN])?;
+        register_under_upline(
+            &mut player_data,
+            &mut upline_data,
+            holder,
+            &mut program_account_data,
+            0,
+        )?;
+    }
+
+    Ok(())
+}
+
+// Decode the account data of a player account, a previous layout is read with the defaults of the fields it misses
+pub fn decode_player(data: &[u8]) -> Result<Player, ClientError> {
+    decode(data, &LEGACY_PLAYER_LENS, "Player")
+}
+
+// Decode the account data of the program account
+pub fn decode_game_info(data: &[u8]) -> Result<GameInfo, ClientError> {
+    decode(data, &[], "GameInfo")
+}
+
+// Data of one of the legacy lengths is left to the unpack of the account type
+fn decode<T: Pack + IsInitialized>(
+    data: &[u8],
+    legacy_lens: &[usize],
+    account: &'static str,
+) -> Result<T, ClientError> {
+    if data.len() != T::LEN && !legacy_lens.contains(&data.len()) {
+        return Err(ClientError::InvalidDataSize {
+            account,
+            expected: T::LEN,
+            actual: data.len(),
+        });
+    }
+    let state = T::unpack_from_slice(data).map_err(|_| ClientError::InvalidData { account })?;
+    if !state.is_initialized() {
+        return Err(ClientError::NotInitialized { account });
+    }
+    Ok(state)
+}
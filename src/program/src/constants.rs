@@ -0,0 +1,71 @@
+// Values shared by the program and the client builders, kept in one place so they cannot drift apart
+
+// First byte of the instruction data, an instruction encoded for another program version is rejected before its tag
+pub const INSTRUCTION_MAGIC: u8 = 0x4c;
+
+// Instruction tags, the byte after the magic, see Command for the accounts and data of each
+pub const INIT_TAG: u8 = 0;
+pub const REGISTER_TAG: u8 = 1;
+pub const ADD_REWARD_TAG: u8 = 2;
+pub const CLAIM_TAG: u8 = 3;
+pub const CLOSE_ACCOUNT_TAG: u8 = 4;
+pub const SET_ADMIN_TAG: u8 = 5;
+pub const TRANSFER_REWARD_TAG: u8 = 6;
+pub const BATCH_REGISTER_TAG: u8 = 7;
+pub const SET_PAUSED_TAG: u8 = 8;
+pub const SWEEP_FEES_TAG: u8 = 9;
+pub const ADMIN_SET_REWARD_TAG: u8 = 10;
+pub const SET_UPLINE_TAG: u8 = 11;
+pub const SET_VAULT_AUTHORITY_TAG: u8 = 12;
+pub const MIGRATE_PLAYER_TAG: u8 = 13;
+pub const ADD_ADMIN_TAG: u8 = 14;
+pub const REMOVE_ADMIN_TAG: u8 = 15;
+pub const SLASH_REWARD_TAG: u8 = 16;
+pub const GET_REWARD_TAG: u8 = 17;
+pub const FUND_POOL_TAG: u8 = 18;
+pub const SET_RELAYER_TAG: u8 = 19;
+pub const FREEZE_PLAYER_TAG: u8 = 20;
+pub const EXPORT_PLAYER_TAG: u8 = 21;
+pub const SWEEP_DUST_TAG: u8 = 22;
+pub const ADMIN_CLAIM_BATCH_TAG: u8 = 23;
+pub const SET_TIER_THRESHOLDS_TAG: u8 = 24;
+pub const SET_REFERRAL_BONUS_TAG: u8 = 25;
+pub const SET_VEST_DURATION_TAG: u8 = 26;
+pub const SET_REWARD_MULTIPLIER_TAG: u8 = 27;
+
+// Seed prefixes of the program derived addresses
+// The vault PDA, owner (in term of token) of the program token account
+pub const PDA_SEED: &str = "game_seed";
+// The player account, followed by the holder pubkey
+pub const PLAYER_SEED: &str = "player";
+// The program account
+pub const GAME_INFO_SEED: &str = "game_info";
+
+// Number of admin slots of the program account
+pub const MAX_ADMINS: usize = 3;
+// Size of the current layout of the accounts, the layouts themselves are in the state module
+pub const GAME_INFO_LEN: usize = 1
+    + 1
+    + 32 * MAX_ADMINS
+    + 32
+    + 8
+    + 8
+    + 1
+    + 1
+    + 32
+    + 8
+    + 8
+    + 8
+    + 8
+    + 8
+    + 8
+    + 32
+    + 1
+    + 2 * 3
+    + 8
+    + 8 * 3
+    + 8
+    + 2
+    + 32;
+pub const PLAYER_LEN: usize =
+    1 + 32 + 8 + 32 + 4 + 32 + 4 + 8 + 8 + 8 + 8 + 1 + 8 + 1 + 1 + 8 + 8 + 8;
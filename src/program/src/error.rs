@@ -13,12 +13,13 @@ pub enum CommandError {
 pub enum GameError {
     // The program account already initialized
     #[error("Program already initialized")]
-    AlreadyInitialize,
+    AlreadyInitialized,
 
     // The program account not initialized
     #[error("Program not initialized")]
-    NotInitialize,
+    NotInitialized,
 
+    // Reward arithmetic overflowed or underflowed
     #[error("Reward amount overflow")]
     RewardAmountOverflow,
 
@@ -28,8 +29,112 @@ pub enum GameError {
     #[error("Unclaimable amount")]
     UnclaimableAmount,
 
+    // The upline is the player itself or is held by the same holder
     #[error("Upline cannot be yourself")]
     SelfRecursiveUpline,
+
+    // The signer is not the admin stored in the program account
+    #[error("Invalid admin")]
+    InvalidAdmin,
+
+    // The token account does not match the one bound to the program account
+    #[error("Invalid token account")]
+    InvalidTokenAccount,
+
+    // The upline player account has not been registered
+    #[error("Upline not registered")]
+    UplineNotRegistered,
+
+    #[error("Insufficient reward")]
+    InsufficientReward,
+
+    // The player account is not the PDA derived from the player holder
+    #[error("Invalid player account")]
+    InvalidPlayerAccount,
+
+    // The player still has reward to claim
+    #[error("Reward not claimed")]
+    RewardNotClaimed,
+
+    // The account does not hold enough lamports to be rent exempt
+    // Codes 12 and 13 were merged into SelfRecursiveUpline and RewardAmountOverflow, the codes from here on are kept
+    #[error("Account not rent exempt")]
+    NotRentExempt = 14,
+
+    // The player claimed too recently
+    #[error("Claim cooldown not elapsed")]
+    ClaimCooldown,
+
+    // The admin paused the game for maintenance
+    #[error("Game is paused")]
+    GamePaused,
+
+    // The program account is not the PDA derived from the game info seed
+    #[error("Invalid program account")]
+    InvalidProgramAccount,
+
+    // The player already has an upline, it can only be set once
+    #[error("Upline already set")]
+    UplineAlreadySet,
+
+    // The player would be deeper in the referral chain than the program account allows
+    #[error("Max referral depth exceeded")]
+    MaxDepthExceeded,
+
+    // The reward to claim is below the minimum claim of the program account
+    #[error("Reward below the minimum claim")]
+    BelowMinimumClaim,
+    // The player account already has the current layout
+    #[error("Player account already migrated")]
+    AlreadyMigrated,
+    // Crediting the reward would take the player above the max reward per player
+    #[error("Reward cap exceeded")]
+    RewardCapExceeded,
+    // The account is already one of the admins
+    #[error("Admin already added")]
+    AdminAlreadyAdded,
+    // Every admin slot of the program account is in use
+    #[error("Too many admins")]
+    TooManyAdmins,
+    // The program account must keep at least one admin
+    #[error("Cannot remove the last admin")]
+    LastAdmin,
+    // Only the relayer of the program account can register on behalf of another holder
+    #[error("Invalid relayer")]
+    InvalidRelayer,
+    // The admin froze the player, it cannot claim nor refer until unfrozen
+    #[error("Player frozen")]
+    PlayerFrozen,
+    // The upline levels of the reward split add up to more than the whole reward
+    #[error("Invalid reward split")]
+    InvalidRewardSplit,
+    // The same account is passed in two slots which must be different accounts
+    #[error("Duplicate account")]
+    DuplicateAccount,
+    // The instruction data does not start with the magic of this program version
+    #[error("Unsupported instruction version")]
+    UnsupportedVersion,
+    // The tier thresholds are not increasing, or a tier is enabled after a disabled one
+    #[error("Invalid tier thresholds")]
+    InvalidTierThresholds,
+    // The program token account holds less than the amount to pay
+    #[error("Vault underfunded")]
+    VaultUnderfunded,
+    // A claim or a reward transfer of nothing, rejected before any account is touched
+    #[error("Zero amount")]
+    ZeroAmount,
+    // The upline chain leads back to the player, setting it would close a cycle
+    #[error("Circular referral")]
+    CircularReferral,
+    // An account the instruction writes to was passed as a readonly meta
+    #[error("Account not writable")]
+    AccountNotWritable,
+    // The amount to claim or transfer is more than the part of the reward already vested
+    #[error("Reward still vesting")]
+    RewardStillVesting,
+    // A reward multiplier of 0 would credit nothing
+    #[error("Invalid reward multiplier")]
+    InvalidRewardMultiplier,
 }
 
 // Implement conversion for GameError to ProgramError
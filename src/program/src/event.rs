@@ -0,0 +1,42 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{log::sol_log_data, program_error::ProgramError, pubkey::Pubkey};
+
+// Events for off-chain indexers, logged through sol_log_data
+// Borsh serialize enum as 1 byte variant index followed by the fields, the index is the event discriminator
+// Every event starts with the nonce taken from GameInfo, so it is right after the discriminator whatever the event
+// Nonces of a program account increase by one per state-changing instruction, or per event for the instructions emitting
+// events, so the nonces of the events are strictly increasing and the program account nonce tells how many were taken
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, Eq, PartialEq)]
+pub enum GameEvent {
+    // discriminator = 0
+    Initialized {
+        nonce: u64,
+        admin: Pubkey,
+    },
+    // discriminator = 1
+    PlayerRegistered {
+        nonce: u64,
+        player: Pubkey,
+        upline: Option<Pubkey>,
+    },
+    // discriminator = 2
+    RewardClaimed {
+        nonce: u64,
+        player: Pubkey,
+        amount: u64,
+    },
+    // discriminator = 3, amount is what was actually slashed
+    RewardSlashed {
+        nonce: u64,
+        player: Pubkey,
+        amount: u64,
+    },
+}
+
+impl GameEvent {
+    pub fn emit(&self) -> Result<(), ProgramError> {
+        let data = self.try_to_vec()?;
+        sol_log_data(&[&data]);
+        Ok(())
+    }
+}
@@ -1,4 +1,14 @@
+use crate::constants::*;
+use crate::error::GameError;
 use solana_program::{msg, program_error::ProgramError, program_option::COption, pubkey::Pubkey};
+#[cfg(feature = "client")]
+use {
+    crate::processor::{player_pda, spl_memo, vault_pda},
+    solana_program::{
+        instruction::{AccountMeta, Instruction},
+        system_program, sysvar,
+    },
+};
 
 // #[derive(BorshDeserialize, Debug)]
 // pub struct Payload {
@@ -7,31 +17,66 @@ use solana_program::{msg, program_error::ProgramError, program_option::COption,
 //     pub value: String,
 // }
 
-#[derive(Debug)]
+// Max depth of an Init sent without the max depth byte, deep enough to never reject a register
+pub const DEFAULT_MAX_DEPTH: u8 = u8::MAX;
+
+// Reward split of an Init sent without the level bps, 25%, 15% and 10% to the three upline levels
+pub const DEFAULT_LEVEL_BPS: [u16; 3] = [2_500, 1_500, 1_000];
+
+// The instruction data is the INSTRUCTION_MAGIC byte, then the tag, then the data of the command
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Command {
     // Start initialize the program account
     // tag = 0
-    // 0 - [signer]   - The admin (holder) account
-    // 1 - [writable] - Program account
-    // 2 - [writable] - An token account created by the admin, and pre-funded
-    // 3 - []         - The token program
-    Init,
+    // 0 - [signer, writable] - The admin (holder) account, which pay for the program account rent
+    // 1 - [writable]         - Program account, PDA of the game info seed
+    // 2 - []                 - The system program
+    // 3 - [writable]         - An token account created by the admin, and pre-funded
+    // 4 - []                 - The mint of the token account
+    // 5 - []                 - The token program, SPL TOKEN or Token-2022, the game then uses it for every transfer
+    // The max depth byte follows the referral bonus, DEFAULT_MAX_DEPTH is used when it is missing
+    // then the min claim u64, the register fee u64, the max reward per player u64 and the decay bps per day u64
+    // 0 is used when they are missing
+    // The three level bps u16 can only follow all of them, DEFAULT_LEVEL_BPS is used when they are missing
+    Init {
+        referral_bonus: u64,
+        max_depth: u8,
+        min_claim: u64,
+        register_fee: u64,
+        max_reward_per_player: u64,
+        decay_bps_per_day: u64,
+        level_bps: [u16; 3],
+    },
 
-    // User register themselves to the program
+    // User register themselves to the program, or the relayer register a holder who does not sign
     // tag = 1
-    // 0 - [signer]   - The player (holder) account
-    // 1 - [writable] - The player account for the program
-    // 2 - []         - The program account
-    // 3 - []         - The upline player account for the program
-    Register,
+    // 0 - [signer, writable] - The player (holder) account or the relayer, which pay for the player account rent and the register fee
+    // 1 - [writable]         - The player account for the program, PDA of the holder
+    // 2 - [writable]         - The program account
+    // 3 - []                 - The system program
+    // 4 - []                 - The clock sysvar
+    // 5 - [writable]         - The upline player account for the program
+    // An optional flag byte follows the tag, when it is 1 an already registered player is not an error
+    // then the holder pubkey when the relayer registers on their behalf
+    Register {
+        idempotent: bool,
+        holder: Option<Pubkey>,
+    },
 
     // Admin add reward to player
     // tag = 2
     // 0 - [signer]   - The admin (holder) account
     // 1 - [writable] - Program account
     // 2 - [writable] - The player program account
-    // 3 - [writable] - The player upline program account
-    AddReward { reward_amount: u64 },
+    // 3 - [writable] - The player upline program account, if any
+    // 4 - [writable] - The second level upline program account, if any
+    // 5 - [writable] - The third level upline program account, if any
+    // An optional flag byte follows the amount, when it is 1 each share is cut down to the max reward per player
+    // instead of failing the whole instruction, the part cut off is kept as dust
+    AddReward {
+        reward_amount: u64,
+        saturate: bool,
+    },
 
     // Player claim reward
     // tag = 3
@@ -42,38 +87,510 @@ pub enum Command {
     // 4-  []         - The PDA, owner (in term of token, not account owner) of token account
     // 5 - []         - The player token account
     // 6 - []         - The token program
-    Claim,
+    // 7 - []         - The clock sysvar
+    // 8 - []         - Optional, the memo program, to attach a memo of the player and claimed amount
+    // An optional u64 amount follows the tag, the whole reward to claim is claimed when it is missing, a zero amount is rejected
+    Claim {
+        amount: Option<u64>,
+    },
+
+    // Player close their player account and reclaim the rent
+    // tag = 4
+    // 0 - [signer]   - The player (holder) account
+    // 1 - [writable] - The player program account
+    // 2 - [writable] - The account to receive the rent lamports
+    // 3 - [writable] - Program account, its number of players is decremented
+    // 4 - [writable] - The player upline program account, only when the player has one, its downline count is updated
+    // 5.. [writable] - Optional, the downline player program accounts, moved to the player upline
+    CloseAccount,
+
+    // Admin hand over their admin slot of the program account to a new admin
+    // tag = 5
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - []         - The new admin (holder) account
+    SetAdmin,
+
+    // Player transfer part of their reward to claim to another player
+    // tag = 6
+    // 0 - [signer]   - The source player (holder) account
+    // 1 - [writable] - The source player program account
+    // 2 - [writable] - The destination player program account
+    // 3 - [writable] - Program account, for the decay applied to both players
+    // The amount must be greater than 0
+    TransferReward {
+        amount: u64,
+    },
+
+    // Register several players under the same upline at once
+    // tag = 7
+    // 0 - [writable]         - The program account
+    // 1 - []                 - The system program
+    // 2 - []                 - The clock sysvar
+    // 3 - [writable]         - The upline player account for the program
+    // Then for each player to register:
+    // n     - [signer, writable] - The player (holder) account, which pay for the player account rent
+    // n + 1 - [writable]         - The player account for the program, PDA of the holder
+    BatchRegister,
+
+    // Admin pause or resume register and claim, e.g. during maintenance
+    // tag = 8
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    SetPaused {
+        paused: bool,
+    },
+
+    // Admin move the lamports of the program account above the rent exempt minimum out
+    // tag = 9
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - [writable] - The account to receive the fees
+    SweepFees,
+
+    // Admin overwrite the reward to claim of a player, e.g. to resolve a dispute
+    // tag = 10
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - [writable] - The player program account
+    AdminSetReward {
+        reward_to_claim: u64,
+    },
+
+    // Player registered without upline attribute a referrer, only once
+    // tag = 11
+    // 0 - [signer]   - The player (holder) account
+    // 1 - [writable] - The player program account
+    // 2 - [writable] - The program account
+    // 3 - [writable] - The upline player account for the program
+    // 4.. - []       - The player accounts above the upline, from its own upline up, walked to reject a circular referral
+    //                  All of them are required, up to the top of the chain or the max depth
+    SetUpline,
+
+    // Admin hand over the program token account to a new authority, claims cannot be paid from it afterward
+    // tag = 12
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - [writable] - The token account of the current program
+    // 3 - []         - The PDA, owner (in term of token, not account owner) of token account
+    // 4 - []         - The new owner of the token account
+    // 5 - []         - The token program
+    SetVaultAuthority,
+
+    // Move a player account of a previous layout to the current one, the new fields get their default
+    // tag = 13
+    // 0 - [signer, writable] - The player (holder) account, which pay for the rent of the bigger account
+    // 1 - [writable]         - The player program account
+    // 2 - []                 - The system program
+    // 3 - [writable]         - Program account
+    // 4 - []                 - The player upline program account, only when the player has one and predates the depth
+    MigratePlayer,
+
+    // Admin add another admin to a free admin slot of the program account
+    // tag = 14
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - []         - The new admin (holder) account
+    AddAdmin,
+
+    // Admin remove an admin, possibly themselves, the last admin cannot be removed
+    // tag = 15
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - []         - The removed admin (holder) account
+    RemoveAdmin,
+
+    // Admin slash the reward to claim of a player as a penalty, by up to the amount
+    // tag = 16
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - [writable] - The player program account
+    SlashReward {
+        amount: u64,
+    },
+
+    // Read only query of the reward to claim of a player, for the programs calling through CPI
+    // The reward to claim is set as the return data, as a u64 little endian
+    // tag = 17
+    // 0 - [] - The player program account
+    GetReward,
+
+    // Admin add to the matching pool, which matches the referral bonuses until it runs out
+    // tag = 18
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    FundPool {
+        amount: u64,
+    },
+
+    // Admin set the relayer allowed to register players on behalf of their holder
+    // Passing the system program, which is the default pubkey, removes the relayer
+    // tag = 19
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - []         - The relayer account
+    SetRelayer,
+
+    // Admin freeze or unfreeze a player, a frozen player can neither claim nor be taken as upline
+    // tag = 20
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - [writable] - The player program account
+    FreezePlayer {
+        frozen: bool,
+    },
+
+    // Read only export of the whole player account, for the backups rebuilding the state from the transactions
+    // The player address and the packed player account are logged through sol_log_data, as two fields
+    // tag = 21
+    // 0 - [] - The player program account
+    ExportPlayer,
+
+    // Admin transfer the dust, the part of the rewards rounded off by the split, out of the program token account
+    // tag = 22
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - [writable] - The token account of the current program
+    // 3 - []         - The PDA, owner (in term of token, not account owner) of token account
+    // 4 - [writable] - The token account to receive the dust
+    // 5 - []         - The token program
+    SweepDust,
+
+    // Admin claim the whole reward to claim of several players at once, e.g. for an airdrop
+    // The minimum claim and the cooldown are not enforced, the decay and the freeze are
+    // tag = 23
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - [writable] - The token account of the current program
+    // 3 - []         - The PDA, owner (in term of token, not account owner) of token account
+    // 4 - []         - The token program
+    // 5 - []         - The clock sysvar
+    // Then for each player to claim for:
+    // n     - [writable] - The player program account
+    // n + 1 - [writable] - The token account to receive the reward, owned by the player holder
+    AdminClaimBatch,
+
+    // Admin set the lifetime total_earned needed for the bronze, silver and gold tiers, 0 disables a tier
+    // The tier of a player is recomputed on each credit
+    // tag = 24
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    SetTierThresholds {
+        tier_thresholds: [u64; 3],
+    },
+
+    // Admin change the referral bonus credited to the upline on each register, the bonuses already credited stay
+    // tag = 25
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    SetReferralBonus {
+        referral_bonus: u64,
+    },
+
+    // Admin change the seconds over which the rewards credited from now on unlock, 0 for instantly claimable rewards
+    // tag = 26
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    SetVestDuration {
+        vest_duration: u64,
+    },
+
+    // Admin change the multiplier of the rewards and referral bonuses credited from now on, in basis points
+    // 10000 credits them as is, 20000 doubles them, 0 is rejected
+    // tag = 27
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    SetRewardMultiplier {
+        reward_multiplier_bps: u16,
+    },
 }
 
 impl Command {
     // Self = Type of the current object, which is Command enum
     // &self = short form of self: &Self
     // See https://stackoverflow.com/questions/32304595/whats-the-difference-between-self-and-self
-    // @todo
     pub fn pack(&self) -> Vec<u8> {
-        return vec![0];
+        let mut buf = Vec::with_capacity(10);
+        buf.push(INSTRUCTION_MAGIC);
+        match self {
+            Self::Init {
+                referral_bonus,
+                max_depth,
+                min_claim,
+                register_fee,
+                max_reward_per_player,
+                decay_bps_per_day,
+                level_bps,
+            } => {
+                buf.push(INIT_TAG);
+                buf.extend_from_slice(&referral_bonus.to_le_bytes());
+                buf.push(*max_depth);
+                buf.extend_from_slice(&min_claim.to_le_bytes());
+                buf.extend_from_slice(&register_fee.to_le_bytes());
+                buf.extend_from_slice(&max_reward_per_player.to_le_bytes());
+                buf.extend_from_slice(&decay_bps_per_day.to_le_bytes());
+                for bps in level_bps {
+                    buf.extend_from_slice(&bps.to_le_bytes());
+                }
+            }
+            Self::Register { idempotent, holder } => {
+                buf.push(REGISTER_TAG);
+                // Strict register keeps the single byte data used before the flag was added
+                if *idempotent || holder.is_some() {
+                    buf.push(*idempotent as u8);
+                }
+                if let Some(holder) = holder {
+                    buf.extend_from_slice(holder.as_ref());
+                }
+            }
+            Self::AddReward {
+                reward_amount,
+                saturate,
+            } => {
+                buf.push(ADD_REWARD_TAG);
+                buf.extend_from_slice(&reward_amount.to_le_bytes());
+                // Erroring add reward keeps the amount only data used before the flag was added
+                if *saturate {
+                    buf.push(1);
+                }
+            }
+            Self::Claim { amount } => {
+                buf.push(CLAIM_TAG);
+                // Full claim keeps the single byte data used before the amount was added
+                if let Some(amount) = amount {
+                    buf.extend_from_slice(&amount.to_le_bytes());
+                }
+            }
+            Self::CloseAccount => buf.push(CLOSE_ACCOUNT_TAG),
+            Self::SetAdmin => buf.push(SET_ADMIN_TAG),
+            Self::TransferReward { amount } => {
+                buf.push(TRANSFER_REWARD_TAG);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::BatchRegister => buf.push(BATCH_REGISTER_TAG),
+            Self::SetPaused { paused } => {
+                buf.push(SET_PAUSED_TAG);
+                buf.push(*paused as u8);
+            }
+            Self::SweepFees => buf.push(SWEEP_FEES_TAG),
+            Self::AdminSetReward { reward_to_claim } => {
+                buf.push(ADMIN_SET_REWARD_TAG);
+                buf.extend_from_slice(&reward_to_claim.to_le_bytes());
+            }
+            Self::SetUpline => buf.push(SET_UPLINE_TAG),
+            Self::SetVaultAuthority => buf.push(SET_VAULT_AUTHORITY_TAG),
+            Self::MigratePlayer => buf.push(MIGRATE_PLAYER_TAG),
+            Self::AddAdmin => buf.push(ADD_ADMIN_TAG),
+            Self::RemoveAdmin => buf.push(REMOVE_ADMIN_TAG),
+            Self::SlashReward { amount } => {
+                buf.push(SLASH_REWARD_TAG);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::GetReward => buf.push(GET_REWARD_TAG),
+            Self::FundPool { amount } => {
+                buf.push(FUND_POOL_TAG);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::SetRelayer => buf.push(SET_RELAYER_TAG),
+            Self::FreezePlayer { frozen } => {
+                buf.push(FREEZE_PLAYER_TAG);
+                buf.push(*frozen as u8);
+            }
+            Self::ExportPlayer => buf.push(EXPORT_PLAYER_TAG),
+            Self::SweepDust => buf.push(SWEEP_DUST_TAG),
+            Self::AdminClaimBatch => buf.push(ADMIN_CLAIM_BATCH_TAG),
+            Self::SetVestDuration { vest_duration } => {
+                buf.push(SET_VEST_DURATION_TAG);
+                buf.extend_from_slice(&vest_duration.to_le_bytes());
+            }
+            Self::SetRewardMultiplier {
+                reward_multiplier_bps,
+            } => {
+                buf.push(SET_REWARD_MULTIPLIER_TAG);
+                buf.extend_from_slice(&reward_multiplier_bps.to_le_bytes());
+            }
+            Self::SetReferralBonus { referral_bonus } => {
+                buf.push(SET_REFERRAL_BONUS_TAG);
+                buf.extend_from_slice(&referral_bonus.to_le_bytes());
+            }
+            Self::SetTierThresholds { tier_thresholds } => {
+                buf.push(SET_TIER_THRESHOLDS_TAG);
+                for threshold in tier_thresholds {
+                    buf.extend_from_slice(&threshold.to_le_bytes());
+                }
+            }
+        }
+        buf
     }
 
     pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
-        // Get the first byte, which = command user wish to execute
+        // The magic comes first, the layout after it is only known for the current version
+        let (magic, input) = input
+            .split_first()
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        if *magic != INSTRUCTION_MAGIC {
+            msg!("Unsupported instruction magic {}", magic);
+            return Err(GameError::UnsupportedVersion.into());
+        }
+        // Get the next byte, which = command user wish to execute
         let (tag, rest) = input
             .split_first()
             .ok_or(ProgramError::InvalidInstructionData)?;
         msg!("Instruction tag {}", tag);
-        Ok(match tag {
-            0 => Self::Init, // use statement instead of return, which terminate the function. The Self::Init will be passed into Ok enum return return by unpack function
-            1 => Self::Register,
-            2 => {
-                // Get 8 bytes (u64)
-                // Borsh serialization is little endian, therefore use from_le_bytes
-                let reward_amount = u64::from_le_bytes(rest[0..8].try_into().unwrap());
-                Self::AddReward { reward_amount }
-            }
-            3 => Self::Claim,
+        Ok(match *tag {
+            // use statement instead of return, which terminate the function. The Self::Init will be passed into Ok enum return return by unpack function
+            INIT_TAG => {
+                let referral_bonus = Self::unpack_amount(rest)?;
+                // Older clients stop after the referral bonus, the max depth, the min claim, the register fee
+                // or the max reward per player
+                let (max_depth, amounts, level_bps) = match rest.get(8..) {
+                    Option::Some([]) => (DEFAULT_MAX_DEPTH, [0; 4], DEFAULT_LEVEL_BPS),
+                    Option::Some([max_depth, fields @ ..]) => {
+                        let (amounts, levels) = match fields.len() {
+                            len if len % 8 == 0 && len <= 32 => (fields, Option::None),
+                            len if len == 32 + 2 * 3 => {
+                                (&fields[..32], Option::Some(&fields[32..]))
+                            }
+                            _ => return Err(ProgramError::InvalidInstructionData),
+                        };
+                        // Each missing amount is 0
+                        let mut unpacked = [0; 4];
+                        for (amount, chunk) in unpacked.iter_mut().zip(amounts.chunks(8)) {
+                            *amount = Self::unpack_amount(chunk)?;
+                        }
+                        let mut level_bps = DEFAULT_LEVEL_BPS;
+                        if let Option::Some(levels) = levels {
+                            for (bps, chunk) in level_bps.iter_mut().zip(levels.chunks(2)) {
+                                *bps = u16::from_le_bytes([chunk[0], chunk[1]]);
+                            }
+                        }
+                        (*max_depth, unpacked, level_bps)
+                    }
+                    _ => return Err(ProgramError::InvalidInstructionData),
+                };
+                let [min_claim, register_fee, max_reward_per_player, decay_bps_per_day] = amounts;
+                Self::Init {
+                    referral_bonus,
+                    max_depth,
+                    min_claim,
+                    register_fee,
+                    max_reward_per_player,
+                    decay_bps_per_day,
+                    level_bps,
+                }
+            }
+            REGISTER_TAG => {
+                let (flag, holder) = match rest {
+                    [] => (0, Option::None),
+                    [flag] => (*flag, Option::None),
+                    [flag, holder @ ..] if holder.len() == 32 => {
+                        (*flag, Option::Some(Pubkey::new(holder)))
+                    }
+                    _ => return Err(ProgramError::InvalidInstructionData),
+                };
+                Self::Register {
+                    idempotent: match flag {
+                        0 => false,
+                        1 => true,
+                        _ => return Err(ProgramError::InvalidInstructionData),
+                    },
+                    holder,
+                }
+            }
+            ADD_REWARD_TAG => Self::AddReward {
+                reward_amount: Self::unpack_amount(rest)?,
+                saturate: match rest.get(8..) {
+                    Option::Some([]) => false,
+                    Option::Some([0]) => false,
+                    Option::Some([1]) => true,
+                    _ => return Err(ProgramError::InvalidInstructionData),
+                },
+            },
+            CLAIM_TAG => Self::Claim {
+                amount: match rest {
+                    [] => Option::None,
+                    _ if rest.len() == 8 => Option::Some(Self::unpack_amount(rest)?),
+                    _ => return Err(ProgramError::InvalidInstructionData),
+                },
+            },
+            CLOSE_ACCOUNT_TAG => Self::CloseAccount,
+            SET_ADMIN_TAG => Self::SetAdmin,
+            TRANSFER_REWARD_TAG => Self::TransferReward {
+                amount: Self::unpack_amount(rest)?,
+            },
+            BATCH_REGISTER_TAG => Self::BatchRegister,
+            SET_PAUSED_TAG => Self::SetPaused {
+                paused: match rest.first() {
+                    Option::Some(0) => false,
+                    Option::Some(1) => true,
+                    _ => return Err(ProgramError::InvalidInstructionData),
+                },
+            },
+            SWEEP_FEES_TAG => Self::SweepFees,
+            ADMIN_SET_REWARD_TAG => Self::AdminSetReward {
+                reward_to_claim: Self::unpack_amount(rest)?,
+            },
+            SET_UPLINE_TAG => Self::SetUpline,
+            SET_VAULT_AUTHORITY_TAG => Self::SetVaultAuthority,
+            MIGRATE_PLAYER_TAG => Self::MigratePlayer,
+            ADD_ADMIN_TAG => Self::AddAdmin,
+            REMOVE_ADMIN_TAG => Self::RemoveAdmin,
+            SLASH_REWARD_TAG => Self::SlashReward {
+                amount: Self::unpack_amount(rest)?,
+            },
+            GET_REWARD_TAG => Self::GetReward,
+            FUND_POOL_TAG => Self::FundPool {
+                amount: Self::unpack_amount(rest)?,
+            },
+            SET_RELAYER_TAG => Self::SetRelayer,
+            FREEZE_PLAYER_TAG => Self::FreezePlayer {
+                frozen: match rest.first() {
+                    Option::Some(0) => false,
+                    Option::Some(1) => true,
+                    _ => return Err(ProgramError::InvalidInstructionData),
+                },
+            },
+            EXPORT_PLAYER_TAG => Self::ExportPlayer,
+            SWEEP_DUST_TAG => Self::SweepDust,
+            ADMIN_CLAIM_BATCH_TAG => Self::AdminClaimBatch,
+            SET_REFERRAL_BONUS_TAG => Self::SetReferralBonus {
+                referral_bonus: Self::unpack_amount(rest)?,
+            },
+            SET_VEST_DURATION_TAG => Self::SetVestDuration {
+                vest_duration: Self::unpack_amount(rest)?,
+            },
+            SET_REWARD_MULTIPLIER_TAG => Self::SetRewardMultiplier {
+                reward_multiplier_bps: rest
+                    .get(..2)
+                    .and_then(|slice| slice.try_into().ok())
+                    .map(u16::from_le_bytes)
+                    .ok_or(ProgramError::InvalidInstructionData)?,
+            },
+            SET_TIER_THRESHOLDS_TAG => {
+                // All three thresholds are always sent
+                if rest.len() != 8 * 3 {
+                    return Err(ProgramError::InvalidInstructionData);
+                }
+                let mut tier_thresholds = [0; 3];
+                for (threshold, chunk) in tier_thresholds.iter_mut().zip(rest.chunks(8)) {
+                    *threshold = Self::unpack_amount(chunk)?;
+                }
+                Self::SetTierThresholds { tier_thresholds }
+            }
             _ => return Err(ProgramError::InvalidInstructionData), // early return the unpack function with Err, instead of returning the Err as argument for Ok
         })
     }
 
+    // Get 8 bytes (u64) at the starting of the byte buffer
+    // Borsh serialization is little endian, therefore use from_le_bytes
+    fn unpack_amount(input: &[u8]) -> Result<u64, ProgramError> {
+        input
+            .get(..8)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(ProgramError::InvalidInstructionData)
+    }
+
     // Assume the received byte buffer, the starting of it will be Option<Pubkey>
     // When Option being serialized, 1st byte will indicate it is Option::None or Option::Some
     // The following 32 bytes will be the Pubkey
@@ -85,6 +602,9 @@ impl Command {
             // 1st byte is 1
             Option::Some((&1, rest)) => {
                 // Take 32 bytes after the indication byte, the following byte after the public key will shadow the rest variable in argument
+                if rest.len() < 32 {
+                    return Err(ProgramError::InvalidInstructionData);
+                }
                 let (pub_key, rest) = rest.split_at(32);
                 // Create PubKey instance
                 let pk = Pubkey::new(pub_key);
@@ -94,3 +614,680 @@ impl Command {
         }
     }
 }
+
+// Instruction builders for the client, accounts are ordered as documented on each Command
+// They pass the classic SPL token program, see with_token_program for a game set up with Token-2022
+#[cfg(feature = "client")]
+#[allow(clippy::too_many_arguments)]
+pub fn init(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    program_account: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    referral_bonus: u64,
+    max_depth: u8,
+    min_claim: u64,
+    register_fee: u64,
+    max_reward_per_player: u64,
+    decay_bps_per_day: u64,
+    level_bps: [u16; 3],
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*admin, true),
+            AccountMeta::new(*program_account, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: Command::Init {
+            referral_bonus,
+            max_depth,
+            min_claim,
+            register_fee,
+            max_reward_per_player,
+            decay_bps_per_day,
+            level_bps,
+        }
+        .pack(),
+    }
+}
+
+// Swap the classic SPL token program passed by a builder for the token program of the game
+#[cfg(feature = "client")]
+pub fn with_token_program(mut instruction: Instruction, token_program: &Pubkey) -> Instruction {
+    for account in instruction.accounts.iter_mut() {
+        if spl_token::check_id(&account.pubkey) {
+            account.pubkey = *token_program;
+        }
+    }
+    instruction
+}
+
+#[cfg(feature = "client")]
+pub fn register(
+    program_id: &Pubkey,
+    holder: &Pubkey,
+    program_account: &Pubkey,
+    upline: Option<&Pubkey>,
+    idempotent: bool,
+) -> Instruction {
+    let (player, _) = player_pda(program_id, holder);
+    let mut accounts = vec![
+        AccountMeta::new(*holder, true),
+        AccountMeta::new(player, false),
+        AccountMeta::new(*program_account, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+    ];
+    if let Some(upline) = upline {
+        accounts.push(AccountMeta::new(*upline, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: Command::Register {
+            idempotent,
+            holder: Option::None,
+        }
+        .pack(),
+    }
+}
+
+// Register the holder by the relayer, which signs and pays instead of the holder
+#[cfg(feature = "client")]
+pub fn relay_register(
+    program_id: &Pubkey,
+    relayer: &Pubkey,
+    holder: &Pubkey,
+    program_account: &Pubkey,
+    upline: Option<&Pubkey>,
+    idempotent: bool,
+) -> Instruction {
+    let (player, _) = player_pda(program_id, holder);
+    let mut accounts = vec![
+        AccountMeta::new(*relayer, true),
+        AccountMeta::new(player, false),
+        AccountMeta::new(*program_account, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+    ];
+    if let Some(upline) = upline {
+        accounts.push(AccountMeta::new(*upline, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: Command::Register {
+            idempotent,
+            holder: Option::Some(*holder),
+        }
+        .pack(),
+    }
+}
+
+// Uplines are ordered from the direct upline
+#[cfg(feature = "client")]
+pub fn add_reward(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    program_account: &Pubkey,
+    player: &Pubkey,
+    uplines: &[Pubkey],
+    reward_amount: u64,
+    saturate: bool,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(*program_account, false),
+        AccountMeta::new(*player, false),
+    ];
+    for upline in uplines {
+        accounts.push(AccountMeta::new(*upline, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: Command::AddReward {
+            reward_amount,
+            saturate,
+        }
+        .pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn admin_set_reward(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    program_account: &Pubkey,
+    player: &Pubkey,
+    reward_to_claim: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*program_account, false),
+            AccountMeta::new(*player, false),
+        ],
+        data: Command::AdminSetReward { reward_to_claim }.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn slash_reward(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    program_account: &Pubkey,
+    player: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*program_account, false),
+            AccountMeta::new(*player, false),
+        ],
+        data: Command::SlashReward { amount }.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn set_relayer(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    program_account: &Pubkey,
+    relayer: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*program_account, false),
+            AccountMeta::new_readonly(*relayer, false),
+        ],
+        data: Command::SetRelayer.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn freeze_player(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    program_account: &Pubkey,
+    player: &Pubkey,
+    frozen: bool,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*program_account, false),
+            AccountMeta::new(*player, false),
+        ],
+        data: Command::FreezePlayer { frozen }.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn fund_pool(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    program_account: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*program_account, false),
+        ],
+        data: Command::FundPool { amount }.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn get_reward(program_id: &Pubkey, player: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![AccountMeta::new_readonly(*player, false)],
+        data: Command::GetReward.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn export_player(program_id: &Pubkey, player: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![AccountMeta::new_readonly(*player, false)],
+        data: Command::ExportPlayer.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn claim(
+    program_id: &Pubkey,
+    holder: &Pubkey,
+    program_account: &Pubkey,
+    token_account: &Pubkey,
+    player_token_account: &Pubkey,
+    amount: Option<u64>,
+) -> Instruction {
+    let (player, _) = player_pda(program_id, holder);
+    let (pda, _) = vault_pda(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*holder, true),
+            AccountMeta::new(*program_account, false),
+            AccountMeta::new(player, false),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(pda, false),
+            AccountMeta::new(*player_token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ],
+        data: Command::Claim { amount }.pack(),
+    }
+}
+
+// Claim and attach a memo of the player and claimed amount, for off-chain reconciliation
+#[cfg(feature = "client")]
+pub fn claim_with_memo(
+    program_id: &Pubkey,
+    holder: &Pubkey,
+    program_account: &Pubkey,
+    token_account: &Pubkey,
+    player_token_account: &Pubkey,
+    amount: Option<u64>,
+) -> Instruction {
+    let mut instruction = claim(
+        program_id,
+        holder,
+        program_account,
+        token_account,
+        player_token_account,
+        amount,
+    );
+    instruction
+        .accounts
+        .push(AccountMeta::new_readonly(spl_memo::id(), false));
+    instruction
+}
+
+#[cfg(feature = "client")]
+pub fn close_account(
+    program_id: &Pubkey,
+    holder: &Pubkey,
+    program_account: &Pubkey,
+    destination: &Pubkey,
+) -> Instruction {
+    close_account_with_downlines(program_id, holder, program_account, destination, None, &[])
+}
+
+// Close the player account and move all its downlines to the player upline, which comes first when the player has one
+#[cfg(feature = "client")]
+pub fn close_account_with_downlines(
+    program_id: &Pubkey,
+    holder: &Pubkey,
+    program_account: &Pubkey,
+    destination: &Pubkey,
+    upline: Option<&Pubkey>,
+    downlines: &[Pubkey],
+) -> Instruction {
+    let (player, _) = player_pda(program_id, holder);
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*holder, true),
+        AccountMeta::new(player, false),
+        AccountMeta::new(*destination, false),
+        AccountMeta::new(*program_account, false),
+    ];
+    if let Some(upline) = upline {
+        accounts.push(AccountMeta::new(*upline, false));
+    }
+    for downline in downlines {
+        accounts.push(AccountMeta::new(*downline, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: Command::CloseAccount.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn set_admin(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    program_account: &Pubkey,
+    new_admin: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*program_account, false),
+            AccountMeta::new_readonly(*new_admin, false),
+        ],
+        data: Command::SetAdmin.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn add_admin(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    program_account: &Pubkey,
+    new_admin: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*program_account, false),
+            AccountMeta::new_readonly(*new_admin, false),
+        ],
+        data: Command::AddAdmin.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn remove_admin(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    program_account: &Pubkey,
+    removed_admin: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*program_account, false),
+            AccountMeta::new_readonly(*removed_admin, false),
+        ],
+        data: Command::RemoveAdmin.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn set_paused(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    program_account: &Pubkey,
+    paused: bool,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*program_account, false),
+        ],
+        data: Command::SetPaused { paused }.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn sweep_fees(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    program_account: &Pubkey,
+    destination: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*program_account, false),
+            AccountMeta::new(*destination, false),
+        ],
+        data: Command::SweepFees.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn set_vault_authority(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    program_account: &Pubkey,
+    token_account: &Pubkey,
+    new_authority: &Pubkey,
+) -> Instruction {
+    let (pda, _) = vault_pda(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*program_account, false),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(pda, false),
+            AccountMeta::new_readonly(*new_authority, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: Command::SetVaultAuthority.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn sweep_dust(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    program_account: &Pubkey,
+    token_account: &Pubkey,
+    destination: &Pubkey,
+) -> Instruction {
+    let (pda, _) = vault_pda(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*program_account, false),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(pda, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: Command::SweepDust.pack(),
+    }
+}
+
+// Players are given as (player program account, destination token account) pairs
+#[cfg(feature = "client")]
+pub fn admin_claim_batch(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    program_account: &Pubkey,
+    token_account: &Pubkey,
+    players: &[(Pubkey, Pubkey)],
+) -> Instruction {
+    let (pda, _) = vault_pda(program_id);
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(*program_account, false),
+        AccountMeta::new(*token_account, false),
+        AccountMeta::new_readonly(pda, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+    ];
+    for (player, destination) in players {
+        accounts.push(AccountMeta::new(*player, false));
+        accounts.push(AccountMeta::new(*destination, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: Command::AdminClaimBatch.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn set_tier_thresholds(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    program_account: &Pubkey,
+    tier_thresholds: [u64; 3],
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*program_account, false),
+        ],
+        data: Command::SetTierThresholds { tier_thresholds }.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn set_referral_bonus(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    program_account: &Pubkey,
+    referral_bonus: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*program_account, false),
+        ],
+        data: Command::SetReferralBonus { referral_bonus }.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn set_vest_duration(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    program_account: &Pubkey,
+    vest_duration: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*program_account, false),
+        ],
+        data: Command::SetVestDuration { vest_duration }.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn set_reward_multiplier(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    program_account: &Pubkey,
+    reward_multiplier_bps: u16,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*program_account, false),
+        ],
+        data: Command::SetRewardMultiplier {
+            reward_multiplier_bps,
+        }
+        .pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn transfer_reward(
+    program_id: &Pubkey,
+    holder: &Pubkey,
+    program_account: &Pubkey,
+    destination_player: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let (player, _) = player_pda(program_id, holder);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*holder, true),
+            AccountMeta::new(player, false),
+            AccountMeta::new(*destination_player, false),
+            AccountMeta::new(*program_account, false),
+        ],
+        data: Command::TransferReward { amount }.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn batch_register(
+    program_id: &Pubkey,
+    program_account: &Pubkey,
+    upline: &Pubkey,
+    holders: &[Pubkey],
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*program_account, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new(*upline, false),
+    ];
+    for holder in holders {
+        let (player, _) = player_pda(program_id, holder);
+        accounts.push(AccountMeta::new(*holder, true));
+        accounts.push(AccountMeta::new(player, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: Command::BatchRegister.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn set_upline(
+    program_id: &Pubkey,
+    holder: &Pubkey,
+    program_account: &Pubkey,
+    upline: &Pubkey,
+    ancestors: &[Pubkey],
+) -> Instruction {
+    let (player, _) = player_pda(program_id, holder);
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*holder, true),
+        AccountMeta::new(player, false),
+        AccountMeta::new(*program_account, false),
+        AccountMeta::new(*upline, false),
+    ];
+    accounts.extend(
+        ancestors
+            .iter()
+            .map(|ancestor| AccountMeta::new_readonly(*ancestor, false)),
+    );
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: Command::SetUpline.pack(),
+    }
+}
+
+#[cfg(feature = "client")]
+pub fn migrate_player(
+    program_id: &Pubkey,
+    holder: &Pubkey,
+    program_account: &Pubkey,
+    upline: Option<&Pubkey>,
+) -> Instruction {
+    let (player, _) = player_pda(program_id, holder);
+    let mut accounts = vec![
+        AccountMeta::new(*holder, true),
+        AccountMeta::new(player, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(*program_account, false),
+    ];
+    if let Some(upline) = upline {
+        accounts.push(AccountMeta::new_readonly(*upline, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: Command::MigratePlayer.pack(),
+    }
+}
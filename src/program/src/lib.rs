@@ -1,4 +1,10 @@
+pub mod accounts;
+// Off-chain helpers, switched off by default to keep them out of the BPF build
+#[cfg(feature = "client")]
+pub mod client;
+pub mod constants;
 pub mod error;
+pub mod event;
 pub mod instruction;
 pub mod processor;
 pub mod state;
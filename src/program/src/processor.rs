@@ -1,194 +1,2148 @@
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
+    clock::Clock,
     entrypoint::ProgramResult,
+    instruction::Instruction,
+    log::{sol_log_compute_units, sol_log_data},
     msg,
-    program::{invoke, invoke_signed},
+    program::{invoke, invoke_signed, set_return_data},
     program_error::ProgramError,
     program_option::COption,
     program_pack::Pack,
     pubkey::Pubkey,
+    rent::Rent,
+    system_instruction, system_program,
+    sysvar::Sysvar,
 };
-pub struct Processor;
 
 // Import command module, for parsing instruction_data
 use crate::instruction::Command;
+// Import the named accounts of each instruction
+use crate::accounts::{
+    AddAdminAccounts, AddRewardAccounts, AdminClaimBatchAccounts, AdminSetRewardAccounts,
+    BatchRegisterAccounts, ClaimAccounts, CloseAccountAccounts, ExportPlayerAccounts,
+    FreezePlayerAccounts, FundPoolAccounts, GetRewardAccounts, InitAccounts, MigratePlayerAccounts,
+    RegisterAccounts, RemoveAdminAccounts, SetAdminAccounts, SetPausedAccounts,
+    SetReferralBonusAccounts, SetRelayerAccounts, SetRewardMultiplierAccounts,
+    SetTierThresholdsAccounts, SetUplineAccounts, SetVaultAuthorityAccounts,
+    SetVestDurationAccounts, SlashRewardAccounts, SweepDustAccounts, SweepFeesAccounts,
+    TransferRewardAccounts,
+};
 // Import state module
+use crate::constants::{GAME_INFO_SEED, MAX_ADMINS, PDA_SEED, PLAYER_SEED};
 use crate::error::GameError;
-use crate::state::{GameInfo, Player};
+use crate::event::GameEvent;
+use crate::state::{
+    GameInfo, Player, Vesting, BPS_DENOMINATOR, CURRENT_VERSION, LEGACY_PLAYER_LENS,
+    PRE_DEPTH_PLAYER_LEN, PRE_REFERRAL_CODE_PLAYER_LEN,
+};
+
+// Minimum number of seconds between two claims of the same player
+pub const COOLDOWN_SECS: i64 = 24 * 60 * 60;
+
+// The SPL Memo program, claim can record a memo with it
+pub mod spl_memo {
+    solana_program::declare_id!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+}
+
+// The SPL Token-2022 program, a game can be set up with it instead of the classic SPL token program
+pub mod spl_token_2022 {
+    solana_program::declare_id!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+}
+
+// Either token program a game can be initialized with
+fn is_token_program(key: &Pubkey) -> bool {
+    spl_token::check_id(key) || spl_token_2022::check_id(key)
+}
+
+// Compute bps / BPS_DENOMINATOR of the amount, widened to u128 so the multiplication cannot wrap
+// The bps is at most BPS_DENOMINATOR, so the share always fits back in a u64
+fn bps_of(amount: u64, bps: u16) -> u64 {
+    (amount as u128 * bps as u128 / BPS_DENOMINATOR) as u64
+}
+
+// Split the amount into one share per upline level, followed by the share of the player which gets the remaining bps
+// A level never takes more than what is left of BPS_DENOMINATOR, and every share is rounded down,
+// so the shares never add up to more than the amount, the rounded off part is the dust
+pub fn distribute(amount: u64, level_bps: &[u16]) -> Vec<u64> {
+    let mut remaining_bps = BPS_DENOMINATOR as u16;
+    let mut shares: Vec<u64> = level_bps
+        .iter()
+        .map(|&bps| {
+            let bps = bps.min(remaining_bps);
+            remaining_bps -= bps;
+            bps_of(amount, bps)
+        })
+        .collect();
+    shares.push(bps_of(amount, remaining_bps));
+    shares
+}
+
+// Credit a share of an added reward or a referral bonus to the player, cut down to the reward cap when saturate is set
+// Returns the part of the share credited, which is also the part vesting and counted toward the tier
+fn credit_share(
+    player: &mut Player,
+    share: u64,
+    game_info: &GameInfo,
+    now: i64,
+    saturate: bool,
+) -> Result<u64, GameError> {
+    let credited = if saturate {
+        player.credit_reward_saturating(share, game_info.max_reward_per_player, now)?
+    } else {
+        player.credit_reward(share, game_info.max_reward_per_player, now)?;
+        share
+    };
+    player.vest(credited, game_info.vest_duration, now)?;
+    player.update_tier(&game_info.tier_thresholds);
+    Ok(credited)
+}
+
+// Checks of a register against the game info, the signer is the holder itself or the relayer registering on its behalf
+// Shared with the client dry run of the register, so the two cannot drift
+pub fn check_register(
+    game_info: &GameInfo,
+    signer: &Pubkey,
+    holder: &Pubkey,
+) -> Result<(), GameError> {
+    // Players can only register once the admin initialized the program account
+    if !game_info.is_initialized {
+        return Err(GameError::NotInitialized);
+    }
+    if game_info.paused {
+        return Err(GameError::GamePaused);
+    }
+    // Registering on behalf of another holder is reserved to the relayer
+    if *holder != *signer && !game_info.is_relayer(signer) {
+        return Err(GameError::InvalidRelayer);
+    }
+    Ok(())
+}
+
+// Put the registering player of the holder under the upline, and credit the upline its referral bonus
+// The game info and the upline are left to pack, returns the bonus credited and the bonus before the reward cap
+// Shared with the client dry run of the register, so the two cannot drift
+pub fn register_under_upline(
+    player: &mut Player,
+    upline: &mut Player,
+    holder: &Pubkey,
+    game_info: &mut GameInfo,
+    now: i64,
+) -> Result<(u64, u64), ProgramError> {
+    if !upline.is_initialized {
+        return Err(GameError::UplineNotRegistered.into());
+    }
+    if upline.frozen {
+        return Err(GameError::PlayerFrozen.into());
+    }
+    // The upline cannot be held by the same holder
+    if upline.owner == *holder {
+        return Err(GameError::SelfRecursiveUpline.into());
+    }
+
+    // Bound the referral chain, every level is walked when rewarding
+    player.depth = upline.depth.saturating_add(1);
+    if player.depth > game_info.max_depth {
+        return Err(GameError::MaxDepthExceeded.into());
+    }
+
+    upline.downline_count = upline
+        .downline_count
+        .checked_add(1)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    let (referral_bonus, matched_amount) = game_info.draw_referral_bonus()?;
+    let referral_bonus = game_info.multiply_reward(referral_bonus)?;
+    // The bonus always saturates, an upline at the reward cap must not keep new players from registering under it
+    let credited_bonus = credit_share(upline, referral_bonus, game_info, now, true)?;
+    // The pool only pays for the bonus credited, the capped part of the match stays in it
+    game_info.refund_matching_pool(matched_amount, referral_bonus - credited_bonus)?;
+    Ok((credited_bonus, referral_bonus))
+}
+
+// Each holder can only have one player account, which is derived from the holder public key
+pub fn player_pda(program_id: &Pubkey, holder: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PLAYER_SEED.as_bytes(), holder.as_ref()], program_id)
+}
+
+// The program account lives at a well-known address, so clients can always find it
+pub fn game_info_pda(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[GAME_INFO_SEED.as_bytes()], program_id)
+}
+
+// The PDA owning the program token account, which sign the reward transfer on claim
+pub fn vault_pda(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PDA_SEED.as_bytes()], program_id)
+}
+
+// The admin instructions are only for a signing admin of the program account
+pub fn require_admin(account: &AccountInfo, game_info: &GameInfo) -> ProgramResult {
+    if !account.is_signer || !game_info.is_admin(account.key) {
+        msg!("Instruction only can be executed by a signing admin");
+        return Err(GameError::InvalidAdmin.into());
+    }
+    Ok(())
+}
+
+// Token-2022 accounts start with the layout of the classic program, followed by their extensions if any
+fn unpack_token_account(
+    token_account: &AccountInfo,
+) -> Result<spl_token::state::Account, ProgramError> {
+    let data = token_account.try_borrow_data()?;
+    let base = data
+        .get(..spl_token::state::Account::LEN)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    spl_token::state::Account::unpack(base)
+}
+
+// The spl_token builders only take the classic program id, Token-2022 reads the same data for the instructions used here
+fn for_token_program(mut instruction: Instruction, token_program: &Pubkey) -> Instruction {
+    instruction.program_id = *token_program;
+    instruction
+}
+
+// Reject a token program other than the one the game was initialized with, before any CPI
+fn check_token_program(token_program: &AccountInfo, game_info: &GameInfo) -> ProgramResult {
+    if *token_program.key != game_info.token_program {
+        msg!("Token program is not the token program of the game");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
+
+// Reject a payout the program token account cannot cover, before the token transfer fails with a less helpful error
+fn check_vault_balance(program_token_account: &AccountInfo, amount: u64) -> ProgramResult {
+    let balance = unpack_token_account(program_token_account)?.amount;
+    if balance < amount {
+        msg!(
+            "Program token account holds {} but {} is to be paid, it must be funded first",
+            balance,
+            amount
+        );
+        return Err(GameError::VaultUnderfunded.into());
+    }
+    Ok(())
+}
+
+// The vault PDA from the bump stored at init, without searching for the bump again
+fn vault_address(program_id: &Pubkey, vault_bump: u8) -> Result<Pubkey, ProgramError> {
+    Pubkey::create_program_address(&[PDA_SEED.as_bytes(), &[vault_bump]], program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)
+}
+
+// Create the account at a PDA of the current program with space bytes, the payer funds its rent exemption
+// Anyone can send lamports to the PDA before, which create_account rejects, so an account already holding lamports
+// is topped up to the rent exempt minimum and then allocated and assigned instead
+fn create_pda_account<'a>(
+    payer_account: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    system_program_account: &AccountInfo<'a>,
+    space: usize,
+    program_id: &Pubkey,
+    seeds: &[&[u8]],
+) -> ProgramResult {
+    let rent_exempt_minimum = Rent::get()?.minimum_balance(space);
+    if account.lamports() == 0 {
+        return invoke_signed(
+            &system_instruction::create_account(
+                payer_account.key,
+                account.key,
+                rent_exempt_minimum,
+                space as u64,
+                program_id,
+            ),
+            &[
+                payer_account.clone(),
+                account.clone(),
+                system_program_account.clone(),
+            ],
+            &[seeds],
+        );
+    }
+    let missing_lamports = rent_exempt_minimum.saturating_sub(account.lamports());
+    if missing_lamports > 0 {
+        invoke(
+            &system_instruction::transfer(payer_account.key, account.key, missing_lamports),
+            &[
+                payer_account.clone(),
+                account.clone(),
+                system_program_account.clone(),
+            ],
+        )?;
+    }
+    invoke_signed(
+        &system_instruction::allocate(account.key, space as u64),
+        &[account.clone(), system_program_account.clone()],
+        &[seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(account.key, program_id),
+        &[account.clone(), system_program_account.clone()],
+        &[seeds],
+    )
+}
+
+pub struct Processor;
+
+impl Processor {
+    pub fn process(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        instruction_data: &[u8],
+    ) -> ProgramResult {
+        // Log the remaining compute units around the instruction, the difference is what the instruction consumed
+        // It is logged on failure as well, to profile the path which ran out of compute
+        sol_log_compute_units();
+        let result = Self::process_instruction(program_id, accounts, instruction_data);
+        sol_log_compute_units();
+        result
+    }
+
+    fn process_instruction(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        instruction_data: &[u8],
+    ) -> ProgramResult {
+        let instruction = Command::unpack(instruction_data)?;
+        match instruction {
+            Command::Init {
+                referral_bonus,
+                max_depth,
+                min_claim,
+                register_fee,
+                max_reward_per_player,
+                decay_bps_per_day,
+                level_bps,
+            } => Self::process_init(
+                program_id,
+                accounts,
+                referral_bonus,
+                max_depth,
+                min_claim,
+                register_fee,
+                max_reward_per_player,
+                decay_bps_per_day,
+                level_bps,
+            ),
+            Command::Register { idempotent, holder } => {
+                Self::process_register(program_id, accounts, idempotent, holder)
+            }
+            Command::BatchRegister => Self::process_batch_register(program_id, accounts),
+            Command::AddReward {
+                reward_amount,
+                saturate,
+            } => Self::process_add_reward(program_id, accounts, reward_amount, saturate),
+            Command::Claim { amount } => Self::process_claim_reward(program_id, accounts, amount),
+            Command::CloseAccount => Self::process_close_account(program_id, accounts),
+            Command::SetAdmin => Self::process_set_admin(program_id, accounts),
+            Command::SetPaused { paused } => Self::process_set_paused(program_id, accounts, paused),
+            Command::SweepFees => Self::process_sweep_fees(program_id, accounts),
+            Command::AdminSetReward { reward_to_claim } => {
+                Self::process_admin_set_reward(program_id, accounts, reward_to_claim)
+            }
+            Command::SetUpline => Self::process_set_upline(program_id, accounts),
+            Command::SetVaultAuthority => Self::process_set_vault_authority(program_id, accounts),
+            Command::MigratePlayer => Self::process_migrate_player(program_id, accounts),
+            Command::AddAdmin => Self::process_add_admin(program_id, accounts),
+            Command::RemoveAdmin => Self::process_remove_admin(program_id, accounts),
+            Command::SlashReward { amount } => {
+                Self::process_slash_reward(program_id, accounts, amount)
+            }
+            Command::GetReward => Self::process_get_reward(program_id, accounts),
+            Command::FundPool { amount } => Self::process_fund_pool(program_id, accounts, amount),
+            Command::SetRelayer => Self::process_set_relayer(program_id, accounts),
+            Command::FreezePlayer { frozen } => {
+                Self::process_freeze_player(program_id, accounts, frozen)
+            }
+            Command::ExportPlayer => Self::process_export_player(program_id, accounts),
+            Command::SweepDust => Self::process_sweep_dust(program_id, accounts),
+            Command::AdminClaimBatch => Self::process_admin_claim_batch(program_id, accounts),
+            Command::SetVestDuration { vest_duration } => {
+                Self::process_set_vest_duration(program_id, accounts, vest_duration)
+            }
+            Command::SetRewardMultiplier {
+                reward_multiplier_bps,
+            } => Self::process_set_reward_multiplier(program_id, accounts, reward_multiplier_bps),
+            Command::SetReferralBonus { referral_bonus } => {
+                Self::process_set_referral_bonus(program_id, accounts, referral_bonus)
+            }
+            Command::SetTierThresholds { tier_thresholds } => {
+                Self::process_set_tier_thresholds(program_id, accounts, tier_thresholds)
+            }
+            Command::TransferReward { amount } => {
+                Self::process_transfer_reward(program_id, accounts, amount)
+            }
+        }
+    }
+
+    // 0 - [signer]   - The source player (holder) account
+    // 1 - [writable] - The source player program account
+    // 2 - [writable] - The destination player program account
+    // 3 - [writable] - Program account
+    pub fn process_transfer_reward(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: u64,
+    ) -> ProgramResult {
+        msg!("process_transfer_reward");
+        let TransferRewardAccounts {
+            holder: player_holder_account,
+            player: player_program_account,
+            destination_player: destination_player_program_account,
+            game_info: program_account,
+        } = TransferRewardAccounts::from_account_iter(accounts)?;
+
+        if amount == 0 {
+            msg!("Transfer amount must be greater than 0");
+            return Err(GameError::ZeroAmount.into());
+        }
+
+        if program_account.owner != program_id {
+            msg!("Program account owner is not current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+
+        if player_program_account.owner != program_id {
+            msg!("Player program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if destination_player_program_account.owner != program_id {
+            msg!("Destination player program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if destination_player_program_account.key == player_program_account.key {
+            msg!("Destination player cannot be same account as current player");
+            return Err(GameError::InvalidPlayerAccount.into());
+        }
+
+        let mut player_program_account_data =
+            Player::unpack(&player_program_account.try_borrow_data()?)?;
+        if player_program_account_data.owner != *player_holder_account.key {
+            msg!("Player program account do not belongs to signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Otherwise a frozen player could move its reward to another player it holds and claim it there
+        if player_program_account_data.frozen {
+            msg!("Player is frozen by the admin");
+            return Err(GameError::PlayerFrozen.into());
+        }
+
+        if player_program_account_data.program_account != *program_account.key {
+            msg!("Player program account is not registered to the program account");
+            return Err(GameError::InvalidPlayerAccount.into());
+        }
+
+        let mut destination_player_program_account_data =
+            Player::unpack(&destination_player_program_account.try_borrow_data()?)?;
+        // Reward can only move between players of the same program account
+        if destination_player_program_account_data.program_account
+            != player_program_account_data.program_account
+        {
+            msg!("Destination player belongs to another program account");
+            return Err(GameError::InvalidPlayerAccount.into());
+        }
+
+        // Same as a claim, the decayed part of both rewards is forfeited before the transfer
+        // Otherwise the reward could escape its decay on a player never credited, which does not decay
+        let now = Clock::get()?.unix_timestamp;
+        for player_data in [
+            &mut player_program_account_data,
+            &mut destination_player_program_account_data,
+        ] {
+            let decay = player_data.accrued_decay(program_account_data.decay_bps_per_day, now);
+            if decay > 0 {
+                msg!("Reward decayed by {}", decay);
+                player_data.reward_to_claim -= decay;
+                player_data.last_credit_at = now;
+            }
+        }
+
+        // The locked part of the reward cannot be moved to another player before it vests
+        if amount <= player_program_account_data.reward_to_claim
+            && amount > player_program_account_data.claimable(now)
+        {
+            msg!("Reward is still vesting");
+            return Err(GameError::RewardStillVesting.into());
+        }
+        player_program_account_data.reward_to_claim = player_program_account_data
+            .reward_to_claim
+            .checked_sub(amount)
+            .ok_or(GameError::InsufficientReward)?;
+        destination_player_program_account_data.reward_to_claim =
+            destination_player_program_account_data
+                .reward_to_claim
+                .checked_add(amount)
+                .ok_or(GameError::RewardAmountOverflow)?;
+        // The moved reward decays on the destination from now
+        destination_player_program_account_data.last_credit_at = now;
+
+        msg!("Transfer reward {}", amount);
+        Player::pack(
+            player_program_account_data,
+            &mut player_program_account.try_borrow_mut_data()?,
+        )?;
+        Player::pack(
+            destination_player_program_account_data,
+            &mut destination_player_program_account.try_borrow_mut_data()?,
+        )?;
+
+        program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        Ok(())
+    }
+
+    // 0 - [signer]   - The player (holder) account
+    // 1 - [writable] - The player program account
+    // 2 - [writable] - Program account
+    // 3 - [writable] - The upline player program account
+    // 4.. - []       - The player program accounts above the upline, from its own upline up to the top or the max depth
+    pub fn process_set_upline(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        msg!("process_set_upline");
+        let SetUplineAccounts {
+            holder: player_holder_account,
+            player: player_program_account,
+            game_info: program_account,
+            upline: upline_player_program_account,
+            ancestors: ancestor_player_program_accounts,
+        } = SetUplineAccounts::from_account_iter(accounts)?;
+
+        if player_program_account.owner != program_id {
+            msg!("Player program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut player_program_account_data =
+            Player::unpack(&player_program_account.try_borrow_data()?)?;
+        if player_program_account_data.owner != *player_holder_account.key {
+            msg!("Player program account do not belongs to signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if player_program_account_data.upline.is_some() {
+            msg!("Player already has an upline");
+            return Err(GameError::UplineAlreadySet.into());
+        }
+
+        if program_account.owner != program_id {
+            msg!("Program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if *program_account.key != player_program_account_data.program_account {
+            msg!("Player belongs to another program account");
+            return Err(GameError::InvalidPlayerAccount.into());
+        }
+
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+
+        if upline_player_program_account.key == player_program_account.key {
+            msg!("Upline cannot be same account as current player");
+            return Err(GameError::SelfRecursiveUpline.into());
+        }
+
+        if upline_player_program_account.owner != program_id {
+            msg!("Upline player program account owner is not the current program");
+            return Err(GameError::UplineNotRegistered.into());
+        }
+
+        let mut upline_player_data =
+            Player::unpack_unchecked(&upline_player_program_account.try_borrow_data()?)?;
+        if !upline_player_data.is_initialized {
+            msg!("Upline player program account is not initialized");
+            return Err(GameError::UplineNotRegistered.into());
+        }
+
+        if upline_player_data.frozen {
+            msg!("Upline player is frozen by the admin");
+            return Err(GameError::PlayerFrozen.into());
+        }
+
+        if upline_player_data.owner == *player_holder_account.key {
+            msg!("Upline cannot be held by the current player holder");
+            return Err(GameError::SelfRecursiveUpline.into());
+        }
+
+        if upline_player_data.program_account != player_program_account_data.program_account {
+            msg!("Upline belongs to another program account");
+            return Err(GameError::InvalidUpline.into());
+        }
+
+        // Walk the chain above the upline, bounded by the max depth, meeting the player again would close a cycle
+        // The upline of the upline is read from its data, the accounts further up are the ones passed after it
+        // The whole chain must be passed, up to its top or the max depth, a shorter one fails instead of skipping the walk
+        let mut ancestor_iter = ancestor_player_program_accounts.iter();
+        let mut ancestor = upline_player_data.upline;
+        for _ in 0..program_account_data.max_depth {
+            let ancestor_key = match ancestor {
+                COption::Some(ancestor_key) => ancestor_key,
+                COption::None => break,
+            };
+            if ancestor_key == *player_program_account.key {
+                msg!("Upline chain leads back to the player");
+                return Err(GameError::CircularReferral.into());
+            }
+            let ancestor_player_program_account = match ancestor_iter.next() {
+                Some(account) => account,
+                None => {
+                    msg!("Upline chain ends before its top or the max depth");
+                    return Err(ProgramError::NotEnoughAccountKeys);
+                }
+            };
+            if *ancestor_player_program_account.key != ancestor_key {
+                msg!("Account passed is not the next upline of the chain");
+                return Err(GameError::InvalidUpline.into());
+            }
+            if ancestor_player_program_account.owner != program_id {
+                msg!("Upline player program account owner is not the current program");
+                return Err(GameError::UplineNotRegistered.into());
+            }
+            ancestor = Player::unpack(&ancestor_player_program_account.try_borrow_data()?)?.upline;
+        }
+
+        // The depth of existing downlines would go stale, only a player without downline can be moved
+        if player_program_account_data.downline_count > 0 {
+            msg!("Player with downlines cannot set an upline");
+            return Err(GameError::InvalidUpline.into());
+        }
+
+        let depth = upline_player_data.depth.saturating_add(1);
+        if depth > program_account_data.max_depth {
+            msg!("Player would be deeper than the max referral depth");
+            return Err(GameError::MaxDepthExceeded.into());
+        }
+
+        upline_player_data.downline_count = upline_player_data
+            .downline_count
+            .checked_add(1)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        Player::pack(
+            upline_player_data,
+            &mut upline_player_program_account.try_borrow_mut_data()?,
+        )?;
+
+        player_program_account_data.upline = COption::Some(*upline_player_program_account.key);
+        player_program_account_data.depth = depth;
+        Player::pack(
+            player_program_account_data,
+            &mut player_program_account.try_borrow_mut_data()?,
+        )?;
+
+        program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        Ok(())
+    }
+
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - []         - The new admin (holder) account
+    pub fn process_set_admin(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        msg!("process_set_admin");
+        let SetAdminAccounts {
+            admin: admin_holder_account,
+            game_info: program_account,
+            new_admin: new_admin_holder_account,
+        } = SetAdminAccounts::from_account_iter(accounts)?;
+
+        if program_account.owner != program_id {
+            msg!("Program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+        require_admin(admin_holder_account, &program_account_data)?;
+
+        if program_account_data.is_admin(new_admin_holder_account.key) {
+            msg!("New admin is already an admin");
+            return Err(GameError::AdminAlreadyAdded.into());
+        }
+
+        // The new admin takes the slot of the signing admin
+        for admin in program_account_data.admins.iter_mut() {
+            if admin == admin_holder_account.key {
+                *admin = *new_admin_holder_account.key;
+            }
+        }
+
+        program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        Ok(())
+    }
+
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - []         - The new admin (holder) account
+    pub fn process_add_admin(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        msg!("process_add_admin");
+        let AddAdminAccounts {
+            admin: admin_holder_account,
+            game_info: program_account,
+            new_admin: new_admin_holder_account,
+        } = AddAdminAccounts::from_account_iter(accounts)?;
+
+        if program_account.owner != program_id {
+            msg!("Program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+        require_admin(admin_holder_account, &program_account_data)?;
+
+        if program_account_data.is_admin(new_admin_holder_account.key) {
+            msg!("New admin is already an admin");
+            return Err(GameError::AdminAlreadyAdded.into());
+        }
+
+        let free_slot = program_account_data
+            .admins
+            .iter_mut()
+            .find(|admin| **admin == Pubkey::default())
+            .ok_or(GameError::TooManyAdmins)?;
+        *free_slot = *new_admin_holder_account.key;
+
+        program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        Ok(())
+    }
+
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - []         - The removed admin (holder) account
+    pub fn process_remove_admin(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        msg!("process_remove_admin");
+        let RemoveAdminAccounts {
+            admin: admin_holder_account,
+            game_info: program_account,
+            removed_admin: removed_admin_holder_account,
+        } = RemoveAdminAccounts::from_account_iter(accounts)?;
+
+        if program_account.owner != program_id {
+            msg!("Program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+        require_admin(admin_holder_account, &program_account_data)?;
+
+        if !program_account_data.is_admin(removed_admin_holder_account.key) {
+            msg!("Removed account is not an admin");
+            return Err(GameError::InvalidAdmin.into());
+        }
+
+        // Nobody could run the admin instructions anymore
+        if program_account_data.admin_count() == 1 {
+            msg!("The last admin cannot be removed");
+            return Err(GameError::LastAdmin.into());
+        }
+
+        for admin in program_account_data.admins.iter_mut() {
+            if admin == removed_admin_holder_account.key {
+                *admin = Pubkey::default();
+            }
+        }
+
+        program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn process_set_paused(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        paused: bool,
+    ) -> ProgramResult {
+        msg!("process_set_paused");
+        let SetPausedAccounts {
+            admin: admin_holder_account,
+            game_info: program_account,
+        } = SetPausedAccounts::from_account_iter(accounts)?;
+
+        if program_account.owner != program_id {
+            msg!("Program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+        require_admin(admin_holder_account, &program_account_data)?;
+
+        program_account_data.paused = paused;
+
+        program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        Ok(())
+    }
+
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - [writable] - The account to receive the fees
+    pub fn process_sweep_fees(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        msg!("process_sweep_fees");
+        let SweepFeesAccounts {
+            admin: admin_holder_account,
+            game_info: program_account,
+            destination: destination_account,
+        } = SweepFeesAccounts::from_account_iter(accounts)?;
+
+        if program_account.owner != program_id {
+            msg!("Program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+        require_admin(admin_holder_account, &program_account_data)?;
+
+        // Only the lamports above the rent exempt minimum are fees, the program account must stay alive
+        let fees = program_account
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(program_account.data_len()));
+        **destination_account.lamports.borrow_mut() = destination_account
+            .lamports()
+            .checked_add(fees)
+            .ok_or(GameError::RewardAmountOverflow)?;
+        **program_account.lamports.borrow_mut() -= fees;
+
+        program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        Ok(())
+    }
+
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - [writable] - The token account of the current program
+    // 3 - []         - The PDA, owner (in term of token, not account owner) of token account
+    // 4 - [writable] - The token account to receive the dust
+    // 5 - []         - The token program
+    pub fn process_sweep_dust(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        msg!("process_sweep_dust");
+        let SweepDustAccounts {
+            admin: admin_holder_account,
+            game_info: program_account,
+            vault_token_account: program_token_account,
+            vault_authority: pda_account,
+            destination: destination_token_account,
+            token_program,
+        } = SweepDustAccounts::from_account_iter(accounts)?;
+
+        if program_account.owner != program_id {
+            msg!("Program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+        require_admin(admin_holder_account, &program_account_data)?;
+
+        if *program_token_account.key != program_account_data.spl_token_account {
+            msg!("Program token account do not match with current program token account");
+            return Err(GameError::InvalidTokenAccount.into());
+        }
+
+        let vault_bump = program_account_data.vault_bump;
+        let pda = vault_address(program_id, vault_bump)?;
+        if *pda_account.key != pda {
+            msg!("PDA account is not the vault PDA of the current program");
+            return Err(ProgramError::InvalidSeeds);
+        }
+
+        check_token_program(token_program, &program_account_data)?;
+
+        // The dust was never credited to a player, so taking it out of the vault leaves every claim paid
+        let dust = program_account_data.dust;
+        if dust > 0 {
+            let transfer_dust_instruction = for_token_program(
+                spl_token::instruction::transfer(
+                    &spl_token::id(),
+                    program_token_account.key,
+                    destination_token_account.key,
+                    &pda,
+                    &[&pda],
+                    dust,
+                )?,
+                token_program.key,
+            );
+
+            msg!("Sweep dust {} from the program token account", dust);
+            invoke_signed(
+                &transfer_dust_instruction,
+                &[
+                    program_token_account.clone(),
+                    destination_token_account.clone(),
+                    token_program.clone(),
+                    pda_account.clone(),
+                ],
+                &[&[PDA_SEED.as_bytes(), &[vault_bump]]],
+            )?;
+        }
+
+        program_account_data.dust = 0;
+        program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        Ok(())
+    }
+
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - [writable] - The token account of the current program
+    // 3 - []         - The PDA, owner (in term of token, not account owner) of token account
+    // 4 - []         - The new owner of the token account
+    // 5 - []         - The token program
+    pub fn process_set_vault_authority(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        msg!("process_set_vault_authority");
+        let SetVaultAuthorityAccounts {
+            admin: admin_holder_account,
+            game_info: program_account,
+            vault_token_account: program_token_account,
+            vault_authority: pda_account,
+            new_authority: new_authority_account,
+            token_program,
+        } = SetVaultAuthorityAccounts::from_account_iter(accounts)?;
+
+        if program_account.owner != program_id {
+            msg!("Program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+        require_admin(admin_holder_account, &program_account_data)?;
+
+        if *program_token_account.key != program_account_data.spl_token_account {
+            msg!("Program token account do not match with current program token account");
+            return Err(GameError::InvalidTokenAccount.into());
+        }
+
+        let vault_bump = program_account_data.vault_bump;
+        let pda = vault_address(program_id, vault_bump)?;
+        if *pda_account.key != pda {
+            msg!("PDA account is not the vault PDA of the current program");
+            return Err(ProgramError::InvalidSeeds);
+        }
+
+        check_token_program(token_program, &program_account_data)?;
+
+        // Same as init, but the current owner is the PDA, which only the program can sign for
+        let set_authority_instruction = for_token_program(
+            spl_token::instruction::set_authority(
+                &spl_token::id(),
+                program_token_account.key,
+                Some(new_authority_account.key),
+                spl_token::instruction::AuthorityType::AccountOwner,
+                &pda,
+                &[&pda],
+            )?,
+            token_program.key,
+        );
+
+        msg!("Hand over the program token account to the new authority");
+        invoke_signed(
+            &set_authority_instruction,
+            &[
+                program_token_account.clone(),
+                pda_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[PDA_SEED.as_bytes(), &[vault_bump]]],
+        )?;
+
+        program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        Ok(())
+    }
+
+    // 0 - [signer, writable] - The player (holder) account
+    // 1 - [writable]         - The player program account
+    // 2 - []                 - The system program
+    // 3 - [writable]         - Program account
+    // 4 - []                 - The player upline program account, only when the player has one and predates the depth
+    pub fn process_migrate_player(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        msg!("process_migrate_player");
+        let MigratePlayerAccounts {
+            holder: player_holder_account,
+            player: player_program_account,
+            system_program: system_program_account,
+            game_info: program_account,
+            upline: upline_player_program_account,
+        } = MigratePlayerAccounts::from_account_iter(accounts)?;
+
+        if player_program_account.owner != program_id {
+            msg!("Player program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // The size tells the layout, an account of the current size has nothing left to migrate
+        let legacy_len = player_program_account.data_len();
+        if legacy_len == Player::LEN {
+            msg!("Player program account already has the current layout");
+            return Err(GameError::AlreadyMigrated.into());
+        }
+        if !LEGACY_PLAYER_LENS.contains(&legacy_len) {
+            msg!("Player program account size is not a previous layout");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut player_program_account_data =
+            Player::unpack_legacy(&player_program_account.try_borrow_data()?)?;
+        if !player_program_account_data.is_initialized {
+            msg!("Player program account is not initialized");
+            return Err(GameError::NotInitialized.into());
+        }
+
+        if player_program_account_data.owner != *player_holder_account.key {
+            msg!("Player program account do not belongs to signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if program_account.owner != program_id {
+            msg!("Program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if *program_account.key != player_program_account_data.program_account {
+            msg!("Player belongs to another program account");
+            return Err(GameError::InvalidPlayerAccount.into());
+        }
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+
+        // The depth of a player with upline is one below its upline
+        if legacy_len <= PRE_DEPTH_PLAYER_LEN {
+            if let COption::Some(upline_key) = player_program_account_data.upline {
+                let upline_player_program_account =
+                    upline_player_program_account.ok_or_else(|| {
+                        msg!("The upline account of the player must be passed");
+                        ProgramError::NotEnoughAccountKeys
+                    })?;
+                if *upline_player_program_account.key != upline_key {
+                    msg!("Upline account passed was not current player upline");
+                    return Err(GameError::InvalidUpline.into());
+                }
+                if upline_player_program_account.owner != program_id {
+                    msg!("Upline program account owner is not current program");
+                    return Err(ProgramError::IncorrectProgramId);
+                }
+                let upline_player_data =
+                    Player::unpack_from_slice(&upline_player_program_account.try_borrow_data()?)?;
+                // An upline of a layout without depth only knows it when it is at the top of the chain
+                if upline_player_program_account.data_len() <= PRE_DEPTH_PLAYER_LEN
+                    && upline_player_data.upline.is_some()
+                {
+                    msg!("Upline account must be migrated first");
+                    return Err(GameError::InvalidUpline.into());
+                }
+                player_program_account_data.depth = upline_player_data.depth.saturating_add(1);
+            }
+        }
+
+        // The bigger account needs a bigger rent exempt minimum, paid by the holder
+        let rent_top_up = Rent::get()?
+            .minimum_balance(Player::LEN)
+            .saturating_sub(player_program_account.lamports());
+        if rent_top_up > 0 {
+            if !system_program::check_id(system_program_account.key) {
+                msg!("System program account is not the system program");
+                return Err(ProgramError::IncorrectProgramId);
+            }
+            invoke(
+                &system_instruction::transfer(
+                    player_holder_account.key,
+                    player_program_account.key,
+                    rent_top_up,
+                ),
+                &[
+                    player_holder_account.clone(),
+                    player_program_account.clone(),
+                    system_program_account.clone(),
+                ],
+            )?;
+        }
+
+        player_program_account.realloc(Player::LEN, true)?;
+
+        // Zero is the default of every new field, except the referral code which is derived from the account,
+        // and the depth of a player with upline, set above
+        if legacy_len <= PRE_REFERRAL_CODE_PLAYER_LEN {
+            player_program_account_data.referral_code =
+                Player::referral_code_of(player_program_account.key);
+        }
+
+        Player::pack(
+            player_program_account_data,
+            &mut player_program_account.try_borrow_mut_data()?,
+        )?;
+
+        program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        Ok(())
+    }
+
+    // 0 - [signer]   - The player (holder) account
+    // 1 - [writable] - The player program account
+    // 2 - [writable] - The account to receive the rent lamports
+    // 3 - [writable] - Program account
+    // 4 - [writable] - The player upline program account, only when the player has one
+    // 5.. [writable] - Every downline player program account of the player, moved to the player upline
+    pub fn process_close_account(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        msg!("process_close_account");
+        let CloseAccountAccounts {
+            holder: player_holder_account,
+            player: player_program_account,
+            destination: destination_account,
+            game_info: program_account,
+            reassigned: reassigned_accounts,
+        } = CloseAccountAccounts::from_account_iter(accounts)?;
+
+        if player_program_account.owner != program_id {
+            msg!("Player program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let player_program_account_data =
+            Player::unpack(&player_program_account.try_borrow_data()?)?;
+
+        if player_program_account_data.owner != *player_holder_account.key {
+            msg!("Player program account do not belongs to signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Prevent player losing the reward by closing the account
+        if player_program_account_data.reward_to_claim != 0 {
+            msg!("Player must claim the reward before closing the account");
+            return Err(GameError::RewardNotClaimed.into());
+        }
+
+        if program_account.owner != program_id {
+            msg!("Program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if *program_account.key != player_program_account_data.program_account {
+            msg!("Player belongs to another program account");
+            return Err(GameError::InvalidPlayerAccount.into());
+        }
+
+        // The player leaves the game, a counter already at zero has drifted and is not wrapped around
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+        program_account_data.total_players = program_account_data
+            .total_players
+            .checked_sub(1)
+            .ok_or(GameError::RewardAmountOverflow)?;
+        program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        // The passed downlines would point to a dead upline, they move up to the player upline instead
+        let (upline_player_program_account, downline_player_program_accounts) =
+            match (player_program_account_data.upline, reassigned_accounts) {
+                (COption::Some(upline_key), [upline_player_program_account, downlines @ ..]) => {
+                    if *upline_player_program_account.key != upline_key {
+                        msg!("Upline account passed was not current player upline");
+                        return Err(GameError::InvalidUpline.into());
+                    }
+                    if upline_player_program_account.owner != program_id {
+                        msg!("Upline program account owner is not current program");
+                        return Err(ProgramError::IncorrectProgramId);
+                    }
+                    (Some(upline_player_program_account), downlines)
+                }
+                // Its downline count would keep counting the closed player, and the upline could never close
+                (COption::Some(_), []) => {
+                    msg!("The upline account of the player must be passed");
+                    return Err(ProgramError::NotEnoughAccountKeys);
+                }
+                (COption::None, _) => (None, reassigned_accounts),
+            };
+
+        // A downline left out would keep pointing to the closed account, and no reward could be added to it anymore
+        // Each account passed is checked to be a downline below, a downline passed twice fails the check the second time
+        let downline_count = player_program_account_data.downline_count as usize;
+        if downline_player_program_accounts.len() < downline_count {
+            msg!(
+                "All {} downlines of the player must be passed",
+                downline_count
+            );
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        if downline_player_program_accounts.len() > downline_count {
+            msg!(
+                "More accounts passed than the {} downlines of the player",
+                downline_count
+            );
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        for downline_player_program_account in downline_player_program_accounts {
+            if downline_player_program_account.owner != program_id {
+                msg!("Downline program account owner is not current program");
+                return Err(ProgramError::IncorrectProgramId);
+            }
+            let mut downline_player_data =
+                Player::unpack(&downline_player_program_account.try_borrow_data()?)?;
+            if downline_player_data.upline != COption::Some(*player_program_account.key) {
+                msg!("Downline account passed is not a downline of the current player");
+                return Err(GameError::InvalidUpline.into());
+            }
+            // The downline takes the place of the player in the chain, the depth below it is left as is
+            // The players further down are then one level shallower than their stored depth, which is safe to overstate:
+            // depth is only compared to max_depth to reject registering deeper, so it can only reject early, never
+            // let a chain grow past max_depth, and the reward split walks the upline accounts, not the depth
+            downline_player_data.upline = player_program_account_data.upline;
+            downline_player_data.depth = player_program_account_data.depth;
+            Player::pack(
+                downline_player_data,
+                &mut downline_player_program_account.try_borrow_mut_data()?,
+            )?;
+        }
+
+        if let Some(upline_player_program_account) = upline_player_program_account {
+            let mut upline_player_data =
+                Player::unpack(&upline_player_program_account.try_borrow_data()?)?;
+            // The player leaves the upline downlines, the reassigned ones join them
+            upline_player_data.downline_count = upline_player_data
+                .downline_count
+                .saturating_sub(1)
+                .checked_add(downline_player_program_accounts.len() as u32)
+                .ok_or(ProgramError::InvalidAccountData)?;
+            Player::pack(
+                upline_player_data,
+                &mut upline_player_program_account.try_borrow_mut_data()?,
+            )?;
+        }
+
+        // Move all lamports out, the runtime will garbage collect the account with zero lamports
+        let rent_lamports = player_program_account.lamports();
+        **destination_account.lamports.borrow_mut() = destination_account
+            .lamports()
+            .checked_add(rent_lamports)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        **player_program_account.lamports.borrow_mut() = 0;
+
+        // Zero the data, so the account cannot be reused within the same transaction
+        player_program_account.try_borrow_mut_data()?.fill(0);
+
+        Ok(())
+    }
+
+    // 0 - [signer]   - The player (holder) account
+    // 1 - [writable] - Program account
+    // 2 - [writable] - The player program account
+    // 3 - []         - The token account of the current program
+    // 4-  []         - The PDA, owner (in term of token, not account owner) of token account
+    // 5 - []         - The player token account
+    // 6 - []         - The token program
+    // 7 - []         - The clock sysvar
+    // 8 - []         - Optional, the memo program
+    pub fn process_claim_reward(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: Option<u64>,
+    ) -> ProgramResult {
+        msg!("process_claim_reward");
+        let ClaimAccounts {
+            holder: player_holder_account,
+            game_info: program_account,
+            player: player_program_account,
+            vault_token_account: program_token_account,
+            vault_authority: pda_account,
+            player_token_account,
+            token_program,
+            clock: clock_sysvar_account,
+            memo_program,
+        } = ClaimAccounts::from_account_iter(accounts)?;
+
+        // An explicit zero amount would only issue a no-op token transfer
+        if amount == Some(0) {
+            msg!("Claim amount must be greater than 0");
+            return Err(GameError::ZeroAmount.into());
+        }
+
+        // Make sure program account owner is the current program
+        if program_account.owner != program_id {
+            msg!("Program account owner is not current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+        if program_account_data.paused {
+            msg!("Game is paused");
+            return Err(GameError::GamePaused.into());
+        }
+
+        // Make sure player program account owner is the current program
+        if player_program_account.owner != program_id {
+            msg!("Player program account owner is not current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut player_program_account_data =
+            Player::unpack(&player_program_account.try_borrow_data()?)?;
+
+        // Make sure player program account owned by signer
+        // An admin signer gets no exception, it can only claim the reward of a player it holds
+        if player_program_account_data.owner != *player_holder_account.key {
+            msg!("Player program account do not belongs to signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if player_program_account_data.frozen {
+            msg!("Player is frozen by the admin");
+            return Err(GameError::PlayerFrozen.into());
+        }
+
+        // The decayed part of the reward is forfeited, the claim is checked against what is left
+        let clock = Clock::from_account_info(clock_sysvar_account)?;
+        let decay = player_program_account_data
+            .accrued_decay(program_account_data.decay_bps_per_day, clock.unix_timestamp);
+        if decay > 0 {
+            msg!("Reward decayed by {}", decay);
+            player_program_account_data.reward_to_claim -= decay;
+        }
+
+        // Make sure there's reward to claim
+        if player_program_account_data.reward_to_claim == 0 {
+            msg!("No reward to claim");
+            return Err(GameError::UnclaimableAmount.into());
+        }
+
+        // Without an amount the whole vested reward is claimed, otherwise the rest is left for a later claim
+        let claimable = player_program_account_data.claimable(clock.unix_timestamp);
+        let claimed_amount = amount.unwrap_or(claimable);
+        if claimed_amount > player_program_account_data.reward_to_claim {
+            msg!("Claim amount is greater than the reward to claim");
+            return Err(GameError::InsufficientReward.into());
+        }
+        if claimed_amount == 0 || claimed_amount > claimable {
+            msg!("Reward is still vesting, {} can be claimed", claimable);
+            return Err(GameError::RewardStillVesting.into());
+        }
+
+        if claimed_amount < program_account_data.min_claim {
+            msg!(
+                "Reward to claim is below the minimum claim of {}",
+                program_account_data.min_claim
+            );
+            return Err(GameError::BelowMinimumClaim.into());
+        }
+
+        // When init, program_token_account ownership (not account owner) has been transfer to pda (an account without private key)
+        // The program signs for it with the bump stored at init
+        let vault_bump = program_account_data.vault_bump;
+        let pda = vault_address(program_id, vault_bump)?;
+        if *pda_account.key != pda {
+            msg!("PDA account is not the vault PDA of the current program");
+            return Err(ProgramError::InvalidSeeds);
+        }
+
+        // Make sure it is the token account used during the program initialization
+        if *program_token_account.key != program_account_data.spl_token_account {
+            msg!("Program token account do not match with current program token account");
+            return Err(GameError::InvalidTokenAccount.into());
+        }
+
+        if *player_token_account.owner != program_account_data.token_program {
+            msg!("Player token account owner is not the token program of the game");
+            return Err(ProgramError::IllegalOwner);
+        }
+
+        // The player can claim into any token account they control, as long as it holds the game mint
+        let player_token_account_data = unpack_token_account(player_token_account)?;
+        if player_token_account_data.owner != *player_holder_account.key {
+            msg!("Player token account is not owned by the player holder");
+            return Err(GameError::InvalidTokenAccount.into());
+        }
+        if player_token_account_data.mint != program_account_data.mint {
+            msg!("Player token account mint does not match the program mint");
+            return Err(GameError::InvalidTokenAccount.into());
+        }
+
+        check_token_program(token_program, &program_account_data)?;
+
+        if let Some(memo_program) = memo_program {
+            if !spl_memo::check_id(memo_program.key) {
+                msg!("Memo program is not SPL MEMO program");
+                return Err(ProgramError::IncorrectProgramId);
+            }
+        }
+
+        // A player who never claimed can claim right away
+        if player_program_account_data.last_claim_at != 0
+            && clock
+                .unix_timestamp
+                .saturating_sub(player_program_account_data.last_claim_at)
+                < COOLDOWN_SECS
+        {
+            msg!("Player must wait {} seconds between claims", COOLDOWN_SECS);
+            return Err(GameError::ClaimCooldown.into());
+        }
+
+        check_vault_balance(program_token_account, claimed_amount)?;
+
+        //https://docs.rs/spl-token/3.2.0/spl_token/instruction/fn.transfer.html
+        let transfer_to_player_instruction = for_token_program(
+            spl_token::instruction::transfer(
+                &spl_token::id(),
+                program_token_account.key,
+                player_token_account.key,
+                &pda,
+                &[&pda],
+                claimed_amount,
+            )?,
+            token_program.key,
+        );
+
+        msg!("Claim reward by transfer from program token account to the player");
+        // All account involved in the instruction need to be passed when invoke
+        invoke_signed(
+            &transfer_to_player_instruction,
+            &[
+                program_token_account.clone(),
+                player_token_account.clone(),
+                token_program.clone(),
+                pda_account.clone(),
+            ],
+            &[&[PDA_SEED.as_bytes(), &[vault_bump]]],
+        )?;
+
+        // The memo lets the off-chain accounting match the token transfer to the player and amount
+        if let Some(memo_program) = memo_program {
+            let memo_instruction = Instruction {
+                program_id: spl_memo::id(),
+                accounts: vec![],
+                data: format!("claim {} {}", player_program_account.key, claimed_amount)
+                    .into_bytes(),
+            };
+            invoke(&memo_instruction, &[memo_program.clone()])?;
+        }
+
+        // After transfer, deduct the claimed amount from the player reward, checked above to not underflow
+        player_program_account_data.reward_to_claim -= claimed_amount;
+        player_program_account_data.last_claim_at = clock.unix_timestamp;
+        // The decay up to now has been applied, the rest decays from this claim
+        if decay > 0 {
+            player_program_account_data.last_credit_at = clock.unix_timestamp;
+        }
+
+        Player::pack(
+            player_program_account_data,
+            &mut player_program_account.try_borrow_mut_data()?,
+        )?;
+
+        let nonce = program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        GameEvent::RewardClaimed {
+            nonce,
+            player: *player_program_account.key,
+            amount: claimed_amount,
+        }
+        .emit()?;
+
+        Ok(())
+    }
+
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - [writable] - The token account of the current program
+    // 3 - []         - The PDA, owner (in term of token, not account owner) of token account
+    // 4 - []         - The token program
+    // 5 - []         - The clock sysvar
+    // Then for each player to claim for:
+    // n     - [writable] - The player program account
+    // n + 1 - [writable] - The token account to receive the reward, owned by the player holder
+    pub fn process_admin_claim_batch(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        msg!("process_admin_claim_batch");
+        let AdminClaimBatchAccounts {
+            admin: admin_holder_account,
+            game_info: program_account,
+            vault_token_account: program_token_account,
+            vault_authority: pda_account,
+            token_program,
+            clock: clock_sysvar_account,
+            players,
+        } = AdminClaimBatchAccounts::from_account_iter(accounts)?;
+
+        if program_account.owner != program_id {
+            msg!("Program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+        require_admin(admin_holder_account, &program_account_data)?;
+
+        if *program_token_account.key != program_account_data.spl_token_account {
+            msg!("Program token account do not match with current program token account");
+            return Err(GameError::InvalidTokenAccount.into());
+        }
+
+        let vault_bump = program_account_data.vault_bump;
+        let pda = vault_address(program_id, vault_bump)?;
+        if *pda_account.key != pda {
+            msg!("PDA account is not the vault PDA of the current program");
+            return Err(ProgramError::InvalidSeeds);
+        }
+
+        check_token_program(token_program, &program_account_data)?;
+
+        let clock = Clock::from_account_info(clock_sysvar_account)?;
+        for pair in players.chunks(2) {
+            let (player_program_account, destination_token_account) = (&pair[0], &pair[1]);
+            if player_program_account.owner != program_id {
+                msg!("Player program account owner is not the current program");
+                return Err(ProgramError::IncorrectProgramId);
+            }
+
+            let mut player_program_account_data =
+                Player::unpack(&player_program_account.try_borrow_data()?)?;
+            if player_program_account_data.program_account != *program_account.key {
+                msg!("Player program account is not registered to the program account");
+                return Err(GameError::InvalidPlayerAccount.into());
+            }
+
+            if player_program_account_data.frozen {
+                msg!("Player is frozen by the admin");
+                return Err(GameError::PlayerFrozen.into());
+            }
+
+            // The reward is paid to the player holder only, never to a token account of the admin
+            if *destination_token_account.owner != program_account_data.token_program {
+                msg!("Destination token account owner is not the token program of the game");
+                return Err(ProgramError::IllegalOwner);
+            }
+            let destination_token_account_data = unpack_token_account(destination_token_account)?;
+            if destination_token_account_data.owner != player_program_account_data.owner {
+                msg!("Destination token account is not owned by the player holder");
+                return Err(GameError::InvalidTokenAccount.into());
+            }
+            if destination_token_account_data.mint != program_account_data.mint {
+                msg!("Destination token account mint does not match the program mint");
+                return Err(GameError::InvalidTokenAccount.into());
+            }
+
+            // Same as a claim by the player, the decayed part is forfeited
+            let decay = player_program_account_data
+                .accrued_decay(program_account_data.decay_bps_per_day, clock.unix_timestamp);
+            if decay > 0 {
+                msg!("Reward decayed by {}", decay);
+                player_program_account_data.reward_to_claim -= decay;
+                player_program_account_data.last_credit_at = clock.unix_timestamp;
+            }
+
+            // A player without vested reward is skipped, so a batch is not failed by a single one
+            let claimed_amount = player_program_account_data.claimable(clock.unix_timestamp);
+            if claimed_amount > 0 {
+                check_vault_balance(program_token_account, claimed_amount)?;
+                let transfer_to_player_instruction = for_token_program(
+                    spl_token::instruction::transfer(
+                        &spl_token::id(),
+                        program_token_account.key,
+                        destination_token_account.key,
+                        &pda,
+                        &[&pda],
+                        claimed_amount,
+                    )?,
+                    token_program.key,
+                );
+
+                msg!(
+                    "Claim reward {} for {}",
+                    claimed_amount,
+                    player_program_account.key
+                );
+                invoke_signed(
+                    &transfer_to_player_instruction,
+                    &[
+                        program_token_account.clone(),
+                        destination_token_account.clone(),
+                        token_program.clone(),
+                        pda_account.clone(),
+                    ],
+                    &[&[PDA_SEED.as_bytes(), &[vault_bump]]],
+                )?;
+
+                // The part still vesting stays with the player for a later claim
+                player_program_account_data.reward_to_claim = player_program_account_data
+                    .reward_to_claim
+                    .checked_sub(claimed_amount)
+                    .ok_or(GameError::RewardAmountOverflow)?;
+                player_program_account_data.last_claim_at = clock.unix_timestamp;
+            }
+
+            Player::pack(
+                player_program_account_data,
+                &mut player_program_account.try_borrow_mut_data()?,
+            )?;
+
+            if claimed_amount > 0 {
+                GameEvent::RewardClaimed {
+                    nonce: program_account_data.next_nonce()?,
+                    player: *player_program_account.key,
+                    amount: claimed_amount,
+                }
+                .emit()?;
+            }
+        }
+
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        Ok(())
+    }
+
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - []         - The relayer account
+    pub fn process_set_relayer(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        msg!("process_set_relayer");
+        let SetRelayerAccounts {
+            admin: admin_holder_account,
+            game_info: program_account,
+            relayer: relayer_account,
+        } = SetRelayerAccounts::from_account_iter(accounts)?;
+
+        if program_account.owner != program_id {
+            msg!("Program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+        require_admin(admin_holder_account, &program_account_data)?;
+
+        // The system program is the default pubkey, so passing it removes the relayer
+        program_account_data.relayer = *relayer_account.key;
+
+        program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        Ok(())
+    }
+
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    pub fn process_set_vest_duration(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        vest_duration: u64,
+    ) -> ProgramResult {
+        msg!("process_set_vest_duration");
+        let SetVestDurationAccounts {
+            admin: admin_holder_account,
+            game_info: program_account,
+        } = SetVestDurationAccounts::from_account_iter(accounts)?;
+
+        if program_account.owner != program_id {
+            msg!("Program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+        require_admin(admin_holder_account, &program_account_data)?;
+
+        // The rewards already vesting keep the duration they were credited with
+        msg!("Vest duration {}", vest_duration);
+        program_account_data.vest_duration = vest_duration;
+
+        program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        Ok(())
+    }
+
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    pub fn process_set_reward_multiplier(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        reward_multiplier_bps: u16,
+    ) -> ProgramResult {
+        msg!("process_set_reward_multiplier");
+        let SetRewardMultiplierAccounts {
+            admin: admin_holder_account,
+            game_info: program_account,
+        } = SetRewardMultiplierAccounts::from_account_iter(accounts)?;
+
+        if program_account.owner != program_id {
+            msg!("Program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+        require_admin(admin_holder_account, &program_account_data)?;
+
+        if reward_multiplier_bps == 0 {
+            msg!("Reward multiplier cannot be 0");
+            return Err(GameError::InvalidRewardMultiplier.into());
+        }
+
+        msg!("Reward multiplier bps {}", reward_multiplier_bps);
+        program_account_data.reward_multiplier_bps = reward_multiplier_bps;
+
+        program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        Ok(())
+    }
+
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    pub fn process_set_referral_bonus(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        referral_bonus: u64,
+    ) -> ProgramResult {
+        msg!("process_set_referral_bonus");
+        let SetReferralBonusAccounts {
+            admin: admin_holder_account,
+            game_info: program_account,
+        } = SetReferralBonusAccounts::from_account_iter(accounts)?;
+
+        if program_account.owner != program_id {
+            msg!("Program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+        require_admin(admin_holder_account, &program_account_data)?;
+
+        // Only the registers from now on get the new bonus
+        msg!(
+            "Referral bonus changed from {} to {}",
+            program_account_data.referral_bonus,
+            referral_bonus
+        );
+        program_account_data.referral_bonus = referral_bonus;
+
+        program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        Ok(())
+    }
+
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    pub fn process_set_tier_thresholds(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        tier_thresholds: [u64; 3],
+    ) -> ProgramResult {
+        msg!("process_set_tier_thresholds");
+        let SetTierThresholdsAccounts {
+            admin: admin_holder_account,
+            game_info: program_account,
+        } = SetTierThresholdsAccounts::from_account_iter(accounts)?;
+
+        if program_account.owner != program_id {
+            msg!("Program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+        require_admin(admin_holder_account, &program_account_data)?;
+
+        // Each enabled tier needs more than the one before it, and no tier is enabled after a disabled one
+        let enabled = tier_thresholds
+            .iter()
+            .take_while(|&&threshold| threshold != 0)
+            .count();
+        if tier_thresholds[..enabled]
+            .windows(2)
+            .any(|pair| pair[0] >= pair[1])
+            || tier_thresholds[enabled..]
+                .iter()
+                .any(|&threshold| threshold != 0)
+        {
+            msg!("Tier thresholds must be increasing, followed by the disabled tiers");
+            return Err(GameError::InvalidTierThresholds.into());
+        }
+
+        // The tiers of the players are only recomputed on their next credit
+        program_account_data.tier_thresholds = tier_thresholds;
+
+        program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        Ok(())
+    }
+
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - [writable] - The player program account
+    pub fn process_freeze_player(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        frozen: bool,
+    ) -> ProgramResult {
+        msg!("process_freeze_player");
+        let FreezePlayerAccounts {
+            admin: admin_holder_account,
+            game_info: program_account,
+            player: player_program_account,
+        } = FreezePlayerAccounts::from_account_iter(accounts)?;
+
+        if program_account.owner != program_id {
+            msg!("Program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+        require_admin(admin_holder_account, &program_account_data)?;
+
+        if player_program_account.owner != program_id {
+            msg!("Player program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut player_program_account_data =
+            Player::unpack(&player_program_account.try_borrow_data()?)?;
+
+        // The admin of a program account can only freeze the players registered to it
+        if player_program_account_data.program_account != *program_account.key {
+            msg!("Player program account is not registered to the program account");
+            return Err(GameError::InvalidPlayerAccount.into());
+        }
+
+        player_program_account_data.frozen = frozen;
+
+        Player::pack(
+            player_program_account_data,
+            &mut player_program_account.try_borrow_mut_data()?,
+        )?;
+
+        program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
 
-const PDA_SEED: &str = "game_seed";
+        Ok(())
+    }
 
-impl Processor {
-    pub fn process(
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    pub fn process_fund_pool(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        instruction_data: &[u8],
+        amount: u64,
     ) -> ProgramResult {
-        let instruction = Command::unpack(instruction_data)?;
-        match instruction {
-            Command::Init => Self::process_init(program_id, accounts),
-            Command::Register => Self::process_register(program_id, accounts),
-            Command::AddReward { reward_amount } => {
-                Self::process_add_reward(program_id, accounts, reward_amount)
-            }
-            Command::Claim => Self::process_claim_reward(program_id, accounts),
+        msg!("process_fund_pool");
+        let FundPoolAccounts {
+            admin: admin_holder_account,
+            game_info: program_account,
+        } = FundPoolAccounts::from_account_iter(accounts)?;
+
+        if program_account.owner != program_id {
+            msg!("Program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
         }
+
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+        require_admin(admin_holder_account, &program_account_data)?;
+
+        // Like add reward, only the accounting is kept, the admin makes sure the vault holds enough token
+        program_account_data.matching_pool = program_account_data
+            .matching_pool
+            .checked_add(amount)
+            .ok_or(GameError::RewardAmountOverflow)?;
+        msg!("Matching pool {}", program_account_data.matching_pool);
+
+        program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        Ok(())
     }
 
-    // 0 - [signer]   - The player (holder) account
-    // 1 - []         - Program account
-    // 2 - [writable] - The player program account
-    // 3 - []         - The token account of the current program
-    // 4-  []         - The PDA, owner (in term of token, not account owner) of token account
-    // 5 - []         - The player token account
-    // 6 - []         - The token program
-    pub fn process_claim_reward(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
-        msg!("process_claim_reward");
-        let account_iter = &mut accounts.iter();
-        let player_holder_account = next_account_info(account_iter)?;
+    // 0 - [] - The player program account
+    pub fn process_get_reward(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        msg!("process_get_reward");
+        let GetRewardAccounts {
+            player: player_program_account,
+        } = GetRewardAccounts::from_account_iter(accounts)?;
 
-        // Make sure signature for player who claim the reward is provided
-        if !player_holder_account.is_signer {
-            msg!("Player program account must be signed");
-            return Err(ProgramError::MissingRequiredSignature);
+        // Only a player account of the current program holds a reward worth reading
+        if player_program_account.owner != program_id {
+            msg!("Player program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
         }
 
-        let program_account = next_account_info(account_iter)?;
+        let player_program_account_data =
+            Player::unpack(&player_program_account.try_borrow_data()?)?;
+        // The calling program reads it with get_return_data right after the CPI
+        set_return_data(&player_program_account_data.reward_to_claim.to_le_bytes());
+
+        Ok(())
+    }
+
+    // 0 - [] - The player program account
+    pub fn process_export_player(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        msg!("process_export_player");
+        let ExportPlayerAccounts {
+            player: player_program_account,
+        } = ExportPlayerAccounts::from_account_iter(accounts)?;
+
+        if player_program_account.owner != program_id {
+            msg!("Player program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // Unpack first, so a not initialized account is rejected
+        // and a previous layout is exported as the current one, which the backups expect
+        let player_program_account_data =
+            Player::unpack(&player_program_account.try_borrow_data()?)?;
+        let mut exported = [0; Player::LEN];
+        Player::pack(player_program_account_data, &mut exported)?;
+        sol_log_data(&[player_program_account.key.as_ref(), &exported]);
+
+        Ok(())
+    }
+
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - [writable] - The player program account
+    pub fn process_slash_reward(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: u64,
+    ) -> ProgramResult {
+        msg!("process_slash_reward");
+        let SlashRewardAccounts {
+            admin: admin_holder_account,
+            game_info: program_account,
+            player: player_program_account,
+        } = SlashRewardAccounts::from_account_iter(accounts)?;
 
-        // Make sure program account owner is the current program
         if program_account.owner != program_id {
-            msg!("Program account owner is not current program");
+            msg!("Program account owner is not the current program");
             return Err(ProgramError::IncorrectProgramId);
         }
 
-        let program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
-        let player_program_account = next_account_info(account_iter)?;
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+        require_admin(admin_holder_account, &program_account_data)?;
 
-        // Make sure player program account owner is the current program
         if player_program_account.owner != program_id {
-            msg!("Player program account owner is not current program");
+            msg!("Player program account owner is not the current program");
             return Err(ProgramError::IncorrectProgramId);
         }
 
         let mut player_program_account_data =
             Player::unpack(&player_program_account.try_borrow_data()?)?;
-
-        // Make sure player program account owned by signer
-        if player_program_account_data.owner != *player_holder_account.key {
-            msg!("Player program account do not belongs to signer");
-            return Err(ProgramError::MissingRequiredSignature);
+        if player_program_account_data.program_account != *program_account.key {
+            msg!("Player program account is not registered to the program account");
+            return Err(GameError::InvalidPlayerAccount.into());
         }
 
-        // Make sure there's reward to claim
-        if player_program_account_data.reward_to_claim == 0 {
-            msg!("No reward to claim");
-            return Err(GameError::UnclaimableAmount.into());
+        // Slashing more than the reward to claim empties it, total_earned is left as is
+        let slashed_amount = amount.min(player_program_account_data.reward_to_claim);
+        player_program_account_data.reward_to_claim -= slashed_amount;
+        msg!("Slash reward {}", slashed_amount);
+
+        Player::pack(
+            player_program_account_data,
+            &mut player_program_account.try_borrow_mut_data()?,
+        )?;
+
+        let nonce = program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        GameEvent::RewardSlashed {
+            nonce,
+            player: *player_program_account.key,
+            amount: slashed_amount,
         }
+        .emit()?;
 
-        let program_token_account = next_account_info(account_iter)?;
-        let pda_account = next_account_info(account_iter)?;
+        Ok(())
+    }
 
-        // When init, program_token_account ownership (not account owner) has been transfer to pda (an account without private key)
-        let (pda, nonce) = Pubkey::find_program_address(&[PDA_SEED.as_bytes()], program_id);
+    // 0 - [signer]   - The admin (holder) account
+    // 1 - [writable] - Program account
+    // 2 - [writable] - The player program account
+    pub fn process_admin_set_reward(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        reward_to_claim: u64,
+    ) -> ProgramResult {
+        msg!("process_admin_set_reward");
+        let AdminSetRewardAccounts {
+            admin: admin_holder_account,
+            game_info: program_account,
+            player: player_program_account,
+        } = AdminSetRewardAccounts::from_account_iter(accounts)?;
 
-        // Make sure it is the token account used during the program initialization
-        if *program_token_account.key != program_account_data.spl_token_account {
-            msg!("Program token account do not match with current program token account");
-            return Err(ProgramError::InvalidAccountData);
+        if program_account.owner != program_id {
+            msg!("Program account owner is not the current program");
+            return Err(ProgramError::IncorrectProgramId);
         }
 
-        let player_token_account = next_account_info(account_iter)?;
-        // No need to check player_token_account owner == spl_token::id(), if invalid owner, program will panic when do transfer
-        let token_program = next_account_info(account_iter)?;
-        if !spl_token::check_id(token_program.key) {
-            msg!("Token program is not SPL TOKEN program");
+        let mut program_account_data = GameInfo::unpack(&program_account.try_borrow_data()?)?;
+        require_admin(admin_holder_account, &program_account_data)?;
+
+        if player_program_account.owner != program_id {
+            msg!("Player program account owner is not the current program");
             return Err(ProgramError::IncorrectProgramId);
         }
 
-        //https://docs.rs/spl-token/3.2.0/spl_token/instruction/fn.transfer.html
-        let transfer_to_player_instruction = spl_token::instruction::transfer(
-            &spl_token::id(),
-            program_token_account.key,
-            player_token_account.key,
-            &pda,
-            &[&pda],
-            player_program_account_data.reward_to_claim,
-        )?;
+        let mut player_program_account_data =
+            Player::unpack(&player_program_account.try_borrow_data()?)?;
 
-        msg!("Claim reward by transfer from program token account to the player");
-        // All account involved in the instruction need to be passed when invoke
-        invoke_signed(
-            &transfer_to_player_instruction,
-            &[
-                program_token_account.clone(),
-                player_token_account.clone(),
-                token_program.clone(),
-                pda_account.clone(),
-            ],
-            &[&[PDA_SEED.as_bytes(), &[nonce]]],
-        )?;
+        // The admin of a program account can only correct the players registered to it
+        if player_program_account_data.program_account != *program_account.key {
+            msg!("Player program account is not registered to the program account");
+            return Err(GameError::InvalidPlayerAccount.into());
+        }
 
-        // After transfer, reset reward amount for player
-        player_program_account_data.reward_to_claim = 0;
+        // Overwrite rather than credit, total_earned is left as is, the cap still applies
+        if program_account_data.max_reward_per_player != 0
+            && reward_to_claim > program_account_data.max_reward_per_player
+        {
+            msg!("Reward to claim is above the max reward per player");
+            return Err(GameError::RewardCapExceeded.into());
+        }
+        player_program_account_data.reward_to_claim = reward_to_claim;
 
         Player::pack(
             player_program_account_data,
             &mut player_program_account.try_borrow_mut_data()?,
         )?;
 
+        program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
         Ok(())
     }
 
     // 0 - [signer]   - The admin (holder) account
-    // 1 - []         - Program account
+    // 1 - [writable] - Program account
     // 2 - [writable] - The player program account
-    // 3 - [writable] - The player upline program account
+    // 3 - [writable] - The player upline program account, if any
+    // 4 - [writable] - The second level upline program account, if any
+    // 5 - [writable] - The third level upline program account, if any
     pub fn process_add_reward(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
         reward_amount: u64,
+        saturate: bool,
     ) -> ProgramResult {
         msg!("process_add_reward");
-        let account_iter = &mut accounts.iter();
-        let admin_holder_account = next_account_info(account_iter)?;
-
-        if !admin_holder_account.is_signer {
-            msg!("Admin account must be signed");
-            return Err(ProgramError::IllegalOwner);
-        }
+        let AddRewardAccounts {
+            admin: admin_holder_account,
+            game_info: program_account,
+            player: player_program_account,
+            uplines: upline_player_program_accounts,
+        } = AddRewardAccounts::from_account_iter(accounts)?;
+        let account_iter = &mut upline_player_program_accounts.iter();
 
-        let program_account = next_account_info(account_iter)?;
         if program_account.owner != program_id {
             msg!("Program account owner is not the current program");
             return Err(ProgramError::IncorrectProgramId);
         }
 
-        let player_program_account = next_account_info(account_iter)?;
         if player_program_account.owner != program_id {
             msg!("Player program account owner is not the current program");
             return Err(ProgramError::IncorrectProgramId);
         }
 
-        let program_account_data = GameInfo::unpack_unchecked(&program_account.try_borrow_data()?)?;
+        let mut program_account_data =
+            GameInfo::unpack_unchecked(&program_account.try_borrow_data()?)?;
         if !program_account_data.is_initialized {
             msg!("Program account not initialized");
-            return Err(GameError::NotInitialize.into());
+            return Err(GameError::NotInitialized.into());
         }
 
-        if program_account_data.admin != *admin_holder_account.key {
-            msg!("Add reward only can be executed by admin");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
+        require_admin(admin_holder_account, &program_account_data)?;
 
         let mut player_program_account_data =
             Player::unpack_unchecked(&player_program_account.try_borrow_data()?)?;
 
         if !player_program_account_data.is_initialized {
             msg!("Player program account is not initialized");
-            return Err(GameError::NotInitialize.into());
+            return Err(GameError::NotInitialized.into());
         }
 
-        if player_program_account_data.upline != COption::None {
+        // Credit time of the decay, read from the sysvar so the uplines can stay the last accounts
+        let clock = Clock::get()?;
+
+        // Walk up the referral chain, each upline account must match the upline stored in the previous player
+        let mut uplines = Vec::with_capacity(program_account_data.level_bps.len());
+        let mut upline = player_program_account_data.upline;
+        while uplines.len() < program_account_data.level_bps.len() {
+            let upline_key = match upline {
+                COption::Some(upline_key) => upline_key,
+                COption::None => break,
+            };
             let upline_player_program_account = next_account_info(account_iter)?;
 
             if upline_player_program_account.owner != program_id {
@@ -196,36 +2150,46 @@ impl Processor {
                 return Err(ProgramError::IncorrectProgramId);
             }
 
-            let mut upline_player_program_account_data =
+            let upline_player_program_account_data =
                 Player::unpack_unchecked(&upline_player_program_account.try_borrow_data()?)?;
 
             if !upline_player_program_account_data.is_initialized {
                 msg!("Upline player program account is not initialized");
-                return Err(GameError::NotInitialize.into());
+                return Err(GameError::UplineNotRegistered.into());
             }
 
-            if *upline_player_program_account.key != player_program_account_data.upline.unwrap() {
+            if *upline_player_program_account.key != upline_key {
                 msg!("Upline account passed was not current player upline");
                 return Err(GameError::InvalidUpline.into());
             }
 
-            let upline_reward = reward_amount * 10 / 100;
-            let player_reward = reward_amount * 90 / 100;
-
-            player_program_account_data.reward_to_claim =
-                u64::checked_add(player_program_account_data.reward_to_claim, player_reward)
-                    .ok_or(GameError::RewardAmountOverflow)?;
+            upline = upline_player_program_account_data.upline;
+            uplines.push((
+                upline_player_program_account,
+                upline_player_program_account_data,
+            ));
+        }
 
-            upline_player_program_account_data.reward_to_claim = u64::checked_add(
-                upline_player_program_account_data.reward_to_claim,
+        // The multiplier applies to the whole reward, before it is split
+        let reward_amount = program_account_data.multiply_reward(reward_amount)?;
+
+        // The split of each level is read from the program account, set at init
+        // Share of the missing uplines in a shorter chain stays with the player
+        let shares = distribute(
+            reward_amount,
+            &program_account_data.level_bps[..uplines.len()],
+        );
+        for (
+            (upline_player_program_account, mut upline_player_program_account_data),
+            upline_reward,
+        ) in uplines.into_iter().zip(shares.iter().copied())
+        {
+            let upline_reward = credit_share(
+                &mut upline_player_program_account_data,
                 upline_reward,
-            )
-            .ok_or(GameError::RewardAmountOverflow)?;
-
-            msg!("Player reward {}", player_reward);
-            Player::pack(
-                player_program_account_data,
-                &mut player_program_account.try_borrow_mut_data()?,
+                &program_account_data,
+                clock.unix_timestamp,
+                saturate,
             )?;
 
             msg!("Upline reward {}", upline_reward);
@@ -233,92 +2197,200 @@ impl Processor {
                 upline_player_program_account_data,
                 &mut upline_player_program_account.try_borrow_mut_data()?,
             )?;
-        } else {
-            player_program_account_data.reward_to_claim =
-                u64::checked_add(player_program_account_data.reward_to_claim, reward_amount)
-                    .ok_or(GameError::RewardAmountOverflow)?;
+        }
 
-            Player::pack(
-                player_program_account_data,
-                &mut player_program_account.try_borrow_mut_data()?,
-            )?;
+        let player_reward = credit_share(
+            &mut player_program_account_data,
+            shares[shares.len() - 1],
+            &program_account_data,
+            clock.unix_timestamp,
+            saturate,
+        )?;
+
+        msg!("Player reward {}", player_reward);
+        Player::pack(
+            player_program_account_data,
+            &mut player_program_account.try_borrow_mut_data()?,
+        )?;
+
+        // Less than one per credited account, the shares never add up to more than the reward
+        // The part of a share cut down to the reward cap is not dust, it is credited to nobody and stays in the vault
+        let dust = reward_amount
+            .checked_sub(shares.iter().sum())
+            .ok_or(GameError::RewardAmountOverflow)?;
+        if dust > 0 {
+            msg!("Reward dust {}", dust);
+            program_account_data.dust = program_account_data
+                .dust
+                .checked_add(dust)
+                .ok_or(GameError::RewardAmountOverflow)?;
         }
 
-        // msg!("Add reward {}", reward_amount);
+        program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
         Ok(())
     }
 
     // 0 - [signer]   - The admin (holder) account
     // 1 - [writable] - Program account
     // 2 - [writable] - An token account created by the admin, and pre-funded
-    // 3 - []         - The token program
-    pub fn process_init(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    // 3 - []         - The mint of the token account
+    // 4 - []         - The token program, SPL TOKEN or Token-2022
+    #[allow(clippy::too_many_arguments)]
+    pub fn process_init(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        referral_bonus: u64,
+        max_depth: u8,
+        min_claim: u64,
+        register_fee: u64,
+        max_reward_per_player: u64,
+        decay_bps_per_day: u64,
+        level_bps: [u16; 3],
+    ) -> ProgramResult {
         msg!("process_init");
-        // Make the account info array iterable
-        let account_iter = &mut accounts.iter();
-        let admin_account = next_account_info(account_iter)?;
-        // Make sure the account is signed. This is to prevent holder pass other holder account into the program
-        if !admin_account.is_signer {
-            msg!("Admin account must be signed");
-            return Err(ProgramError::MissingRequiredSignature);
+        // The admin must sign, this is to prevent holder pass other holder account into the program
+        let InitAccounts {
+            admin: admin_account,
+            game_info: program_account,
+            system_program: system_program_account,
+            token_account,
+            mint: mint_account,
+            token_program,
+        } = InitAccounts::from_account_iter(accounts)?;
+
+        // Make sure the program account is the one derived from the game info seed, so there is a single game
+        let (game_info, bump) = game_info_pda(program_id);
+        if *program_account.key != game_info {
+            msg!("Program account is not derived from the game info seed");
+            return Err(GameError::InvalidProgramAccount.into());
         }
 
-        let program_account = next_account_info(account_iter)?;
-        // Make sure the program account passed is for this program
-        if program_account.owner != program_id {
-            msg!("Program account owner is not current program");
+        if !system_program::check_id(system_program_account.key) {
+            msg!("System program account is not the system program");
             return Err(ProgramError::IncorrectProgramId);
         }
 
+        // Make sure the token_program is the SPL TOKEN or the Token-2022 on-chain program, before any CPI
+        if !is_token_program(token_program.key) {
+            msg!("Token program is neither SPL TOKEN nor Token-2022 program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // The uplines cannot be credited more than the reward added to the player
+        let total_level_bps: u128 = level_bps.iter().map(|bps| *bps as u128).sum();
+        if total_level_bps > BPS_DENOMINATOR {
+            msg!(
+                "Reward split levels add up to more than {} bps",
+                BPS_DENOMINATOR
+            );
+            return Err(GameError::InvalidRewardSplit.into());
+        }
+
+        // Create the program account if it does not exist yet, owned by the current program and funded by the admin
+        if program_account.owner != program_id {
+            create_pda_account(
+                admin_account,
+                program_account,
+                system_program_account,
+                GameInfo::LEN,
+                program_id,
+                &[GAME_INFO_SEED.as_bytes(), &[bump]],
+            )?;
+        }
+
+        // Make sure the program account will not be garbage collected
+        if !Rent::get()?.is_exempt(program_account.lamports(), program_account.data_len()) {
+            msg!("Program account is not rent exempt");
+            return Err(GameError::NotRentExempt.into());
+        }
+
         let mut program_account_data =
             GameInfo::unpack_unchecked(&program_account.try_borrow_data()?)?;
         if program_account_data.is_initialized {
             msg!("Program account already initialized");
-            return Err(GameError::AlreadyInitialize.into());
+            return Err(GameError::AlreadyInitialized.into());
         }
 
-        let token_account = next_account_info(account_iter)?;
-        // Make sure token_account belongs to TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA (SPL TOKEN)
-        if *token_account.owner != spl_token::id() {
-            msg!("Token account owner is not SPL TOKEN program");
+        // Make sure token_account belongs to the token program passed, the one the game then uses
+        if token_account.owner != token_program.key {
+            msg!("Token account owner is not the token program");
             return Err(ProgramError::IllegalOwner);
         }
 
-        // How to check whether the token_account is an account for token program ? Eg: rent space ? Or, should I check it ?
-        // Todo: Transfer token_account ownership to current program PDA
-        // Derive program address
-        // Destructure pubkey as pda, put _ to avoid compiler complain unused nonce
-        let (pda, _nonce) = Pubkey::find_program_address(&[PDA_SEED.as_bytes()], program_id);
+        // Make sure the token account is controlled by the admin, so the admin can hand it over to the PDA
+        let token_account_data = unpack_token_account(token_account)?;
+        if token_account_data.owner != *admin_account.key {
+            msg!("Token account is not owned by the admin");
+            return Err(GameError::InvalidTokenAccount.into());
+        }
 
-        let token_program = next_account_info(account_iter)?;
-        // Make sure the token_program is the SPL_TOKEN on-chain program
-        if !spl_token::check_id(token_program.key) {
-            msg!("Token program is not SPL TOKEN program");
-            return Err(ProgramError::IncorrectProgramId);
+        if mint_account.owner != token_program.key {
+            msg!("Mint account owner is not the token program");
+            return Err(ProgramError::IllegalOwner);
         }
+        if token_account_data.mint != *mint_account.key {
+            msg!("Token account mint does not match the mint account");
+            return Err(GameError::InvalidTokenAccount.into());
+        }
+
+        // Derive the vault PDA, its bump is stored so claims can sign for it
+        let (pda, vault_bump) = vault_pda(program_id);
 
         // Transfer token_account ownership (not the program owner, the owner here is the owner in token_account data) to the pda
-        let set_authority_instruction = spl_token::instruction::set_authority(
+        let set_authority_instruction = for_token_program(
+            spl_token::instruction::set_authority(
+                &spl_token::id(),
+                token_account.key,
+                Some(&pda),
+                spl_token::instruction::AuthorityType::AccountOwner,
+                admin_account.key,
+                &[admin_account.key],
+            )?,
             token_program.key,
-            token_account.key,
-            Some(&pda),
-            spl_token::instruction::AuthorityType::AccountOwner,
-            admin_account.key,
-            &[&admin_account.key],
-        )?;
+        );
 
         // Invoke token_account ownership transfer
         // Order need to match https://github.com/solana-labs/solana-program-library/blob/master/token/program/src/instruction.rs ?
         // All account involved in the instruction need to be passed when invoke
         invoke(
             &set_authority_instruction,
-            &[token_account.clone(), admin_account.clone()],
+            &[
+                token_account.clone(),
+                admin_account.clone(),
+                token_program.clone(),
+            ],
         )?;
 
         // Update program account data
-        program_account_data.admin = *admin_account.key;
+        program_account_data.version = CURRENT_VERSION;
+        program_account_data.admins = [Pubkey::default(); MAX_ADMINS];
+        program_account_data.admins[0] = *admin_account.key;
         program_account_data.is_initialized = true;
         program_account_data.spl_token_account = *token_account.key;
+        program_account_data.total_players = 0;
+        program_account_data.referral_bonus = referral_bonus;
+        program_account_data.paused = false;
+        program_account_data.max_depth = max_depth;
+        // Taken from the token account, the mint account has been checked against it above
+        program_account_data.mint = token_account_data.mint;
+        program_account_data.min_claim = min_claim;
+        program_account_data.register_fee = register_fee;
+        program_account_data.max_reward_per_player = max_reward_per_player;
+        program_account_data.nonce = 0;
+        program_account_data.decay_bps_per_day = decay_bps_per_day;
+        program_account_data.vault_bump = vault_bump;
+        program_account_data.level_bps = level_bps;
+        program_account_data.dust = 0;
+        program_account_data.tier_thresholds = [0; 3];
+        program_account_data.vest_duration = 0;
+        program_account_data.reward_multiplier_bps = BPS_DENOMINATOR as u16;
+        program_account_data.token_program = *token_program.key;
+        let nonce = program_account_data.next_nonce()?;
 
         // Pack / serialize the updated program account data
         GameInfo::pack(
@@ -326,36 +2398,89 @@ impl Processor {
             &mut program_account.try_borrow_mut_data()?,
         )?;
 
+        GameEvent::Initialized {
+            nonce,
+            admin: *admin_account.key,
+        }
+        .emit()?;
+
         Ok(())
     }
 
-    // 0 - [signer]   - The player (holder) account
-    // 1 - [writable] - The player account for the program
-    // 2 - []         - The program account
-    // 3 - []         - The upline player account for the program
-    pub fn process_register(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    // 0 - [signer, writable] - The player (holder) account or the relayer, which pay for the player account rent
+    // 1 - [writable]         - The player account for the program, PDA of the holder
+    // 2 - [writable]         - The program account
+    // 3 - []                 - The system program
+    // 4 - []                 - The clock sysvar
+    // 5 - [writable]         - The upline player account for the program
+    pub fn process_register(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        idempotent: bool,
+        holder: Option<Pubkey>,
+    ) -> ProgramResult {
         msg!("process_register");
-        let account_iter = &mut accounts.iter();
-        let player_holder_account = next_account_info(account_iter)?;
+        let register_accounts = RegisterAccounts::from_account_iter(accounts)?;
+        // Without a holder in the instruction data, the signer registers themselves
+        let holder = holder.unwrap_or(*register_accounts.holder.key);
+
+        Self::register_player(
+            program_id,
+            &holder,
+            register_accounts.holder,
+            register_accounts.player,
+            register_accounts.game_info,
+            register_accounts.system_program,
+            register_accounts.clock,
+            register_accounts.upline,
+            idempotent,
+        )
+    }
 
-        if !player_holder_account.is_signer {
-            msg!("Player holder account must be signed");
-            return Err(ProgramError::MissingRequiredSignature);
+    pub fn process_batch_register(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        msg!("process_batch_register");
+        let batch_register_accounts = BatchRegisterAccounts::from_account_iter(accounts)?;
+
+        // Any invalid pair fails the whole transaction, so no player of the batch is registered
+        for pair in batch_register_accounts.players.chunks(2) {
+            Self::register_player(
+                program_id,
+                pair[0].key,
+                &pair[0],
+                &pair[1],
+                batch_register_accounts.game_info,
+                batch_register_accounts.system_program,
+                batch_register_accounts.clock,
+                Some(batch_register_accounts.upline),
+                false,
+            )?;
         }
 
-        let player_program_account = next_account_info(account_iter)?;
-        if player_program_account.owner != program_id {
-            msg!("Player program account owner is not the current program");
-            return Err(ProgramError::IncorrectProgramId);
-        }
+        Ok(())
+    }
 
-        let mut player_data = Player::unpack_unchecked(&player_program_account.try_borrow_data()?)?;
-        if player_data.is_initialized {
-            msg!("Player program account already initialized (registered)");
-            return Err(ProgramError::AccountAlreadyInitialized);
+    // Register a single player of the holder, shared by register and batch register
+    // The player holder account is the signer paying for the player, the holder itself or the relayer
+    // Its signature has been checked by the instruction accounts
+    #[allow(clippy::too_many_arguments)]
+    fn register_player<'a>(
+        program_id: &Pubkey,
+        holder: &Pubkey,
+        player_holder_account: &AccountInfo<'a>,
+        player_program_account: &AccountInfo<'a>,
+        program_account: &AccountInfo<'a>,
+        system_program_account: &AccountInfo<'a>,
+        clock_sysvar_account: &AccountInfo<'a>,
+        upline_player_program_account: Option<&AccountInfo<'a>>,
+        idempotent: bool,
+    ) -> ProgramResult {
+        // Make sure the player account is the one derived from the holder, so a holder cannot register twice
+        let (pda, bump) = player_pda(program_id, holder);
+        if *player_program_account.key != pda {
+            msg!("Player program account is not derived from the player holder account");
+            return Err(GameError::InvalidPlayerAccount.into());
         }
 
-        let program_account = next_account_info(account_iter)?;
         // Bind player account with program account to prevent user create another program account, add reward to themselves, and pass the "fake" player account
         // Check program owner = current program
         if program_account.owner != program_id {
@@ -363,36 +2488,153 @@ impl Processor {
             return Err(ProgramError::IncorrectProgramId);
         }
 
-        let has_upline = accounts.len() == 4;
+        let mut program_account_data =
+            GameInfo::unpack_unchecked(&program_account.try_borrow_data()?)?;
+        if let Err(error) = check_register(&program_account_data, player_holder_account.key, holder)
+        {
+            msg!("Player cannot register: {}", error);
+            return Err(error.into());
+        }
+
+        if !system_program::check_id(system_program_account.key) {
+            msg!("System program account is not the system program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let clock = Clock::from_account_info(clock_sysvar_account)?;
 
-        if has_upline {
-            let upline_player_program_account = next_account_info(account_iter)?;
-            // Check upline owner = current program
-            if upline_player_program_account.owner != program_id {
-                msg!("Upline player program account owner is not the current program");
-                return Err(ProgramError::IncorrectProgramId);
+        // Create the player account if it does not exist yet, owned by the current program and funded by the holder
+        if player_program_account.owner != program_id {
+            create_pda_account(
+                player_holder_account,
+                player_program_account,
+                system_program_account,
+                Player::LEN,
+                program_id,
+                &[PLAYER_SEED.as_bytes(), holder.as_ref(), &[bump]],
+            )?;
+        }
+
+        // The player account may have been created by the client before register
+        if !Rent::get()?.is_exempt(
+            player_program_account.lamports(),
+            player_program_account.data_len(),
+        ) {
+            msg!("Player program account is not rent exempt");
+            return Err(GameError::NotRentExempt.into());
+        }
+
+        let mut player_data = Player::unpack_unchecked(&player_program_account.try_borrow_data()?)?;
+        if player_data.is_initialized {
+            // A retried register is a no-op, the player and upline are left untouched
+            if idempotent {
+                msg!("Player program account already registered, skipped");
+                return Ok(());
             }
+            msg!("Player program account already initialized (registered)");
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
 
+        if let Some(upline_player_program_account) = upline_player_program_account {
             // Check upline is not self-recursive
             if upline_player_program_account.key == player_program_account.key {
                 msg!("Upline cannot be same account as current player");
                 return Err(GameError::SelfRecursiveUpline.into());
             }
 
+            // Check upline owner = current program, otherwise the upline could point to an arbitrary account
+            if upline_player_program_account.owner != program_id {
+                msg!("Upline player program account owner is not the current program");
+                return Err(GameError::UplineNotRegistered.into());
+            }
+
+            let mut upline_player_data =
+                Player::unpack_unchecked(&upline_player_program_account.try_borrow_data()?)?;
+            let (credited_bonus, referral_bonus) = register_under_upline(
+                &mut player_data,
+                &mut upline_player_data,
+                holder,
+                &mut program_account_data,
+                clock.unix_timestamp,
+            )
+            .map_err(|error| {
+                msg!("Player cannot register under the upline: {}", error);
+                error
+            })?;
+            if credited_bonus < referral_bonus {
+                msg!(
+                    "Referral bonus cut down to {} by the reward cap",
+                    credited_bonus
+                );
+            }
+            Player::pack(
+                upline_player_data,
+                &mut upline_player_program_account.try_borrow_mut_data()?,
+            )?;
+
             player_data.upline = COption::Some(*upline_player_program_account.key);
         } else {
             player_data.upline = COption::None;
+            player_data.depth = 0;
         }
 
         player_data.is_initialized = true;
-        player_data.owner = *player_holder_account.key;
+        player_data.owner = *holder;
+        player_data.program_account = *program_account.key;
         player_data.reward_to_claim = 0;
+        player_data.downline_count = 0;
+        player_data.registered_at = clock.unix_timestamp;
+        player_data.last_claim_at = 0;
+        player_data.total_earned = 0;
+        player_data.referral_code = Player::referral_code_of(player_program_account.key);
+        player_data.last_credit_at = 0;
+        player_data.frozen = false;
+        player_data.tier = 0;
+        player_data.vesting = Vesting::default();
+
+        // The fee stays on the program account, on top of its rent exempt minimum, until the admin sweep it
+        if program_account_data.register_fee > 0 {
+            if !player_holder_account.is_writable {
+                msg!("Player holder account must be writable to pay the register fee");
+                return Err(GameError::AccountNotWritable.into());
+            }
+            invoke(
+                &system_instruction::transfer(
+                    player_holder_account.key,
+                    program_account.key,
+                    program_account_data.register_fee,
+                ),
+                &[
+                    player_holder_account.clone(),
+                    program_account.clone(),
+                    system_program_account.clone(),
+                ],
+            )?;
+        }
 
+        let upline = player_data.upline.into();
         Player::pack(
             player_data,
             &mut player_program_account.try_borrow_mut_data()?,
         )?;
 
+        program_account_data.total_players = program_account_data
+            .total_players
+            .checked_add(1)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        let nonce = program_account_data.next_nonce()?;
+        GameInfo::pack(
+            program_account_data,
+            &mut program_account.try_borrow_mut_data()?,
+        )?;
+
+        GameEvent::PlayerRegistered {
+            nonce,
+            player: *player_program_account.key,
+            upline,
+        }
+        .emit()?;
+
         Ok(())
     }
 }
@@ -1,5 +1,9 @@
+use crate::constants::{GAME_INFO_LEN, MAX_ADMINS, PLAYER_LEN};
+use crate::error::GameError;
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
+    hash::hash,
     msg,
     program_error::ProgramError,
     program_option::COption,
@@ -7,13 +11,132 @@ use solana_program::{
     pubkey::Pubkey,
 };
 
-#[derive(Debug)]
+// Layout version of GameInfo, bump it whenever the layout changes
+// Accounts of an older version are not read, see the migration comment of Pack for GameInfo
+pub const CURRENT_VERSION: u8 = 19;
+
+// Borsh layout of the fields matches LEN, Pack is kept as a thin wrapper for the length and version checks
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, Eq, PartialEq)]
 pub struct GameInfo {
+    // Layout version, 0 until the program account is initialized
+    pub version: u8,          // 1
     pub is_initialized: bool, // 1
-    // Account authorized to add reward to player
-    pub admin: Pubkey, // 32
+    // Accounts authorized to add reward to player, any of them can sign the admin instructions
+    // Unused slots are the default pubkey, the admin of init is the first one
+    pub admins: [Pubkey; MAX_ADMINS], // 32 * 3
     // Token account which has been pre-funded
     pub spl_token_account: Pubkey, // 32
+    // Number of players registered to the program account
+    pub total_players: u64, // 8
+    // Reward credited to the upline whenever a downline registers
+    pub referral_bonus: u64, // 8
+    // Register and claim are rejected while the admin paused the game
+    pub paused: bool, // 1
+    // Deepest position in the referral chain a player can register at, a player without upline is at depth 0
+    pub max_depth: u8, // 1
+    // Mint of the token account, player token accounts must hold the same mint to claim
+    pub mint: Pubkey, // 32
+    // Smallest reward a player can claim at once, 0 disables the check
+    pub min_claim: u64, // 8
+    // Lamports paid by the holder to the program account on register, 0 for a free register
+    pub register_fee: u64, // 8
+    // Highest reward_to_claim a player can reach, crediting above it is rejected, 0 disables the cap
+    pub max_reward_per_player: u64, // 8
+    // Nonce of the last state-changing instruction or emitted event, 0 before the first one
+    pub nonce: u64, // 8
+    // Share of the unclaimed reward lost for every whole day since it was credited, in basis points, 0 disables the decay
+    pub decay_bps_per_day: u64, // 8
+    // Budget funded by the admin, each referral bonus draws the same amount again from it while it lasts
+    pub matching_pool: u64, // 8
+    // Account allowed to register players on behalf of their holder, the default pubkey when there is none
+    pub relayer: Pubkey, // 32
+    // Bump of the vault PDA owning spl_token_account, stored at init so claims do not search for it again
+    pub vault_bump: u8, // 1
+    // Share of a reward added to a player credited to its direct upline, second and third level upline, in basis points
+    // The rest goes to the player, they never add up to more than BPS_DENOMINATOR
+    pub level_bps: [u16; 3], // 2 * 3
+    // Part of the added rewards rounded off by the reward split and credited to nobody, until the admin sweeps it
+    // The part of a share cut down to the reward cap by a saturating add reward is not counted, a sweep only takes the rounding
+    pub dust: u64, // 8
+    // Lifetime total_earned a player needs to reach each tier, bronze, silver then gold
+    // 0 disables the tier and the ones after it
+    pub tier_thresholds: [u64; 3], // 8 * 3
+    // Seconds over which each credited reward unlocks linearly before it can be claimed, 0 for instantly claimable rewards
+    pub vest_duration: u64, // 8
+    // Multiplier of every reward and referral bonus credited, in basis points, BPS_DENOMINATOR credits them as is
+    pub reward_multiplier_bps: u16, // 2
+    // Program of the mint and of every token account of the game, the classic SPL token program or Token-2022, set at init
+    pub token_program: Pubkey, // 32
+}
+
+impl GameInfo {
+    pub fn is_admin(&self, key: &Pubkey) -> bool {
+        *key != Pubkey::default() && self.admins.contains(key)
+    }
+
+    pub fn is_relayer(&self, key: &Pubkey) -> bool {
+        *key != Pubkey::default() && self.relayer == *key
+    }
+
+    // Number of admin slots in use
+    pub fn admin_count(&self) -> usize {
+        self.admins
+            .iter()
+            .filter(|admin| **admin != Pubkey::default())
+            .count()
+    }
+
+    // Referral bonus paid to the upline on register, matched from the pool up to what is left in it
+    // Returns the bonus and the part of it matched from the pool
+    // The pool is drawn down, the program account must be packed afterward to keep it
+    pub fn draw_referral_bonus(&mut self) -> Result<(u64, u64), GameError> {
+        let matched_amount = self.referral_bonus.min(self.matching_pool);
+        self.matching_pool -= matched_amount;
+        let referral_bonus = self
+            .referral_bonus
+            .checked_add(matched_amount)
+            .ok_or(GameError::RewardAmountOverflow)?;
+        Ok((referral_bonus, matched_amount))
+    }
+
+    // Give back to the pool the part of the matched amount of a referral bonus the reward cap left out
+    // The uncredited amount is multiplied by reward_multiplier_bps, it is divided back, and left out of the matched amount first
+    // Returns the amount given back
+    pub fn refund_matching_pool(
+        &mut self,
+        matched_amount: u64,
+        uncredited_amount: u64,
+    ) -> Result<u64, GameError> {
+        if self.reward_multiplier_bps == 0 {
+            return Ok(0);
+        }
+        let refunded_amount = (uncredited_amount as u128 * BPS_DENOMINATOR
+            / self.reward_multiplier_bps as u128)
+            .min(matched_amount as u128) as u64;
+        self.matching_pool = self
+            .matching_pool
+            .checked_add(refunded_amount)
+            .ok_or(GameError::RewardAmountOverflow)?;
+        Ok(refunded_amount)
+    }
+
+    // Amount actually credited for a reward or a referral bonus of amount, once multiplied by reward_multiplier_bps
+    // Computed in u128, a multiplied amount above u64::MAX is rejected
+    pub fn multiply_reward(&self, amount: u64) -> Result<u64, GameError> {
+        let multiplied_amount =
+            amount as u128 * self.reward_multiplier_bps as u128 / BPS_DENOMINATOR;
+        u64::try_from(multiplied_amount).map_err(|_| GameError::RewardAmountOverflow)
+    }
+
+    // Take the nonce of the next state-changing instruction or event, the program account must be packed afterward to keep it
+    // Every instruction changing the program or a player account takes one, an instruction emitting events one per event
+    pub fn next_nonce(&mut self) -> Result<u64, GameError> {
+        self.nonce = self
+            .nonce
+            .checked_add(1)
+            .ok_or(GameError::RewardAmountOverflow)?;
+        Ok(self.nonce)
+    }
 }
 
 // Pack expect Sealed and IsInitialized
@@ -28,51 +151,48 @@ impl IsInitialized for GameInfo {
 }
 
 impl Pack for GameInfo {
-    const LEN: usize = 1 + 32 + 32;
+    // Migration: version 1 accounts, before paused was added, are 82 bytes
+    // version 2 accounts, before max_depth was added, are 83 bytes
+    // version 3 accounts, before mint was added, are 84 bytes
+    // version 4 accounts, before min_claim was added, are 116 bytes
+    // version 5 accounts, before register_fee was added, are 124 bytes
+    // version 6 accounts, before max_reward_per_player was added, are 132 bytes
+    // version 7 accounts, before nonce was added, are 140 bytes
+    // version 8 accounts, with a single admin, are 148 bytes
+    // version 9 accounts, before decay_bps_per_day was added, are 212 bytes
+    // version 10 accounts, before matching_pool was added, are 220 bytes
+    // version 11 accounts, before relayer was added, are 228 bytes
+    // version 12 accounts, before vault_bump was added, are 260 bytes
+    // version 13 accounts, before level_bps was added, are 261 bytes
+    // version 14 accounts, before dust was added, are 267 bytes
+    // version 15 accounts, before tier_thresholds was added, are 275 bytes
+    // version 16 accounts, before vest_duration was added, are 299 bytes
+    // version 17 accounts, before reward_multiplier_bps was added, are 307 bytes
+    // and version 18 accounts, before token_program was added, are 309 bytes
+    // All of them are rejected by unpack_unchecked, there is no GameInfo migration like the one of Player
+    // Upgrading a deployed game to a new layout needs such a migration written first,
+    // as its program account cannot be initialized again at the same address
+    const LEN: usize = GAME_INFO_LEN;
     // Unpack account data (byte buffer) to GameInfo
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
-        // Shadow src argument, and use array_ref! to make src slice-able
-        let src = array_ref![src, 0, GameInfo::LEN];
-        // Slice src based on struct property byte
-        let (is_initialized, owner, spl_token_account) = array_refs![&src, 1, 32, 32];
-        // Convert is_initialized from byte to bool
-        let is_initialized = match is_initialized {
-            // First element is 0
-            [0] => false,
-            // First element is 1
-            [1] => true,
-            _ => return Err(ProgramError::InvalidAccountData),
+        // Only the current layout is read, the length check of unpack_unchecked already rejected the older ones
+        // The version only guards against an account written by a newer program, of the same length
+        match src[0] {
+            0..=CURRENT_VERSION => {}
+            version => {
+                msg!("Unsupported program account version {}", version);
+                return Err(ProgramError::InvalidAccountData);
+            }
         };
-        // Return GameInfo struct, which unpacked from account data
-        Ok(GameInfo {
-            is_initialized,
-            // Dereference owner to get the byte array in heap
-            admin: Pubkey::new_from_array(*owner),
-            // Dereference spl_token_account to get the byte array in heap
-            spl_token_account: Pubkey::new_from_array(*spl_token_account),
-        })
+        // Borsh write the fields in declaration order, booleans other than 0 or 1 are rejected
+        GameInfo::try_from_slice(src).map_err(|_| ProgramError::InvalidAccountData)
     }
 
     // Pack GameInfo struct into account data (byte buffer)
     fn pack_into_slice(&self, dst: &mut [u8]) {
-        // Shadow dst argument, and make it slice-able
-        let dst = array_mut_ref![dst, 0, GameInfo::LEN];
-        // Slice dst into mutable byte chunks
-        // Added _dst postfix to avoid shadowing when destructure from GameInfo struct
-        let (is_initialized_dst, admin_dst, spl_token_account_dst) =
-            mut_array_refs![dst, 1, 32, 32];
-        // Destructure GameInfo struct
-        let GameInfo {
-            is_initialized,
-            admin,
-            spl_token_account,
-        } = self;
-        // Since the sliced chunks are mutable, direct modify the chunks content will reflect in account data
-        is_initialized_dst[0] = *is_initialized as u8;
-        // Convert owner from Pubkey struct to byte array, then copy it into owner_dst
-        admin_dst.copy_from_slice(admin.as_ref());
-        // Convert spl_token_account from Pubkey struct to byte array, then copy it into spl_token_account_dst
-        spl_token_account_dst.copy_from_slice(spl_token_account.as_ref());
+        // Pack already checked dst is exactly LEN bytes, so serializing cannot run out of space
+        self.serialize(&mut &mut *dst)
+            .expect("GameInfo::LEN matches the Borsh layout");
     }
 
     fn unpack_unchecked(input: &[u8]) -> Result<Self, ProgramError> {
@@ -96,13 +216,178 @@ impl Pack for GameInfo {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Player {
     pub is_initialized: bool,    // 1 byte
     pub owner: Pubkey,           // 32 byte
     pub reward_to_claim: u64,    // 8 byte, follow SPL token amount byte
     pub program_account: Pubkey, // 32 byte
     pub upline: COption<Pubkey>, // 4 + 32 byte  msg!("{:?}", size_of::<COption<Pubkey>>()) shows 36
+    // Number of players registered with this player as upline
+    pub downline_count: u32, // 4 byte
+    // Unix timestamp of the register, taken from the Clock sysvar
+    pub registered_at: i64, // 8 byte
+    // Unix timestamp of the last claim, 0 if the player never claimed
+    pub last_claim_at: i64, // 8 byte
+    // Lifetime reward credited by the program, never decreased on claim and not increased by transfers between players
+    pub total_earned: u64, // 8 byte
+    // Short code derived from the player account, shared instead of the raw pubkey
+    pub referral_code: [u8; 8], // 8 byte
+    // Number of uplines above the player, 0 when registered without upline
+    pub depth: u8, // 1 byte
+    // Unix timestamp of the last reward credited by the program, the reward to claim decays from there
+    // 0 if the player was never credited since the field was added, which never decays
+    pub last_credit_at: i64, // 8 byte
+    // Set by the admin for abuse handling, a frozen player can neither claim nor be taken as upline
+    pub frozen: bool, // 1 byte
+    // Number of GameInfo tier thresholds reached by total_earned, 0 below bronze and 3 for gold
+    pub tier: u8, // 1 byte
+    // Part of the reward to claim still unlocking, all zero when nothing is vesting
+    pub vesting: Vesting, // 8 + 8 + 8 byte
+}
+
+// A vesting entry, amount unlocks linearly from start over duration seconds
+// Later credits are merged in, the amount still locked and the new reward restart together from the new credit
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Vesting {
+    pub amount: u64,   // 8 byte
+    pub start: i64,    // 8 byte
+    pub duration: u64, // 8 byte
+}
+
+impl Vesting {
+    // Part of the amount still locked at the now unix timestamp, computed in u128 so it cannot wrap
+    pub fn locked(&self, now: i64) -> u64 {
+        // A clock behind the start is no time elapsed
+        let elapsed = now.saturating_sub(self.start).max(0) as u64;
+        if elapsed >= self.duration {
+            return 0;
+        }
+        let vested = self.amount as u128 * elapsed as u128 / self.duration as u128;
+        self.amount - vested as u64
+    }
+}
+
+// Sizes of the previous Player layouts, oldest first, see the migration comment of Pack for Player
+// Fields have only ever been appended, so an old layout is a prefix of the current one
+pub const LEGACY_PLAYER_LENS: [usize; 10] = [109, 113, 121, 129, 137, 145, 146, 154, 155, 156];
+// Last previous layouts without the referral code and without the depth, migrate player fills them in
+pub const PRE_REFERRAL_CODE_PLAYER_LEN: usize = 129;
+pub const PRE_DEPTH_PLAYER_LEN: usize = 145;
+
+// Only whole days since the last credit count toward the decay
+pub const DECAY_PERIOD_SECS: i64 = 24 * 60 * 60;
+// Basis points of a whole amount, for the decay and the reward split
+pub const BPS_DENOMINATOR: u128 = 10_000;
+
+impl Player {
+    // Read a player account of a previous layout, the fields missing from it are zero
+    pub fn unpack_legacy(src: &[u8]) -> Result<Self, ProgramError> {
+        if !LEGACY_PLAYER_LENS.contains(&src.len()) {
+            msg!("Player account size {} is not a previous layout", src.len());
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Player::unpack_from_slice(src)
+    }
+
+    // Credit reward from the program at the now unix timestamp, which also count toward the lifetime total_earned
+    // The reward to claim cannot go above max_reward_per_player, unless it is 0
+    pub fn credit_reward(
+        &mut self,
+        amount: u64,
+        max_reward_per_player: u64,
+        now: i64,
+    ) -> Result<(), GameError> {
+        let reward_to_claim = self
+            .reward_to_claim
+            .checked_add(amount)
+            .ok_or(GameError::RewardAmountOverflow)?;
+        if max_reward_per_player != 0 && reward_to_claim > max_reward_per_player {
+            return Err(GameError::RewardCapExceeded);
+        }
+        self.reward_to_claim = reward_to_claim;
+        // total_earned is only for display, saturate instead of rejecting the reward
+        self.total_earned = self.total_earned.saturating_add(amount);
+        self.last_credit_at = now;
+        Ok(())
+    }
+
+    // Credit reward like credit_reward, except the part above max_reward_per_player is left out instead of rejected
+    // Returns the part of the amount credited
+    pub fn credit_reward_saturating(
+        &mut self,
+        amount: u64,
+        max_reward_per_player: u64,
+        now: i64,
+    ) -> Result<u64, GameError> {
+        let amount = if max_reward_per_player == 0 {
+            amount
+        } else {
+            amount.min(max_reward_per_player.saturating_sub(self.reward_to_claim))
+        };
+        // A player already at or above a lowered cap has nothing credited, instead of failing the whole reward
+        if amount == 0 {
+            return Ok(0);
+        }
+        self.credit_reward(amount, max_reward_per_player, now)?;
+        Ok(amount)
+    }
+
+    // Recompute the tier from total_earned, to be called after every credit
+    // The thresholds are increasing up to the first 0, which disables the remaining tiers
+    pub fn update_tier(&mut self, tier_thresholds: &[u64; 3]) {
+        self.tier = tier_thresholds
+            .iter()
+            .take_while(|&&threshold| threshold != 0 && self.total_earned >= threshold)
+            .count() as u8;
+    }
+
+    // Lock amount of the reward just credited at the now unix timestamp for vest_duration seconds, 0 leaves it claimable
+    // The part of the previous entry still locked is locked again with it
+    pub fn vest(&mut self, amount: u64, vest_duration: u64, now: i64) -> Result<(), GameError> {
+        if vest_duration == 0 {
+            return Ok(());
+        }
+        self.vesting = Vesting {
+            amount: self
+                .vesting
+                .locked(now)
+                .checked_add(amount)
+                .ok_or(GameError::RewardAmountOverflow)?,
+            start: now,
+            duration: vest_duration,
+        };
+        Ok(())
+    }
+
+    // Part of the reward to claim unlocked at the now unix timestamp
+    // The reward can drop below the locked amount through the decay, nothing is claimable until it vests further
+    pub fn claimable(&self, now: i64) -> u64 {
+        self.reward_to_claim
+            .saturating_sub(self.vesting.locked(now))
+    }
+
+    // Part of the reward to claim lost at the now unix timestamp, decay_bps_per_day of it for every whole day since the last credit
+    // Computed in u128 and saturated, so it is never more than the reward to claim
+    pub fn accrued_decay(&self, decay_bps_per_day: u64, now: i64) -> u64 {
+        if self.last_credit_at == 0 {
+            return 0;
+        }
+        // A clock behind the last credit is no time elapsed
+        let elapsed_days = now.saturating_sub(self.last_credit_at).max(0) / DECAY_PERIOD_SECS;
+        let decay = (self.reward_to_claim as u128)
+            .saturating_mul(decay_bps_per_day as u128)
+            .saturating_mul(elapsed_days as u128)
+            / BPS_DENOMINATOR;
+        decay.min(self.reward_to_claim as u128) as u64
+    }
+
+    // First 8 bytes of the sha256 of the player account, so the code never changes for a player
+    pub fn referral_code_of(player: &Pubkey) -> [u8; 8] {
+        let mut code = [0; 8];
+        code.copy_from_slice(&hash(player.as_ref()).to_bytes()[..8]);
+        code
+    }
 }
 
 impl Sealed for Player {}
@@ -113,15 +398,52 @@ impl IsInitialized for Player {
     }
 }
 
+// Player keeps a manual layout: upline is a COption with a 4 byte tag and always 32 bytes of pubkey, so the size is constant
+// Borsh would write an Option with a 1 byte tag and skip the pubkey when None, which cannot be a fixed Pack::LEN
 impl Pack for Player {
-    const LEN: usize = 1 + 32 + 8 + 32 + 4 + 32;
+    // Migration: accounts created before downline_count was added are 109 bytes, before registered_at was added are 113 bytes,
+    // before last_claim_at was added are 121 bytes, before total_earned was added are 129 bytes
+    // before referral_code was added are 137 bytes, before depth was added are 145 bytes
+    // before last_credit_at was added are 146 bytes, before frozen was added are 154 bytes
+    // before tier was added are 155 bytes and before vesting was added are 156 bytes
+    // All of them are read by unpack with the default of the fields they miss, so the program keeps serving them
+    // right after an upgrade, but they cannot be packed into until moved to the current layout by migrate player
+    const LEN: usize = PLAYER_LEN;
     // Unpack account data (byte buffer) to Player
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        // Fields have only been appended, a previous layout is read as the current one padded with zero
+        if src.len() != Player::LEN {
+            if !LEGACY_PLAYER_LENS.contains(&src.len()) {
+                msg!("Player account size {} is not a known layout", src.len());
+                return Err(ProgramError::InvalidAccountData);
+            }
+            let mut padded = [0; Player::LEN];
+            padded[..src.len()].copy_from_slice(src);
+            return Player::unpack_from_slice(&padded);
+        }
         // Shadow src argument, and use array_ref! to make src slice-able
         let src = array_ref![src, 0, Player::LEN];
         // Slice src based on struct property byte
-        let (is_initialized, owner, reward_to_claim, program_account, has_upline, upline) =
-            array_refs![&src, 1, 32, 8, 32, 4, 32];
+        let (
+            is_initialized,
+            owner,
+            reward_to_claim,
+            program_account,
+            has_upline,
+            upline,
+            downline_count,
+            registered_at,
+            last_claim_at,
+            total_earned,
+            referral_code,
+            depth,
+            last_credit_at,
+            frozen,
+            tier,
+            vest_amount,
+            vest_start,
+            vest_duration,
+        ) = array_refs![&src, 1, 32, 8, 32, 4, 32, 4, 8, 8, 8, 8, 1, 8, 1, 1, 8, 8, 8];
         // Convert is_initialized from byte to bool
         let is_initialized = match is_initialized {
             // First element is 0
@@ -130,6 +452,12 @@ impl Pack for Player {
             [1] => true,
             _ => return Err(ProgramError::InvalidAccountData),
         };
+        let frozen = match frozen {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        // Any other tag is corrupt data, it is rejected rather than read as None
         let upline = match has_upline {
             [0, 0, 0, 0] => COption::None,
             [1, 0, 0, 0] => COption::Some(Pubkey::new_from_array(*upline)),
@@ -143,6 +471,20 @@ impl Pack for Player {
             reward_to_claim: u64::from_le_bytes(*reward_to_claim),
             program_account: Pubkey::new_from_array(*program_account),
             upline,
+            downline_count: u32::from_le_bytes(*downline_count),
+            registered_at: i64::from_le_bytes(*registered_at),
+            last_claim_at: i64::from_le_bytes(*last_claim_at),
+            total_earned: u64::from_le_bytes(*total_earned),
+            referral_code: *referral_code,
+            depth: depth[0],
+            last_credit_at: i64::from_le_bytes(*last_credit_at),
+            frozen,
+            tier: tier[0],
+            vesting: Vesting {
+                amount: u64::from_le_bytes(*vest_amount),
+                start: i64::from_le_bytes(*vest_start),
+                duration: u64::from_le_bytes(*vest_duration),
+            },
         })
     }
 
@@ -159,7 +501,19 @@ impl Pack for Player {
             program_account_dst,
             has_upline_dst,
             upline_dst,
-        ) = mut_array_refs![dst, 1, 32, 8, 32, 4, 32];
+            downline_count_dst,
+            registered_at_dst,
+            last_claim_at_dst,
+            total_earned_dst,
+            referral_code_dst,
+            depth_dst,
+            last_credit_at_dst,
+            frozen_dst,
+            tier_dst,
+            vest_amount_dst,
+            vest_start_dst,
+            vest_duration_dst,
+        ) = mut_array_refs![dst, 1, 32, 8, 32, 4, 32, 4, 8, 8, 8, 8, 1, 8, 1, 1, 8, 8, 8];
         // Destructure Player struct
         let Player {
             is_initialized,
@@ -167,6 +521,16 @@ impl Pack for Player {
             reward_to_claim,
             program_account,
             upline,
+            downline_count,
+            registered_at,
+            last_claim_at,
+            total_earned,
+            referral_code,
+            depth,
+            last_credit_at,
+            frozen,
+            tier,
+            vesting,
         } = self;
         // Since the sliced chunks are mutable, direct modify the chunks content will reflect in account data
         is_initialized_dst[0] = *is_initialized as u8;
@@ -174,7 +538,7 @@ impl Pack for Player {
         owner_dst.copy_from_slice(owner.as_ref());
         // Convert reward_to_chain to byte array, then copy it into reward_to_claim_dst
         reward_to_claim_dst.copy_from_slice(&reward_to_claim.to_le_bytes());
-        program_account_dst.copy_from_slice(&program_account.as_ref());
+        program_account_dst.copy_from_slice(program_account.as_ref());
         match upline {
             COption::None => has_upline_dst.copy_from_slice(&[0, 0, 0, 0]),
             COption::Some(pubkey) => {
@@ -182,6 +546,18 @@ impl Pack for Player {
                 upline_dst.copy_from_slice(pubkey.as_ref());
             }
         }
+        downline_count_dst.copy_from_slice(&downline_count.to_le_bytes());
+        registered_at_dst.copy_from_slice(&registered_at.to_le_bytes());
+        last_claim_at_dst.copy_from_slice(&last_claim_at.to_le_bytes());
+        total_earned_dst.copy_from_slice(&total_earned.to_le_bytes());
+        referral_code_dst.copy_from_slice(referral_code);
+        depth_dst[0] = *depth;
+        last_credit_at_dst.copy_from_slice(&last_credit_at.to_le_bytes());
+        frozen_dst[0] = *frozen as u8;
+        tier_dst[0] = *tier;
+        vest_amount_dst.copy_from_slice(&vesting.amount.to_le_bytes());
+        vest_start_dst.copy_from_slice(&vesting.start.to_le_bytes());
+        vest_duration_dst.copy_from_slice(&vesting.duration.to_le_bytes());
     }
 
     fn unpack_unchecked(input: &[u8]) -> Result<Self, ProgramError> {
@@ -194,13 +570,7 @@ impl Pack for Player {
         //         spl_token_account: Pubkey::new_from_array([0; 32]),
         //     });
         // }
-        if input.len() != Player::LEN {
-            msg!(
-                "Invalid account data storage, required size = {}",
-                Self::LEN
-            );
-            return Err(ProgramError::InvalidAccountData);
-        }
+        // The size is checked by unpack_from_slice, which also accepts the previous layouts
         Self::unpack_from_slice(input)
     }
 }
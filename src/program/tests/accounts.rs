@@ -0,0 +1,300 @@
+use learn_solana::{
+    accounts::{
+        AddRewardAccounts, BatchRegisterAccounts, ClaimAccounts, CloseAccountAccounts,
+        RegisterAccounts, SetAdminAccounts,
+    },
+    error::GameError,
+};
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+
+// Backing storage of an AccountInfo, only the signer and writable flags matter to the constructors
+struct TestAccount {
+    key: Pubkey,
+    is_signer: bool,
+    is_writable: bool,
+    lamports: u64,
+    data: Vec<u8>,
+    owner: Pubkey,
+}
+
+impl TestAccount {
+    fn new(is_signer: bool, is_writable: bool) -> Self {
+        Self {
+            key: Pubkey::new_unique(),
+            is_signer,
+            is_writable,
+            lamports: 0,
+            data: vec![],
+            owner: Pubkey::default(),
+        }
+    }
+}
+
+fn account_infos(accounts: &mut [TestAccount]) -> Vec<AccountInfo> {
+    accounts
+        .iter_mut()
+        .map(|account| {
+            AccountInfo::new(
+                &account.key,
+                account.is_signer,
+                account.is_writable,
+                &mut account.lamports,
+                &mut account.data,
+                &account.owner,
+                false,
+                0,
+            )
+        })
+        .collect()
+}
+
+// holder, player, program account, system program, clock
+fn register_accounts() -> Vec<TestAccount> {
+    vec![
+        TestAccount::new(true, true),
+        TestAccount::new(false, true),
+        TestAccount::new(false, true),
+        TestAccount::new(false, false),
+        TestAccount::new(false, false),
+    ]
+}
+
+#[test]
+fn register_accounts_fields() {
+    let mut accounts = register_accounts();
+    let infos = account_infos(&mut accounts);
+    let register = RegisterAccounts::from_account_iter(&infos).unwrap();
+    assert_eq!(register.holder.key, infos[0].key);
+    assert_eq!(register.player.key, infos[1].key);
+    assert_eq!(register.game_info.key, infos[2].key);
+    assert_eq!(register.system_program.key, infos[3].key);
+    assert_eq!(register.clock.key, infos[4].key);
+    assert!(register.upline.is_none());
+
+    let mut accounts = register_accounts();
+    accounts.push(TestAccount::new(false, true));
+    let infos = account_infos(&mut accounts);
+    let register = RegisterAccounts::from_account_iter(&infos).unwrap();
+    assert_eq!(register.upline.unwrap().key, infos[5].key);
+
+    // A read only holder is accepted, it only has to pay when the player account or a fee is due
+    let mut accounts = register_accounts();
+    accounts[0].is_writable = false;
+    let infos = account_infos(&mut accounts);
+    assert!(RegisterAccounts::from_account_iter(&infos).is_ok());
+}
+
+#[test]
+fn register_accounts_flags() {
+    let mut accounts = register_accounts();
+    accounts[0].is_signer = false;
+    let infos = account_infos(&mut accounts);
+    assert_eq!(
+        RegisterAccounts::from_account_iter(&infos).err(),
+        Some(ProgramError::MissingRequiredSignature)
+    );
+
+    for index in [1, 2] {
+        let mut accounts = register_accounts();
+        accounts[index].is_writable = false;
+        let infos = account_infos(&mut accounts);
+        assert_eq!(
+            RegisterAccounts::from_account_iter(&infos).err(),
+            Some(GameError::AccountNotWritable.into())
+        );
+    }
+
+    let mut accounts = register_accounts();
+    accounts.push(TestAccount::new(false, false));
+    let infos = account_infos(&mut accounts);
+    assert_eq!(
+        RegisterAccounts::from_account_iter(&infos).err(),
+        Some(GameError::AccountNotWritable.into())
+    );
+
+    let mut accounts = register_accounts();
+    accounts.pop();
+    let infos = account_infos(&mut accounts);
+    assert_eq!(
+        RegisterAccounts::from_account_iter(&infos).err(),
+        Some(ProgramError::NotEnoughAccountKeys)
+    );
+}
+
+#[test]
+fn batch_register_accounts_flags() {
+    // program account, system program, clock, upline, then (holder, player) pairs
+    let batch_accounts = || {
+        vec![
+            TestAccount::new(false, true),
+            TestAccount::new(false, false),
+            TestAccount::new(false, false),
+            TestAccount::new(false, true),
+            TestAccount::new(true, true),
+            TestAccount::new(false, true),
+            TestAccount::new(true, true),
+            TestAccount::new(false, true),
+        ]
+    };
+
+    let mut accounts = batch_accounts();
+    let infos = account_infos(&mut accounts);
+    let batch_register = BatchRegisterAccounts::from_account_iter(&infos).unwrap();
+    assert_eq!(batch_register.upline.key, infos[3].key);
+    assert_eq!(batch_register.players.len(), 4);
+
+    let mut accounts = batch_accounts();
+    accounts[6].is_signer = false;
+    let infos = account_infos(&mut accounts);
+    assert_eq!(
+        BatchRegisterAccounts::from_account_iter(&infos).err(),
+        Some(ProgramError::MissingRequiredSignature)
+    );
+
+    let mut accounts = batch_accounts();
+    accounts[7].is_writable = false;
+    let infos = account_infos(&mut accounts);
+    assert_eq!(
+        BatchRegisterAccounts::from_account_iter(&infos).err(),
+        Some(GameError::AccountNotWritable.into())
+    );
+
+    let mut accounts = batch_accounts();
+    accounts.pop();
+    let infos = account_infos(&mut accounts);
+    assert_eq!(
+        BatchRegisterAccounts::from_account_iter(&infos).err(),
+        Some(ProgramError::NotEnoughAccountKeys)
+    );
+}
+
+#[test]
+fn claim_accounts_flags() {
+    // holder, program account, player, program token, PDA, player token, token program, clock
+    let claim_accounts = || {
+        vec![
+            TestAccount::new(true, false),
+            TestAccount::new(false, true),
+            TestAccount::new(false, true),
+            TestAccount::new(false, true),
+            TestAccount::new(false, false),
+            TestAccount::new(false, true),
+            TestAccount::new(false, false),
+            TestAccount::new(false, false),
+        ]
+    };
+
+    let mut accounts = claim_accounts();
+    let infos = account_infos(&mut accounts);
+    let claim = ClaimAccounts::from_account_iter(&infos).unwrap();
+    assert_eq!(claim.vault_authority.key, infos[4].key);
+    assert_eq!(claim.player_token_account.key, infos[5].key);
+
+    let mut accounts = claim_accounts();
+    accounts[0].is_signer = false;
+    let infos = account_infos(&mut accounts);
+    assert_eq!(
+        ClaimAccounts::from_account_iter(&infos).err(),
+        Some(ProgramError::MissingRequiredSignature)
+    );
+
+    for index in [1, 2, 3, 5] {
+        let mut accounts = claim_accounts();
+        accounts[index].is_writable = false;
+        let infos = account_infos(&mut accounts);
+        assert_eq!(
+            ClaimAccounts::from_account_iter(&infos).err(),
+            Some(GameError::AccountNotWritable.into())
+        );
+    }
+}
+
+#[test]
+fn admin_accounts_flags() {
+    // admin, program account, player, then an upline
+    let mut accounts = vec![
+        TestAccount::new(true, false),
+        TestAccount::new(false, true),
+        TestAccount::new(false, true),
+        TestAccount::new(false, true),
+    ];
+    let infos = account_infos(&mut accounts);
+    let add_reward = AddRewardAccounts::from_account_iter(&infos).unwrap();
+    assert_eq!(add_reward.uplines.len(), 1);
+
+    for index in [1, 3] {
+        accounts[index].is_writable = false;
+        let infos = account_infos(&mut accounts);
+        assert_eq!(
+            AddRewardAccounts::from_account_iter(&infos).err(),
+            Some(GameError::AccountNotWritable.into())
+        );
+        accounts[index].is_writable = true;
+    }
+
+    // admin, program account, new admin
+    let mut accounts = vec![
+        TestAccount::new(true, false),
+        TestAccount::new(false, false),
+        TestAccount::new(false, false),
+    ];
+    let infos = account_infos(&mut accounts);
+    assert_eq!(
+        SetAdminAccounts::from_account_iter(&infos).err(),
+        Some(GameError::AccountNotWritable.into())
+    );
+
+    accounts[0].is_signer = false;
+    accounts[1].is_writable = true;
+    let infos = account_infos(&mut accounts);
+    assert_eq!(
+        SetAdminAccounts::from_account_iter(&infos).err(),
+        Some(ProgramError::MissingRequiredSignature)
+    );
+}
+
+#[test]
+fn duplicate_accounts() {
+    // The program account passed again as the program token account of a claim
+    let mut accounts = vec![
+        TestAccount::new(true, false),
+        TestAccount::new(false, true),
+        TestAccount::new(false, true),
+        TestAccount::new(false, true),
+        TestAccount::new(false, false),
+        TestAccount::new(false, true),
+        TestAccount::new(false, false),
+        TestAccount::new(false, false),
+    ];
+    accounts[3].key = accounts[1].key;
+    let infos = account_infos(&mut accounts);
+    assert_eq!(
+        ClaimAccounts::from_account_iter(&infos).err(),
+        Some(GameError::DuplicateAccount.into())
+    );
+
+    // The holder passed again as the player account of a register
+    let mut accounts = register_accounts();
+    accounts[1].key = accounts[0].key;
+    let infos = account_infos(&mut accounts);
+    assert_eq!(
+        RegisterAccounts::from_account_iter(&infos).err(),
+        Some(GameError::DuplicateAccount.into())
+    );
+
+    // The player account passed again as the destination of its own rent
+    let mut accounts = vec![
+        TestAccount::new(true, false),
+        TestAccount::new(false, true),
+        TestAccount::new(false, true),
+        TestAccount::new(false, true),
+    ];
+    let infos = account_infos(&mut accounts);
+    assert!(CloseAccountAccounts::from_account_iter(&infos).is_ok());
+    accounts[2].key = accounts[1].key;
+    let infos = account_infos(&mut accounts);
+    assert_eq!(
+        CloseAccountAccounts::from_account_iter(&infos).err(),
+        Some(GameError::DuplicateAccount.into())
+    );
+}
@@ -0,0 +1,488 @@
+use learn_solana::{
+    client::{
+        decode_game_info, decode_player, player_pdas, render_referral_code,
+        subtree_reward_to_claim, validate_register, vault_balance_requirement, ClientAccount,
+        ClientError,
+    },
+    error::GameError,
+    processor::player_pda,
+    state::{GameInfo, Player, Vesting, CURRENT_VERSION},
+};
+use solana_program::{
+    program_error::ProgramError, program_option::COption, program_pack::Pack, pubkey::Pubkey,
+    rent::Rent, system_program,
+};
+use std::collections::HashSet;
+
+fn player_fixture() -> Player {
+    Player {
+        is_initialized: true,
+        owner: Pubkey::new_unique(),
+        reward_to_claim: 125,
+        program_account: Pubkey::new_unique(),
+        upline: COption::Some(Pubkey::new_unique()),
+        downline_count: 2,
+        registered_at: 1_650_000_000,
+        last_claim_at: 1_650_086_400,
+        total_earned: 250,
+        referral_code: [1, 2, 3, 4, 5, 6, 7, 8],
+        depth: 1,
+        last_credit_at: 1_650_000_000,
+        frozen: false,
+        tier: 0,
+        vesting: Vesting {
+            amount: 100,
+            start: 1_650_000_000,
+            duration: 86_400,
+        },
+    }
+}
+
+#[test]
+fn decode_player_account() {
+    let player = player_fixture();
+    let mut data = vec![0; Player::LEN];
+    Player::pack(player.clone(), &mut data).unwrap();
+    assert_eq!(decode_player(&data).unwrap(), player);
+
+    // Player account of the layout before vesting was added
+    assert_eq!(
+        decode_player(&data[..156]).unwrap(),
+        Player {
+            vesting: Vesting::default(),
+            ..player
+        }
+    );
+}
+
+#[test]
+fn decode_game_info_account() {
+    let game_info = GameInfo {
+        version: CURRENT_VERSION,
+        is_initialized: true,
+        admins: [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::default(),
+        ],
+        spl_token_account: Pubkey::new_unique(),
+        total_players: 3,
+        referral_bonus: 10,
+        paused: false,
+        max_depth: 3,
+        mint: Pubkey::new_unique(),
+        min_claim: 50,
+        register_fee: 1_000,
+        max_reward_per_player: 500,
+        nonce: 7,
+        decay_bps_per_day: 100,
+        matching_pool: 40,
+        relayer: Pubkey::new_unique(),
+        vault_bump: 254,
+        level_bps: [2_500, 1_500, 1_000],
+        dust: 0,
+        tier_thresholds: [0; 3],
+        vest_duration: 0,
+        reward_multiplier_bps: 10_000,
+        token_program: spl_token::id(),
+    };
+    let mut data = vec![0; GameInfo::LEN];
+    GameInfo::pack(game_info.clone(), &mut data).unwrap();
+    assert_eq!(decode_game_info(&data).unwrap(), game_info);
+}
+
+#[test]
+fn decode_invalid_account() {
+    // Data of another account type
+    let error = decode_player(&[0; GameInfo::LEN]).unwrap_err();
+    assert_eq!(
+        error,
+        ClientError::InvalidDataSize {
+            account: "Player",
+            expected: Player::LEN,
+            actual: GameInfo::LEN,
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "Invalid Player account data size, expected 180 bytes but got 341"
+    );
+
+    // Allocated but not registered
+    assert_eq!(
+        decode_player(&[0; Player::LEN]).unwrap_err(),
+        ClientError::NotInitialized { account: "Player" }
+    );
+    assert_eq!(
+        decode_game_info(&[0; GameInfo::LEN]).unwrap_err(),
+        ClientError::NotInitialized {
+            account: "GameInfo"
+        }
+    );
+
+    // Program account written by a newer version of the program
+    let mut data = vec![0; GameInfo::LEN];
+    data[0] = CURRENT_VERSION + 1;
+    data[1] = 1;
+    assert_eq!(
+        decode_game_info(&data).unwrap_err(),
+        ClientError::InvalidData {
+            account: "GameInfo"
+        }
+    );
+
+    // Invalid upline option tag
+    let mut data = vec![0; Player::LEN];
+    Player::pack(player_fixture(), &mut data).unwrap();
+    data[1 + 32 + 8 + 32] = 2;
+    assert_eq!(
+        decode_player(&data).unwrap_err(),
+        ClientError::InvalidData { account: "Player" }
+    );
+}
+
+#[test]
+fn render_player_referral_code() {
+    let player = Pubkey::new_from_array([1; 32]);
+    assert_eq!(
+        render_referral_code(&Player::referral_code_of(&player)),
+        "LCjD8QTpHRt"
+    );
+    assert_eq!(
+        render_referral_code(&player_fixture().referral_code),
+        "An6UebxCZd"
+    );
+}
+
+#[test]
+fn player_pdas_of_holders() {
+    let program_id = Pubkey::new_unique();
+    let holders: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+    let pdas = player_pdas(&program_id, &holders);
+
+    // Same addresses on every call, one per holder in the same order, each the one the program derives
+    assert_eq!(player_pdas(&program_id, &holders), pdas);
+    assert_eq!(pdas.len(), holders.len());
+    for (holder, (player, bump)) in holders.iter().zip(&pdas) {
+        assert_eq!(player_pda(&program_id, holder), (*player, *bump));
+        assert!(!player.is_on_curve());
+    }
+    assert_eq!(
+        pdas.iter()
+            .map(|(player, _)| player)
+            .collect::<HashSet<_>>()
+            .len(),
+        holders.len()
+    );
+
+    // The addresses belong to the program they are derived for
+    assert_ne!(player_pdas(&Pubkey::new_unique(), &holders), pdas);
+    assert!(player_pdas(&program_id, &[]).is_empty());
+}
+
+#[test]
+fn subtree_reward() {
+    // a <- b <- d, a <- c, and e outside of the tree
+    let [a, b, c, d, e] = [(); 5].map(|_| Pubkey::new_unique());
+    let player = |upline: Option<Pubkey>, reward_to_claim| Player {
+        upline: upline.into(),
+        reward_to_claim,
+        ..player_fixture()
+    };
+    let players = [
+        (d, player(Some(b), 40)),
+        (a, player(None, 10)),
+        (b, player(Some(a), 20)),
+        (c, player(Some(a), 30)),
+        (e, player(None, 100)),
+    ];
+    assert_eq!(subtree_reward_to_claim(&players, &a), 100);
+    assert_eq!(subtree_reward_to_claim(&players, &b), 60);
+    assert_eq!(subtree_reward_to_claim(&players, &d), 40);
+    assert_eq!(subtree_reward_to_claim(&players, &e), 100);
+    assert_eq!(subtree_reward_to_claim(&players, &Pubkey::new_unique()), 0);
+
+    // A cycle in hand-made data is only counted once
+    let players = [(a, player(Some(b), 10)), (b, player(Some(a), 20))];
+    assert_eq!(subtree_reward_to_claim(&players, &a), 30);
+}
+
+#[test]
+fn vault_requirement() {
+    let player_data = |reward_to_claim| {
+        let mut data = vec![0; Player::LEN];
+        Player::pack(
+            Player {
+                reward_to_claim,
+                ..player_fixture()
+            },
+            &mut data,
+        )
+        .unwrap();
+        data
+    };
+    let (one, two, three) = (player_data(125), player_data(0), player_data(75));
+    // A closed player account is zeroed
+    let closed = vec![0; Player::LEN];
+    assert_eq!(vault_balance_requirement(&[]), Ok(0));
+    assert_eq!(
+        vault_balance_requirement(&[&one, &two, &closed, &three]),
+        Ok(200)
+    );
+
+    // Saturate rather than wrap, the vault can never hold more anyway
+    let huge = player_data(u64::MAX);
+    assert_eq!(vault_balance_requirement(&[&huge, &one]), Ok(u64::MAX));
+
+    assert_eq!(
+        vault_balance_requirement(&[&one, &one[..100]]),
+        Err(ClientError::InvalidDataSize {
+            account: "Player",
+            expected: Player::LEN,
+            actual: 100,
+        })
+    );
+}
+
+// Rent exempt account with the given data, as fetched by the client
+fn client_account(key: Pubkey, owner: Pubkey, data: &[u8]) -> ClientAccount {
+    ClientAccount {
+        key,
+        owner,
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+    }
+}
+
+#[test]
+fn validate_register_state() {
+    let program_id = Pubkey::new_unique();
+    let holder = Pubkey::new_unique();
+    let (player, _) = player_pda(&program_id, &holder);
+    let upline = Pubkey::new_unique();
+    let game_info = GameInfo {
+        version: CURRENT_VERSION,
+        is_initialized: true,
+        admins: [Pubkey::new_unique(), Pubkey::default(), Pubkey::default()],
+        spl_token_account: Pubkey::new_unique(),
+        total_players: 1,
+        referral_bonus: 10,
+        paused: false,
+        max_depth: 3,
+        mint: Pubkey::new_unique(),
+        min_claim: 0,
+        register_fee: 0,
+        max_reward_per_player: 0,
+        nonce: 2,
+        decay_bps_per_day: 0,
+        matching_pool: 0,
+        relayer: Pubkey::default(),
+        vault_bump: 255,
+        level_bps: [2_500, 1_500, 1_000],
+        dust: 0,
+        tier_thresholds: [0; 3],
+        vest_duration: 0,
+        reward_multiplier_bps: 10_000,
+        token_program: spl_token::id(),
+    };
+    let upline_player = Player {
+        owner: Pubkey::new_unique(),
+        depth: 1,
+        ..player_fixture()
+    };
+    let pack_game_info = |game_info: GameInfo| {
+        let mut data = vec![0; GameInfo::LEN];
+        GameInfo::pack(game_info, &mut data).unwrap();
+        data
+    };
+    let pack_player = |player: Player| {
+        let mut data = vec![0; Player::LEN];
+        Player::pack(player, &mut data).unwrap();
+        data
+    };
+    let game_info_data = pack_game_info(game_info.clone());
+    let upline_data = pack_player(upline_player.clone());
+    // A player account the register still has to create
+    let new_player = ClientAccount {
+        key: player,
+        owner: system_program::id(),
+        lamports: 0,
+        data: &[],
+    };
+    let program_account = client_account(Pubkey::new_unique(), program_id, &game_info_data);
+    let upline_account = client_account(upline, program_id, &upline_data);
+    let validate = |player_account: &ClientAccount,
+                    program_account: &ClientAccount,
+                    upline_account: Option<&ClientAccount>| {
+        validate_register(
+            &program_id,
+            &holder,
+            &holder,
+            player_account,
+            program_account,
+            upline_account,
+            false,
+        )
+    };
+
+    assert_eq!(validate(&new_player, &program_account, None), Ok(()));
+    assert_eq!(
+        validate(&new_player, &program_account, Some(&upline_account)),
+        Ok(())
+    );
+    // The default pubkey placeholder is no upline
+    let placeholder_upline = client_account(Pubkey::default(), system_program::id(), &[]);
+    assert_eq!(
+        validate(&new_player, &program_account, Some(&placeholder_upline)),
+        Ok(())
+    );
+
+    // Player account not derived from the holder
+    let other_player = ClientAccount {
+        key: Pubkey::new_unique(),
+        ..new_player
+    };
+    assert_eq!(
+        validate(&other_player, &program_account, None),
+        Err(GameError::InvalidPlayerAccount.into())
+    );
+
+    // Program account of another program, not initialized, or paused
+    let foreign_program_account =
+        client_account(program_account.key, Pubkey::new_unique(), &game_info_data);
+    assert_eq!(
+        validate(&new_player, &foreign_program_account, None),
+        Err(ProgramError::IncorrectProgramId)
+    );
+    let uninitialized_data = vec![0; GameInfo::LEN];
+    let uninitialized_program_account =
+        client_account(program_account.key, program_id, &uninitialized_data);
+    assert_eq!(
+        validate(&new_player, &uninitialized_program_account, None),
+        Err(GameError::NotInitialized.into())
+    );
+    let paused_data = pack_game_info(GameInfo {
+        paused: true,
+        ..game_info
+    });
+    let paused_program_account = client_account(program_account.key, program_id, &paused_data);
+    assert_eq!(
+        validate(&new_player, &paused_program_account, None),
+        Err(GameError::GamePaused.into())
+    );
+
+    // Player account created by the client, without enough lamports, or already registered
+    let allocated_data = vec![0; Player::LEN];
+    let allocated_player = client_account(player, program_id, &allocated_data);
+    assert_eq!(validate(&allocated_player, &program_account, None), Ok(()));
+    let underfunded_player = ClientAccount {
+        lamports: 1,
+        ..client_account(player, program_id, &allocated_data)
+    };
+    assert_eq!(
+        validate(&underfunded_player, &program_account, None),
+        Err(GameError::NotRentExempt.into())
+    );
+    let registered_data = pack_player(Player {
+        owner: holder,
+        ..player_fixture()
+    });
+    let registered_player = client_account(player, program_id, &registered_data);
+    assert_eq!(
+        validate(&registered_player, &program_account, None),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+    assert_eq!(
+        validate_register(
+            &program_id,
+            &holder,
+            &holder,
+            &registered_player,
+            &program_account,
+            None,
+            true
+        ),
+        Ok(())
+    );
+
+    // Upline being the player itself, held by the holder, not registered, frozen, or too deep
+    let self_upline = client_account(player, program_id, &upline_data);
+    assert_eq!(
+        validate(&new_player, &program_account, Some(&self_upline)),
+        Err(GameError::SelfRecursiveUpline.into())
+    );
+    let same_holder_data = pack_player(Player {
+        owner: holder,
+        ..upline_player
+    });
+    let same_holder_upline = client_account(upline, program_id, &same_holder_data);
+    assert_eq!(
+        validate(&new_player, &program_account, Some(&same_holder_upline)),
+        Err(GameError::SelfRecursiveUpline.into())
+    );
+    let foreign_upline = client_account(upline, Pubkey::new_unique(), &upline_data);
+    assert_eq!(
+        validate(&new_player, &program_account, Some(&foreign_upline)),
+        Err(GameError::UplineNotRegistered.into())
+    );
+    let unregistered_upline = client_account(upline, program_id, &allocated_data);
+    assert_eq!(
+        validate(&new_player, &program_account, Some(&unregistered_upline)),
+        Err(GameError::UplineNotRegistered.into())
+    );
+    let frozen_data = pack_player(Player {
+        frozen: true,
+        ..upline_player
+    });
+    let frozen_upline = client_account(upline, program_id, &frozen_data);
+    assert_eq!(
+        validate(&new_player, &program_account, Some(&frozen_upline)),
+        Err(GameError::PlayerFrozen.into())
+    );
+    let deep_data = pack_player(Player {
+        depth: 3,
+        ..upline_player
+    });
+    let deep_upline = client_account(upline, program_id, &deep_data);
+    assert_eq!(
+        validate(&new_player, &program_account, Some(&deep_upline)),
+        Err(GameError::MaxDepthExceeded.into())
+    );
+
+    // Referral bonus above the reward cap of the upline, cut down by the processor instead of rejected
+    let capped_data = pack_game_info(GameInfo {
+        max_reward_per_player: upline_player.reward_to_claim,
+        reward_multiplier_bps: 20_000,
+        ..game_info
+    });
+    let capped_program_account = client_account(program_account.key, program_id, &capped_data);
+    assert_eq!(
+        validate(&new_player, &capped_program_account, Some(&upline_account)),
+        Ok(())
+    );
+
+    // Registered on behalf of the holder by the relayer, or by another signer
+    let relayer = Pubkey::new_unique();
+    let relayed_data = pack_game_info(GameInfo {
+        relayer,
+        ..game_info
+    });
+    let relayed_program_account = client_account(program_account.key, program_id, &relayed_data);
+    for (signer, result) in [
+        (relayer, Ok(())),
+        (Pubkey::new_unique(), Err(GameError::InvalidRelayer.into())),
+    ] {
+        assert_eq!(
+            validate_register(
+                &program_id,
+                &signer,
+                &holder,
+                &new_player,
+                &relayed_program_account,
+                Some(&upline_account),
+                false
+            ),
+            result
+        );
+    }
+}
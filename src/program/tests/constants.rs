@@ -0,0 +1,120 @@
+use learn_solana::{
+    constants::*,
+    instruction::Command,
+    processor::{game_info_pda, player_pda, vault_pda},
+    state::{GameInfo, Player},
+};
+use solana_program::{program_pack::Pack, pubkey::Pubkey};
+
+#[test]
+fn tag_constants() {
+    let commands = [
+        (
+            Command::Init {
+                referral_bonus: 0,
+                max_depth: 0,
+                min_claim: 0,
+                register_fee: 0,
+                max_reward_per_player: 0,
+                decay_bps_per_day: 0,
+                level_bps: [0; 3],
+            },
+            INIT_TAG,
+        ),
+        (
+            Command::Register {
+                idempotent: false,
+                holder: None,
+            },
+            REGISTER_TAG,
+        ),
+        (
+            Command::AddReward {
+                reward_amount: 0,
+                saturate: false,
+            },
+            ADD_REWARD_TAG,
+        ),
+        (Command::Claim { amount: None }, CLAIM_TAG),
+        (Command::CloseAccount, CLOSE_ACCOUNT_TAG),
+        (Command::SetAdmin, SET_ADMIN_TAG),
+        (Command::TransferReward { amount: 0 }, TRANSFER_REWARD_TAG),
+        (Command::BatchRegister, BATCH_REGISTER_TAG),
+        (Command::SetPaused { paused: false }, SET_PAUSED_TAG),
+        (Command::SweepFees, SWEEP_FEES_TAG),
+        (
+            Command::AdminSetReward { reward_to_claim: 0 },
+            ADMIN_SET_REWARD_TAG,
+        ),
+        (Command::SetUpline, SET_UPLINE_TAG),
+        (Command::SetVaultAuthority, SET_VAULT_AUTHORITY_TAG),
+        (Command::MigratePlayer, MIGRATE_PLAYER_TAG),
+        (Command::AddAdmin, ADD_ADMIN_TAG),
+        (Command::RemoveAdmin, REMOVE_ADMIN_TAG),
+        (Command::SlashReward { amount: 0 }, SLASH_REWARD_TAG),
+        (Command::GetReward, GET_REWARD_TAG),
+        (Command::FundPool { amount: 0 }, FUND_POOL_TAG),
+        (Command::SetRelayer, SET_RELAYER_TAG),
+        (Command::FreezePlayer { frozen: false }, FREEZE_PLAYER_TAG),
+        (Command::ExportPlayer, EXPORT_PLAYER_TAG),
+        (Command::SweepDust, SWEEP_DUST_TAG),
+        (Command::AdminClaimBatch, ADMIN_CLAIM_BATCH_TAG),
+        (
+            Command::SetTierThresholds {
+                tier_thresholds: [0; 3],
+            },
+            SET_TIER_THRESHOLDS_TAG,
+        ),
+        (
+            Command::SetReferralBonus { referral_bonus: 0 },
+            SET_REFERRAL_BONUS_TAG,
+        ),
+        (
+            Command::SetVestDuration { vest_duration: 0 },
+            SET_VEST_DURATION_TAG,
+        ),
+        (
+            Command::SetRewardMultiplier {
+                reward_multiplier_bps: 0,
+            },
+            SET_REWARD_MULTIPLIER_TAG,
+        ),
+    ];
+    for (command, tag) in commands.iter() {
+        let packed = command.pack();
+        assert_eq!(packed[..2], [INSTRUCTION_MAGIC, *tag], "{:?}", command);
+        // The processor dispatches on the unpacked command, the tag alone must select the same one
+        assert_eq!(Command::unpack(&packed).unwrap(), *command);
+    }
+
+    // Every tag is used once, and the first free tag is rejected
+    let mut tags: Vec<u8> = commands.iter().map(|(_, tag)| *tag).collect();
+    tags.sort_unstable();
+    assert_eq!(tags, (0..commands.len() as u8).collect::<Vec<u8>>());
+    assert!(Command::unpack(&[INSTRUCTION_MAGIC, commands.len() as u8]).is_err());
+}
+
+#[test]
+fn seed_constants() {
+    let program_id = Pubkey::new_unique();
+    let holder = Pubkey::new_unique();
+    assert_eq!(
+        player_pda(&program_id, &holder),
+        Pubkey::find_program_address(&[PLAYER_SEED.as_bytes(), holder.as_ref()], &program_id)
+    );
+    assert_eq!(
+        game_info_pda(&program_id),
+        Pubkey::find_program_address(&[GAME_INFO_SEED.as_bytes()], &program_id)
+    );
+    assert_eq!(
+        vault_pda(&program_id),
+        Pubkey::find_program_address(&[PDA_SEED.as_bytes()], &program_id)
+    );
+}
+
+#[test]
+fn size_constants() {
+    assert_eq!(Player::LEN, PLAYER_LEN);
+    assert_eq!(GameInfo::LEN, GAME_INFO_LEN);
+    assert_eq!((PLAYER_LEN, GAME_INFO_LEN, MAX_ADMINS), (180, 341, 3));
+}
@@ -0,0 +1,56 @@
+use learn_solana::error::{CommandError, GameError};
+use solana_program::program_error::ProgramError;
+
+// Clients decode "custom program error: 0x.." using these codes, so they must never change
+#[test]
+fn game_error_codes() {
+    let codes = [
+        (GameError::AlreadyInitialized, 0),
+        (GameError::NotInitialized, 1),
+        (GameError::RewardAmountOverflow, 2),
+        (GameError::InvalidUpline, 3),
+        (GameError::UnclaimableAmount, 4),
+        (GameError::SelfRecursiveUpline, 5),
+        (GameError::InvalidAdmin, 6),
+        (GameError::InvalidTokenAccount, 7),
+        (GameError::UplineNotRegistered, 8),
+        (GameError::InsufficientReward, 9),
+        (GameError::InvalidPlayerAccount, 10),
+        (GameError::RewardNotClaimed, 11),
+        (GameError::NotRentExempt, 14),
+        (GameError::ClaimCooldown, 15),
+        (GameError::GamePaused, 16),
+        (GameError::InvalidProgramAccount, 17),
+        (GameError::UplineAlreadySet, 18),
+        (GameError::MaxDepthExceeded, 19),
+        (GameError::BelowMinimumClaim, 20),
+        (GameError::AlreadyMigrated, 21),
+        (GameError::RewardCapExceeded, 22),
+        (GameError::AdminAlreadyAdded, 23),
+        (GameError::TooManyAdmins, 24),
+        (GameError::LastAdmin, 25),
+        (GameError::InvalidRelayer, 26),
+        (GameError::PlayerFrozen, 27),
+        (GameError::InvalidRewardSplit, 28),
+        (GameError::DuplicateAccount, 29),
+        (GameError::UnsupportedVersion, 30),
+        (GameError::InvalidTierThresholds, 31),
+        (GameError::VaultUnderfunded, 32),
+        (GameError::ZeroAmount, 33),
+        (GameError::CircularReferral, 34),
+        (GameError::AccountNotWritable, 35),
+        (GameError::RewardStillVesting, 36),
+        (GameError::InvalidRewardMultiplier, 37),
+    ];
+    for (error, code) in codes {
+        assert_eq!(ProgramError::from(error), ProgramError::Custom(code));
+    }
+}
+
+#[test]
+fn command_error_codes() {
+    assert_eq!(
+        ProgramError::from(CommandError::InvalidCommand),
+        ProgramError::Custom(0)
+    );
+}
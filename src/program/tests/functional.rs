@@ -1,18 +1,31 @@
 #![allow(unused_variables)]
+use borsh::{BorshDeserialize, BorshSerialize};
+use lazy_static::lazy_static;
 use learn_solana::{
+    constants::INSTRUCTION_MAGIC,
     entrypoint::main,
-    state::{GameInfo, Player},
+    event::GameEvent,
+    instruction::{self, DEFAULT_LEVEL_BPS, DEFAULT_MAX_DEPTH},
+    processor::{game_info_pda, player_pda, spl_memo, spl_token_2022, vault_pda, COOLDOWN_SECS},
+    state::{GameInfo, Player, CURRENT_VERSION, DECAY_PERIOD_SECS},
 };
 use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
     hash::Hash,
     instruction::{AccountMeta, Instruction},
+    native_token::LAMPORTS_PER_SOL,
     program_option::COption,
     program_pack::Pack,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
     pubkey::Pubkey,
     rent::Rent,
+    system_program,
+    sysvar::{self, clock::Clock},
 };
 use solana_program_test::*;
 use solana_sdk::{
+    account::Account,
     signature::{Keypair, Signer},
     system_instruction,
     transaction::Transaction,
@@ -21,55 +34,121 @@ use spl_token::{
     self,
     instruction::{initialize_account, initialize_mint, mint_to},
 };
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex, Once,
+};
+
+// Compute units budget of each path, tune them deliberately when a feature makes an instruction more expensive
+// The budget is only metered when the program runs as BPF (cargo test-bpf), the native processor is not metered
+// The setup transactions (spl-token and system program) run under the same budget, and stay well below it
+const INIT_MAX_COMPUTE_UNITS: u64 = 20_000;
+// Register derive the player PDA and create it through the system program
+const REGISTER_MAX_COMPUTE_UNITS: u64 = 40_000;
+// Register with an upline also unpack and pack the upline account
+const MULTI_LEVEL_REGISTER_MAX_COMPUTE_UNITS: u64 = 50_000;
+
+lazy_static! {
+    // Data logged by the program through sol_log_data, one entry per call
+    static ref LOGGED_DATA: Mutex<Vec<Vec<Vec<u8>>>> = Mutex::new(vec![]);
+    // Instructions invoked by the program through CPI
+    static ref INVOKED_INSTRUCTIONS: Mutex<Vec<Instruction>> = Mutex::new(vec![]);
+}
+
+// Number of sol_log_compute_units calls, the native processor cannot meter the units themselves
+static LOGGED_COMPUTE_UNITS: AtomicUsize = AtomicUsize::new(0);
+
+// Program test does not keep sol_log_data in the transaction log messages, so capture it in the syscall stubs
+// Everything else is forwarded to the program test syscall stubs
+struct LogCapturingSyscallStubs {
+    inner: Box<dyn SyscallStubs>,
+}
+
+impl SyscallStubs for LogCapturingSyscallStubs {
+    fn sol_log(&self, message: &str) {
+        self.inner.sol_log(message);
+    }
+    fn sol_log_compute_units(&self) {
+        LOGGED_COMPUTE_UNITS.fetch_add(1, Ordering::SeqCst);
+        self.inner.sol_log_compute_units();
+    }
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        INVOKED_INSTRUCTIONS
+            .lock()
+            .unwrap()
+            .push(instruction.clone());
+        self.inner
+            .sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner.sol_get_clock_sysvar(var_addr)
+    }
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner.sol_get_epoch_schedule_sysvar(var_addr)
+    }
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner.sol_get_fees_sysvar(var_addr)
+    }
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner.sol_get_rent_sysvar(var_addr)
+    }
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.inner.sol_get_return_data()
+    }
+    fn sol_set_return_data(&mut self, data: &[u8]) {
+        self.inner.sol_set_return_data(data);
+    }
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        LOGGED_DATA
+            .lock()
+            .unwrap()
+            .push(fields.iter().map(|field| field.to_vec()).collect());
+        self.inner.sol_log_data(fields);
+    }
+}
+
+struct NoopSyscallStubs;
+
+impl SyscallStubs for NoopSyscallStubs {}
+
+// Program test install its syscall stubs when the first bank started, wrap them once afterward
+fn capture_logged_data() {
+    static CAPTURE_LOGGED_DATA: Once = Once::new();
+    CAPTURE_LOGGED_DATA.call_once(|| {
+        let inner = set_syscall_stubs(Box::new(NoopSyscallStubs));
+        set_syscall_stubs(Box::new(LogCapturingSyscallStubs { inner }));
+    });
+}
 
 #[tokio::test]
 async fn claim_reward() {
-    let (
-        mint_account_keypair,
+    let TestGame {
         admin_account_keypair,
-        program_account_keypair,
+        program_account,
         token_account_keypair,
         player_one_holder_keypair,
-        player_one_account_keypair,
+        player_one_account,
         player_two_holder_keypair,
-        player_two_account_keypair,
+        player_two_account,
         player_one_token_account_keypair,
-        player_two_token_account_keypair,
         program_id,
         mut banks_client,
         payer,
         recent_blockhash,
-    ) = setup().await;
-    let init_instruction_transaction = build_init_instruction_transaction(
-        &admin_account_keypair,
-        &program_account_keypair,
-        &token_account_keypair,
-        &payer,
-        program_id,
-        recent_blockhash,
-    );
-    banks_client
-        .process_transaction(init_instruction_transaction)
-        .await
-        .unwrap();
-    let create_player_account_transaction = build_create_player_account_transaction(
-        &player_one_account_keypair,
-        &player_two_account_keypair,
-        &payer,
-        program_id,
-        recent_blockhash,
-    );
-    banks_client
-        .process_transaction(create_player_account_transaction)
-        .await
-        .unwrap();
+        ..
+    } = setup_initialized(InitParams::default()).await;
 
     // Register player one
     let transaction = build_register_player_transaction(
         &payer,
         &player_one_holder_keypair,
-        &player_one_account_keypair,
-        &program_account_keypair,
+        &player_one_account,
+        &program_account,
         None,
         program_id,
         recent_blockhash,
@@ -80,9 +159,9 @@ async fn claim_reward() {
     let transaction = build_register_player_transaction(
         &payer,
         &player_two_holder_keypair,
-        &player_two_account_keypair,
-        &program_account_keypair,
-        Some(&player_one_account_keypair),
+        &player_two_account,
+        &program_account,
+        Some(&player_one_account),
         program_id,
         recent_blockhash,
     );
@@ -91,9 +170,9 @@ async fn claim_reward() {
     // Test add reward to player two with admin
     let add_reward_transaction = build_add_reward_transaction(
         &admin_account_keypair,
-        &program_account_keypair,
-        &player_two_account_keypair,
-        Some(&player_one_account_keypair),
+        &program_account,
+        &player_two_account,
+        &[&player_one_account],
         program_id,
         100,
         &payer,
@@ -107,9 +186,9 @@ async fn claim_reward() {
     // Test add reward to player one
     let add_reward_transaction = build_add_reward_transaction(
         &admin_account_keypair,
-        &program_account_keypair,
-        &player_one_account_keypair,
-        None,
+        &program_account,
+        &player_one_account,
+        &[],
         program_id,
         100,
         &payer,
@@ -124,8 +203,8 @@ async fn claim_reward() {
     // Test claim other player reward
     let transaction = build_claim_reward_transaction(
         &player_one_holder_keypair,
-        &program_account_keypair,
-        &player_two_account_keypair, // Claim player two reward using player one signature
+        &program_account,
+        &player_two_account, // Claim player two reward using player one signature
         &token_account_keypair,
         pda,
         &player_one_token_account_keypair,
@@ -134,18 +213,16 @@ async fn claim_reward() {
         recent_blockhash,
     );
     let result = banks_client.process_transaction(transaction).await;
-    match result {
-        Ok(()) => {}
-        Err(e) => {
-            assert_eq!(e.to_string().contains("missing required signature"), true);
-        }
-    };
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("missing required signature"));
 
     // Test player one claim reward
     let transaction = build_claim_reward_transaction(
         &player_one_holder_keypair,
-        &program_account_keypair,
-        &player_one_account_keypair,
+        &program_account,
+        &player_one_account,
         &token_account_keypair,
         pda,
         &player_one_token_account_keypair,
@@ -155,465 +232,6846 @@ async fn claim_reward() {
     );
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let player_one_account = banks_client
-        .get_account(player_one_account_keypair.pubkey())
+    let player_one_account_info = banks_client
+        .get_account(player_one_account)
         .await
+        .unwrap()
         .unwrap();
-    match player_one_account {
-        Some(account) => {
-            let player_one_state = Player::unpack(&account.data).unwrap();
-            assert_eq!(player_one_state.reward_to_claim, 0);
-        }
-        _ => {} // Unreachable
-    };
+    let player_one_state = Player::unpack(&player_one_account_info.data).unwrap();
+    assert_eq!(player_one_state.reward_to_claim, 0);
 
     let player_one_token_account = banks_client
         .get_account(player_one_token_account_keypair.pubkey())
         .await
+        .unwrap()
         .unwrap();
-    match player_one_token_account {
-        Some(account) => {
-            let player_one_token_account_state =
-                spl_token::state::Account::unpack(&account.data).unwrap();
-            assert_eq!(player_one_token_account_state.amount, 110); // 100 + 10% from player two
-        }
-        _ => {}
-    }
+    let player_one_token_account_state =
+        spl_token::state::Account::unpack(&player_one_token_account.data).unwrap();
+    assert_eq!(player_one_token_account_state.amount, 125); // 100 + 25% from player two
+
+    // The claimed amount should be taken from the program token account
+    let program_token_account = banks_client
+        .get_account(token_account_keypair.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    let program_token_account_state =
+        spl_token::state::Account::unpack(&program_token_account.data).unwrap();
+    assert_eq!(program_token_account_state.amount, 1000000000000 - 125);
+
+    // Test player one claim again without any reward left
+    let recent_blockhash = get_new_blockhash(&mut banks_client, &recent_blockhash).await;
+    let transaction = build_claim_reward_transaction(
+        &player_one_holder_keypair,
+        &program_account,
+        &player_one_account,
+        &token_account_keypair,
+        pda,
+        &player_one_token_account_keypair,
+        program_id,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x4")); // GameError::UnclaimableAmount
 }
 
 #[tokio::test]
-async fn add_reward() {
-    let (
-        mint_account_keypair,
+async fn claim_reward_vault_underfunded() {
+    let TestGame {
         admin_account_keypair,
-        program_account_keypair,
+        program_account,
         token_account_keypair,
         player_one_holder_keypair,
-        player_one_account_keypair,
-        player_two_holder_keypair,
-        player_two_account_keypair,
+        player_one_account,
         player_one_token_account_keypair,
-        player_two_token_account_keypair,
         program_id,
         mut banks_client,
         payer,
         recent_blockhash,
-    ) = setup().await;
-    let init_instruction_transaction = build_init_instruction_transaction(
-        &admin_account_keypair,
-        &program_account_keypair,
-        &token_account_keypair,
-        &payer,
-        program_id,
-        recent_blockhash,
-    );
-    banks_client
-        .process_transaction(init_instruction_transaction)
-        .await
-        .unwrap();
-    let create_player_account_transaction = build_create_player_account_transaction(
-        &player_one_account_keypair,
-        &player_two_account_keypair,
-        &payer,
-        program_id,
-        recent_blockhash,
-    );
-    banks_client
-        .process_transaction(create_player_account_transaction)
-        .await
-        .unwrap();
-    // Register player one
+        ..
+    } = setup_initialized(InitParams::default()).await;
     let transaction = build_register_player_transaction(
         &payer,
         &player_one_holder_keypair,
-        &player_one_account_keypair,
-        &program_account_keypair,
+        &player_one_account,
+        &program_account,
         None,
         program_id,
         recent_blockhash,
     );
     banks_client.process_transaction(transaction).await.unwrap();
 
-    // Register player two, with player one as upline
-    let transaction = build_register_player_transaction(
-        &payer,
-        &player_two_holder_keypair,
-        &player_two_account_keypair,
-        &program_account_keypair,
-        Some(&player_one_account_keypair),
-        program_id,
-        recent_blockhash,
-    );
-    banks_client.process_transaction(transaction).await.unwrap();
-
-    // Test add reward with non-admin account
-    let fake_admin_account_keypair = Keypair::new();
-    let add_reward_transaction = build_add_reward_transaction(
-        &fake_admin_account_keypair,
-        &program_account_keypair,
-        &player_one_account_keypair,
-        None,
-        program_id,
-        100,
-        &payer,
-        recent_blockhash,
-    );
-    let result = banks_client
-        .process_transaction(add_reward_transaction)
-        .await;
-    match result {
-        Ok(()) => {}
-        Err(error) => {
-            assert_eq!(
-                error.to_string().contains("missing required signature"),
-                true
-            );
-        }
-    };
-    // End
-
-    // Test add reward with invalid player account
+    // One more than the program token account was funded with
     let add_reward_transaction = build_add_reward_transaction(
         &admin_account_keypair,
-        &program_account_keypair,
-        &Keypair::new(),
-        None,
+        &program_account,
+        &player_one_account,
+        &[],
         program_id,
-        100,
+        1000000000000 + 1,
         &payer,
         recent_blockhash,
     );
-    let result = banks_client
+    banks_client
         .process_transaction(add_reward_transaction)
-        .await;
-    match result {
-        Ok(()) => {}
-        Err(error) => {
-            assert_eq!(error.to_string().contains("incorrect program id"), true);
-        }
-    };
-    // End
+        .await
+        .unwrap();
 
-    // Test add reward with non-exists upline account
-    let add_reward_transaction = build_add_reward_transaction(
-        &admin_account_keypair,
-        &program_account_keypair,
-        &player_two_account_keypair,
-        Some(&Keypair::new()),
+    let (pda, _nonce) = vault_pda(&program_id);
+    let transaction = build_claim_reward_transaction(
+        &player_one_holder_keypair,
+        &program_account,
+        &player_one_account,
+        &token_account_keypair,
+        pda,
+        &player_one_token_account_keypair,
         program_id,
-        100,
         &payer,
         recent_blockhash,
     );
-    let result = banks_client
-        .process_transaction(add_reward_transaction)
-        .await;
-    match result {
-        Ok(()) => {}
-        Err(error) => {
-            assert_eq!(error.to_string().contains("incorrect program id"), true);
-        }
-    };
-    // End
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x20")); // GameError::VaultUnderfunded
 
-    // Test add reward to invalid upline account
-    let add_reward_transaction = build_add_reward_transaction(
-        &admin_account_keypair,
-        &program_account_keypair,
-        &player_two_account_keypair,
-        Some(&player_two_account_keypair),
-        program_id,
-        100,
-        &payer,
-        recent_blockhash,
+    // Nothing was paid, the whole reward is still to claim
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        Player::unpack(&account.data).unwrap().reward_to_claim,
+        1000000000000 + 1
     );
-    let result = banks_client
-        .process_transaction(add_reward_transaction)
-        .await;
-    match result {
-        Ok(()) => {}
-        Err(error) => {
-            assert_eq!(error.to_string().contains("custom program error"), true);
-        }
-    };
-    // End
+}
 
-    // Test add reward to player two with admin
-    let add_reward_transaction = build_add_reward_transaction(
-        &admin_account_keypair,
-        &program_account_keypair,
-        &player_two_account_keypair,
-        Some(&player_one_account_keypair),
+#[tokio::test]
+async fn claim_reward_admin_player() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        player_one_token_account_keypair,
         program_id,
-        100,
-        &payer,
+        mut banks_client,
+        payer,
         recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+    for (holder_keypair, player_account) in [
+        (&player_one_holder_keypair, &player_one_account),
+        (&player_two_holder_keypair, &player_two_account),
+    ] {
+        let transaction = build_register_player_transaction(
+            &payer,
+            holder_keypair,
+            player_account,
+            &program_account,
+            None,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+        let add_reward_transaction = build_add_reward_transaction(
+            &admin_account_keypair,
+            &program_account,
+            player_account,
+            &[],
+            program_id,
+            100,
+            &payer,
+            recent_blockhash,
+        );
+        banks_client
+            .process_transaction(add_reward_transaction)
+            .await
+            .unwrap();
+    }
+
+    // The holder of player one becomes an admin as well
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::add_admin(
+            &program_id,
+            &admin_account_keypair.pubkey(),
+            &program_account,
+            &player_one_holder_keypair.pubkey(),
+        )],
+        Some(&payer.pubkey()),
     );
-    banks_client
-        .process_transaction(add_reward_transaction)
-        .await
-        .unwrap();
+    transaction.sign(&[&payer, &admin_account_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
 
-    let player_one_account = banks_client
-        .get_account(player_one_account_keypair.pubkey())
-        .await
-        .unwrap();
-    match player_one_account {
-        Some(account) => {
-            let player_one_state = Player::unpack(&account.data).unwrap();
-            assert_eq!(player_one_state.reward_to_claim, 10); // 10% from player two
-        }
-        _ => {} // Unreachable
+    let build_claim_transaction = |player_account: &Pubkey| {
+        let mut claim_instruction = instruction::claim(
+            &program_id,
+            &player_one_holder_keypair.pubkey(),
+            &program_account,
+            &token_account_keypair.pubkey(),
+            &player_one_token_account_keypair.pubkey(),
+            None,
+        );
+        claim_instruction.accounts[2].pubkey = *player_account;
+        let mut transaction =
+            Transaction::new_with_payer(&[claim_instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer, &player_one_holder_keypair], recent_blockhash);
+        transaction
     };
 
-    let player_two_account = banks_client
-        .get_account(player_two_account_keypair.pubkey())
-        .await
-        .unwrap();
-    match player_two_account {
-        Some(account) => {
-            let player_two_state = Player::unpack(&account.data).unwrap();
-            assert_eq!(player_two_state.reward_to_claim, 90);
-        }
-        _ => {} // Unreachable
-    };
+    // Test the admin claiming the reward of player two, being an admin is no substitute for owning the player
+    let result = banks_client
+        .process_transaction(build_claim_transaction(&player_two_account))
+        .await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("missing required signature"));
 
-    // Test add reward to player one
-    let add_reward_transaction = build_add_reward_transaction(
-        &admin_account_keypair,
-        &program_account_keypair,
-        &player_one_account_keypair,
-        None,
-        program_id,
-        100,
-        &payer,
-        recent_blockhash,
-    );
+    // Test the admin claiming the reward of its own player, like any other player
     banks_client
-        .process_transaction(add_reward_transaction)
+        .process_transaction(build_claim_transaction(&player_one_account))
         .await
         .unwrap();
 
-    let player_one_account = banks_client
-        .get_account(player_one_account_keypair.pubkey())
+    for (player_account, reward_to_claim) in [(player_one_account, 0), (player_two_account, 100)] {
+        let account = banks_client
+            .get_account(player_account)
+            .await
+            .unwrap()
+            .unwrap();
+        let player_state = Player::unpack(&account.data).unwrap();
+        assert_eq!(player_state.reward_to_claim, reward_to_claim);
+    }
+    let account = banks_client
+        .get_account(player_one_token_account_keypair.pubkey())
         .await
+        .unwrap()
         .unwrap();
-    match player_one_account {
-        Some(account) => {
-            let player_one_state = Player::unpack(&account.data).unwrap();
-            assert_eq!(player_one_state.reward_to_claim, 110); // 100 + 10% from player two
-        }
-        _ => {} // Unreachable
-    };
+    assert_eq!(
+        spl_token::state::Account::unpack(&account.data)
+            .unwrap()
+            .amount,
+        100
+    );
 }
 
 #[tokio::test]
-async fn register_player() {
-    let (
-        mint_account_keypair,
+async fn claim_reward_memo() {
+    let TestGame {
         admin_account_keypair,
-        program_account_keypair,
+        program_account,
         token_account_keypair,
         player_one_holder_keypair,
-        player_one_account_keypair,
-        player_two_holder_keypair,
-        player_two_account_keypair,
+        player_one_account,
         player_one_token_account_keypair,
-        player_two_token_account_keypair,
         program_id,
         mut banks_client,
         payer,
         recent_blockhash,
-    ) = setup().await;
-    let init_instruction_transaction = build_init_instruction_transaction(
-        &admin_account_keypair,
-        &program_account_keypair,
-        &token_account_keypair,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+    capture_logged_data();
+
+    let transaction = build_register_player_transaction(
         &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
         program_id,
         recent_blockhash,
     );
-    banks_client
-        .process_transaction(init_instruction_transaction)
-        .await
-        .unwrap();
-    let create_player_account_transaction = build_create_player_account_transaction(
-        &player_one_account_keypair,
-        &player_two_account_keypair,
+    banks_client.process_transaction(transaction).await.unwrap();
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    let claim_instruction = instruction::claim_with_memo(
+        &program_id,
+        &player_one_holder_keypair.pubkey(),
+        &program_account,
+        &token_account_keypair.pubkey(),
+        &player_one_token_account_keypair.pubkey(),
+        None,
+    );
+
+    // Test claim with another program in place of the memo program
+    let mut other_memo_instruction = claim_instruction.clone();
+    other_memo_instruction.accounts[8].pubkey = system_program::id();
+    let mut transaction =
+        Transaction::new_with_payer(&[other_memo_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &player_one_holder_keypair], recent_blockhash);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("incorrect program id for instruction"));
+
+    // Test claim with the memo, the memo program records the player and the claimed amount
+    let mut transaction = Transaction::new_with_payer(&[claim_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &player_one_holder_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let expected_memo = format!("claim {} 100", player_one_account).into_bytes();
+    let invoked_instructions = INVOKED_INSTRUCTIONS.lock().unwrap();
+    assert!(invoked_instructions.iter().any(|instruction| {
+        instruction.program_id == spl_memo::id() && instruction.data == expected_memo
+    }));
+    drop(invoked_instructions);
+
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(Player::unpack(&account.data).unwrap().reward_to_claim, 0);
+}
+
+#[tokio::test]
+async fn claim_reward_vault_pda() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_one_token_account_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+
+    // Init hands the vault over to the PDA and stores its bump
+    let (pda, bump) = vault_pda(&program_id);
+    let game_info = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(GameInfo::unpack(&game_info.data).unwrap().vault_bump, bump);
+    let token_account_state = |account: Option<Account>| {
+        spl_token::state::Account::unpack(&account.unwrap().data).unwrap()
+    };
+    let vault = token_account_state(
+        banks_client
+            .get_account(token_account_keypair.pubkey())
+            .await
+            .unwrap(),
+    );
+    assert_eq!(vault.owner, pda);
+    let vault_amount = vault.amount;
+
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    // Test transfer out of the vault without the program, by the former owner and by the player
+    for signer in [&admin_account_keypair, &player_one_holder_keypair] {
+        let instruction = spl_token::instruction::transfer(
+            &spl_token::id(),
+            &token_account_keypair.pubkey(),
+            &player_one_token_account_keypair.pubkey(),
+            &signer.pubkey(),
+            &[],
+            100,
+        )
+        .unwrap();
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer, signer], recent_blockhash);
+        let result = banks_client.process_transaction(transaction).await;
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("custom program error: 0x4")); // TokenError::OwnerMismatch
+    }
+
+    // Test claim with another account in place of the vault PDA
+    let transaction = build_claim_reward_transaction(
+        &player_one_holder_keypair,
+        &program_account,
+        &player_one_account,
+        &token_account_keypair,
+        admin_account_keypair.pubkey(),
+        &player_one_token_account_keypair,
+        program_id,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("Provided seeds do not result in a valid address"));
+
+    // Test claim, the program signs the transfer for the PDA
+    let transaction = build_claim_reward_transaction(
+        &player_one_holder_keypair,
+        &program_account,
+        &player_one_account,
+        &token_account_keypair,
+        pda,
+        &player_one_token_account_keypair,
+        program_id,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let vault = token_account_state(
+        banks_client
+            .get_account(token_account_keypair.pubkey())
+            .await
+            .unwrap(),
+    );
+    assert_eq!(vault.amount, vault_amount - 100);
+    let player_token_account = token_account_state(
+        banks_client
+            .get_account(player_one_token_account_keypair.pubkey())
+            .await
+            .unwrap(),
+    );
+    assert_eq!(player_token_account.amount, 100);
+}
+
+#[tokio::test]
+async fn claim_reward_token_account() {
+    let TestGame {
+        mint_account_keypair,
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_token_account_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    // Another token account owned by player one, for the game mint and for another mint
+    let claim_token_account_keypair = Keypair::new();
+    banks_client
+        .process_transaction(build_create_and_init_player_token_account(
+            &payer,
+            &claim_token_account_keypair,
+            &player_one_holder_keypair,
+            &mint_account_keypair,
+            recent_blockhash,
+        ))
+        .await
+        .unwrap();
+    let other_mint_account_keypair = Keypair::new();
+    let other_mint_token_account_keypair = Keypair::new();
+    banks_client
+        .process_transaction(build_create_and_init_token_transaction(
+            &payer,
+            &other_mint_account_keypair,
+            &other_mint_token_account_keypair,
+            &player_one_holder_keypair,
+            recent_blockhash,
+        ))
+        .await
+        .unwrap();
+
+    let (pda, _nonce) = vault_pda(&program_id);
+    // Test claim into a token account owned by player two, or for another mint
+    for player_token_account_keypair in [
+        &player_two_token_account_keypair,
+        &other_mint_token_account_keypair,
+    ] {
+        let transaction = build_claim_reward_transaction(
+            &player_one_holder_keypair,
+            &program_account,
+            &player_one_account,
+            &token_account_keypair,
+            pda,
+            player_token_account_keypair,
+            program_id,
+            &payer,
+            recent_blockhash,
+        );
+        let result = banks_client.process_transaction(transaction).await;
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("custom program error: 0x7")); // GameError::InvalidTokenAccount
+    }
+
+    // Test claim into a token account owned by player one other than the one created during setup
+    let transaction = build_claim_reward_transaction(
+        &player_one_holder_keypair,
+        &program_account,
+        &player_one_account,
+        &token_account_keypair,
+        pda,
+        &claim_token_account_keypair,
+        program_id,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client
+        .get_account(claim_token_account_keypair.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        spl_token::state::Account::unpack(&account.data)
+            .unwrap()
+            .amount,
+        100
+    );
+    let account = banks_client
+        .get_account(player_two_token_account_keypair.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        spl_token::state::Account::unpack(&account.data)
+            .unwrap()
+            .amount,
+        0
+    );
+}
+
+#[tokio::test]
+async fn claim_reward_min_claim() {
+    let min_claim = 100;
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        player_one_token_account_keypair,
+        player_two_token_account_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams {
+        min_claim,
+        ..InitParams::default()
+    })
+    .await;
+
+    // Player one is one below the min claim, player two is above it
+    for (holder_keypair, player_account, reward_amount) in [
+        (
+            &player_one_holder_keypair,
+            &player_one_account,
+            min_claim - 1,
+        ),
+        (
+            &player_two_holder_keypair,
+            &player_two_account,
+            min_claim + 1,
+        ),
+    ] {
+        let transaction = build_register_player_transaction(
+            &payer,
+            holder_keypair,
+            player_account,
+            &program_account,
+            None,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+        let add_reward_transaction = build_add_reward_transaction(
+            &admin_account_keypair,
+            &program_account,
+            player_account,
+            &[],
+            program_id,
+            reward_amount,
+            &payer,
+            recent_blockhash,
+        );
+        banks_client
+            .process_transaction(add_reward_transaction)
+            .await
+            .unwrap();
+    }
+
+    let (pda, _nonce) = vault_pda(&program_id);
+    let transaction = build_claim_reward_transaction(
+        &player_one_holder_keypair,
+        &program_account,
+        &player_one_account,
+        &token_account_keypair,
+        pda,
+        &player_one_token_account_keypair,
+        program_id,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x14")); // GameError::BelowMinimumClaim
+
+    // Player one reach the min claim exactly
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        1,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    let recent_blockhash = get_new_blockhash(&mut banks_client, &recent_blockhash).await;
+    for (holder_keypair, player_account, player_token_account_keypair, claimed_amount) in [
+        (
+            &player_one_holder_keypair,
+            &player_one_account,
+            &player_one_token_account_keypair,
+            min_claim,
+        ),
+        (
+            &player_two_holder_keypair,
+            &player_two_account,
+            &player_two_token_account_keypair,
+            min_claim + 1,
+        ),
+    ] {
+        let transaction = build_claim_reward_transaction(
+            holder_keypair,
+            &program_account,
+            player_account,
+            &token_account_keypair,
+            pda,
+            player_token_account_keypair,
+            program_id,
+            &payer,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+
+        let account = banks_client
+            .get_account(player_token_account_keypair.pubkey())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            spl_token::state::Account::unpack(&account.data)
+                .unwrap()
+                .amount,
+            claimed_amount
+        );
+    }
+}
+
+#[tokio::test]
+async fn claim_reward_partial() {
+    let TestGameContext {
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_one_token_account_keypair,
+        program_id,
+        mut context,
+        ..
+    } = setup_context_initialized(InitParams::default()).await;
+    let mut banks_client = context.banks_client.clone();
+    // Copy the payer, the context is borrowed mutably to warp the slot
+    let payer = &Keypair::from_bytes(&context.payer.to_bytes()).unwrap();
+    let recent_blockhash = context.last_blockhash;
+    let transaction = build_register_player_transaction(
+        payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        100,
+        payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    let build_claim_transaction = |amount: Option<u64>, recent_blockhash: Hash| {
+        let mut transaction = Transaction::new_with_payer(
+            &[instruction::claim(
+                &program_id,
+                &player_one_holder_keypair.pubkey(),
+                &program_account,
+                &token_account_keypair.pubkey(),
+                &player_one_token_account_keypair.pubkey(),
+                amount,
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[payer, &player_one_holder_keypair], recent_blockhash);
+        transaction
+    };
+
+    // Test claim nothing, and more than the reward to claim
+    for (amount, error) in [
+        (0, "custom program error: 0x21"),  // GameError::ZeroAmount
+        (101, "custom program error: 0x9"), // GameError::InsufficientReward
+    ] {
+        let transaction = build_claim_transaction(Some(amount), recent_blockhash);
+        let result = banks_client.process_transaction(transaction).await;
+        assert!(result.unwrap_err().to_string().contains(error));
+    }
+
+    // Test partial claim, then claim the rest once the cooldown elapsed
+    for (slot, amount, claimed_amount, reward_to_claim) in
+        [(10, Some(40), 40, 60), (20, None, 100, 0)]
+    {
+        context.warp_to_slot(slot).unwrap();
+        let mut clock = banks_client.get_sysvar::<Clock>().await.unwrap();
+        clock.unix_timestamp += COOLDOWN_SECS;
+        context.set_sysvar(&clock);
+        let recent_blockhash = get_new_blockhash(&mut banks_client, &recent_blockhash).await;
+        let transaction = build_claim_transaction(amount, recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+
+        let account = banks_client
+            .get_account(player_one_account)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            Player::unpack(&account.data).unwrap().reward_to_claim,
+            reward_to_claim
+        );
+        let account = banks_client
+            .get_account(player_one_token_account_keypair.pubkey())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            spl_token::state::Account::unpack(&account.data)
+                .unwrap()
+                .amount,
+            claimed_amount
+        );
+    }
+}
+
+#[tokio::test]
+async fn claim_reward_vesting() {
+    let TestGameContext {
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        player_one_token_account_keypair,
+        program_id,
+        mut context,
+        ..
+    } = setup_context_initialized(InitParams::default()).await;
+    let mut banks_client = context.banks_client.clone();
+    // Copy the payer, the context is borrowed mutably to warp the slot
+    let payer = &Keypair::from_bytes(&context.payer.to_bytes()).unwrap();
+    let recent_blockhash = context.last_blockhash;
+    for (holder_keypair, player_account) in [
+        (&player_one_holder_keypair, &player_one_account),
+        (&player_two_holder_keypair, &player_two_account),
+    ] {
+        let transaction = build_register_player_transaction(
+            payer,
+            holder_keypair,
+            player_account,
+            &program_account,
+            None,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+
+    // Rewards credited from now on unlock over four days
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::set_vest_duration(
+            &program_id,
+            &admin_account_keypair.pubkey(),
+            &program_account,
+            4 * COOLDOWN_SECS as u64,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[payer, &admin_account_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        1_000,
+        payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let vesting = Player::unpack(&account.data).unwrap().vesting;
+    assert_eq!(vesting.amount, 1_000);
+    assert_eq!(vesting.duration, 4 * COOLDOWN_SECS as u64);
+
+    let build_claim_transaction = |recent_blockhash: Hash| {
+        let mut transaction = Transaction::new_with_payer(
+            &[instruction::claim(
+                &program_id,
+                &player_one_holder_keypair.pubkey(),
+                &program_account,
+                &token_account_keypair.pubkey(),
+                &player_one_token_account_keypair.pubkey(),
+                None,
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[payer, &player_one_holder_keypair], recent_blockhash);
+        transaction
+    };
+
+    // Test claim right after the credit, nothing is vested yet
+    let transaction = build_claim_transaction(recent_blockhash);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x24")); // GameError::RewardStillVesting
+
+    // Test transfer the locked reward to another player
+    let transaction = build_transfer_reward_transaction(
+        &player_one_holder_keypair,
+        &player_one_account,
+        &player_two_account,
+        &program_account,
+        program_id,
+        1,
+        payer,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x24")); // GameError::RewardStillVesting
+
+    // A quarter of the reward is vested after one day, then all of it after four
+    for (slot, elapsed_days, claimed_amount, reward_to_claim) in
+        [(10, 1, 250, 750), (20, 4, 1_000, 0)]
+    {
+        context.warp_to_slot(slot).unwrap();
+        let mut clock = banks_client.get_sysvar::<Clock>().await.unwrap();
+        clock.unix_timestamp = vesting.start + elapsed_days * COOLDOWN_SECS;
+        context.set_sysvar(&clock);
+        let recent_blockhash = get_new_blockhash(&mut banks_client, &recent_blockhash).await;
+
+        let transaction = build_claim_transaction(recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+
+        let account = banks_client
+            .get_account(player_one_account)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            Player::unpack(&account.data).unwrap().reward_to_claim,
+            reward_to_claim
+        );
+        let account = banks_client
+            .get_account(player_one_token_account_keypair.pubkey())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            spl_token::state::Account::unpack(&account.data)
+                .unwrap()
+                .amount,
+            claimed_amount
+        );
+    }
+}
+
+#[tokio::test]
+async fn claim_reward_cooldown() {
+    let TestGameContext {
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_one_token_account_keypair,
+        program_id,
+        mut context,
+        ..
+    } = setup_context_initialized(InitParams::default()).await;
+    let mut banks_client = context.banks_client.clone();
+    // Copy the payer, the context is borrowed mutably to warp the slot
+    let payer = &Keypair::from_bytes(&context.payer.to_bytes()).unwrap();
+    let recent_blockhash = context.last_blockhash;
+    let transaction = build_register_player_transaction(
+        payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let (pda, _nonce) = vault_pda(&program_id);
+    let mut unix_timestamp = banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .unix_timestamp;
+    for (slot, elapsed_secs, claimable) in [
+        (10, 0, true),
+        (20, COOLDOWN_SECS - 1, false),
+        (30, COOLDOWN_SECS, true),
+    ] {
+        // Warp to get a new blockhash, otherwise the claim transactions are identical
+        context.warp_to_slot(slot).unwrap();
+        let recent_blockhash = get_new_blockhash(&mut banks_client, &recent_blockhash).await;
+        let add_reward_transaction = build_add_reward_transaction(
+            &admin_account_keypair,
+            &program_account,
+            &player_one_account,
+            &[],
+            program_id,
+            100,
+            payer,
+            recent_blockhash,
+        );
+        banks_client
+            .process_transaction(add_reward_transaction)
+            .await
+            .unwrap();
+
+        // Move the clock forward from the last claim
+        unix_timestamp += elapsed_secs;
+        let mut clock = banks_client.get_sysvar::<Clock>().await.unwrap();
+        clock.unix_timestamp = unix_timestamp;
+        context.set_sysvar(&clock);
+
+        let transaction = build_claim_reward_transaction(
+            &player_one_holder_keypair,
+            &program_account,
+            &player_one_account,
+            &token_account_keypair,
+            pda,
+            &player_one_token_account_keypair,
+            program_id,
+            payer,
+            recent_blockhash,
+        );
+        let result = banks_client.process_transaction(transaction).await;
+        if claimable {
+            result.unwrap();
+            let account = banks_client
+                .get_account(player_one_account)
+                .await
+                .unwrap()
+                .unwrap();
+            let player_one_state = Player::unpack(&account.data).unwrap();
+            assert_eq!(player_one_state.reward_to_claim, 0);
+            assert_eq!(player_one_state.last_claim_at, unix_timestamp);
+        } else {
+            assert!(result
+                .unwrap_err()
+                .to_string()
+                .contains("custom program error: 0xf")); // GameError::ClaimCooldown
+        }
+    }
+}
+
+#[tokio::test]
+async fn claim_reward_decay() {
+    // The unclaimed reward decays by 10% every day
+    let TestGameContext {
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_one_token_account_keypair,
+        program_id,
+        mut context,
+        ..
+    } = setup_context_initialized(InitParams {
+        decay_bps_per_day: 1_000,
+        ..InitParams::default()
+    })
+    .await;
+    let mut banks_client = context.banks_client.clone();
+    // Copy the payer, the context is borrowed mutably to warp the slot
+    let payer = &Keypair::from_bytes(&context.payer.to_bytes()).unwrap();
+    let recent_blockhash = context.last_blockhash;
+    let transaction = build_register_player_transaction(
+        payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let (pda, _nonce) = vault_pda(&program_id);
+    // Claimed after 2 days and some seconds, then after 20 days, when nothing is left
+    for (slot, elapsed_secs, claimed_amount) in [
+        (10, 2 * DECAY_PERIOD_SECS + 10, Some(80)),
+        (20, 20 * DECAY_PERIOD_SECS, None),
+    ] {
+        // Warp to get a new blockhash, otherwise the claim transactions are identical
+        context.warp_to_slot(slot).unwrap();
+        let recent_blockhash = get_new_blockhash(&mut banks_client, &recent_blockhash).await;
+        let add_reward_transaction = build_add_reward_transaction(
+            &admin_account_keypair,
+            &program_account,
+            &player_one_account,
+            &[],
+            program_id,
+            100,
+            payer,
+            recent_blockhash,
+        );
+        banks_client
+            .process_transaction(add_reward_transaction)
+            .await
+            .unwrap();
+
+        // The reward decays from the time it was credited
+        let account = banks_client
+            .get_account(player_one_account)
+            .await
+            .unwrap()
+            .unwrap();
+        let player_one_state = Player::unpack(&account.data).unwrap();
+        let mut clock = banks_client.get_sysvar::<Clock>().await.unwrap();
+        assert_eq!(player_one_state.last_credit_at, clock.unix_timestamp);
+        clock.unix_timestamp = player_one_state.last_credit_at + elapsed_secs;
+        context.set_sysvar(&clock);
+
+        let account = banks_client
+            .get_account(player_one_token_account_keypair.pubkey())
+            .await
+            .unwrap()
+            .unwrap();
+        let balance_before = spl_token::state::Account::unpack(&account.data)
+            .unwrap()
+            .amount;
+        let transaction = build_claim_reward_transaction(
+            &player_one_holder_keypair,
+            &program_account,
+            &player_one_account,
+            &token_account_keypair,
+            pda,
+            &player_one_token_account_keypair,
+            program_id,
+            payer,
+            recent_blockhash,
+        );
+        let result = banks_client.process_transaction(transaction).await;
+        match claimed_amount {
+            Some(claimed_amount) => {
+                result.unwrap();
+                let account = banks_client
+                    .get_account(player_one_token_account_keypair.pubkey())
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(
+                    spl_token::state::Account::unpack(&account.data)
+                        .unwrap()
+                        .amount,
+                    balance_before + claimed_amount
+                );
+                let account = banks_client
+                    .get_account(player_one_account)
+                    .await
+                    .unwrap()
+                    .unwrap();
+                let player_one_state = Player::unpack(&account.data).unwrap();
+                assert_eq!(player_one_state.reward_to_claim, 0);
+                assert_eq!(player_one_state.total_earned, 100);
+            }
+            None => {
+                assert!(result
+                    .unwrap_err()
+                    .to_string()
+                    .contains("custom program error: 0x4")); // GameError::UnclaimableAmount
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn total_earned() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_one_token_account_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Credit, claim, then credit again
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+    let (pda, _nonce) = vault_pda(&program_id);
+    let transaction = build_claim_reward_transaction(
+        &player_one_holder_keypair,
+        &program_account,
+        &player_one_account,
+        &token_account_keypair,
+        pda,
+        &player_one_token_account_keypair,
+        program_id,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        40,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_one_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_one_state.reward_to_claim, 40);
+    assert_eq!(player_one_state.total_earned, 140);
+}
+
+#[tokio::test]
+async fn batch_register() {
+    let player_three_holder_keypair = Keypair::new();
+    let player_four_holder_keypair = Keypair::new();
+    let player_five_holder_keypair = Keypair::new();
+    let TestGame {
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized_with_extra_holders(
+        &[
+            &player_three_holder_keypair,
+            &player_four_holder_keypair,
+            &player_five_holder_keypair,
+        ],
+        InitParams::default(),
+    )
+    .await;
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let holder_keypairs = [
+        &player_two_holder_keypair,
+        &player_three_holder_keypair,
+        &player_four_holder_keypair,
+        &player_five_holder_keypair,
+    ];
+    let build_batch_register_transaction = |holder_keypairs: &[&Keypair]| {
+        let holders: Vec<Pubkey> = holder_keypairs.iter().map(|k| k.pubkey()).collect();
+        let mut transaction = Transaction::new_with_payer(
+            &[instruction::batch_register(
+                &program_id,
+                &program_account,
+                &player_one_account,
+                &holders,
+            )],
+            Some(&payer.pubkey()),
+        );
+        let mut signers = vec![&payer];
+        signers.extend_from_slice(holder_keypairs);
+        transaction.partial_sign(&signers, recent_blockhash);
+        transaction
+    };
+
+    // Test batch containing the already registered upline, no player of the batch is registered
+    let transaction = build_batch_register_transaction(&[
+        &player_two_holder_keypair,
+        &player_three_holder_keypair,
+        &player_one_holder_keypair,
+    ]);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("instruction requires an uninitialized account"));
+    let account = banks_client
+        .get_account(player_two_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(
+        !Player::unpack_unchecked(&account.data)
+            .unwrap()
+            .is_initialized
+    );
+
+    // Test register four players at once
+    let transaction = build_batch_register_transaction(&holder_keypairs);
+    banks_client.process_transaction(transaction).await.unwrap();
+    for holder_keypair in holder_keypairs {
+        let (player_account, _) = player_pda(&program_id, &holder_keypair.pubkey());
+        let account = banks_client
+            .get_account(player_account)
+            .await
+            .unwrap()
+            .unwrap();
+        let player_state = Player::unpack(&account.data).unwrap();
+        assert_eq!(player_state.owner, holder_keypair.pubkey());
+        assert_eq!(player_state.upline, COption::Some(player_one_account));
+    }
+
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(Player::unpack(&account.data).unwrap().downline_count, 4);
+    let account = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(GameInfo::unpack(&account.data).unwrap().total_players, 5);
+}
+
+#[tokio::test]
+async fn undersized_accounts() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+
+    // An account owned by the program, but too small to hold a GameInfo or a Player
+    let undersized_account_keypair = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[system_instruction::create_account(
+            &payer.pubkey(),
+            &undersized_account_keypair.pubkey(),
+            Rent::default().minimum_balance(GameInfo::LEN - 1),
+            GameInfo::LEN as u64 - 1,
+            &program_id,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &undersized_account_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Test register against the undersized account as program account
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &undersized_account_keypair.pubkey(),
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("invalid account data for instruction"));
+
+    // Test add reward to the undersized account as player account
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &undersized_account_keypair.pubkey(),
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client
+        .process_transaction(add_reward_transaction)
+        .await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("invalid account data for instruction"));
+}
+
+#[tokio::test]
+async fn compute_units_logged() {
+    let TestGame {
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+
+    // The remaining compute units are logged at the start and the end of the instruction, even when it fails
+    // Other tests run in parallel and log too, so only a lower bound can be asserted
+    for (recent_blockhash, succeed) in [
+        (recent_blockhash, true),
+        (
+            get_new_blockhash(&mut banks_client, &recent_blockhash).await,
+            false,
+        ),
+    ] {
+        let logged_compute_units = LOGGED_COMPUTE_UNITS.load(Ordering::SeqCst);
+        let transaction = build_register_player_transaction(
+            &payer,
+            &player_one_holder_keypair,
+            &player_one_account,
+            &program_account,
+            None,
+            program_id,
+            recent_blockhash,
+        );
+        let result = banks_client.process_transaction(transaction).await;
+        assert_eq!(result.is_ok(), succeed);
+        assert!(LOGGED_COMPUTE_UNITS.load(Ordering::SeqCst) >= logged_compute_units + 2);
+    }
+}
+
+#[tokio::test]
+async fn init_compute_units() {
+    let TestGame {
+        mint_account_keypair,
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_with_compute_max_units(&[], Some(INIT_MAX_COMPUTE_UNITS)).await;
+    let init_instruction_transaction = build_init_instruction_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &token_account_keypair,
+        &mint_account_keypair.pubkey(),
+        0,
+        &payer,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(init_instruction_transaction)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn register_compute_units() {
+    let TestGame {
+        mint_account_keypair,
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_with_compute_max_units(&[], Some(REGISTER_MAX_COMPUTE_UNITS)).await;
+    let init_instruction_transaction = build_init_instruction_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &token_account_keypair,
+        &mint_account_keypair.pubkey(),
+        0,
+        &payer,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(init_instruction_transaction)
+        .await
+        .unwrap();
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+}
+
+#[tokio::test]
+async fn multi_level_register_compute_units() {
+    let player_three_holder_keypair = Keypair::new();
+    let TestGame {
+        mint_account_keypair,
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_with_compute_max_units(
+        &[&player_three_holder_keypair],
+        Some(MULTI_LEVEL_REGISTER_MAX_COMPUTE_UNITS),
+    )
+    .await;
+    let (player_three_account, _) = player_pda(&program_id, &player_three_holder_keypair.pubkey());
+    let init_instruction_transaction = build_init_instruction_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &token_account_keypair,
+        &mint_account_keypair.pubkey(),
+        10,
+        &payer,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(init_instruction_transaction)
+        .await
+        .unwrap();
+
+    // Build referral chain, player three -> player two -> player one
+    let registrations = [
+        (&player_one_holder_keypair, &player_one_account, None),
+        (
+            &player_two_holder_keypair,
+            &player_two_account,
+            Some(&player_one_account),
+        ),
+        (
+            &player_three_holder_keypair,
+            &player_three_account,
+            Some(&player_two_account),
+        ),
+    ];
+    for (holder_keypair, player_account, upline_account) in registrations {
+        let transaction = build_register_player_transaction(
+            &payer,
+            holder_keypair,
+            player_account,
+            &program_account,
+            upline_account,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn add_reward() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+    // Register player one
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Register player two, with player one as upline
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_two_holder_keypair,
+        &player_two_account,
+        &program_account,
+        Some(&player_one_account),
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Test add reward with non-admin account
+    let fake_admin_account_keypair = Keypair::new();
+    let add_reward_transaction = build_add_reward_transaction(
+        &fake_admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client
+        .process_transaction(add_reward_transaction)
+        .await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x6")); // GameError::InvalidAdmin
+                                                 // End
+
+    // Test add reward with invalid player account
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &Pubkey::new_unique(),
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client
+        .process_transaction(add_reward_transaction)
+        .await;
+    match result {
+        Ok(()) => {}
+        Err(error) => {
+            assert!(error.to_string().contains("incorrect program id"));
+        }
+    };
+    // End
+
+    // Test add reward with non-exists upline account
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_two_account,
+        &[&Pubkey::new_unique()],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client
+        .process_transaction(add_reward_transaction)
+        .await;
+    match result {
+        Ok(()) => {}
+        Err(error) => {
+            assert!(error.to_string().contains("incorrect program id"));
+        }
+    };
+    // End
+
+    // Test add reward to invalid upline account
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_two_account,
+        &[&player_two_account],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client
+        .process_transaction(add_reward_transaction)
+        .await;
+    match result {
+        Ok(()) => {}
+        Err(error) => {
+            assert!(error.to_string().contains("custom program error"));
+        }
+    };
+    // End
+
+    // Test add reward to player two with admin
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_two_account,
+        &[&player_one_account],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    let player_one_account_info = banks_client.get_account(player_one_account).await.unwrap();
+    if let Some(account) = player_one_account_info {
+        let player_one_state = Player::unpack(&account.data).unwrap();
+        assert_eq!(player_one_state.reward_to_claim, 25); // 25% from player two
+    }
+
+    let player_two_account_info = banks_client.get_account(player_two_account).await.unwrap();
+    if let Some(account) = player_two_account_info {
+        let player_two_state = Player::unpack(&account.data).unwrap();
+        assert_eq!(player_two_state.reward_to_claim, 75); // 50% + unused second and third level share
+    }
+
+    // Test add reward to player one
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    let player_one_account_info = banks_client.get_account(player_one_account).await.unwrap();
+    if let Some(account) = player_one_account_info {
+        let player_one_state = Player::unpack(&account.data).unwrap();
+        assert_eq!(player_one_state.reward_to_claim, 125); // 100 + 25% from player two
+    }
+}
+
+#[tokio::test]
+async fn add_reward_overflow() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        u64::MAX,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    // Test add reward overflowing the player reward to claim
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        1,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client
+        .process_transaction(add_reward_transaction)
+        .await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x2")); // GameError::RewardAmountOverflow
+
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_one_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_one_state.reward_to_claim, u64::MAX);
+}
+
+#[tokio::test]
+async fn add_reward_large_amount() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_two_holder_keypair,
+        &player_two_account,
+        &program_account,
+        Some(&player_one_account),
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Test upline percentage of an amount that would overflow u64 when multiplied
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_two_account,
+        &[&player_one_account],
+        program_id,
+        u64::MAX,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    let upline_reward = (u64::MAX as u128 * 25 / 100) as u64;
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_one_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_one_state.reward_to_claim, upline_reward);
+
+    let account = banks_client
+        .get_account(player_two_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_two_state = Player::unpack(&account.data).unwrap();
+    let player_reward = (u64::MAX as u128 * 75 / 100) as u64;
+    assert_eq!(player_two_state.reward_to_claim, player_reward);
+
+    // Both shares round down, what is left is kept as dust
+    let account = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        GameInfo::unpack(&account.data).unwrap().dust,
+        u64::MAX - upline_reward - player_reward
+    );
+}
+
+#[tokio::test]
+async fn add_reward_multi_level() {
+    let player_three_holder_keypair = Keypair::new();
+    let player_four_holder_keypair = Keypair::new();
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized_with_extra_holders(
+        &[&player_three_holder_keypair, &player_four_holder_keypair],
+        InitParams::default(),
+    )
+    .await;
+    let (player_three_account, _) = player_pda(&program_id, &player_three_holder_keypair.pubkey());
+    let (player_four_account, _) = player_pda(&program_id, &player_four_holder_keypair.pubkey());
+
+    // Build referral chain, player four -> player three -> player two -> player one
+    let registrations = [
+        (&player_one_holder_keypair, &player_one_account, None),
+        (
+            &player_two_holder_keypair,
+            &player_two_account,
+            Some(&player_one_account),
+        ),
+        (
+            &player_three_holder_keypair,
+            &player_three_account,
+            Some(&player_two_account),
+        ),
+        (
+            &player_four_holder_keypair,
+            &player_four_account,
+            Some(&player_three_account),
+        ),
+    ];
+    for (holder_keypair, player_account, upline_account) in registrations {
+        let transaction = build_register_player_transaction(
+            &payer,
+            holder_keypair,
+            player_account,
+            &program_account,
+            upline_account,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+
+    // Test add reward with upline chain in wrong order
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_four_account,
+        &[
+            &player_two_account,
+            &player_three_account,
+            &player_one_account,
+        ],
+        program_id,
+        1000,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client
+        .process_transaction(add_reward_transaction)
+        .await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x3")); // GameError::InvalidUpline
+
+    // Test add reward to player four, with all three levels of upline
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_four_account,
+        &[
+            &player_three_account,
+            &player_two_account,
+            &player_one_account,
+        ],
+        program_id,
+        1000,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    for (player_account, expected_reward) in [
+        (player_four_account, 500),
+        (player_three_account, 250),
+        (player_two_account, 150),
+        (player_one_account, 100),
+    ] {
+        let account = banks_client
+            .get_account(player_account)
+            .await
+            .unwrap()
+            .unwrap();
+        let player_state = Player::unpack(&account.data).unwrap();
+        assert_eq!(player_state.reward_to_claim, expected_reward);
+    }
+}
+
+#[tokio::test]
+async fn add_reward_level_split() {
+    // Split above the whole reward, then splits whose levels get each a different share
+    // Expected rewards of player four, three, two and one once player four and then player two got 1000
+    for (level_bps, expected_rewards) in [
+        ([5_000, 4_000, 1_001], None),
+        ([5_000, 2_000, 0], Some([300, 500, 700, 500])),
+        ([0, 0, 10_000], Some([0, 0, 1_000, 1_000])),
+    ] {
+        let player_three_holder_keypair = Keypair::new();
+        let player_four_holder_keypair = Keypair::new();
+        let TestGame {
+            mint_account_keypair,
+            admin_account_keypair,
+            program_account,
+            token_account_keypair,
+            player_one_holder_keypair,
+            player_one_account,
+            player_two_holder_keypair,
+            player_two_account,
+            program_id,
+            mut banks_client,
+            payer,
+            recent_blockhash,
+            ..
+        } = setup_with_extra_holders(&[&player_three_holder_keypair, &player_four_holder_keypair])
+            .await;
+        let (player_three_account, _) =
+            player_pda(&program_id, &player_three_holder_keypair.pubkey());
+        let (player_four_account, _) =
+            player_pda(&program_id, &player_four_holder_keypair.pubkey());
+        let mut transaction = Transaction::new_with_payer(
+            &[instruction::init(
+                &program_id,
+                &admin_account_keypair.pubkey(),
+                &program_account,
+                &token_account_keypair.pubkey(),
+                &mint_account_keypair.pubkey(),
+                0,
+                DEFAULT_MAX_DEPTH,
+                0,
+                0,
+                0,
+                0,
+                level_bps,
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &admin_account_keypair], recent_blockhash);
+        let result = banks_client.process_transaction(transaction).await;
+        let expected_rewards = match expected_rewards {
+            Some(expected_rewards) => expected_rewards,
+            None => {
+                assert!(result
+                    .unwrap_err()
+                    .to_string()
+                    .contains("custom program error: 0x1c")); // GameError::InvalidRewardSplit
+                continue;
+            }
+        };
+        result.unwrap();
+
+        // Build referral chain, player four -> player three -> player two -> player one
+        for (holder_keypair, player_account, upline_account) in [
+            (&player_one_holder_keypair, &player_one_account, None),
+            (
+                &player_two_holder_keypair,
+                &player_two_account,
+                Some(&player_one_account),
+            ),
+            (
+                &player_three_holder_keypair,
+                &player_three_account,
+                Some(&player_two_account),
+            ),
+            (
+                &player_four_holder_keypair,
+                &player_four_account,
+                Some(&player_three_account),
+            ),
+        ] {
+            let transaction = build_register_player_transaction(
+                &payer,
+                holder_keypair,
+                player_account,
+                &program_account,
+                upline_account,
+                program_id,
+                recent_blockhash,
+            );
+            banks_client.process_transaction(transaction).await.unwrap();
+        }
+
+        // All three levels, then a single level where the share of the missing levels stays with the player
+        for (player_account, upline_accounts) in [
+            (
+                &player_four_account,
+                vec![
+                    &player_three_account,
+                    &player_two_account,
+                    &player_one_account,
+                ],
+            ),
+            (&player_two_account, vec![&player_one_account]),
+        ] {
+            let add_reward_transaction = build_add_reward_transaction(
+                &admin_account_keypair,
+                &program_account,
+                player_account,
+                &upline_accounts,
+                program_id,
+                1000,
+                &payer,
+                recent_blockhash,
+            );
+            banks_client
+                .process_transaction(add_reward_transaction)
+                .await
+                .unwrap();
+        }
+
+        for (player_account, expected_reward) in [
+            player_four_account,
+            player_three_account,
+            player_two_account,
+            player_one_account,
+        ]
+        .into_iter()
+        .zip(expected_rewards)
+        {
+            let account = banks_client
+                .get_account(player_account)
+                .await
+                .unwrap()
+                .unwrap();
+            let player_state = Player::unpack(&account.data).unwrap();
+            assert_eq!(player_state.reward_to_claim, expected_reward);
+        }
+    }
+}
+
+#[tokio::test]
+async fn register_player() {
+    let TestGame {
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+
+    // Test register player with player account not derived from the holder
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &Pubkey::new_unique(),
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0xa")); // GameError::InvalidPlayerAccount
+                                                 // End
+
+    // Test register player with player account derived from another holder
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_two_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0xa")); // GameError::InvalidPlayerAccount
+                                                 // End
+
+    // Test register player with invalid program account
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &Pubkey::new_unique(),
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    match result {
+        Ok(()) => {}
+        Err(error) => {
+            assert!(error.to_string().contains("incorrect program id"));
+        }
+    };
+    // End
+
+    // Test register player with invalid upline account
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        Some(&Pubkey::new_unique()),
+        program_id,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x8")); // GameError::UplineNotRegistered
+                                                 // End
+
+    // Test register self as upline
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        Some(&player_one_account),
+        program_id,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    match result {
+        Ok(()) => {}
+        Err(error) => {
+            assert!(error.to_string().contains("custom program error"));
+            // Why decode custom error not working?
+        }
+    };
+    // End
+
+    // Test register player
+    // Register player one
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    let player_one_account_info = banks_client.get_account(player_one_account).await.unwrap();
+    match player_one_account_info {
+        Some(account) => {
+            let player_one_state = Player::unpack(&account.data).unwrap();
+            assert!(player_one_state.is_initialized);
+            assert_eq!(player_one_state.owner, player_one_holder_keypair.pubkey());
+            assert_eq!(player_one_state.reward_to_claim, 0);
+            assert_eq!(player_one_state.upline, COption::None);
+            assert_eq!(player_one_state.downline_count, 0);
+        }
+        _ => {
+            panic!("Player one account not found");
+        }
+    };
+
+    // Register player two, with player one as upline
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_two_holder_keypair,
+        &player_two_account,
+        &program_account,
+        Some(&player_one_account),
+        program_id,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+
+    let player_two_account_info = banks_client.get_account(player_two_account).await.unwrap();
+
+    match player_two_account_info {
+        Some(account) => {
+            let player_two_state = Player::unpack(&account.data).unwrap();
+            assert!(player_two_state.is_initialized);
+            assert_eq!(player_two_state.owner, player_two_holder_keypair.pubkey());
+            assert_eq!(player_two_state.reward_to_claim, 0);
+            assert_eq!(player_two_state.upline, COption::Some(player_one_account));
+            assert_eq!(player_two_state.downline_count, 0);
+        }
+        _ => {
+            panic!("Player two account not found");
+        }
+    };
+
+    // Test register player one again
+    let recent_blockhash = get_new_blockhash(&mut banks_client, &recent_blockhash).await;
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("instruction requires an uninitialized account"));
+}
+
+#[tokio::test]
+async fn register_player_before_init() {
+    let TestGame {
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup().await;
+
+    // Test register player against program account not initialized by the admin
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x1")); // GameError::NotInitialized
+
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_one_state = Player::unpack_unchecked(&account.data).unwrap();
+    assert!(!player_one_state.is_initialized);
+}
+
+#[tokio::test]
+async fn register_self_referral() {
+    let TestGame {
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+
+    // Test register player with its own account as upline
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        Some(&player_one_account),
+        program_id,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x5")); // GameError::SelfRecursiveUpline
+
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_one_state = Player::unpack_unchecked(&account.data).unwrap();
+    assert!(!player_one_state.is_initialized);
+}
+
+#[tokio::test]
+async fn register_timestamp() {
+    let (program_id, program_account, player_one_holder_keypair, player_one_account, context) =
+        setup_initialized_program_context(Rent::default().minimum_balance(Player::LEN)).await;
+
+    let unix_timestamp = 1_650_000_000;
+    context.set_sysvar(&Clock {
+        unix_timestamp,
+        ..Clock::default()
+    });
+
+    let transaction = build_register_player_transaction(
+        &context.payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        context.last_blockhash,
+    );
+    let mut banks_client = context.banks_client.clone();
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_one_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_one_state.registered_at, unix_timestamp);
+    assert_eq!(
+        player_one_state.referral_code,
+        Player::referral_code_of(&player_one_account)
+    );
+}
+
+#[tokio::test]
+async fn register_not_rent_exempt() {
+    let (program_id, program_account, player_one_holder_keypair, player_one_account, context) =
+        setup_initialized_program_context(Rent::default().minimum_balance(Player::LEN) - 1).await;
+
+    // Test register with a player account under-funded by the client
+    let transaction = build_register_player_transaction(
+        &context.payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        context.last_blockhash,
+    );
+    let mut banks_client = context.banks_client.clone();
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0xe")); // GameError::NotRentExempt
+}
+
+#[tokio::test]
+async fn register_referral_bonus() {
+    let TestGame {
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams {
+        referral_bonus: 50,
+        ..InitParams::default()
+    })
+    .await;
+
+    let account = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let program_state = GameInfo::unpack(&account.data).unwrap();
+    assert_eq!(program_state.referral_bonus, 50);
+
+    for (holder_keypair, player_account, upline_account) in [
+        (&player_one_holder_keypair, &player_one_account, None),
+        (
+            &player_two_holder_keypair,
+            &player_two_account,
+            Some(&player_one_account),
+        ),
+    ] {
+        let transaction = build_register_player_transaction(
+            &payer,
+            holder_keypair,
+            player_account,
+            &program_account,
+            upline_account,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+
+    // Only the upline receives the bonus
+    for (player_account, expected_reward_to_claim) in
+        [(player_one_account, 50), (player_two_account, 0)]
+    {
+        let account = banks_client
+            .get_account(player_account)
+            .await
+            .unwrap()
+            .unwrap();
+        let player_state = Player::unpack(&account.data).unwrap();
+        assert_eq!(player_state.reward_to_claim, expected_reward_to_claim);
+    }
+}
+
+#[tokio::test]
+async fn register_matching_pool() {
+    let player_three_holder_keypair = Keypair::new();
+    let player_four_holder_keypair = Keypair::new();
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized_with_extra_holders(
+        &[&player_three_holder_keypair, &player_four_holder_keypair],
+        InitParams {
+            referral_bonus: 10,
+            ..InitParams::default()
+        },
+    )
+    .await;
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let build_fund_pool_transaction = |admin_account_keypair: &Keypair| {
+        let mut transaction = Transaction::new_with_payer(
+            &[instruction::fund_pool(
+                &program_id,
+                &admin_account_keypair.pubkey(),
+                &program_account,
+                15,
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, admin_account_keypair], recent_blockhash);
+        transaction
+    };
+
+    // Test fund with the player holder
+    let transaction = build_fund_pool_transaction(&player_one_holder_keypair);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x6")); // GameError::InvalidAdmin
+
+    let transaction = build_fund_pool_transaction(&admin_account_keypair);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Every downline registers under player one, the bonus of 10 is matched by 10, then by the 5 left, then not at all
+    for (holder_keypair, upline_reward_to_claim, matching_pool) in [
+        (&player_two_holder_keypair, 20, 5),
+        (&player_three_holder_keypair, 35, 0),
+        (&player_four_holder_keypair, 45, 0),
+    ] {
+        let (player_account, _) = player_pda(&program_id, &holder_keypair.pubkey());
+        let transaction = build_register_player_transaction(
+            &payer,
+            holder_keypair,
+            &player_account,
+            &program_account,
+            Some(&player_one_account),
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+
+        let account = banks_client
+            .get_account(player_one_account)
+            .await
+            .unwrap()
+            .unwrap();
+        let player_one_state = Player::unpack(&account.data).unwrap();
+        assert_eq!(player_one_state.reward_to_claim, upline_reward_to_claim);
+        assert_eq!(player_one_state.total_earned, upline_reward_to_claim);
+        let account = banks_client
+            .get_account(program_account)
+            .await
+            .unwrap()
+            .unwrap();
+        let program_state = GameInfo::unpack(&account.data).unwrap();
+        assert_eq!(program_state.matching_pool, matching_pool);
+    }
+}
+
+#[tokio::test]
+async fn register_matching_pool_capped() {
+    let player_three_holder_keypair = Keypair::new();
+    let player_four_holder_keypair = Keypair::new();
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized_with_extra_holders(
+        &[&player_three_holder_keypair, &player_four_holder_keypair],
+        InitParams {
+            referral_bonus: 10,
+            max_reward_per_player: 25,
+            ..InitParams::default()
+        },
+    )
+    .await;
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::fund_pool(
+            &program_id,
+            &admin_account_keypair.pubkey(),
+            &program_account,
+            30,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_account_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // The bonus of 10 matched by 10 is credited in full, then cut down to the 5 left under the cap of 25, then not credited
+    // The capped part of the match goes back to the pool, which only pays for the first bonus
+    for (holder_keypair, upline_reward_to_claim, matching_pool) in [
+        (&player_two_holder_keypair, 20, 20),
+        (&player_three_holder_keypair, 25, 20),
+        (&player_four_holder_keypair, 25, 20),
+    ] {
+        let (player_account, _) = player_pda(&program_id, &holder_keypair.pubkey());
+        let transaction = build_register_player_transaction(
+            &payer,
+            holder_keypair,
+            &player_account,
+            &program_account,
+            Some(&player_one_account),
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+
+        let account = banks_client
+            .get_account(player_one_account)
+            .await
+            .unwrap()
+            .unwrap();
+        let player_one_state = Player::unpack(&account.data).unwrap();
+        assert_eq!(player_one_state.reward_to_claim, upline_reward_to_claim);
+        let account = banks_client
+            .get_account(program_account)
+            .await
+            .unwrap()
+            .unwrap();
+        let program_state = GameInfo::unpack(&account.data).unwrap();
+        assert_eq!(program_state.matching_pool, matching_pool);
+    }
+}
+
+#[tokio::test]
+async fn freeze_player() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        player_one_token_account_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+    let (pda, _) = vault_pda(&program_id);
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    let build_freeze_player_transaction = |signer: &Keypair, frozen, recent_blockhash| {
+        let instruction = instruction::freeze_player(
+            &program_id,
+            &signer.pubkey(),
+            &program_account,
+            &player_one_account,
+            frozen,
+        );
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer, signer], recent_blockhash);
+        transaction
+    };
+
+    // Test freeze player one with player two
+    let transaction =
+        build_freeze_player_transaction(&player_two_holder_keypair, true, recent_blockhash);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x6")); // GameError::InvalidAdmin
+
+    // Test freeze player one with the admin, then claim and refer with it
+    let transaction =
+        build_freeze_player_transaction(&admin_account_keypair, true, recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(Player::unpack(&account.data).unwrap().frozen);
+
+    let transaction = build_claim_reward_transaction(
+        &player_one_holder_keypair,
+        &program_account,
+        &player_one_account,
+        &token_account_keypair,
+        pda,
+        &player_one_token_account_keypair,
+        program_id,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x1b")); // GameError::PlayerFrozen
+
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_two_holder_keypair,
+        &player_two_account,
+        &program_account,
+        Some(&player_one_account),
+        program_id,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x1b")); // GameError::PlayerFrozen
+
+    // Test transfer the frozen reward away, the source is rejected before the destination is read
+    let transaction = build_transfer_reward_transaction(
+        &player_one_holder_keypair,
+        &player_one_account,
+        &player_two_account,
+        &program_account,
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x1b")); // GameError::PlayerFrozen
+
+    // Test unfreeze player one, then claim and refer again
+    let transaction =
+        build_freeze_player_transaction(&admin_account_keypair, false, recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let recent_blockhash = get_new_blockhash(&mut banks_client, &recent_blockhash).await;
+
+    let transaction = build_claim_reward_transaction(
+        &player_one_holder_keypair,
+        &program_account,
+        &player_one_account,
+        &token_account_keypair,
+        pda,
+        &player_one_token_account_keypair,
+        program_id,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let account = banks_client
+        .get_account(player_one_token_account_keypair.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        spl_token::state::Account::unpack(&account.data)
+            .unwrap()
+            .amount,
+        100
+    );
+
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_two_holder_keypair,
+        &player_two_account,
+        &program_account,
+        Some(&player_one_account),
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_one_state = Player::unpack(&account.data).unwrap();
+    assert!(!player_one_state.frozen);
+    assert_eq!(player_one_state.downline_count, 1);
+}
+
+#[tokio::test]
+async fn register_partial_sign() {
+    let holder_keypair = Keypair::new();
+    let TestGame {
+        mint_account_keypair,
+        program_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized_with_extra_holders(&[&holder_keypair], InitParams::default()).await;
+    let (player_account, _) = player_pda(&program_id, &holder_keypair.pubkey());
+
+    // One transaction creating the token account of the holder and registering it, with three signers:
+    // the payer pays the fee and funds the new account, the new account keypair signs its own creation
+    // and the holder signs the register
+    let token_account_keypair = Keypair::new();
+    let rent = banks_client.get_rent().await.unwrap();
+    let instructions = [
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &token_account_keypair.pubkey(),
+            rent.minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN as u64,
+            &spl_token::id(),
+        ),
+        initialize_account(
+            &spl_token::id(),
+            &token_account_keypair.pubkey(),
+            &mint_account_keypair.pubkey(),
+            &holder_keypair.pubkey(),
+        )
+        .unwrap(),
+        instruction::register(
+            &program_id,
+            &holder_keypair.pubkey(),
+            &program_account,
+            None,
+            false,
+        ),
+    ];
+
+    // The common mistake: forgetting the new account keypair
+    // sign refuses to build it, partial_sign leaves its signature empty and the bank rejects the transaction
+    let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
+    assert!(transaction
+        .try_sign(&[&payer, &holder_keypair], recent_blockhash)
+        .is_err());
+    let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
+    transaction.partial_sign(&[&payer], recent_blockhash);
+    transaction.partial_sign(&[&holder_keypair], recent_blockhash);
+    assert!(!transaction.is_signed());
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("Transaction did not pass signature verification"));
+    assert!(banks_client
+        .get_account(token_account_keypair.pubkey())
+        .await
+        .unwrap()
+        .is_none());
+
+    // The correct flow: every signer partial sign the same message with the same blockhash, in any order
+    // Each signature lands in the slot of its pubkey, so the signers can sign on different machines
+    let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
+    transaction.partial_sign(&[&holder_keypair], recent_blockhash);
+    transaction.partial_sign(&[&token_account_keypair], recent_blockhash);
+    transaction.partial_sign(&[&payer], recent_blockhash);
+    assert!(transaction.is_signed());
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client
+        .get_account(token_account_keypair.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        spl_token::state::Account::unpack(&account.data)
+            .unwrap()
+            .owner,
+        holder_keypair.pubkey()
+    );
+    let account = banks_client
+        .get_account(player_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        Player::unpack(&account.data).unwrap().owner,
+        holder_keypair.pubkey()
+    );
+}
+
+#[tokio::test]
+async fn relay_register() {
+    let relayer_keypair = Keypair::new();
+    // The holder onboarded by the relayer, it never signs
+    let holder_keypair = Keypair::new();
+    let holder = holder_keypair.pubkey();
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized_with_extra_holders(
+        &[&relayer_keypair, &holder_keypair],
+        InitParams::default(),
+    )
+    .await;
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let build_transaction = |instruction: Instruction, signer: &Keypair| {
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer, signer], recent_blockhash);
+        transaction
+    };
+    let holder_lamports = banks_client.get_balance(holder).await.unwrap();
+    let (player_account, _) = player_pda(&program_id, &holder);
+    let relay_register_instruction = |relayer_keypair: &Keypair| {
+        instruction::relay_register(
+            &program_id,
+            &relayer_keypair.pubkey(),
+            &holder,
+            &program_account,
+            Some(&player_one_account),
+            false,
+        )
+    };
+
+    // Test relay register before any relayer is set, without upline so that the
+    // transaction differs from the successful one below
+    let transaction = build_transaction(
+        instruction::relay_register(
+            &program_id,
+            &relayer_keypair.pubkey(),
+            &holder,
+            &program_account,
+            None,
+            false,
+        ),
+        &relayer_keypair,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x1a")); // GameError::InvalidRelayer
+
+    // Test set relayer with the player holder, then with the admin
+    for (signer, result) in [
+        (
+            &player_one_holder_keypair,
+            Some("custom program error: 0x6"),
+        ), // GameError::InvalidAdmin
+        (&admin_account_keypair, None),
+    ] {
+        let transaction = build_transaction(
+            instruction::set_relayer(
+                &program_id,
+                &signer.pubkey(),
+                &program_account,
+                &relayer_keypair.pubkey(),
+            ),
+            signer,
+        );
+        match result {
+            Some(error) => assert!(banks_client
+                .process_transaction(transaction)
+                .await
+                .unwrap_err()
+                .to_string()
+                .contains(error)),
+            None => banks_client.process_transaction(transaction).await.unwrap(),
+        }
+    }
+
+    // Test relay register by a holder which is not the relayer
+    let transaction = build_transaction(
+        relay_register_instruction(&player_two_holder_keypair),
+        &player_two_holder_keypair,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x1a")); // GameError::InvalidRelayer
+
+    // Test relay register by the relayer, the player belongs to the holder
+    let transaction = build_transaction(
+        relay_register_instruction(&relayer_keypair),
+        &relayer_keypair,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let account = banks_client
+        .get_account(player_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.owner, program_id);
+    let player_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_state.owner, holder);
+    assert_eq!(player_state.upline, COption::Some(player_one_account));
+    assert_eq!(
+        banks_client.get_balance(holder).await.unwrap(),
+        holder_lamports
+    );
+
+    // Test relay register once the relayer is removed
+    let transaction = build_transaction(
+        instruction::set_relayer(
+            &program_id,
+            &admin_account_keypair.pubkey(),
+            &program_account,
+            &system_program::id(),
+        ),
+        &admin_account_keypair,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let other_holder = Pubkey::new_unique();
+    let transaction = build_transaction(
+        instruction::relay_register(
+            &program_id,
+            &relayer_keypair.pubkey(),
+            &other_holder,
+            &program_account,
+            None,
+            false,
+        ),
+        &relayer_keypair,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x1a")); // GameError::InvalidRelayer
+}
+
+#[tokio::test]
+async fn register_max_depth() {
+    let player_three_holder_keypair = Keypair::new();
+    let player_four_holder_keypair = Keypair::new();
+    let max_depth = 2;
+    let TestGame {
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized_with_extra_holders(
+        &[&player_three_holder_keypair, &player_four_holder_keypair],
+        InitParams {
+            max_depth,
+            ..InitParams::default()
+        },
+    )
+    .await;
+
+    // Build the chain player one <- two <- three, player three is at the max depth
+    let (player_three_account, _) = player_pda(&program_id, &player_three_holder_keypair.pubkey());
+    let (player_four_account, _) = player_pda(&program_id, &player_four_holder_keypair.pubkey());
+    let mut upline = None;
+    for (depth, holder_keypair, player_account) in [
+        (0, &player_one_holder_keypair, &player_one_account),
+        (1, &player_two_holder_keypair, &player_two_account),
+        (2, &player_three_holder_keypair, &player_three_account),
+    ] {
+        let transaction = build_register_player_transaction(
+            &payer,
+            holder_keypair,
+            player_account,
+            &program_account,
+            upline,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+        let account = banks_client
+            .get_account(*player_account)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(Player::unpack(&account.data).unwrap().depth, depth);
+        upline = Some(player_account);
+    }
+
+    // Player four would be one level deeper than the max depth
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_four_holder_keypair,
+        &player_four_account,
+        &program_account,
+        Some(&player_three_account),
+        program_id,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x13")); // GameError::MaxDepthExceeded
+
+    let account = banks_client
+        .get_account(player_three_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(Player::unpack(&account.data).unwrap().downline_count, 0);
+}
+
+#[tokio::test]
+async fn register_fee() {
+    let register_fee = 5_000_000;
+    let TestGame {
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams {
+        register_fee,
+        ..InitParams::default()
+    })
+    .await;
+
+    let holder_balance = banks_client
+        .get_balance(player_one_holder_keypair.pubkey())
+        .await
+        .unwrap();
+    let program_account_balance = banks_client.get_balance(program_account).await.unwrap();
+
+    // The payer pays the transaction fee, and the player account is already allocated, so only the fee moves
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(
+        banks_client
+            .get_balance(player_one_holder_keypair.pubkey())
+            .await
+            .unwrap(),
+        holder_balance - register_fee
+    );
+    assert_eq!(
+        banks_client.get_balance(program_account).await.unwrap(),
+        program_account_balance + register_fee
+    );
+}
+
+#[tokio::test]
+async fn register_downline_count() {
+    let player_three_holder_keypair = Keypair::new();
+    let player_four_holder_keypair = Keypair::new();
+    let TestGame {
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized_with_extra_holders(
+        &[&player_three_holder_keypair, &player_four_holder_keypair],
+        InitParams::default(),
+    )
+    .await;
+    let (player_three_account, _) = player_pda(&program_id, &player_three_holder_keypair.pubkey());
+    let (player_four_account, _) = player_pda(&program_id, &player_four_holder_keypair.pubkey());
+
+    // Register player one without upline, then the rest with player one as upline
+    let registrations = [
+        (&player_one_holder_keypair, &player_one_account, None),
+        (
+            &player_two_holder_keypair,
+            &player_two_account,
+            Some(&player_one_account),
+        ),
+        (
+            &player_three_holder_keypair,
+            &player_three_account,
+            Some(&player_one_account),
+        ),
+        (
+            &player_four_holder_keypair,
+            &player_four_account,
+            Some(&player_one_account),
+        ),
+    ];
+    for (holder_keypair, player_account, upline_account) in registrations {
+        let transaction = build_register_player_transaction(
+            &payer,
+            holder_keypair,
+            player_account,
+            &program_account,
+            upline_account,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+
+    for (player_account, expected_downline_count) in [
+        (player_one_account, 3),
+        (player_two_account, 0),
+        (player_three_account, 0),
+        (player_four_account, 0),
+    ] {
+        let account = banks_client
+            .get_account(player_account)
+            .await
+            .unwrap()
+            .unwrap();
+        let player_state = Player::unpack(&account.data).unwrap();
+        assert_eq!(player_state.downline_count, expected_downline_count);
+    }
+}
+
+#[tokio::test]
+async fn register_total_players() {
+    let player_three_holder_keypair = Keypair::new();
+    let TestGame {
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized_with_extra_holders(
+        &[&player_three_holder_keypair],
+        InitParams::default(),
+    )
+    .await;
+    let (player_three_account, _) = player_pda(&program_id, &player_three_holder_keypair.pubkey());
+
+    for (holder_keypair, player_account) in [
+        (&player_one_holder_keypair, &player_one_account),
+        (&player_two_holder_keypair, &player_two_account),
+        (&player_three_holder_keypair, &player_three_account),
+    ] {
+        let transaction = build_register_player_transaction(
+            &payer,
+            holder_keypair,
+            player_account,
+            &program_account,
+            None,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+
+    // A failed register should not be counted
+    let recent_blockhash = get_new_blockhash(&mut banks_client, &recent_blockhash).await;
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    let account = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let program_state = GameInfo::unpack(&account.data).unwrap();
+    assert_eq!(program_state.total_players, 3);
+}
+
+#[tokio::test]
+async fn close_account() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+
+    // Register player one and player two
+    for holder_keypair in [&player_one_holder_keypair, &player_two_holder_keypair] {
+        let (player_account, _) = player_pda(&program_id, &holder_keypair.pubkey());
+        let transaction = build_register_player_transaction(
+            &payer,
+            holder_keypair,
+            &player_account,
+            &program_account,
+            None,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_two_account,
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    let destination = Pubkey::new_unique();
+
+    // Test close player account with unclaimed reward
+    let transaction = build_close_account_transaction(
+        &player_two_holder_keypair,
+        &player_two_account,
+        &destination,
+        &program_account,
+        program_id,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0xb")); // GameError::RewardNotClaimed
+
+    // Test close other player account
+    let transaction = build_close_account_transaction(
+        &player_two_holder_keypair,
+        &player_one_account,
+        &destination,
+        &program_account,
+        program_id,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("missing required signature"));
+
+    // Test close player one account
+    let rent_lamports = banks_client.get_balance(player_one_account).await.unwrap();
+    let transaction = build_close_account_transaction(
+        &player_one_holder_keypair,
+        &player_one_account,
+        &destination,
+        &program_account,
+        program_id,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(
+        banks_client.get_balance(destination).await.unwrap(),
+        rent_lamports
+    );
+    assert!(banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .is_none());
+    let account = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(GameInfo::unpack(&account.data).unwrap().total_players, 1);
+}
+
+#[tokio::test]
+async fn close_account_reassign_downlines() {
+    let player_three_holder_keypair = Keypair::new();
+    let TestGame {
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized_with_extra_holders(
+        &[&player_three_holder_keypair],
+        InitParams::default(),
+    )
+    .await;
+    let (player_three_account, _) = player_pda(&program_id, &player_three_holder_keypair.pubkey());
+
+    // Chain of player one <- player two <- player three
+    for (holder_keypair, player_account, upline_account) in [
+        (&player_one_holder_keypair, &player_one_account, None),
+        (
+            &player_two_holder_keypair,
+            &player_two_account,
+            Some(&player_one_account),
+        ),
+        (
+            &player_three_holder_keypair,
+            &player_three_account,
+            Some(&player_two_account),
+        ),
+    ] {
+        let transaction = build_register_player_transaction(
+            &payer,
+            holder_keypair,
+            player_account,
+            &program_account,
+            upline_account,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+
+    let destination = Pubkey::new_unique();
+    let close_player_two = |upline: &Pubkey, downlines: &[Pubkey]| {
+        let mut transaction = Transaction::new_with_payer(
+            &[instruction::close_account_with_downlines(
+                &program_id,
+                &player_two_holder_keypair.pubkey(),
+                &program_account,
+                &destination,
+                Some(upline),
+                downlines,
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &player_two_holder_keypair], recent_blockhash);
+        transaction
+    };
+
+    // Test reassign to an account which is not the player two upline
+    let result = banks_client
+        .process_transaction(close_player_two(&player_three_account, &[]))
+        .await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x3")); // GameError::InvalidUpline
+
+    // Test close player two without its downline, or with it passed twice
+    for (downlines, expected_error) in [
+        (&[][..], "insufficient account keys"),
+        (
+            &[player_three_account, player_three_account],
+            "invalid program argument",
+        ),
+    ] {
+        let result = banks_client
+            .process_transaction(close_player_two(&player_one_account, downlines))
+            .await;
+        assert!(result.unwrap_err().to_string().contains(expected_error));
+    }
+
+    // Test reassign a player which is not a downline of player two
+    let result = banks_client
+        .process_transaction(close_player_two(&player_one_account, &[player_one_account]))
+        .await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x3")); // GameError::InvalidUpline
+
+    // Test close the middle player two, player three moves up to player one
+    banks_client
+        .process_transaction(close_player_two(
+            &player_one_account,
+            &[player_three_account],
+        ))
+        .await
+        .unwrap();
+    assert!(banks_client
+        .get_account(player_two_account)
+        .await
+        .unwrap()
+        .is_none());
+
+    let account = banks_client
+        .get_account(player_three_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_three_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_three_state.upline, COption::Some(player_one_account));
+    assert_eq!(player_three_state.depth, 1);
+
+    // Player one lost player two and gained player three
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_one_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_one_state.downline_count, 1);
+
+    // Three players registered, one closed
+    let account = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(GameInfo::unpack(&account.data).unwrap().total_players, 2);
+}
+
+#[tokio::test]
+async fn close_account_leaf_with_upline() {
+    let TestGame {
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+
+    // Player two registers under player one and has no downline
+    for (holder_keypair, player_account, upline_account) in [
+        (&player_one_holder_keypair, &player_one_account, None),
+        (
+            &player_two_holder_keypair,
+            &player_two_account,
+            Some(&player_one_account),
+        ),
+    ] {
+        let transaction = build_register_player_transaction(
+            &payer,
+            holder_keypair,
+            player_account,
+            &program_account,
+            upline_account,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+
+    let destination = Pubkey::new_unique();
+    let close = |holder_keypair: &Keypair, upline: Option<&Pubkey>| {
+        let mut transaction = Transaction::new_with_payer(
+            &[instruction::close_account_with_downlines(
+                &program_id,
+                &holder_keypair.pubkey(),
+                &program_account,
+                &destination,
+                upline,
+                &[],
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, holder_keypair], recent_blockhash);
+        transaction
+    };
+
+    // Test close player two without its upline
+    let result = banks_client
+        .process_transaction(close(&player_two_holder_keypair, None))
+        .await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("insufficient account keys"));
+
+    // Test close player two with its upline, player one loses its only downline
+    banks_client
+        .process_transaction(close(&player_two_holder_keypair, Some(&player_one_account)))
+        .await
+        .unwrap();
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(Player::unpack(&account.data).unwrap().downline_count, 0);
+
+    // Test close player one afterward, without any downline left to pass
+    banks_client
+        .process_transaction(close(&player_one_holder_keypair, None))
+        .await
+        .unwrap();
+    assert!(banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn export_player() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+    capture_logged_data();
+
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    // Test export an account which is not a player account of the program
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::export_player(
+            &program_id,
+            &player_one_holder_keypair.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("incorrect program id for instruction"));
+
+    // Test export player one, the logged bytes decode back into the player account
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::export_player(&program_id, &player_one_account)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_state.reward_to_claim, 100);
+    let logged_data = LOGGED_DATA.lock().unwrap();
+    let exported = logged_data
+        .iter()
+        .find(|fields| fields.len() == 2 && fields[0] == player_one_account.as_ref())
+        .unwrap();
+    assert_eq!(Player::unpack(&exported[1]).unwrap(), player_state);
+}
+
+#[tokio::test]
+async fn game_events() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        player_one_token_account_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+
+    // Register player one, and player two with player one as upline
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_two_holder_keypair,
+        &player_two_account,
+        &program_account,
+        Some(&player_one_account),
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Add reward to player one, then claim it
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+    let (pda, _nonce) = Pubkey::find_program_address(&["game_seed".as_bytes()], &program_id);
+    let transaction = build_claim_reward_transaction(
+        &player_one_holder_keypair,
+        &program_account,
+        &player_one_account,
+        &token_account_keypair,
+        pda,
+        &player_one_token_account_keypair,
+        program_id,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Add reward does not emit an event, it still takes nonce 4
+    let expected_events = [
+        GameEvent::Initialized {
+            nonce: 1,
+            admin: admin_account_keypair.pubkey(),
+        },
+        GameEvent::PlayerRegistered {
+            nonce: 2,
+            player: player_one_account,
+            upline: None,
+        },
+        GameEvent::PlayerRegistered {
+            nonce: 3,
+            player: player_two_account,
+            upline: Some(player_one_account),
+        },
+        GameEvent::RewardClaimed {
+            nonce: 5,
+            player: player_one_account,
+            amount: 100,
+        },
+    ];
+    let logged_data = LOGGED_DATA.lock().unwrap();
+    for (discriminator, event) in [0, 1, 1, 2].into_iter().zip(expected_events.clone()) {
+        let data = event.try_to_vec().unwrap();
+        assert_eq!(data[0], discriminator);
+        assert!(logged_data.contains(&vec![data]));
+    }
+
+    // Other tests log events concurrently, keep the events of this test in the order they were logged
+    let logged_events: Vec<GameEvent> = logged_data
+        .iter()
+        .filter_map(|fields| GameEvent::try_from_slice(&fields[0]).ok())
+        .filter(|event| expected_events.contains(event))
+        .collect();
+    assert_eq!(logged_events, expected_events);
+    let nonces: Vec<u64> = logged_events
+        .iter()
+        .map(|event| match event {
+            GameEvent::Initialized { nonce, .. }
+            | GameEvent::PlayerRegistered { nonce, .. }
+            | GameEvent::RewardClaimed { nonce, .. }
+            | GameEvent::RewardSlashed { nonce, .. } => *nonce,
+        })
+        .collect();
+    assert!(nonces.windows(2).all(|pair| pair[0] < pair[1]));
+    drop(logged_data);
+
+    // The program account keeps the nonce of the last instruction
+    let account = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(GameInfo::unpack(&account.data).unwrap().nonce, 5);
+}
+
+#[tokio::test]
+async fn set_admin() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let new_admin_account_keypair = Keypair::new();
+
+    // Test set admin with non-admin account
+    let transaction = build_set_admin_transaction(
+        &new_admin_account_keypair,
+        &program_account,
+        &new_admin_account_keypair.pubkey(),
+        program_id,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x6")); // GameError::InvalidAdmin
+
+    // Test rotate admin
+    let transaction = build_set_admin_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &new_admin_account_keypair.pubkey(),
+        program_id,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let program_state = GameInfo::unpack(&account.data).unwrap();
+    assert_eq!(program_state.admins[0], new_admin_account_keypair.pubkey());
+
+    // Test add reward with the old admin
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client
+        .process_transaction(add_reward_transaction)
+        .await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x6")); // GameError::InvalidAdmin
+
+    // Test add reward with the new admin
+    let add_reward_transaction = build_add_reward_transaction(
+        &new_admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn multiple_admins() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_two_holder_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+    let second_admin_keypair = &player_one_holder_keypair;
+    let build_transaction = |instruction: Instruction, signer: &Keypair| {
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer, signer], recent_blockhash);
+        transaction
+    };
+
+    // Only an admin can add an admin
+    let transaction = build_transaction(
+        instruction::add_admin(
+            &program_id,
+            &second_admin_keypair.pubkey(),
+            &program_account,
+            &second_admin_keypair.pubkey(),
+        ),
+        second_admin_keypair,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x6")); // GameError::InvalidAdmin
+
+    let transaction = build_transaction(
+        instruction::add_admin(
+            &program_id,
+            &admin_account_keypair.pubkey(),
+            &program_account,
+            &second_admin_keypair.pubkey(),
+        ),
+        &admin_account_keypair,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // The second admin can run the admin instructions
+    let transaction = build_transaction(
+        instruction::set_paused(
+            &program_id,
+            &second_admin_keypair.pubkey(),
+            &program_account,
+            true,
+        ),
+        second_admin_keypair,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let account = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let program_state = GameInfo::unpack(&account.data).unwrap();
+    assert!(program_state.paused);
+    assert_eq!(
+        program_state.admins,
+        [
+            admin_account_keypair.pubkey(),
+            second_admin_keypair.pubkey(),
+            Pubkey::default(),
+        ]
+    );
+
+    // An admin cannot be added twice, nor more than the admin slots
+    let transaction = build_transaction(
+        instruction::add_admin(
+            &program_id,
+            &second_admin_keypair.pubkey(),
+            &program_account,
+            &admin_account_keypair.pubkey(),
+        ),
+        second_admin_keypair,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x17")); // GameError::AdminAlreadyAdded
+    let transaction = build_transaction(
+        instruction::add_admin(
+            &program_id,
+            &admin_account_keypair.pubkey(),
+            &program_account,
+            &player_two_holder_keypair.pubkey(),
+        ),
+        &admin_account_keypair,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let transaction = build_transaction(
+        instruction::add_admin(
+            &program_id,
+            &admin_account_keypair.pubkey(),
+            &program_account,
+            &Pubkey::new_unique(),
+        ),
+        &admin_account_keypair,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x18")); // GameError::TooManyAdmins
+
+    // A removed admin can no longer run the admin instructions
+    for removed_admin_keypair in [second_admin_keypair, &player_two_holder_keypair] {
+        let transaction = build_transaction(
+            instruction::remove_admin(
+                &program_id,
+                &admin_account_keypair.pubkey(),
+                &program_account,
+                &removed_admin_keypair.pubkey(),
+            ),
+            &admin_account_keypair,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+    let transaction = build_transaction(
+        instruction::set_paused(
+            &program_id,
+            &second_admin_keypair.pubkey(),
+            &program_account,
+            false,
+        ),
+        second_admin_keypair,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x6")); // GameError::InvalidAdmin
+
+    // The last admin stays
+    let transaction = build_transaction(
+        instruction::remove_admin(
+            &program_id,
+            &admin_account_keypair.pubkey(),
+            &program_account,
+            &admin_account_keypair.pubkey(),
+        ),
+        &admin_account_keypair,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x19")); // GameError::LastAdmin
+
+    let account = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let program_state = GameInfo::unpack(&account.data).unwrap();
+    assert!(program_state.paused);
+    assert_eq!(
+        program_state.admins,
+        [
+            admin_account_keypair.pubkey(),
+            Pubkey::default(),
+            Pubkey::default(),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn set_paused() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        player_one_token_account_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    let build_set_paused_transaction = |admin_account_keypair: &Keypair, paused: bool| {
+        let mut transaction = Transaction::new_with_payer(
+            &[instruction::set_paused(
+                &program_id,
+                &admin_account_keypair.pubkey(),
+                &program_account,
+                paused,
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, admin_account_keypair], recent_blockhash);
+        transaction
+    };
+
+    // Test pause with non-admin account
+    let transaction = build_set_paused_transaction(&player_one_holder_keypair, true);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x6")); // GameError::InvalidAdmin
+
+    // Test register and claim while paused
+    let transaction = build_set_paused_transaction(&admin_account_keypair, true);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let account = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(GameInfo::unpack(&account.data).unwrap().paused);
+
+    let (pda, _nonce) = vault_pda(&program_id);
+    let register_transaction = |recent_blockhash| {
+        build_register_player_transaction(
+            &payer,
+            &player_two_holder_keypair,
+            &player_two_account,
+            &program_account,
+            None,
+            program_id,
+            recent_blockhash,
+        )
+    };
+    let claim_transaction = |recent_blockhash| {
+        build_claim_reward_transaction(
+            &player_one_holder_keypair,
+            &program_account,
+            &player_one_account,
+            &token_account_keypair,
+            pda,
+            &player_one_token_account_keypair,
+            program_id,
+            &payer,
+            recent_blockhash,
+        )
+    };
+    let result = banks_client
+        .process_transaction(register_transaction(recent_blockhash))
+        .await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x10")); // GameError::GamePaused
+    let result = banks_client
+        .process_transaction(claim_transaction(recent_blockhash))
+        .await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x10")); // GameError::GamePaused
+
+    // Test register and claim once resumed, with a new blockhash so that the failed transactions are not replayed
+    let transaction = build_set_paused_transaction(&admin_account_keypair, false);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let recent_blockhash = get_new_blockhash(&mut banks_client, &recent_blockhash).await;
+    banks_client
+        .process_transaction(register_transaction(recent_blockhash))
+        .await
+        .unwrap();
+    banks_client
+        .process_transaction(claim_transaction(recent_blockhash))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn sweep_fees() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+
+    // Accumulate fees on the program account
+    let fees = LAMPORTS_PER_SOL / 10;
+    let mut transaction = Transaction::new_with_payer(
+        &[system_instruction::transfer(
+            &payer.pubkey(),
+            &program_account,
+            fees,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let destination = Pubkey::new_unique();
+    let build_sweep_fees_transaction = |admin_account_keypair: &Keypair| {
+        let mut transaction = Transaction::new_with_payer(
+            &[instruction::sweep_fees(
+                &program_id,
+                &admin_account_keypair.pubkey(),
+                &program_account,
+                &destination,
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, admin_account_keypair], recent_blockhash);
+        transaction
+    };
+
+    // Test sweep fees with non-admin account
+    let transaction = build_sweep_fees_transaction(&player_one_holder_keypair);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x6")); // GameError::InvalidAdmin
+
+    // Test sweep fees with the admin
+    let transaction = build_sweep_fees_transaction(&admin_account_keypair);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let rent_exempt_minimum = Rent::default().minimum_balance(GameInfo::LEN);
+    assert_eq!(banks_client.get_balance(destination).await.unwrap(), fees);
+    let account = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.lamports, rent_exempt_minimum);
+    GameInfo::unpack(&account.data).unwrap();
+}
+
+#[tokio::test]
+async fn admin_claim_batch() {
+    let player_three_holder_keypair = Keypair::new();
+    let player_three_token_account_keypair = Keypair::new();
+    let TestGame {
+        mint_account_keypair,
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        player_one_token_account_keypair,
+        player_two_token_account_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+    } = setup_initialized_with_extra_holders(
+        &[&player_three_holder_keypair],
+        InitParams::default(),
+    )
+    .await;
+    let (player_three_account, _) = player_pda(&program_id, &player_three_holder_keypair.pubkey());
+    banks_client
+        .process_transaction(build_create_and_init_player_token_account(
+            &payer,
+            &player_three_token_account_keypair,
+            &player_three_holder_keypair,
+            &mint_account_keypair,
+            recent_blockhash,
+        ))
+        .await
+        .unwrap();
+
+    let players = [
+        (
+            &player_one_holder_keypair,
+            player_one_account,
+            player_one_token_account_keypair.pubkey(),
+            100,
+        ),
+        (
+            &player_two_holder_keypair,
+            player_two_account,
+            player_two_token_account_keypair.pubkey(),
+            200,
+        ),
+        (
+            &player_three_holder_keypair,
+            player_three_account,
+            player_three_token_account_keypair.pubkey(),
+            300,
+        ),
+    ];
+    for (holder_keypair, player_account, _, reward) in players.iter() {
+        let transaction = build_register_player_transaction(
+            &payer,
+            holder_keypair,
+            player_account,
+            &program_account,
+            None,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+        let add_reward_transaction = build_add_reward_transaction(
+            &admin_account_keypair,
+            &program_account,
+            player_account,
+            &[],
+            program_id,
+            *reward,
+            &payer,
+            recent_blockhash,
+        );
+        banks_client
+            .process_transaction(add_reward_transaction)
+            .await
+            .unwrap();
+    }
+
+    let build_admin_claim_batch_transaction =
+        |admin_account_keypair: &Keypair, pairs: &[(Pubkey, Pubkey)]| {
+            let mut transaction = Transaction::new_with_payer(
+                &[instruction::admin_claim_batch(
+                    &program_id,
+                    &admin_account_keypair.pubkey(),
+                    &program_account,
+                    &token_account_keypair.pubkey(),
+                    pairs,
+                )],
+                Some(&payer.pubkey()),
+            );
+            transaction.sign(&[&payer, admin_account_keypair], recent_blockhash);
+            transaction
+        };
+    let pairs: Vec<(Pubkey, Pubkey)> = players
+        .iter()
+        .map(|(_, player_account, destination, _)| (*player_account, *destination))
+        .collect();
+
+    // Test admin claim batch with non-admin account
+    let transaction = build_admin_claim_batch_transaction(&player_one_holder_keypair, &pairs);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x6")); // GameError::InvalidAdmin
+
+    // Test admin claim batch into the token account of another holder
+    let transaction = build_admin_claim_batch_transaction(
+        &admin_account_keypair,
+        &[
+            pairs[0],
+            (
+                player_two_account,
+                player_three_token_account_keypair.pubkey(),
+            ),
+        ],
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x7")); // GameError::InvalidTokenAccount
+
+    // Test admin claim batch for the three players at once
+    let transaction = build_admin_claim_batch_transaction(&admin_account_keypair, &pairs);
+    banks_client.process_transaction(transaction).await.unwrap();
+    for (_, player_account, destination, reward) in players.iter() {
+        let account = banks_client
+            .get_account(*player_account)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(Player::unpack(&account.data).unwrap().reward_to_claim, 0);
+        let account = banks_client
+            .get_account(*destination)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            spl_token::state::Account::unpack(&account.data)
+                .unwrap()
+                .amount,
+            *reward
+        );
+    }
+    let account = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(GameInfo::unpack(&account.data).unwrap().nonce, 10);
+}
+
+#[tokio::test]
+async fn admin_claim_batch_vesting() {
+    let TestGameContext {
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_one_token_account_keypair,
+        program_id,
+        mut context,
+        ..
+    } = setup_context_initialized(InitParams::default()).await;
+    let mut banks_client = context.banks_client.clone();
+    // Copy the payer, the context is borrowed mutably to warp the slot
+    let payer = &Keypair::from_bytes(&context.payer.to_bytes()).unwrap();
+    let recent_blockhash = context.last_blockhash;
+    let transaction = build_register_player_transaction(
+        payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // The reward unlocks over four days
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::set_vest_duration(
+            &program_id,
+            &admin_account_keypair.pubkey(),
+            &program_account,
+            4 * COOLDOWN_SECS as u64,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[payer, &admin_account_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        1_000,
+        payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let vesting = Player::unpack(&account.data).unwrap().vesting;
+
+    // Half of the reward is paid after two days and the locked half is kept, then the rest after four
+    for (slot, elapsed_days, claimed_amount, reward_to_claim) in
+        [(10, 2, 500, 500), (20, 4, 1_000, 0)]
+    {
+        context.warp_to_slot(slot).unwrap();
+        let mut clock = banks_client.get_sysvar::<Clock>().await.unwrap();
+        clock.unix_timestamp = vesting.start + elapsed_days * COOLDOWN_SECS;
+        context.set_sysvar(&clock);
+        let recent_blockhash = get_new_blockhash(&mut banks_client, &recent_blockhash).await;
+
+        let mut transaction = Transaction::new_with_payer(
+            &[instruction::admin_claim_batch(
+                &program_id,
+                &admin_account_keypair.pubkey(),
+                &program_account,
+                &token_account_keypair.pubkey(),
+                &[(
+                    player_one_account,
+                    player_one_token_account_keypair.pubkey(),
+                )],
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[payer, &admin_account_keypair], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+
+        let account = banks_client
+            .get_account(player_one_account)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            Player::unpack(&account.data).unwrap().reward_to_claim,
+            reward_to_claim
+        );
+        let account = banks_client
+            .get_account(player_one_token_account_keypair.pubkey())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            spl_token::state::Account::unpack(&account.data)
+                .unwrap()
+                .amount,
+            claimed_amount
+        );
+    }
+}
+
+#[tokio::test]
+async fn tier_thresholds() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+    for (holder_keypair, player_account, upline_account) in [
+        (&player_one_holder_keypair, &player_one_account, None),
+        (
+            &player_two_holder_keypair,
+            &player_two_account,
+            Some(&player_one_account),
+        ),
+    ] {
+        let transaction = build_register_player_transaction(
+            &payer,
+            holder_keypair,
+            player_account,
+            &program_account,
+            upline_account,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+
+    let build_set_tier_thresholds_transaction =
+        |admin_account_keypair: &Keypair, tier_thresholds: [u64; 3]| {
+            let mut transaction = Transaction::new_with_payer(
+                &[instruction::set_tier_thresholds(
+                    &program_id,
+                    &admin_account_keypair.pubkey(),
+                    &program_account,
+                    tier_thresholds,
+                )],
+                Some(&payer.pubkey()),
+            );
+            transaction.sign(&[&payer, admin_account_keypair], recent_blockhash);
+            transaction
+        };
+
+    // Test set tier thresholds with non-admin account
+    let transaction =
+        build_set_tier_thresholds_transaction(&player_one_holder_keypair, [100, 250, 500]);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x6")); // GameError::InvalidAdmin
+
+    // Test set tier thresholds not increasing, or enabled after a disabled tier
+    for tier_thresholds in [[100, 100, 500], [500, 250, 0], [100, 0, 500]] {
+        let transaction =
+            build_set_tier_thresholds_transaction(&admin_account_keypair, tier_thresholds);
+        let result = banks_client.process_transaction(transaction).await;
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("custom program error: 0x1f")); // GameError::InvalidTierThresholds
+    }
+
+    let transaction =
+        build_set_tier_thresholds_transaction(&admin_account_keypair, [100, 250, 500]);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let account = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        GameInfo::unpack(&account.data).unwrap().tier_thresholds,
+        [100, 250, 500]
+    );
+
+    // Player one is credited directly, then as the upline of player two which takes 25%
+    for (player_account, upline_accounts, amount, player_one_earned, player_one_tier) in [
+        (&player_one_account, vec![], 60, 60, 0),
+        (&player_one_account, vec![], 61, 121, 1),
+        (&player_two_account, vec![&player_one_account], 800, 321, 2),
+        (&player_one_account, vec![], 300, 621, 3),
+    ] {
+        let add_reward_transaction = build_add_reward_transaction(
+            &admin_account_keypair,
+            &program_account,
+            player_account,
+            &upline_accounts,
+            program_id,
+            amount,
+            &payer,
+            recent_blockhash,
+        );
+        banks_client
+            .process_transaction(add_reward_transaction)
+            .await
+            .unwrap();
+        let account = banks_client
+            .get_account(player_one_account)
+            .await
+            .unwrap()
+            .unwrap();
+        let player_one_state = Player::unpack(&account.data).unwrap();
+        assert_eq!(player_one_state.total_earned, player_one_earned);
+        assert_eq!(player_one_state.tier, player_one_tier);
+    }
+
+    // Player two got the remaining 600, past gold
+    let account = banks_client
+        .get_account(player_two_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(Player::unpack(&account.data).unwrap().tier, 3);
+}
+
+#[tokio::test]
+async fn set_referral_bonus() {
+    let player_three_holder_keypair = Keypair::new();
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized_with_extra_holders(
+        &[&player_three_holder_keypair],
+        InitParams {
+            referral_bonus: 10,
+            ..InitParams::default()
+        },
+    )
+    .await;
+    let (player_three_account, _) = player_pda(&program_id, &player_three_holder_keypair.pubkey());
+
+    // Player two registers under player one with the initial bonus
+    for (holder_keypair, player_account, upline_account) in [
+        (&player_one_holder_keypair, &player_one_account, None),
+        (
+            &player_two_holder_keypair,
+            &player_two_account,
+            Some(&player_one_account),
+        ),
+    ] {
+        let transaction = build_register_player_transaction(
+            &payer,
+            holder_keypair,
+            player_account,
+            &program_account,
+            upline_account,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(Player::unpack(&account.data).unwrap().reward_to_claim, 10);
+
+    let build_set_referral_bonus_transaction =
+        |admin_account_keypair: &Keypair, referral_bonus: u64| {
+            let mut transaction = Transaction::new_with_payer(
+                &[instruction::set_referral_bonus(
+                    &program_id,
+                    &admin_account_keypair.pubkey(),
+                    &program_account,
+                    referral_bonus,
+                )],
+                Some(&payer.pubkey()),
+            );
+            transaction.sign(&[&payer, admin_account_keypair], recent_blockhash);
+            transaction
+        };
+
+    // Test set referral bonus with non-admin account
+    let transaction = build_set_referral_bonus_transaction(&player_one_holder_keypair, 50);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x6")); // GameError::InvalidAdmin
+
+    let transaction = build_set_referral_bonus_transaction(&admin_account_keypair, 50);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let account = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(GameInfo::unpack(&account.data).unwrap().referral_bonus, 50);
+
+    // Player three registers under player one with the new bonus, the first bonus is kept as it was
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_three_holder_keypair,
+        &player_three_account,
+        &program_account,
+        Some(&player_one_account),
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_one_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_one_state.reward_to_claim, 60);
+    assert_eq!(player_one_state.total_earned, 60);
+}
+
+#[tokio::test]
+async fn set_reward_multiplier() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams {
+        referral_bonus: 10,
+        ..InitParams::default()
+    })
+    .await;
+    let account = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        GameInfo::unpack(&account.data)
+            .unwrap()
+            .reward_multiplier_bps,
+        10_000
+    );
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let build_set_reward_multiplier_transaction =
+        |admin_account_keypair: &Keypair, reward_multiplier_bps: u16| {
+            let mut transaction = Transaction::new_with_payer(
+                &[instruction::set_reward_multiplier(
+                    &program_id,
+                    &admin_account_keypair.pubkey(),
+                    &program_account,
+                    reward_multiplier_bps,
+                )],
+                Some(&payer.pubkey()),
+            );
+            transaction.sign(&[&payer, admin_account_keypair], recent_blockhash);
+            transaction
+        };
+
+    // Test set reward multiplier with non-admin account
+    let transaction = build_set_reward_multiplier_transaction(&player_one_holder_keypair, 20_000);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x6")); // GameError::InvalidAdmin
+
+    // Test set reward multiplier to 0
+    let transaction = build_set_reward_multiplier_transaction(&admin_account_keypair, 0);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x25")); // GameError::InvalidRewardMultiplier
+
+    // A 2x boost doubles the referral bonus, then the reward and its upline share
+    let transaction = build_set_reward_multiplier_transaction(&admin_account_keypair, 20_000);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_two_holder_keypair,
+        &player_two_account,
+        &program_account,
+        Some(&player_one_account),
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_two_account,
+        &[&player_one_account],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+    for (player_account, reward_to_claim) in
+        [(player_one_account, 20 + 50), (player_two_account, 150)]
+    {
+        let account = banks_client
+            .get_account(player_account)
+            .await
+            .unwrap()
+            .unwrap();
+        let player_state = Player::unpack(&account.data).unwrap();
+        assert_eq!(player_state.reward_to_claim, reward_to_claim);
+        assert_eq!(player_state.total_earned, reward_to_claim);
+    }
+}
+
+#[tokio::test]
+async fn readonly_account_rejected() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+    for (holder_keypair, player_account, upline_account) in [
+        (&player_one_holder_keypair, &player_one_account, None),
+        (
+            &player_two_holder_keypair,
+            &player_two_account,
+            Some(&player_one_account),
+        ),
+    ] {
+        let transaction = build_register_player_transaction(
+            &payer,
+            holder_keypair,
+            player_account,
+            &program_account,
+            upline_account,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+
+    // Test add reward with the program account, the player, then the credited upline passed readonly
+    for index in [1, 2, 3] {
+        let mut add_reward_instruction = instruction::add_reward(
+            &program_id,
+            &admin_account_keypair.pubkey(),
+            &program_account,
+            &player_two_account,
+            &[player_one_account],
+            100,
+            false,
+        );
+        add_reward_instruction.accounts[index].is_writable = false;
+        let mut transaction =
+            Transaction::new_with_payer(&[add_reward_instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer, &admin_account_keypair], recent_blockhash);
+        let result = banks_client.process_transaction(transaction).await;
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("custom program error: 0x23")); // GameError::AccountNotWritable
+    }
+
+    // Test transfer reward to a readonly destination player
+    let mut transfer_reward_instruction = instruction::transfer_reward(
+        &program_id,
+        &player_two_holder_keypair.pubkey(),
+        &program_account,
+        &player_one_account,
+        10,
+    );
+    transfer_reward_instruction.accounts[2].is_writable = false;
+    let mut transaction =
+        Transaction::new_with_payer(&[transfer_reward_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &player_two_holder_keypair], recent_blockhash);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x23")); // GameError::AccountNotWritable
+
+    // Nothing was credited
+    for player_account in [player_one_account, player_two_account] {
+        let account = banks_client
+            .get_account(player_account)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(Player::unpack(&account.data).unwrap().total_earned, 0);
+    }
+}
+
+#[tokio::test]
+async fn sweep_dust() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        player_one_token_account_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+    for (holder_keypair, player_account, upline_account) in [
+        (&player_one_holder_keypair, &player_one_account, None),
+        (
+            &player_two_holder_keypair,
+            &player_two_account,
+            Some(&player_one_account),
+        ),
+    ] {
+        let transaction = build_register_player_transaction(
+            &payer,
+            holder_keypair,
+            player_account,
+            &program_account,
+            upline_account,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+
+    // With a single upline taking 25%, each of these amounts leaves 1 rounded off
+    for amount in [7, 13, 999] {
+        let add_reward_transaction = build_add_reward_transaction(
+            &admin_account_keypair,
+            &program_account,
+            &player_two_account,
+            &[&player_one_account],
+            program_id,
+            amount,
+            &payer,
+            recent_blockhash,
+        );
+        banks_client
+            .process_transaction(add_reward_transaction)
+            .await
+            .unwrap();
+    }
+    let account = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(GameInfo::unpack(&account.data).unwrap().dust, 3);
+
+    let token_balance = |account: Account| {
+        spl_token::state::Account::unpack(&account.data)
+            .unwrap()
+            .amount
+    };
+    let vault_balance = token_balance(
+        banks_client
+            .get_account(token_account_keypair.pubkey())
+            .await
+            .unwrap()
+            .unwrap(),
+    );
+
+    let build_sweep_dust_transaction = |admin_account_keypair: &Keypair| {
+        let mut transaction = Transaction::new_with_payer(
+            &[instruction::sweep_dust(
+                &program_id,
+                &admin_account_keypair.pubkey(),
+                &program_account,
+                &token_account_keypair.pubkey(),
+                &player_one_token_account_keypair.pubkey(),
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, admin_account_keypair], recent_blockhash);
+        transaction
+    };
+
+    // Test sweep dust with non-admin account
+    let transaction = build_sweep_dust_transaction(&player_one_holder_keypair);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x6")); // GameError::InvalidAdmin
+
+    // Test sweep dust with the admin, the dust leaves the vault and is reset
+    let transaction = build_sweep_dust_transaction(&admin_account_keypair);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let account = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(GameInfo::unpack(&account.data).unwrap().dust, 0);
+    let account = banks_client
+        .get_account(token_account_keypair.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(token_balance(account), vault_balance - 3);
+    let account = banks_client
+        .get_account(player_one_token_account_keypair.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(token_balance(account), 3);
+}
+
+#[tokio::test]
+async fn set_vault_authority() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_one_token_account_keypair,
+        player_two_token_account_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    let new_authority_keypair = Keypair::new();
+    let build_set_vault_authority_transaction = |admin_account_keypair: &Keypair| {
+        let mut transaction = Transaction::new_with_payer(
+            &[instruction::set_vault_authority(
+                &program_id,
+                &admin_account_keypair.pubkey(),
+                &program_account,
+                &token_account_keypair.pubkey(),
+                &new_authority_keypair.pubkey(),
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, admin_account_keypair], recent_blockhash);
+        transaction
+    };
+
+    // Test set vault authority with non-admin account
+    let transaction = build_set_vault_authority_transaction(&player_one_holder_keypair);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x6")); // GameError::InvalidAdmin
+
+    // Test set vault authority with the admin
+    let transaction = build_set_vault_authority_transaction(&admin_account_keypair);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let account = banks_client
+        .get_account(token_account_keypair.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        spl_token::state::Account::unpack(&account.data)
+            .unwrap()
+            .owner,
+        new_authority_keypair.pubkey()
+    );
+
+    // The PDA no longer owns the token account, so claims cannot be paid
+    let (pda, _nonce) = vault_pda(&program_id);
+    let transaction = build_claim_reward_transaction(
+        &player_one_holder_keypair,
+        &program_account,
+        &player_one_account,
+        &token_account_keypair,
+        pda,
+        &player_one_token_account_keypair,
+        program_id,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x4")); // spl_token::error::TokenError::OwnerMismatch
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(Player::unpack(&account.data).unwrap().reward_to_claim, 100);
+
+    // The new authority moves the tokens directly
+    let mut transaction = Transaction::new_with_payer(
+        &[spl_token::instruction::transfer(
+            &spl_token::id(),
+            &token_account_keypair.pubkey(),
+            &player_two_token_account_keypair.pubkey(),
+            &new_authority_keypair.pubkey(),
+            &[],
+            50,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &new_authority_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let account = banks_client
+        .get_account(player_two_token_account_keypair.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        spl_token::state::Account::unpack(&account.data)
+            .unwrap()
+            .amount,
+        50
+    );
+}
+
+#[tokio::test]
+async fn register_idempotent() {
+    let TestGame {
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams {
+        referral_bonus: 10,
+        ..InitParams::default()
+    })
+    .await;
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let build_register_transaction = |idempotent: bool| {
+        let mut transaction = Transaction::new_with_payer(
+            &[instruction::register(
+                &program_id,
+                &player_two_holder_keypair.pubkey(),
+                &program_account,
+                Some(&player_one_account),
+                idempotent,
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &player_two_holder_keypair], recent_blockhash);
+        transaction
+    };
+    banks_client
+        .process_transaction(build_register_transaction(false))
+        .await
+        .unwrap();
+
+    let accounts = [player_one_account, player_two_account, program_account];
+    let mut registered_data = vec![];
+    for account in accounts {
+        let account = banks_client.get_account(account).await.unwrap().unwrap();
+        registered_data.push(account.data);
+    }
+
+    // Test register again without the flag, resending the same transaction would return the cached result
+    let recent_blockhash = get_new_blockhash(&mut banks_client, &recent_blockhash).await;
+    let mut transaction = build_register_transaction(false);
+    transaction.sign(&[&payer, &player_two_holder_keypair], recent_blockhash);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("instruction requires an uninitialized account"));
+
+    // Test register again with the flag, the upline is not credited twice
+    banks_client
+        .process_transaction(build_register_transaction(true))
+        .await
+        .unwrap();
+    for (account, data) in accounts.iter().zip(registered_data) {
+        let account = banks_client.get_account(*account).await.unwrap().unwrap();
+        assert_eq!(account.data, data);
+    }
+}
+
+#[tokio::test]
+async fn register_default_upline() {
+    let TestGame {
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams {
+        referral_bonus: 10,
+        ..InitParams::default()
+    })
+    .await;
+
+    // The default pubkey passed as a placeholder upline is no upline
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        Some(&Pubkey::default()),
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_state.upline, COption::None);
+    assert_eq!(player_state.depth, 0);
+    assert_eq!(player_state.reward_to_claim, 0);
+}
+
+#[tokio::test]
+async fn register_unregistered_upline() {
+    let TestGame {
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+
+    // Player one account is allocated and owned by the program, but not registered yet
+    // Player one holder is a system owned account
+    for upline_account in [player_one_account, player_one_holder_keypair.pubkey()] {
+        let transaction = build_register_player_transaction(
+            &payer,
+            &player_two_holder_keypair,
+            &player_two_account,
+            &program_account,
+            Some(&upline_account),
+            program_id,
+            recent_blockhash,
+        );
+        let result = banks_client.process_transaction(transaction).await;
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("custom program error: 0x8")); // GameError::UplineNotRegistered
+    }
+
+    let account = banks_client
+        .get_account(player_two_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(
+        !Player::unpack_unchecked(&account.data)
+            .unwrap()
+            .is_initialized
+    );
+}
+
+#[tokio::test]
+async fn reward_cap() {
+    let player_three_holder_keypair = Keypair::new();
+    let max_reward_per_player = 100;
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized_with_extra_holders(
+        &[&player_three_holder_keypair],
+        InitParams {
+            referral_bonus: 30,
+            max_reward_per_player,
+            ..InitParams::default()
+        },
+    )
+    .await;
+    let (player_three_account, _) = player_pda(&program_id, &player_three_holder_keypair.pubkey());
+
+    // Player one is credited the referral bonus of player two
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_two_holder_keypair,
+        &player_two_account,
+        &program_account,
+        Some(&player_one_account),
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Up to the cap, then exactly at it
+    for reward_amount in [40, 30] {
+        let add_reward_transaction = build_add_reward_transaction(
+            &admin_account_keypair,
+            &program_account,
+            &player_one_account,
+            &[],
+            program_id,
+            reward_amount,
+            &payer,
+            recent_blockhash,
+        );
+        banks_client
+            .process_transaction(add_reward_transaction)
+            .await
+            .unwrap();
+    }
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_one_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_one_state.reward_to_claim, max_reward_per_player);
+
+    // Over the cap, directly or through the upline share of player two
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        1,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client
+        .process_transaction(add_reward_transaction)
+        .await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x16")); // GameError::RewardCapExceeded
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_two_account,
+        &[&player_one_account],
+        program_id,
+        20,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client
+        .process_transaction(add_reward_transaction)
+        .await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x16")); // GameError::RewardCapExceeded
+
+    // A player can still register under an upline at the cap, the referral bonus is cut down to nothing
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_three_holder_keypair,
+        &player_three_account,
+        &program_account,
+        Some(&player_one_account),
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_one_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_one_state.reward_to_claim, max_reward_per_player);
+    assert_eq!(player_one_state.downline_count, 2);
+
+    // The admin cannot set the reward over the cap either
+    let build_admin_set_reward_transaction = |reward_to_claim: u64| {
+        let mut transaction = Transaction::new_with_payer(
+            &[instruction::admin_set_reward(
+                &program_id,
+                &admin_account_keypair.pubkey(),
+                &program_account,
+                &player_two_account,
+                reward_to_claim,
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &admin_account_keypair], recent_blockhash);
+        transaction
+    };
+    let transaction = build_admin_set_reward_transaction(max_reward_per_player + 1);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x16")); // GameError::RewardCapExceeded
+    let transaction = build_admin_set_reward_transaction(max_reward_per_player);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    for (player_account, reward_to_claim) in [
+        (player_one_account, max_reward_per_player),
+        (player_two_account, max_reward_per_player),
+    ] {
+        let account = banks_client
+            .get_account(player_account)
+            .await
+            .unwrap()
+            .unwrap();
+        let player_state = Player::unpack(&account.data).unwrap();
+        assert_eq!(player_state.reward_to_claim, reward_to_claim);
+    }
+}
+
+#[tokio::test]
+async fn reward_cap_saturate() {
+    let max_reward_per_player = 100;
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams {
+        referral_bonus: 30,
+        max_reward_per_player,
+        ..InitParams::default()
+    })
+    .await;
+    for (holder_keypair, player_account, upline_account) in [
+        (&player_one_holder_keypair, &player_one_account, None),
+        (
+            &player_two_holder_keypair,
+            &player_two_account,
+            Some(&player_one_account),
+        ),
+    ] {
+        let transaction = build_register_player_transaction(
+            &payer,
+            holder_keypair,
+            player_account,
+            &program_account,
+            upline_account,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::admin_set_reward(
+            &program_id,
+            &admin_account_keypair.pubkey(),
+            &program_account,
+            &player_one_account,
+            98,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_account_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let build_add_reward_transaction = |reward_amount: u64, saturate: bool| {
+        let mut transaction = Transaction::new_with_payer(
+            &[instruction::add_reward(
+                &program_id,
+                &admin_account_keypair.pubkey(),
+                &program_account,
+                &player_two_account,
+                &[player_one_account],
+                reward_amount,
+                saturate,
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &admin_account_keypair], recent_blockhash);
+        transaction
+    };
+    let get_state = |banks_client: &mut BanksClient| {
+        let mut banks_client = banks_client.clone();
+        async move {
+            let mut reward_to_claim = vec![];
+            for player_account in [player_one_account, player_two_account] {
+                let account = banks_client
+                    .get_account(player_account)
+                    .await
+                    .unwrap()
+                    .unwrap();
+                reward_to_claim.push(Player::unpack(&account.data).unwrap().reward_to_claim);
+            }
+            let account = banks_client
+                .get_account(program_account)
+                .await
+                .unwrap()
+                .unwrap();
+            (
+                reward_to_claim,
+                GameInfo::unpack(&account.data).unwrap().dust,
+            )
+        }
+    };
+    let (reward_to_claim, dust) = get_state(&mut banks_client).await;
+
+    // Without the flag, the upline share of 5 going over the cap fails the whole reward
+    let result = banks_client
+        .process_transaction(build_add_reward_transaction(20, false))
+        .await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x16")); // GameError::RewardCapExceeded
+    assert_eq!(
+        get_state(&mut banks_client).await,
+        (reward_to_claim.clone(), dust)
+    );
+
+    // With it, the upline is credited up to the cap, the 3 left over are credited to nobody and are not dust
+    banks_client
+        .process_transaction(build_add_reward_transaction(20, true))
+        .await
+        .unwrap();
+    assert_eq!(
+        get_state(&mut banks_client).await,
+        (vec![max_reward_per_player, reward_to_claim[1] + 15], dust)
+    );
+
+    // A player already at the cap is credited nothing
+    banks_client
+        .process_transaction(build_add_reward_transaction(4, true))
+        .await
+        .unwrap();
+    assert_eq!(
+        get_state(&mut banks_client).await,
+        (vec![max_reward_per_player, reward_to_claim[1] + 18], dust)
+    );
+}
+
+#[tokio::test]
+async fn admin_set_reward() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    let build_admin_set_reward_transaction = |admin_account_keypair: &Keypair| {
+        let mut transaction = Transaction::new_with_payer(
+            &[instruction::admin_set_reward(
+                &program_id,
+                &admin_account_keypair.pubkey(),
+                &program_account,
+                &player_one_account,
+                30,
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, admin_account_keypair], recent_blockhash);
+        transaction
+    };
+
+    // Test override with the player holder
+    let transaction = build_admin_set_reward_transaction(&player_one_holder_keypair);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x6")); // GameError::InvalidAdmin
+
+    // Test override with the admin
+    let transaction = build_admin_set_reward_transaction(&admin_account_keypair);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_one_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_one_state.reward_to_claim, 30);
+    assert_eq!(player_one_state.total_earned, 100);
+}
+
+#[tokio::test]
+async fn slash_reward() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    let build_slash_reward_transaction = |admin_account_keypair: &Keypair, amount: u64| {
+        let mut transaction = Transaction::new_with_payer(
+            &[instruction::slash_reward(
+                &program_id,
+                &admin_account_keypair.pubkey(),
+                &program_account,
+                &player_one_account,
+                amount,
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, admin_account_keypair], recent_blockhash);
+        transaction
+    };
+
+    // Test slash with the player holder
+    let transaction = build_slash_reward_transaction(&player_one_holder_keypair, 30);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x6")); // GameError::InvalidAdmin
+
+    // Test slash part of the reward with the admin
+    let transaction = build_slash_reward_transaction(&admin_account_keypair, 30);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_one_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_one_state.reward_to_claim, 70);
+    assert_eq!(player_one_state.total_earned, 100);
+
+    // Test slash more than the remaining reward, it stops at zero
+    let transaction = build_slash_reward_transaction(&admin_account_keypair, 1_000);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_one_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_one_state.reward_to_claim, 0);
+}
+
+#[tokio::test]
+async fn set_upline() {
+    let TestGameContext {
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut context,
+        ..
+    } = setup_context_initialized(InitParams::default()).await;
+    let mut banks_client = context.banks_client.clone();
+    // Copy the payer, the context is borrowed mutably to warp the slot
+    let payer = &Keypair::from_bytes(&context.payer.to_bytes()).unwrap();
+    let recent_blockhash = context.last_blockhash;
+
+    // Register both players without upline, in the same second
+    for (holder_keypair, player_account) in [
+        (&player_one_holder_keypair, &player_one_account),
+        (&player_two_holder_keypair, &player_two_account),
+    ] {
+        let transaction = build_register_player_transaction(
+            payer,
+            holder_keypair,
+            player_account,
+            &program_account,
+            None,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+
+    let build_set_upline_transaction =
+        |holder_keypair: &Keypair, upline: &Pubkey, recent_blockhash: Hash| {
+            let mut transaction = Transaction::new_with_payer(
+                &[instruction::set_upline(
+                    &program_id,
+                    &holder_keypair.pubkey(),
+                    &program_account,
+                    upline,
+                    &[],
+                )],
+                Some(&payer.pubkey()),
+            );
+            transaction.sign(&[payer, holder_keypair], recent_blockhash);
+            transaction
+        };
+
+    // Player two sets player one as upline, registered in the same second
+    let transaction = build_set_upline_transaction(
+        &player_two_holder_keypair,
+        &player_one_account,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client
+        .get_account(player_two_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_two_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_two_state.upline, COption::Some(player_one_account));
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_one_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_one_state.downline_count, 1);
+
+    // The upline can only be set once
+    context.warp_to_slot(10).unwrap();
+    let recent_blockhash = get_new_blockhash(&mut banks_client, &recent_blockhash).await;
+    let transaction = build_set_upline_transaction(
+        &player_two_holder_keypair,
+        &player_one_account,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x12")); // GameError::UplineAlreadySet
+
+    // Player one cannot close the two players cycle through player two
+    let transaction = build_set_upline_transaction(
+        &player_one_holder_keypair,
+        &player_two_account,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x22")); // GameError::CircularReferral
+
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_one_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_one_state.downline_count, 1);
+    assert_eq!(player_one_state.upline, COption::None);
+}
+
+#[tokio::test]
+async fn transfer_reward() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+    for (holder_keypair, player_account) in [
+        (&player_one_holder_keypair, &player_one_account),
+        (&player_two_holder_keypair, &player_two_account),
+    ] {
+        let transaction = build_register_player_transaction(
+            &payer,
+            holder_keypair,
+            player_account,
+            &program_account,
+            None,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    // Test transfer reward signed by another holder
+    let transaction = build_transfer_reward_transaction(
+        &player_two_holder_keypair,
+        &player_one_account,
+        &player_two_account,
+        &program_account,
+        program_id,
+        40,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("missing required signature"));
+
+    // Test player one transfer reward to player two
+    let transaction = build_transfer_reward_transaction(
+        &player_one_holder_keypair,
+        &player_one_account,
+        &player_two_account,
+        &program_account,
+        program_id,
+        40,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_one_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_one_state.reward_to_claim, 60);
+    let account = banks_client
+        .get_account(player_two_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_two_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_two_state.reward_to_claim, 40);
+
+    // Test transfer more reward than player one has left
+    let transaction = build_transfer_reward_transaction(
+        &player_one_holder_keypair,
+        &player_one_account,
+        &player_two_account,
+        &program_account,
+        program_id,
+        61,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x9")); // GameError::InsufficientReward
+
+    // Test transfer nothing
+    let transaction = build_transfer_reward_transaction(
+        &player_one_holder_keypair,
+        &player_one_account,
+        &player_two_account,
+        &program_account,
+        program_id,
+        0,
         &payer,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x21")); // GameError::ZeroAmount
+}
+
+#[tokio::test]
+async fn transfer_reward_decay() {
+    // The unclaimed reward decays by 10% every day
+    let TestGameContext {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        program_id,
+        context,
+        ..
+    } = setup_context_initialized(InitParams {
+        decay_bps_per_day: 1_000,
+        ..InitParams::default()
+    })
+    .await;
+    let mut banks_client = context.banks_client.clone();
+    // Copy the payer, the context is borrowed mutably to set the clock
+    let payer = &Keypair::from_bytes(&context.payer.to_bytes()).unwrap();
+    let recent_blockhash = context.last_blockhash;
+    for (holder_keypair, player_account) in [
+        (&player_one_holder_keypair, &player_one_account),
+        (&player_two_holder_keypair, &player_two_account),
+    ] {
+        let transaction = build_register_player_transaction(
+            payer,
+            holder_keypair,
+            player_account,
+            &program_account,
+            None,
+            program_id,
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
         program_id,
+        100,
+        payer,
         recent_blockhash,
     );
     banks_client
-        .process_transaction(create_player_account_transaction)
+        .process_transaction(add_reward_transaction)
         .await
         .unwrap();
 
-    // Test register player with invalid program id
-    let transaction = build_register_player_transaction(
+    // Player two was never credited, moving the reward there two days later does not escape the decay
+    let mut clock = banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp += 2 * DECAY_PERIOD_SECS;
+    context.set_sysvar(&clock);
+    for (amount, error) in [
+        (100, Some("custom program error: 0x9")), // GameError::InsufficientReward
+        (80, None),
+    ] {
+        let transaction = build_transfer_reward_transaction(
+            &player_one_holder_keypair,
+            &player_one_account,
+            &player_two_account,
+            &program_account,
+            program_id,
+            amount,
+            payer,
+            recent_blockhash,
+        );
+        let result = banks_client.process_transaction(transaction).await;
+        match error {
+            Some(error) => assert!(result.unwrap_err().to_string().contains(error)),
+            None => result.unwrap(),
+        }
+    }
+
+    // The moved reward decays on player two from the transfer
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_one_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_one_state.reward_to_claim, 0);
+    let account = banks_client
+        .get_account(player_two_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_two_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_two_state.reward_to_claim, 80);
+    assert_eq!(player_two_state.last_credit_at, clock.unix_timestamp);
+
+    // Two days later, moving it back settles the decay of player two first
+    clock.unix_timestamp += 2 * DECAY_PERIOD_SECS;
+    context.set_sysvar(&clock);
+    for (amount, error) in [
+        (80, Some("custom program error: 0x9")), // GameError::InsufficientReward
+        (64, None),
+    ] {
+        let transaction = build_transfer_reward_transaction(
+            &player_two_holder_keypair,
+            &player_two_account,
+            &player_one_account,
+            &program_account,
+            program_id,
+            amount,
+            payer,
+            recent_blockhash,
+        );
+        let result = banks_client.process_transaction(transaction).await;
+        match error {
+            Some(error) => assert!(result.unwrap_err().to_string().contains(error)),
+            None => result.unwrap(),
+        }
+    }
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_one_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_one_state.reward_to_claim, 64);
+    assert_eq!(player_one_state.last_credit_at, clock.unix_timestamp);
+}
+#[tokio::test]
+async fn init_invalid_accounts() {
+    let TestGame {
+        mint_account_keypair,
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_token_account_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup().await;
+
+    // The program account is derived from the game info seed only
+    assert_eq!(
+        program_account,
+        Pubkey::find_program_address(&[b"game_info"], &program_id).0
+    );
+
+    // Test init with a program account which is not the game info PDA
+    let init_instruction_transaction = build_init_instruction_transaction(
+        &admin_account_keypair,
+        &Pubkey::new_unique(),
+        &token_account_keypair,
+        &mint_account_keypair.pubkey(),
+        0,
         &payer,
-        &player_one_holder_keypair,
-        &Keypair::new(),
-        &program_account_keypair,
-        None,
         program_id,
         recent_blockhash,
     );
-    let result = banks_client.process_transaction(transaction).await;
-    match result {
-        Ok(()) => {}
-        Err(error) => {
-            assert_eq!(error.to_string().contains("incorrect program id"), true);
-        }
-    };
-    // End
+    let result = banks_client
+        .process_transaction(init_instruction_transaction)
+        .await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x11")); // GameError::InvalidProgramAccount
+                                                  // End
 
-    // Test register player with invalid program account
-    let transaction = build_register_player_transaction(
+    // Test init with invalid token account
+    let invalid_token_account_keypair = Keypair::new();
+    let init_instruction_transaction = build_init_instruction_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &invalid_token_account_keypair,
+        &mint_account_keypair.pubkey(),
+        0,
         &payer,
-        &player_one_holder_keypair,
-        &player_one_account_keypair,
-        &Keypair::new(),
-        None,
         program_id,
         recent_blockhash,
     );
-    let result = banks_client.process_transaction(transaction).await;
+    let result = banks_client
+        .process_transaction(init_instruction_transaction)
+        .await;
     match result {
         Ok(()) => {}
         Err(error) => {
-            assert_eq!(error.to_string().contains("incorrect program id"), true);
+            assert!(error.to_string().contains("Provided owner is not allowed"));
         }
-    };
+    }
     // End
 
-    // Test register player with invalid upline account
-    let transaction = build_register_player_transaction(
+    // Test init with token account owned by another holder
+    let init_instruction_transaction = build_init_instruction_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_token_account_keypair,
+        &mint_account_keypair.pubkey(),
+        0,
         &payer,
-        &player_one_holder_keypair,
-        &player_one_account_keypair,
-        &program_account_keypair,
-        Some(&Keypair::new()),
         program_id,
         recent_blockhash,
     );
+    let result = banks_client
+        .process_transaction(init_instruction_transaction)
+        .await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x7")); // GameError::InvalidTokenAccount
+
+    // Test init with mint not matching the token account
+    let init_instruction_transaction = build_init_instruction_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &token_account_keypair,
+        &player_one_token_account_keypair.pubkey(),
+        0,
+        &payer,
+        program_id,
+        recent_blockhash,
+    );
+    let result = banks_client
+        .process_transaction(init_instruction_transaction)
+        .await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x7")); // GameError::InvalidTokenAccount
+}
+
+#[tokio::test]
+async fn bogus_token_program() {
+    let TestGame {
+        mint_account_keypair,
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_one_token_account_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup().await;
+    let bogus_token_program = Pubkey::new_unique();
+
+    // Test init with a token program which is not the SPL token program
+    let mut init_instruction = instruction::init(
+        &program_id,
+        &admin_account_keypair.pubkey(),
+        &program_account,
+        &token_account_keypair.pubkey(),
+        &mint_account_keypair.pubkey(),
+        0,
+        DEFAULT_MAX_DEPTH,
+        0,
+        0,
+        0,
+        0,
+        DEFAULT_LEVEL_BPS,
+    );
+    init_instruction.accounts[5].pubkey = bogus_token_program;
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &admin_account_keypair], recent_blockhash);
     let result = banks_client.process_transaction(transaction).await;
-    match result {
-        Ok(()) => {}
-        Err(error) => {
-            assert_eq!(error.to_string().contains("incorrect program id"), true);
-        }
-    };
-    // End
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("incorrect program id"));
 
-    // Test register self as upline
+    let init_instruction_transaction = build_init_instruction_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &token_account_keypair,
+        &mint_account_keypair.pubkey(),
+        0,
+        &payer,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(init_instruction_transaction)
+        .await
+        .unwrap();
     let transaction = build_register_player_transaction(
         &payer,
         &player_one_holder_keypair,
-        &player_one_account_keypair,
-        &program_account_keypair,
-        Some(&player_one_account_keypair),
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
         program_id,
+        100,
+        &payer,
         recent_blockhash,
     );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    // Test claim with a token program which is not the SPL token program
+    let mut claim_instruction = instruction::claim(
+        &program_id,
+        &player_one_holder_keypair.pubkey(),
+        &program_account,
+        &token_account_keypair.pubkey(),
+        &player_one_token_account_keypair.pubkey(),
+        None,
+    );
+    claim_instruction.accounts[6].pubkey = bogus_token_program;
+    let mut transaction = Transaction::new_with_payer(&[claim_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &player_one_holder_keypair], recent_blockhash);
     let result = banks_client.process_transaction(transaction).await;
-    match result {
-        Ok(()) => {}
-        Err(error) => {
-            assert_eq!(error.to_string().contains("custom program error"), true);
-            // Why decode custom error not working?
-        }
-    };
-    // End
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("incorrect program id"));
+
+    // Nothing was transferred
+    let account = banks_client
+        .get_account(player_one_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let player_one_state = Player::unpack(&account.data).unwrap();
+    assert_eq!(player_one_state.reward_to_claim, 100);
+}
+
+#[tokio::test]
+async fn claim_reward_token_2022() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        player_one_token_account_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup().await;
+    let mint_account_keypair = Keypair::new();
+    let token_account_keypair = Keypair::new();
+    let player_token_account_keypair = Keypair::new();
+    banks_client
+        .process_transaction(build_create_and_init_token_2022_transaction(
+            &payer,
+            &mint_account_keypair,
+            &token_account_keypair,
+            &admin_account_keypair,
+            &player_token_account_keypair,
+            &player_one_holder_keypair,
+            recent_blockhash,
+        ))
+        .await
+        .unwrap();
+
+    // Init the game for the Token-2022 vault, the token program is stored in the game info
+    let init_instruction = instruction::with_token_program(
+        instruction::init(
+            &program_id,
+            &admin_account_keypair.pubkey(),
+            &program_account,
+            &token_account_keypair.pubkey(),
+            &mint_account_keypair.pubkey(),
+            0,
+            DEFAULT_MAX_DEPTH,
+            0,
+            0,
+            0,
+            0,
+            DEFAULT_LEVEL_BPS,
+        ),
+        &spl_token_2022::id(),
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &admin_account_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let program_account_data = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let game_info = GameInfo::try_from_slice(&program_account_data.data).unwrap();
+    assert_eq!(game_info.token_program, spl_token_2022::id());
 
-    // Test register player
-    // Register player one
     let transaction = build_register_player_transaction(
         &payer,
         &player_one_holder_keypair,
-        &player_one_account_keypair,
-        &program_account_keypair,
+        &player_one_account,
+        &program_account,
         None,
         program_id,
         recent_blockhash,
     );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let add_reward_transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_one_account,
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(add_reward_transaction)
+        .await
+        .unwrap();
+
+    // Test claim through the classic token program
+    let claim_instruction = instruction::claim(
+        &program_id,
+        &player_one_holder_keypair.pubkey(),
+        &program_account,
+        &token_account_keypair.pubkey(),
+        &player_token_account_keypair.pubkey(),
+        None,
+    );
+    let mut transaction = Transaction::new_with_payer(&[claim_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &player_one_holder_keypair], recent_blockhash);
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("incorrect program id"));
+
+    // Test claim to a classic token account
+    let claim_instruction = instruction::with_token_program(
+        instruction::claim(
+            &program_id,
+            &player_one_holder_keypair.pubkey(),
+            &program_account,
+            &token_account_keypair.pubkey(),
+            &player_one_token_account_keypair.pubkey(),
+            None,
+        ),
+        &spl_token_2022::id(),
+    );
+    let mut transaction = Transaction::new_with_payer(&[claim_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &player_one_holder_keypair], recent_blockhash);
     let result = banks_client.process_transaction(transaction).await;
-    let player_one_account = banks_client
-        .get_account(player_one_account_keypair.pubkey())
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("Provided owner is not allowed"));
+
+    // Test claim through Token-2022
+    let claim_instruction = instruction::with_token_program(
+        instruction::claim(
+            &program_id,
+            &player_one_holder_keypair.pubkey(),
+            &program_account,
+            &token_account_keypair.pubkey(),
+            &player_token_account_keypair.pubkey(),
+            None,
+        ),
+        &spl_token_2022::id(),
+    );
+    let mut transaction = Transaction::new_with_payer(&[claim_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &player_one_holder_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let player_token_account = banks_client
+        .get_account(player_token_account_keypair.pubkey())
         .await
+        .unwrap()
         .unwrap();
-    match player_one_account {
-        Some(account) => {
-            let player_one_state = Player::unpack(&account.data).unwrap();
-            assert_eq!(player_one_state.is_initialized, true);
-            assert_eq!(player_one_state.owner, player_one_holder_keypair.pubkey());
-            assert_eq!(player_one_state.reward_to_claim, 0);
-            assert_eq!(player_one_state.upline, COption::None);
-        }
-        _ => {
-            panic!("Player one account not found");
-        }
-    };
+    assert_eq!(player_token_account.owner, spl_token_2022::id());
+    let player_token_account_state =
+        spl_token::state::Account::unpack(&player_token_account.data).unwrap();
+    assert_eq!(player_token_account_state.amount, 100);
+}
+
+#[tokio::test]
+async fn register_game_info_wrong_owner() {
+    let TestGame {
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+
+    // A buffer of the size of a program account, but owned by the token program
+    let fake_program_account_keypair = Keypair::new();
+    let rent = banks_client.get_rent().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[system_instruction::create_account(
+            &payer.pubkey(),
+            &fake_program_account_keypair.pubkey(),
+            rent.minimum_balance(GameInfo::LEN),
+            GameInfo::LEN as u64,
+            &spl_token::id(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &fake_program_account_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
 
-    // Register player two, with player one as upline
     let transaction = build_register_player_transaction(
         &payer,
-        &player_two_holder_keypair,
-        &player_two_account_keypair,
-        &program_account_keypair,
-        Some(&player_one_account_keypair),
+        &player_one_holder_keypair,
+        &player_one_account,
+        &fake_program_account_keypair.pubkey(),
+        None,
         program_id,
         recent_blockhash,
     );
     let result = banks_client.process_transaction(transaction).await;
-
-    let player_two_account = banks_client
-        .get_account(player_two_account_keypair.pubkey())
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("incorrect program id"));
+    // The player was not registered
+    let account = banks_client
+        .get_account(player_one_account)
         .await
+        .unwrap()
         .unwrap();
+    assert!(
+        !Player::unpack_unchecked(&account.data)
+            .unwrap()
+            .is_initialized
+    );
+
+    // The real program account is still accepted
+    let transaction = build_register_player_transaction(
+        &payer,
+        &player_one_holder_keypair,
+        &player_one_account,
+        &program_account,
+        None,
+        program_id,
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+}
 
-    match player_two_account {
+#[tokio::test]
+async fn init_instruction() {
+    let TestGame {
+        mint_account_keypair,
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        mut banks_client,
+        ..
+    } = setup_initialized(InitParams {
+        referral_bonus: 10,
+        ..InitParams::default()
+    })
+    .await;
+
+    // It should successfully execute init instruction
+    let program_account = banks_client.get_account(program_account).await.unwrap();
+    match program_account {
         Some(account) => {
-            let player_two_state = Player::unpack(&account.data).unwrap();
-            assert_eq!(player_two_state.is_initialized, true);
-            assert_eq!(player_two_state.owner, player_two_holder_keypair.pubkey());
-            assert_eq!(player_two_state.reward_to_claim, 0);
+            let program_state = GameInfo::unpack(&account.data).unwrap();
+            assert!(program_state.is_initialized);
+            assert_eq!(program_state.version, CURRENT_VERSION);
+            assert_eq!(&program_state.admins[0], &admin_account_keypair.pubkey());
             assert_eq!(
-                player_two_state.upline,
-                COption::Some(player_one_account_keypair.pubkey())
+                &program_state.spl_token_account,
+                &token_account_keypair.pubkey()
             );
+            assert_eq!(program_state.referral_bonus, 10);
+            assert_eq!(program_state.mint, mint_account_keypair.pubkey());
+            assert_eq!(program_state.total_players, 0);
         }
         _ => {
-            panic!("Player two account not found");
+            panic!("Program account not found");
         }
     };
 }
 
 #[tokio::test]
-async fn init_instruction() {
-    let (
+async fn init_twice() {
+    let TestGame {
         mint_account_keypair,
         admin_account_keypair,
-        program_account_keypair,
+        program_account,
         token_account_keypair,
         player_one_holder_keypair,
-        player_one_account_keypair,
-        player_two_holder_keypair,
-        player_two_account_keypair,
         player_one_token_account_keypair,
-        player_two_token_account_keypair,
         program_id,
         mut banks_client,
         payer,
         recent_blockhash,
-    ) = setup().await;
+        ..
+    } = setup_initialized(InitParams::default()).await;
 
-    // Test init with invalid program_account
-    let invalid_program_account_keypair = Keypair::new();
+    // Test init again with the same admin
+    let recent_blockhash = get_new_blockhash(&mut banks_client, &recent_blockhash).await;
     let init_instruction_transaction = build_init_instruction_transaction(
         &admin_account_keypair,
-        &invalid_program_account_keypair,
+        &program_account,
         &token_account_keypair,
+        &mint_account_keypair.pubkey(),
+        0,
         &payer,
         program_id,
         recent_blockhash,
@@ -621,20 +7079,18 @@ async fn init_instruction() {
     let result = banks_client
         .process_transaction(init_instruction_transaction)
         .await;
-    match result {
-        Ok(()) => {}
-        Err(error) => {
-            assert_eq!(error.to_string().contains("incorrect program id"), true);
-        }
-    }
-    // End
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x0")); // GameError::AlreadyInitialized
 
-    // Test init with invalid token account
-    let invalid_token_account_keypair = Keypair::new();
+    // Test take over the program account with another admin and token account
     let init_instruction_transaction = build_init_instruction_transaction(
-        &admin_account_keypair,
-        &program_account_keypair,
-        &invalid_token_account_keypair,
+        &player_one_holder_keypair,
+        &program_account,
+        &player_one_token_account_keypair,
+        &mint_account_keypair.pubkey(),
+        0,
         &payer,
         program_id,
         recent_blockhash,
@@ -642,55 +7098,428 @@ async fn init_instruction() {
     let result = banks_client
         .process_transaction(init_instruction_transaction)
         .await;
-    match result {
-        Ok(()) => {}
-        Err(error) => {
-            assert_eq!(
-                error.to_string().contains("Provided owner is not allowed"),
-                true
-            );
-        }
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("custom program error: 0x0")); // GameError::AlreadyInitialized
+
+    // The state of the first init is preserved
+    let account = banks_client
+        .get_account(program_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let program_state = GameInfo::unpack(&account.data).unwrap();
+    assert_eq!(program_state.admins[0], admin_account_keypair.pubkey());
+    assert_eq!(
+        program_state.spl_token_account,
+        token_account_keypair.pubkey()
+    );
+}
+
+#[tokio::test]
+async fn missing_signatures() {
+    let TestGame {
+        mint_account_keypair,
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_one_token_account_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup().await;
+    let instructions = every_instruction(
+        &program_id,
+        &admin_account_keypair.pubkey(),
+        &player_one_holder_keypair.pubkey(),
+        &program_account,
+        &mint_account_keypair.pubkey(),
+        &token_account_keypair.pubkey(),
+        &player_one_account,
+        &player_one_token_account_keypair.pubkey(),
+        &player_two_holder_keypair.pubkey(),
+    );
+    // Test every instruction with the admin or holder passed as non-signer, only the payer signs
+    for mut instruction in instructions {
+        instruction.accounts[0].is_signer = false;
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        let result = banks_client.process_transaction(transaction).await;
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("missing required signature"));
     }
-    // End
+}
 
-    // Test init instruction
-    let init_instruction_transaction = build_init_instruction_transaction(
-        &admin_account_keypair,
-        &program_account_keypair,
-        &token_account_keypair,
-        &payer,
+#[tokio::test]
+async fn missing_accounts() {
+    let TestGame {
+        mint_account_keypair,
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        player_one_token_account_keypair,
         program_id,
+        mut banks_client,
+        payer,
         recent_blockhash,
+        ..
+    } = setup().await;
+    let mut instructions = every_instruction(
+        &program_id,
+        &admin_account_keypair.pubkey(),
+        &player_one_holder_keypair.pubkey(),
+        &program_account,
+        &mint_account_keypair.pubkey(),
+        &token_account_keypair.pubkey(),
+        &player_one_account,
+        &player_one_token_account_keypair.pubkey(),
+        &player_two_holder_keypair.pubkey(),
     );
-    banks_client
-        .process_transaction(init_instruction_transaction)
-        .await
-        .unwrap();
-    // It should successfully execute init instruction
-    let program_account = banks_client
-        .get_account(program_account_keypair.pubkey())
-        .await
-        .unwrap();
-    match program_account {
-        Some(account) => {
-            let program_state = GameInfo::unpack(&account.data).unwrap();
-            assert_eq!(program_state.is_initialized, true);
-            assert_eq!(&program_state.admin, &admin_account_keypair.pubkey());
-            assert_eq!(
-                &program_state.spl_token_account,
-                &token_account_keypair.pubkey()
-            );
+    instructions.push(instruction::batch_register(
+        &program_id,
+        &program_account,
+        &player_two_account,
+        &[player_one_holder_keypair.pubkey()],
+    ));
+    // Test every instruction with its last required account left out
+    // Only the payer signs, the account count is checked before any signature
+    for mut instruction in instructions {
+        instruction.accounts.pop();
+        for account in instruction.accounts.iter_mut() {
+            account.is_signer = false;
         }
-        _ => {
-            panic!("Program account not found");
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        let result = banks_client.process_transaction(transaction).await;
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("insufficient account keys for instruction"));
+    }
+}
+
+#[tokio::test]
+async fn nonce_every_instruction() {
+    let TestGame {
+        mint_account_keypair,
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        player_one_token_account_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup().await;
+    let instructions = every_instruction(
+        &program_id,
+        &admin_account_keypair.pubkey(),
+        &player_one_holder_keypair.pubkey(),
+        &program_account,
+        &mint_account_keypair.pubkey(),
+        &token_account_keypair.pubkey(),
+        &player_one_account,
+        &player_one_token_account_keypair.pubkey(),
+        &player_two_holder_keypair.pubkey(),
+    );
+    // Test every state-changing instruction takes exactly one nonce
+    let mut nonce = 0;
+    for mut instruction in instructions {
+        match instruction.data[1] {
+            // A player already at the current layout cannot be migrated, covered by the migration tests
+            13 => continue,
+            // The player upline was set by the set upline instruction above
+            4 => instruction
+                .accounts
+                .push(AccountMeta::new(player_two_account, false)),
+            _ => {}
         }
-    };
+        let signer = [&admin_account_keypair, &player_one_holder_keypair]
+            .into_iter()
+            .find(|keypair| keypair.pubkey() == instruction.accounts[0].pubkey)
+            .unwrap();
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer, signer], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+
+        let account = banks_client
+            .get_account(program_account)
+            .await
+            .unwrap()
+            .unwrap();
+        nonce += 1;
+        assert_eq!(GameInfo::unpack(&account.data).unwrap().nonce, nonce);
+    }
+}
+
+#[tokio::test]
+async fn program_account_not_owned_by_program() {
+    let TestGame {
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_one_token_account_keypair,
+        program_id,
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        ..
+    } = setup_initialized(InitParams::default()).await;
+
+    // A system owned account passed as the program account
+    let fake_program_account = player_two_holder_keypair.pubkey();
+    let admin = admin_account_keypair.pubkey();
+    let holder = player_one_holder_keypair.pubkey();
+    // Init only accepts the game info PDA, which is covered by init_instruction
+    let instructions = [
+        (
+            instruction::register(&program_id, &holder, &fake_program_account, None, false),
+            &player_one_holder_keypair,
+        ),
+        (
+            instruction::add_reward(
+                &program_id,
+                &admin,
+                &fake_program_account,
+                &player_one_account,
+                &[],
+                100,
+                false,
+            ),
+            &admin_account_keypair,
+        ),
+        (
+            instruction::claim(
+                &program_id,
+                &holder,
+                &fake_program_account,
+                &token_account_keypair.pubkey(),
+                &player_one_token_account_keypair.pubkey(),
+                None,
+            ),
+            &player_one_holder_keypair,
+        ),
+        (
+            instruction::set_admin(&program_id, &admin, &fake_program_account, &holder),
+            &admin_account_keypair,
+        ),
+    ];
+    for (instruction, signer) in instructions {
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer, signer], recent_blockhash);
+        let result = banks_client.process_transaction(transaction).await;
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("incorrect program id"));
+    }
+
+    // A system owned account passed as the player account
+    let transaction = build_add_reward_transaction(
+        &admin_account_keypair,
+        &program_account,
+        &player_two_holder_keypair.pubkey(),
+        &[],
+        program_id,
+        100,
+        &payer,
+        recent_blockhash,
+    );
+    let result = banks_client.process_transaction(transaction).await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("incorrect program id"));
+}
+
+// One instruction of each kind built by the client builders, the admin or holder is always the first account
+// They are listed in an order that runs one after the other on a fresh game, except migrate player
+#[allow(clippy::too_many_arguments)]
+fn every_instruction(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    holder: &Pubkey,
+    program_account: &Pubkey,
+    mint: &Pubkey,
+    token_account: &Pubkey,
+    player_one_account: &Pubkey,
+    player_one_token_account: &Pubkey,
+    player_two_holder: &Pubkey,
+) -> Vec<Instruction> {
+    let (player_two_account, _) = player_pda(program_id, player_two_holder);
+    vec![
+        instruction::init(
+            program_id,
+            admin,
+            program_account,
+            token_account,
+            mint,
+            0,
+            DEFAULT_MAX_DEPTH,
+            0,
+            0,
+            0,
+            0,
+            DEFAULT_LEVEL_BPS,
+        ),
+        instruction::register(program_id, holder, program_account, None, false),
+        instruction::set_relayer(program_id, admin, program_account, holder),
+        instruction::relay_register(
+            program_id,
+            holder,
+            player_two_holder,
+            program_account,
+            None,
+            false,
+        ),
+        instruction::add_reward(
+            program_id,
+            admin,
+            program_account,
+            player_one_account,
+            &[],
+            100,
+            false,
+        ),
+        instruction::transfer_reward(program_id, holder, program_account, &player_two_account, 50),
+        instruction::claim(
+            program_id,
+            holder,
+            program_account,
+            token_account,
+            player_one_token_account,
+            None,
+        ),
+        instruction::admin_set_reward(program_id, admin, program_account, player_one_account, 10),
+        instruction::slash_reward(program_id, admin, program_account, player_one_account, 10),
+        instruction::fund_pool(program_id, admin, program_account, 0),
+        instruction::set_upline(
+            program_id,
+            holder,
+            program_account,
+            &player_two_account,
+            &[],
+        ),
+        instruction::migrate_player(program_id, holder, program_account, None),
+        instruction::close_account(program_id, holder, program_account, holder),
+        instruction::freeze_player(
+            program_id,
+            admin,
+            program_account,
+            &player_two_account,
+            true,
+        ),
+        instruction::sweep_fees(program_id, admin, program_account, holder),
+        instruction::add_admin(program_id, admin, program_account, holder),
+        instruction::remove_admin(program_id, admin, program_account, holder),
+        instruction::set_paused(program_id, admin, program_account, true),
+        instruction::set_vault_authority(program_id, admin, program_account, token_account, holder),
+        instruction::set_admin(program_id, admin, program_account, holder),
+    ]
+}
+
+fn build_close_account_transaction(
+    player_holder_keypair: &Keypair,
+    player_account: &Pubkey,
+    destination: &Pubkey,
+    program_account: &Pubkey,
+    program_id: Pubkey,
+    payer: &Keypair,
+    recent_blockhash: Hash,
+) -> Transaction {
+    let close_account_instruction = [Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(player_holder_keypair.pubkey(), true),
+            AccountMeta::new(*player_account, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new(*program_account, false),
+        ],
+        data: vec![INSTRUCTION_MAGIC, 4], // Tag = 4
+    }];
+    let mut transaction =
+        Transaction::new_with_payer(&close_account_instruction, Some(&payer.pubkey()));
+    transaction.partial_sign(&[payer, player_holder_keypair], recent_blockhash);
+    transaction
+}
+
+fn build_set_admin_transaction(
+    admin_account_keypair: &Keypair,
+    program_account: &Pubkey,
+    new_admin: &Pubkey,
+    program_id: Pubkey,
+    payer: &Keypair,
+    recent_blockhash: Hash,
+) -> Transaction {
+    let set_admin_instruction = [Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(admin_account_keypair.pubkey(), true),
+            AccountMeta::new(*program_account, false),
+            AccountMeta::new_readonly(*new_admin, false),
+        ],
+        data: vec![INSTRUCTION_MAGIC, 5], // Tag = 5
+    }];
+    let mut transaction =
+        Transaction::new_with_payer(&set_admin_instruction, Some(&payer.pubkey()));
+    transaction.partial_sign(&[payer, admin_account_keypair], recent_blockhash);
+    transaction
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_transfer_reward_transaction(
+    player_holder_keypair: &Keypair,
+    player_account: &Pubkey,
+    destination_player_account: &Pubkey,
+    program_account: &Pubkey,
+    program_id: Pubkey,
+    amount: u64,
+    payer: &Keypair,
+    recent_blockhash: Hash,
+) -> Transaction {
+    let mut data = vec![INSTRUCTION_MAGIC, 6]; // Tag = 6
+    data.extend_from_slice(&amount.to_le_bytes());
+    let transfer_reward_instruction = [Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(player_holder_keypair.pubkey(), true),
+            AccountMeta::new(*player_account, false),
+            AccountMeta::new(*destination_player_account, false),
+            AccountMeta::new(*program_account, false),
+        ],
+        data,
+    }];
+    let mut transaction =
+        Transaction::new_with_payer(&transfer_reward_instruction, Some(&payer.pubkey()));
+    transaction.partial_sign(&[payer, player_holder_keypair], recent_blockhash);
+    transaction
 }
 
+#[allow(clippy::too_many_arguments)]
 fn build_claim_reward_transaction(
     player_holder_keypair: &Keypair,
-    program_account_keypair: &Keypair,
-    player_account_keypair: &Keypair,
+    program_account: &Pubkey,
+    player_account: &Pubkey,
     token_account_keypair: &Keypair,
     pda: Pubkey,
     player_token_account_keypair: &Keypair,
@@ -701,15 +7530,16 @@ fn build_claim_reward_transaction(
     let claim_reward_instruction = [Instruction {
         accounts: vec![
             AccountMeta::new_readonly(player_holder_keypair.pubkey(), true),
-            AccountMeta::new_readonly(program_account_keypair.pubkey(), false),
-            AccountMeta::new(player_account_keypair.pubkey(), false), // Claim from player two account
+            AccountMeta::new(*program_account, false),
+            AccountMeta::new(*player_account, false), // Claim from player two account
             AccountMeta::new(token_account_keypair.pubkey(), false),
             AccountMeta::new_readonly(pda, false),
             AccountMeta::new(player_token_account_keypair.pubkey(), false), // Receive the claimed token using player one token account
             AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
         ],
         program_id,
-        data: vec![3_u8], // Tag = 3
+        data: vec![INSTRUCTION_MAGIC, 3], // Tag = 3
     }];
 
     let mut transaction =
@@ -718,69 +7548,60 @@ fn build_claim_reward_transaction(
     transaction
 }
 
+#[allow(clippy::too_many_arguments)]
 fn build_add_reward_transaction(
     admin_account_keypair: &Keypair,
-    program_account_keypair: &Keypair,
-    player_account_keypair: &Keypair,
-    upline_account_keypair: Option<&Keypair>,
+    program_account: &Pubkey,
+    player_account: &Pubkey,
+    upline_accounts: &[&Pubkey],
     program_id: Pubkey,
     amount: u64,
     payer: &Keypair,
     recent_blockhash: Hash,
 ) -> Transaction {
-    let mut add_reward_data = vec![2_u8]; // Tag = 2
+    let mut add_reward_data = vec![INSTRUCTION_MAGIC, 2]; // Tag = 2
     add_reward_data.extend_from_slice(&u64::to_le_bytes(amount)); // reward
-    if upline_account_keypair.is_some() {
-        let add_reward_instruction = [Instruction {
-            program_id,
-            accounts: vec![
-                AccountMeta::new_readonly(admin_account_keypair.pubkey(), true),
-                AccountMeta::new_readonly(program_account_keypair.pubkey(), false),
-                AccountMeta::new(player_account_keypair.pubkey(), false),
-                AccountMeta::new(upline_account_keypair.unwrap().pubkey(), false),
-            ],
-            data: add_reward_data.clone(),
-        }];
-        let mut transaction =
-            Transaction::new_with_payer(&add_reward_instruction, Some(&payer.pubkey()));
-        transaction.partial_sign(&[payer, admin_account_keypair], recent_blockhash);
-        transaction
-    } else {
-        let add_reward_instruction = [Instruction {
-            program_id,
-            accounts: vec![
-                AccountMeta::new_readonly(admin_account_keypair.pubkey(), true),
-                AccountMeta::new_readonly(program_account_keypair.pubkey(), false),
-                AccountMeta::new(player_account_keypair.pubkey(), false),
-            ],
-            data: add_reward_data.clone(),
-        }];
-        let mut transaction =
-            Transaction::new_with_payer(&add_reward_instruction, Some(&payer.pubkey()));
-        transaction.partial_sign(&[payer, admin_account_keypair], recent_blockhash);
-        transaction
+    let mut accounts = vec![
+        AccountMeta::new_readonly(admin_account_keypair.pubkey(), true),
+        AccountMeta::new(*program_account, false),
+        AccountMeta::new(*player_account, false),
+    ];
+    // Uplines ordered from the direct upline
+    for upline_account in upline_accounts {
+        accounts.push(AccountMeta::new(**upline_account, false));
     }
+    let add_reward_instruction = [Instruction {
+        program_id,
+        accounts,
+        data: add_reward_data,
+    }];
+    let mut transaction =
+        Transaction::new_with_payer(&add_reward_instruction, Some(&payer.pubkey()));
+    transaction.partial_sign(&[payer, admin_account_keypair], recent_blockhash);
+    transaction
 }
 
 fn build_register_player_transaction(
     payer: &Keypair,
     player_holder_keypair: &Keypair,
-    player_account_keypair: &Keypair,
-    program_account_keypair: &Keypair,
-    upline_account_keypair: Option<&Keypair>,
+    player_account: &Pubkey,
+    program_account: &Pubkey,
+    upline_account: Option<&Pubkey>,
     program_id: Pubkey,
     recent_blockhash: Hash,
 ) -> Transaction {
-    if upline_account_keypair.is_some() {
+    if upline_account.is_some() {
         let register_player_instruction = [Instruction {
             program_id,
             accounts: vec![
-                AccountMeta::new_readonly(player_holder_keypair.pubkey(), true),
-                AccountMeta::new(player_account_keypair.pubkey(), false),
-                AccountMeta::new_readonly(program_account_keypair.pubkey(), false),
-                AccountMeta::new_readonly(upline_account_keypair.unwrap().pubkey(), false), //upline
+                AccountMeta::new(player_holder_keypair.pubkey(), true),
+                AccountMeta::new(*player_account, false),
+                AccountMeta::new(*program_account, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(sysvar::clock::id(), false),
+                AccountMeta::new(*upline_account.unwrap(), false), //upline
             ],
-            data: vec![1_u8], // Tag 1
+            data: vec![INSTRUCTION_MAGIC, 1], // Tag 1
         }];
         let mut transaction =
             Transaction::new_with_payer(&register_player_instruction, Some(&payer.pubkey()));
@@ -790,74 +7611,45 @@ fn build_register_player_transaction(
         let register_player_instruction = [Instruction {
             program_id,
             accounts: vec![
-                AccountMeta::new_readonly(player_holder_keypair.pubkey(), true),
-                AccountMeta::new(player_account_keypair.pubkey(), false),
-                AccountMeta::new_readonly(program_account_keypair.pubkey(), false),
-            ],
-            data: vec![1_u8], // Tag 1
-        }];
-        let mut transaction =
-            Transaction::new_with_payer(&register_player_instruction, Some(&payer.pubkey()));
-        transaction.partial_sign(&[payer, player_holder_keypair], recent_blockhash);
-        transaction
-    }
-}
-
-fn build_create_player_account_transaction(
-    player_one_account_keypair: &Keypair,
-    player_two_account_keypair: &Keypair,
-    payer: &Keypair,
-    program_id: Pubkey,
-    recent_blockhash: Hash,
-) -> Transaction {
-    let create_player_account_instruction = [
-        // Create player one account
-        system_instruction::create_account(
-            &payer.pubkey(),
-            &player_one_account_keypair.pubkey(),
-            Rent::default().minimum_balance(Player::LEN),
-            Player::LEN.try_into().unwrap(),
-            &program_id,
-        ),
-        // Create player two account
-        system_instruction::create_account(
-            &payer.pubkey(),
-            &player_two_account_keypair.pubkey(),
-            Rent::default().minimum_balance(Player::LEN),
-            Player::LEN.try_into().unwrap(),
-            &program_id,
-        ),
-    ];
-    let mut create_player_account_transaction =
-        Transaction::new_with_payer(&create_player_account_instruction, Some(&payer.pubkey()));
-    create_player_account_transaction.partial_sign(
-        &[
-            payer,
-            player_one_account_keypair,
-            player_two_account_keypair,
-        ],
-        recent_blockhash,
-    );
-    create_player_account_transaction
+                AccountMeta::new(player_holder_keypair.pubkey(), true),
+                AccountMeta::new(*player_account, false),
+                AccountMeta::new(*program_account, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(sysvar::clock::id(), false),
+            ],
+            data: vec![INSTRUCTION_MAGIC, 1], // Tag 1
+        }];
+        let mut transaction =
+            Transaction::new_with_payer(&register_player_instruction, Some(&payer.pubkey()));
+        transaction.partial_sign(&[payer, player_holder_keypair], recent_blockhash);
+        transaction
+    }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn build_init_instruction_transaction(
     admin_account_keypair: &Keypair,
-    program_account_keypair: &Keypair,
+    program_account: &Pubkey,
     token_account_keypair: &Keypair,
+    mint_account: &Pubkey,
+    referral_bonus: u64,
     payer: &Keypair,
     program_id: Pubkey,
     recent_blockhash: Hash,
 ) -> Transaction {
+    let mut init_data = vec![INSTRUCTION_MAGIC, 0]; // Tag = 0
+    init_data.extend_from_slice(&referral_bonus.to_le_bytes());
     let init_instruction = Instruction {
         program_id,
         accounts: vec![
-            AccountMeta::new_readonly(admin_account_keypair.pubkey(), true),
-            AccountMeta::new(program_account_keypair.pubkey(), false),
+            AccountMeta::new(admin_account_keypair.pubkey(), true),
+            AccountMeta::new(*program_account, false),
+            AccountMeta::new_readonly(system_program::id(), false),
             AccountMeta::new(token_account_keypair.pubkey(), false),
+            AccountMeta::new_readonly(*mint_account, false),
             AccountMeta::new_readonly(spl_token::id(), false),
         ],
-        data: vec![0_u8], // Tag = 0
+        data: init_data,
     };
     let mut init_instruction_transaction =
         Transaction::new_with_payer(&[init_instruction], Option::Some(&payer.pubkey()));
@@ -865,110 +7657,490 @@ fn build_init_instruction_transaction(
     init_instruction_transaction
 }
 
-async fn setup() -> (
-    Keypair,
-    Keypair,
-    Keypair,
-    Keypair,
-    Keypair,
-    Keypair,
-    Keypair,
-    Keypair,
-    Keypair,
-    Keypair,
-    Pubkey,
-    BanksClient,
-    Keypair,
-    Hash,
-) {
+// Accounts of the game started by setup, the player accounts are the PDAs of the two holders
+struct TestGame {
+    mint_account_keypair: Keypair,
+    admin_account_keypair: Keypair,
+    program_account: Pubkey,
+    token_account_keypair: Keypair,
+    player_one_holder_keypair: Keypair,
+    player_one_account: Pubkey,
+    player_two_holder_keypair: Keypair,
+    player_two_account: Pubkey,
+    player_one_token_account_keypair: Keypair,
+    player_two_token_account_keypair: Keypair,
+    program_id: Pubkey,
+    banks_client: BanksClient,
+    payer: Keypair,
+    recent_blockhash: Hash,
+}
+
+// Same accounts as TestGame, without the player two token account
+// The banks client, payer and blockhash are taken from the context
+struct TestGameContext {
+    mint_account_keypair: Keypair,
+    admin_account_keypair: Keypair,
+    program_account: Pubkey,
+    token_account_keypair: Keypair,
+    player_one_holder_keypair: Keypair,
+    player_one_account: Pubkey,
+    player_two_holder_keypair: Keypair,
+    player_two_account: Pubkey,
+    player_one_token_account_keypair: Keypair,
+    program_id: Pubkey,
+    context: ProgramTestContext,
+}
+
+// Settings of the init sent by the initialized setups, the default is the init of a client leaving them all out
+#[derive(Clone, Copy)]
+struct InitParams {
+    referral_bonus: u64,
+    max_depth: u8,
+    min_claim: u64,
+    register_fee: u64,
+    max_reward_per_player: u64,
+    decay_bps_per_day: u64,
+    level_bps: [u16; 3],
+}
+
+impl Default for InitParams {
+    fn default() -> Self {
+        InitParams {
+            referral_bonus: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            min_claim: 0,
+            register_fee: 0,
+            max_reward_per_player: 0,
+            decay_bps_per_day: 0,
+            level_bps: DEFAULT_LEVEL_BPS,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_init_params_transaction(
+    admin_account_keypair: &Keypair,
+    program_account: &Pubkey,
+    token_account_keypair: &Keypair,
+    mint_account: &Pubkey,
+    init_params: InitParams,
+    payer: &Keypair,
+    program_id: Pubkey,
+    recent_blockhash: Hash,
+) -> Transaction {
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::init(
+            &program_id,
+            &admin_account_keypair.pubkey(),
+            program_account,
+            &token_account_keypair.pubkey(),
+            mint_account,
+            init_params.referral_bonus,
+            init_params.max_depth,
+            init_params.min_claim,
+            init_params.register_fee,
+            init_params.max_reward_per_player,
+            init_params.decay_bps_per_day,
+            init_params.level_bps,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[payer, admin_account_keypair], recent_blockhash);
+    transaction
+}
+
+async fn setup() -> TestGame {
+    setup_with_extra_holders(&[]).await
+}
+
+// Same as setup, with the game already initialized by the admin for the token account
+async fn setup_initialized(init_params: InitParams) -> TestGame {
+    setup_initialized_with_extra_holders(&[], init_params).await
+}
+
+// Same as setup_with_extra_holders, with the game already initialized by the admin for the token account
+async fn setup_initialized_with_extra_holders(
+    extra_holder_keypairs: &[&Keypair],
+    init_params: InitParams,
+) -> TestGame {
+    let mut game = setup_with_extra_holders(extra_holder_keypairs).await;
+    let init_instruction_transaction = build_init_params_transaction(
+        &game.admin_account_keypair,
+        &game.program_account,
+        &game.token_account_keypair,
+        &game.mint_account_keypair.pubkey(),
+        init_params,
+        &game.payer,
+        game.program_id,
+        game.recent_blockhash,
+    );
+    game.banks_client
+        .process_transaction(init_instruction_transaction)
+        .await
+        .unwrap();
+    game
+}
+
+// Same as setup_context, with the game already initialized by the admin for the token account
+async fn setup_context_initialized(init_params: InitParams) -> TestGameContext {
+    let mut game = setup_context().await;
+    let init_instruction_transaction = build_init_params_transaction(
+        &game.admin_account_keypair,
+        &game.program_account,
+        &game.token_account_keypair,
+        &game.mint_account_keypair.pubkey(),
+        init_params,
+        &game.context.payer,
+        game.program_id,
+        game.context.last_blockhash,
+    );
+    game.context
+        .banks_client
+        .process_transaction(init_instruction_transaction)
+        .await
+        .unwrap();
+    game
+}
+
+// Resending an identical transaction returns the status of the first one, so wait for the next blockhash
+async fn get_new_blockhash(banks_client: &mut BanksClient, recent_blockhash: &Hash) -> Hash {
+    loop {
+        let blockhash = banks_client.get_latest_blockhash().await.unwrap();
+        if blockhash != *recent_blockhash {
+            return blockhash;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+}
+
+// Start directly from an initialized program account, with the player one account pre-allocated with the given lamports
+// The context is returned so that the test can alter the sysvars
+async fn setup_initialized_program_context(
+    player_account_lamports: u64,
+) -> (Pubkey, Pubkey, Keypair, Pubkey, ProgramTestContext) {
+    let program_id = Pubkey::new_unique();
+    let (program_account, _) = game_info_pda(&program_id);
+    let admin_account_keypair = Keypair::new();
+    let player_one_holder_keypair = Keypair::new();
+    let (player_one_account, _) = player_pda(&program_id, &player_one_holder_keypair.pubkey());
+    let mut program_test = ProgramTest::new("learn_solana", program_id, processor!(main));
+
+    let mut program_account_data = vec![0; GameInfo::LEN];
+    GameInfo {
+        version: CURRENT_VERSION,
+        is_initialized: true,
+        admins: [
+            admin_account_keypair.pubkey(),
+            Pubkey::default(),
+            Pubkey::default(),
+        ],
+        spl_token_account: Pubkey::new_unique(),
+        total_players: 0,
+        referral_bonus: 0,
+        paused: false,
+        max_depth: DEFAULT_MAX_DEPTH,
+        mint: Pubkey::new_unique(),
+        min_claim: 0,
+        register_fee: 0,
+        max_reward_per_player: 0,
+        nonce: 0,
+        decay_bps_per_day: 0,
+        matching_pool: 0,
+        relayer: Pubkey::default(),
+        vault_bump: vault_pda(&program_id).1,
+        level_bps: DEFAULT_LEVEL_BPS,
+        dust: 0,
+        tier_thresholds: [0; 3],
+        vest_duration: 0,
+        reward_multiplier_bps: 10_000,
+        token_program: spl_token::id(),
+    }
+    .pack_into_slice(&mut program_account_data);
+    program_test.add_account(
+        program_account,
+        Account {
+            lamports: Rent::default().minimum_balance(GameInfo::LEN),
+            data: program_account_data,
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+    program_test.add_account(
+        player_one_account,
+        Account {
+            lamports: player_account_lamports,
+            data: vec![0; Player::LEN],
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+    program_test.add_account(
+        player_one_holder_keypair.pubkey(),
+        Account {
+            lamports: LAMPORTS_PER_SOL,
+            ..Account::default()
+        },
+    );
+    let context = program_test.start_with_context().await;
+    capture_logged_data();
+    (
+        program_id,
+        program_account,
+        player_one_holder_keypair,
+        player_one_account,
+        context,
+    )
+}
+
+// Extra holders are funded and get their player account pre-allocated, same as player one and two
+async fn setup_with_extra_holders(extra_holder_keypairs: &[&Keypair]) -> TestGame {
+    setup_with_compute_max_units(extra_holder_keypairs, None).await
+}
+
+// Same as setup_with_extra_holders, with an optional compute units limit for every transaction
+async fn setup_with_compute_max_units(
+    extra_holder_keypairs: &[&Keypair],
+    compute_max_units: Option<u64>,
+) -> TestGame {
     let mint_account_keypair = Keypair::new();
     let admin_account_keypair = Keypair::new();
-    let program_account_keypair = Keypair::new();
     let token_account_keypair = Keypair::new();
     let player_one_holder_keypair = Keypair::new();
-    let player_one_account_keypair = Keypair::new();
     let player_two_holder_keypair = Keypair::new();
-    let player_two_account_keypair = Keypair::new();
     let player_one_token_account_keypair = Keypair::new();
     let player_two_token_account_keypair = Keypair::new();
 
     let program_id = Pubkey::new_unique();
+    let (program_account, _) = game_info_pda(&program_id);
+    let (player_one_account, _) = player_pda(&program_id, &player_one_holder_keypair.pubkey());
+    let (player_two_account, _) = player_pda(&program_id, &player_two_holder_keypair.pubkey());
+    let holder_keypairs = [
+        &[&player_one_holder_keypair, &player_two_holder_keypair],
+        extra_holder_keypairs,
+    ]
+    .concat();
+    let mut program_test = new_program_test(program_id, &holder_keypairs);
+    if let Some(compute_max_units) = compute_max_units {
+        program_test.set_compute_max_units(compute_max_units);
+    }
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    capture_logged_data();
+    setup_accounts(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &mint_account_keypair,
+        &admin_account_keypair,
+        &program_account,
+        &token_account_keypair,
+        &holder_keypairs,
+        &[
+            (
+                &player_one_token_account_keypair,
+                &player_one_holder_keypair,
+            ),
+            (
+                &player_two_token_account_keypair,
+                &player_two_holder_keypair,
+            ),
+        ],
+        &program_id,
+    )
+    .await;
+
+    TestGame {
+        mint_account_keypair,
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        player_one_token_account_keypair,
+        player_two_token_account_keypair,
+        program_id,
+        banks_client,
+        payer,
+        recent_blockhash,
+    }
+}
+
+// Same as setup, but return the context so that the test can alter the sysvars
+// The context must be kept alive, dropping it stops the bank from producing new blockhashes
+async fn setup_context() -> TestGameContext {
+    let mint_account_keypair = Keypair::new();
+    let admin_account_keypair = Keypair::new();
+    let token_account_keypair = Keypair::new();
+    let player_one_holder_keypair = Keypair::new();
+    let player_two_holder_keypair = Keypair::new();
+    let player_one_token_account_keypair = Keypair::new();
+
+    let program_id = Pubkey::new_unique();
+    let (program_account, _) = game_info_pda(&program_id);
+    let (player_one_account, _) = player_pda(&program_id, &player_one_holder_keypair.pubkey());
+    let (player_two_account, _) = player_pda(&program_id, &player_two_holder_keypair.pubkey());
+    let holder_keypairs = [&player_one_holder_keypair, &player_two_holder_keypair];
+    let mut context = new_program_test(program_id, &holder_keypairs)
+        .start_with_context()
+        .await;
+    capture_logged_data();
+    let recent_blockhash = context.last_blockhash;
+    setup_accounts(
+        &mut context.banks_client,
+        &context.payer,
+        recent_blockhash,
+        &mint_account_keypair,
+        &admin_account_keypair,
+        &program_account,
+        &token_account_keypair,
+        &holder_keypairs,
+        &[(
+            &player_one_token_account_keypair,
+            &player_one_holder_keypair,
+        )],
+        &program_id,
+    )
+    .await;
+
+    TestGameContext {
+        mint_account_keypair,
+        admin_account_keypair,
+        program_account,
+        token_account_keypair,
+        player_one_holder_keypair,
+        player_one_account,
+        player_two_holder_keypair,
+        player_two_account,
+        player_one_token_account_keypair,
+        program_id,
+        context,
+    }
+}
+
+fn new_program_test(program_id: Pubkey, holder_keypairs: &[&Keypair]) -> ProgramTest {
     // The program_test will be run in BPF VM
-    let program_test = ProgramTest::new(
+    let mut program_test = ProgramTest::new(
         // name must match with the compiled .so
         // https://docs.rs/solana-program-test/latest/src/solana_program_test/lib.rs.html#492-500
         "learn_solana",
         program_id,
         processor!(main),
     );
-    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
-    // Create and initialize program, token account, mint account
-    banks_client
-        .process_transaction(build_create_and_init_token_and_program_account_transaction(
-            &payer,
-            &program_account_keypair,
-            &program_id,
-            &mint_account_keypair,
-            &token_account_keypair,
-            &admin_account_keypair,
-            recent_blockhash,
-        ))
-        .await
-        .unwrap();
+    // Token-2022 is not bundled with the program test, the classic token processor stands in for it
+    // It checks the token accounts against the program id it runs as, and the game only uses the instructions both share
+    program_test.add_builtin_program(
+        "spl_token_2022",
+        spl_token_2022::id(),
+        processor!(spl_token::processor::Processor::process).unwrap(),
+    );
+    // Program test is not able to resize account data in native mode, so the program and player accounts are pre-allocated
+    // On-chain, init and register create them through CPI
+    program_test.add_account(
+        game_info_pda(&program_id).0,
+        Account {
+            lamports: Rent::default().minimum_balance(GameInfo::LEN),
+            data: vec![0; GameInfo::LEN],
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+    for holder_keypair in holder_keypairs {
+        program_test.add_account(
+            player_pda(&program_id, &holder_keypair.pubkey()).0,
+            Account {
+                lamports: Rent::default().minimum_balance(Player::LEN),
+                data: vec![0; Player::LEN],
+                owner: program_id,
+                ..Account::default()
+            },
+        );
+    }
+    program_test
+}
 
-    // Mint to program token account
+// Create the program, token and mint accounts, fund the holders and create the player token accounts
+#[allow(clippy::too_many_arguments)]
+async fn setup_accounts(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    recent_blockhash: Hash,
+    mint_account_keypair: &Keypair,
+    admin_account_keypair: &Keypair,
+    program_account: &Pubkey,
+    token_account_keypair: &Keypair,
+    holder_keypairs: &[&Keypair],
+    player_token_account_keypairs: &[(&Keypair, &Keypair)],
+    program_id: &Pubkey,
+) {
+    // Create and initialize token account, mint account, the program account is created by init
     banks_client
-        .process_transaction(build_mint_transaction(
-            &payer,
-            &mint_account_keypair,
-            &token_account_keypair,
-            &admin_account_keypair,
-            1000000000000,
+        .process_transaction(build_create_and_init_token_transaction(
+            payer,
+            mint_account_keypair,
+            token_account_keypair,
+            admin_account_keypair,
             recent_blockhash,
         ))
         .await
         .unwrap();
 
-    // Create and initialize player 1 token account
+    // Fund the player holders, which pay for their player account rent during register
     banks_client
-        .process_transaction(build_create_and_init_player_token_account(
-            &payer,
-            &player_one_token_account_keypair,
-            &player_one_holder_keypair,
-            &mint_account_keypair,
+        .process_transaction(build_fund_holders_transaction(
+            payer,
+            holder_keypairs,
             recent_blockhash,
         ))
         .await
         .unwrap();
 
-    // Create and initialize player 2 token account
+    // Mint to program token account
     banks_client
-        .process_transaction(build_create_and_init_player_token_account(
-            &payer,
-            &player_two_token_account_keypair,
-            &player_two_holder_keypair,
-            &mint_account_keypair,
+        .process_transaction(build_mint_transaction(
+            payer,
+            mint_account_keypair,
+            token_account_keypair,
+            admin_account_keypair,
+            1000000000000,
             recent_blockhash,
         ))
         .await
         .unwrap();
 
-    (
-        mint_account_keypair,
-        admin_account_keypair,
-        program_account_keypair,
-        token_account_keypair,
-        player_one_holder_keypair,
-        player_one_account_keypair,
-        player_two_holder_keypair,
-        player_two_account_keypair,
-        player_one_token_account_keypair,
-        player_two_token_account_keypair,
-        program_id,
-        banks_client,
-        payer,
-        recent_blockhash,
-    )
+    // Create and initialize the player token accounts
+    for (player_token_account_keypair, player_holder_keypair) in player_token_account_keypairs {
+        banks_client
+            .process_transaction(build_create_and_init_player_token_account(
+                payer,
+                player_token_account_keypair,
+                player_holder_keypair,
+                mint_account_keypair,
+                recent_blockhash,
+            ))
+            .await
+            .unwrap();
+    }
+}
+
+fn build_fund_holders_transaction(
+    payer: &Keypair,
+    holder_keypairs: &[&Keypair],
+    recent_blockhash: Hash,
+) -> Transaction {
+    let instructions: Vec<Instruction> = holder_keypairs
+        .iter()
+        .map(|holder_keypair| {
+            system_instruction::transfer(
+                &payer.pubkey(),
+                &holder_keypair.pubkey(),
+                LAMPORTS_PER_SOL,
+            )
+        })
+        .collect();
+    let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
+    transaction.partial_sign(&[payer], recent_blockhash);
+    transaction
 }
 
 fn build_create_and_init_player_token_account(
@@ -1023,23 +8195,92 @@ fn build_mint_transaction(
     transaction
 }
 
-fn build_create_and_init_token_and_program_account_transaction(
+// Create the mint, vault and player token account under Token-2022, and fund the vault
+// The classic builders are reused, their program id swapped for Token-2022
+fn build_create_and_init_token_2022_transaction(
     payer: &Keypair,
-    program_account_keypair: &Keypair,
-    program_id: &Pubkey,
     mint_account_keypair: &Keypair,
     token_account_keypair: &Keypair,
     admin_account_keypair: &Keypair,
+    player_token_account_keypair: &Keypair,
+    player_holder_keypair: &Keypair,
     recent_blockhash: Hash,
 ) -> Transaction {
-    let instructions = [
+    let create_account = |account_keypair: &Keypair, len: usize| {
         system_instruction::create_account(
             &payer.pubkey(),
-            &program_account_keypair.pubkey(),
-            Rent::default().minimum_balance(GameInfo::LEN),
-            GameInfo::LEN.try_into().unwrap(),
-            &program_id,
-        ),
+            &account_keypair.pubkey(),
+            Rent::default().minimum_balance(len),
+            len.try_into().unwrap(),
+            &spl_token_2022::id(),
+        )
+    };
+    let mut token_instructions = [
+        initialize_mint(
+            &spl_token::id(),
+            &mint_account_keypair.pubkey(),
+            &admin_account_keypair.pubkey(),
+            None,
+            9,
+        )
+        .unwrap(),
+        initialize_account(
+            &spl_token::id(),
+            &token_account_keypair.pubkey(),
+            &mint_account_keypair.pubkey(),
+            &admin_account_keypair.pubkey(),
+        )
+        .unwrap(),
+        initialize_account(
+            &spl_token::id(),
+            &player_token_account_keypair.pubkey(),
+            &mint_account_keypair.pubkey(),
+            &player_holder_keypair.pubkey(),
+        )
+        .unwrap(),
+        mint_to(
+            &spl_token::id(),
+            &mint_account_keypair.pubkey(),
+            &token_account_keypair.pubkey(),
+            &admin_account_keypair.pubkey(),
+            &[],
+            1000000000000,
+        )
+        .unwrap(),
+    ];
+    for token_instruction in token_instructions.iter_mut() {
+        token_instruction.program_id = spl_token_2022::id();
+    }
+    let instructions = [
+        vec![
+            create_account(mint_account_keypair, spl_token::state::Mint::LEN),
+            create_account(token_account_keypair, spl_token::state::Account::LEN),
+            create_account(player_token_account_keypair, spl_token::state::Account::LEN),
+        ],
+        token_instructions.to_vec(),
+    ]
+    .concat();
+
+    let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
+    transaction.partial_sign(
+        &[payer, mint_account_keypair, token_account_keypair],
+        recent_blockhash,
+    );
+    transaction.partial_sign(
+        &[player_token_account_keypair, admin_account_keypair],
+        recent_blockhash,
+    );
+    transaction
+}
+
+fn build_create_and_init_token_transaction(
+    payer: &Keypair,
+    mint_account_keypair: &Keypair,
+    token_account_keypair: &Keypair,
+    admin_account_keypair: &Keypair,
+    recent_blockhash: Hash,
+) -> Transaction {
+    let instructions = [
         system_instruction::create_account(
             &payer.pubkey(),
             &mint_account_keypair.pubkey(),
@@ -1073,13 +8314,8 @@ fn build_create_and_init_token_and_program_account_transaction(
 
     let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
     transaction.partial_sign(
-        &[
-            payer,
-            program_account_keypair,
-            mint_account_keypair,
-            token_account_keypair,
-        ],
+        &[payer, mint_account_keypair, token_account_keypair],
         recent_blockhash,
     );
-    return transaction;
+    transaction
 }
@@ -0,0 +1,1145 @@
+use learn_solana::{
+    constants::INSTRUCTION_MAGIC,
+    error::GameError,
+    instruction::{self, Command, DEFAULT_LEVEL_BPS, DEFAULT_MAX_DEPTH},
+    processor::{player_pda, spl_memo, spl_token_2022, vault_pda},
+};
+use solana_program::{
+    instruction::AccountMeta, program_error::ProgramError, program_option::COption, pubkey::Pubkey,
+    system_program, sysvar,
+};
+
+#[test]
+fn pack_unpack_round_trip() {
+    let commands = [
+        Command::Init {
+            referral_bonus: 0,
+            max_depth: 0,
+            min_claim: 0,
+            register_fee: 0,
+            max_reward_per_player: 0,
+            decay_bps_per_day: 0,
+            level_bps: [0; 3],
+        },
+        Command::Init {
+            referral_bonus: 10,
+            max_depth: DEFAULT_MAX_DEPTH,
+            min_claim: u64::MAX,
+            register_fee: 1_000,
+            max_reward_per_player: u64::MAX,
+            decay_bps_per_day: u64::MAX,
+            level_bps: [u16::MAX, 0, 1],
+        },
+        Command::Register {
+            idempotent: false,
+            holder: None,
+        },
+        Command::Register {
+            idempotent: true,
+            holder: None,
+        },
+        Command::Register {
+            idempotent: false,
+            holder: Some(Pubkey::new_unique()),
+        },
+        Command::Register {
+            idempotent: true,
+            holder: Some(Pubkey::new_unique()),
+        },
+        Command::AddReward {
+            reward_amount: 0,
+            saturate: false,
+        },
+        Command::AddReward {
+            reward_amount: u64::MAX,
+            saturate: false,
+        },
+        Command::AddReward {
+            reward_amount: 125,
+            saturate: true,
+        },
+        Command::Claim { amount: None },
+        Command::Claim { amount: Some(0) },
+        Command::Claim {
+            amount: Some(u64::MAX),
+        },
+        Command::CloseAccount,
+        Command::SetAdmin,
+        Command::TransferReward { amount: 40 },
+        Command::BatchRegister,
+        Command::SetPaused { paused: true },
+        Command::SetPaused { paused: false },
+        Command::SweepFees,
+        Command::AdminSetReward { reward_to_claim: 0 },
+        Command::AdminSetReward {
+            reward_to_claim: 70,
+        },
+        Command::SetUpline,
+        Command::SetVaultAuthority,
+        Command::MigratePlayer,
+        Command::AddAdmin,
+        Command::RemoveAdmin,
+        Command::SlashReward { amount: 30 },
+        Command::GetReward,
+        Command::FundPool { amount: 500 },
+        Command::SetRelayer,
+        Command::FreezePlayer { frozen: true },
+        Command::FreezePlayer { frozen: false },
+        Command::ExportPlayer,
+        Command::SweepDust,
+        Command::AdminClaimBatch,
+        Command::SetTierThresholds {
+            tier_thresholds: [100, 250, u64::MAX],
+        },
+        Command::SetReferralBonus { referral_bonus: 0 },
+        Command::SetReferralBonus {
+            referral_bonus: u64::MAX,
+        },
+        Command::SetVestDuration { vest_duration: 0 },
+        Command::SetVestDuration {
+            vest_duration: u64::MAX,
+        },
+        Command::SetRewardMultiplier {
+            reward_multiplier_bps: 0,
+        },
+        Command::SetRewardMultiplier {
+            reward_multiplier_bps: u16::MAX,
+        },
+    ];
+    for command in commands {
+        let packed = command.pack();
+        assert_eq!(Command::unpack(&packed).unwrap(), command);
+    }
+}
+
+#[test]
+fn add_reward_layout() {
+    let packed = Command::AddReward {
+        reward_amount: 125,
+        saturate: false,
+    }
+    .pack();
+    assert_eq!(packed, [INSTRUCTION_MAGIC, 2, 125, 0, 0, 0, 0, 0, 0, 0]);
+    // The saturate flag is only sent when set, an explicit 0 is still accepted
+    let packed = Command::AddReward {
+        reward_amount: 125,
+        saturate: true,
+    }
+    .pack();
+    assert_eq!(packed, [INSTRUCTION_MAGIC, 2, 125, 0, 0, 0, 0, 0, 0, 0, 1]);
+    assert_eq!(
+        Command::unpack(&[INSTRUCTION_MAGIC, 2, 125, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap(),
+        Command::AddReward {
+            reward_amount: 125,
+            saturate: false,
+        }
+    );
+    for flag in [[2].as_ref(), &[1, 0]] {
+        let mut data = vec![INSTRUCTION_MAGIC, 2, 125, 0, 0, 0, 0, 0, 0, 0];
+        data.extend_from_slice(flag);
+        assert_eq!(
+            Command::unpack(&data).unwrap_err(),
+            ProgramError::InvalidInstructionData
+        );
+    }
+}
+
+#[test]
+fn unpack_invalid_data() {
+    // Empty data
+    assert_eq!(
+        Command::unpack(&[]).unwrap_err(),
+        ProgramError::InvalidInstructionData
+    );
+    // Magic without a tag
+    assert_eq!(
+        Command::unpack(&[INSTRUCTION_MAGIC]).unwrap_err(),
+        ProgramError::InvalidInstructionData
+    );
+    // Unknown tag
+    assert_eq!(
+        Command::unpack(&[INSTRUCTION_MAGIC, 255]).unwrap_err(),
+        ProgramError::InvalidInstructionData
+    );
+    // AddReward without a full u64 amount
+    assert_eq!(
+        Command::unpack(&[INSTRUCTION_MAGIC, 2, 1, 2, 3]).unwrap_err(),
+        ProgramError::InvalidInstructionData
+    );
+    // Init with a truncated min claim, or extra bytes after it
+    assert_eq!(
+        Command::unpack(&[INSTRUCTION_MAGIC, 0, 10, 0, 0, 0, 0, 0, 0, 0, 3, 0]).unwrap_err(),
+        ProgramError::InvalidInstructionData
+    );
+    assert_eq!(
+        Command::unpack(&[
+            INSTRUCTION_MAGIC,
+            0,
+            10,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            3,
+            5,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0
+        ])
+        .unwrap_err(),
+        ProgramError::InvalidInstructionData
+    );
+    // AdminSetReward without a full u64 value
+    assert_eq!(
+        Command::unpack(&[INSTRUCTION_MAGIC, 10, 1]).unwrap_err(),
+        ProgramError::InvalidInstructionData
+    );
+    // Claim with a truncated amount
+    assert_eq!(
+        Command::unpack(&[INSTRUCTION_MAGIC, 3, 1, 2]).unwrap_err(),
+        ProgramError::InvalidInstructionData
+    );
+    // Register with a flag other than 0 or 1, or with a truncated holder
+    assert_eq!(
+        Command::unpack(&[INSTRUCTION_MAGIC, 1, 2]).unwrap_err(),
+        ProgramError::InvalidInstructionData
+    );
+    assert_eq!(
+        Command::unpack(&[INSTRUCTION_MAGIC, 1, 0, 1, 2, 3]).unwrap_err(),
+        ProgramError::InvalidInstructionData
+    );
+    // SetPaused without a flag, or with a flag other than 0 or 1
+    assert_eq!(
+        Command::unpack(&[INSTRUCTION_MAGIC, 8]).unwrap_err(),
+        ProgramError::InvalidInstructionData
+    );
+    assert_eq!(
+        Command::unpack(&[INSTRUCTION_MAGIC, 8, 2]).unwrap_err(),
+        ProgramError::InvalidInstructionData
+    );
+    // SetTierThresholds with a missing or an extra threshold byte
+    assert_eq!(
+        Command::unpack(&[INSTRUCTION_MAGIC, 24, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap_err(),
+        ProgramError::InvalidInstructionData
+    );
+    let mut data = Command::SetTierThresholds {
+        tier_thresholds: [1, 2, 3],
+    }
+    .pack();
+    data.push(0);
+    assert_eq!(
+        Command::unpack(&data).unwrap_err(),
+        ProgramError::InvalidInstructionData
+    );
+    // SetRewardMultiplier without a full u16 multiplier
+    assert_eq!(
+        Command::unpack(&[INSTRUCTION_MAGIC, 27, 1]).unwrap_err(),
+        ProgramError::InvalidInstructionData
+    );
+    // FreezePlayer without a flag, or with a flag other than 0 or 1
+    assert_eq!(
+        Command::unpack(&[INSTRUCTION_MAGIC, 20]).unwrap_err(),
+        ProgramError::InvalidInstructionData
+    );
+    assert_eq!(
+        Command::unpack(&[INSTRUCTION_MAGIC, 20, 2]).unwrap_err(),
+        ProgramError::InvalidInstructionData
+    );
+}
+
+#[test]
+fn unpack_magic() {
+    let packed = Command::SetPaused { paused: true }.pack();
+    assert_eq!(packed[0], INSTRUCTION_MAGIC);
+    assert_eq!(
+        Command::unpack(&packed).unwrap(),
+        Command::SetPaused { paused: true }
+    );
+
+    // Any other first byte is an instruction of another version, even if the rest is valid
+    let mut wrong_magic = packed.clone();
+    wrong_magic[0] = INSTRUCTION_MAGIC.wrapping_add(1);
+    assert_eq!(
+        Command::unpack(&wrong_magic).unwrap_err(),
+        GameError::UnsupportedVersion.into()
+    );
+    // Neither is the data of the version before the magic, which starts with the tag
+    assert_eq!(
+        Command::unpack(&packed[1..]).unwrap_err(),
+        GameError::UnsupportedVersion.into()
+    );
+}
+
+#[test]
+fn unpack_pubkey_option() {
+    let key = Pubkey::new_unique();
+    let mut input = vec![1];
+    input.extend_from_slice(key.as_ref());
+    input.push(7);
+    let (upline, rest) = Command::unpack_pubkey_option(&input).unwrap();
+    assert_eq!(upline, COption::Some(key));
+    assert_eq!(rest, [7]);
+
+    let (upline, rest) = Command::unpack_pubkey_option(&[0]).unwrap();
+    assert_eq!(upline, COption::None);
+    assert!(rest.is_empty());
+
+    // Some without a full pubkey
+    assert_eq!(
+        Command::unpack_pubkey_option(&[1, 0, 0]).unwrap_err(),
+        ProgramError::InvalidInstructionData
+    );
+}
+
+#[test]
+fn init_builder() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let token_account = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let instruction = instruction::init(
+        &program_id,
+        &admin,
+        &program_account,
+        &token_account,
+        &mint,
+        10,
+        3,
+        50,
+        1_000,
+        5_000,
+        100,
+        [5_000, 2_000, 0],
+    );
+    assert_eq!(instruction.program_id, program_id);
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new(admin, true),
+            AccountMeta::new(program_account, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(token_account, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ]
+    );
+    assert_eq!(
+        Command::unpack(&instruction.data).unwrap(),
+        Command::Init {
+            referral_bonus: 10,
+            max_depth: 3,
+            min_claim: 50,
+            register_fee: 1_000,
+            max_reward_per_player: 5_000,
+            decay_bps_per_day: 100,
+            level_bps: [5_000, 2_000, 0],
+        }
+    );
+}
+
+#[test]
+fn init_without_optional_fields() {
+    // Init data sent before the max depth was added only holds the referral bonus
+    assert_eq!(
+        Command::unpack(&[INSTRUCTION_MAGIC, 0, 10, 0, 0, 0, 0, 0, 0, 0]).unwrap(),
+        Command::Init {
+            referral_bonus: 10,
+            max_depth: DEFAULT_MAX_DEPTH,
+            min_claim: 0,
+            register_fee: 0,
+            max_reward_per_player: 0,
+            decay_bps_per_day: 0,
+            level_bps: DEFAULT_LEVEL_BPS,
+        }
+    );
+    // Before the min claim was added
+    assert_eq!(
+        Command::unpack(&[INSTRUCTION_MAGIC, 0, 10, 0, 0, 0, 0, 0, 0, 0, 3]).unwrap(),
+        Command::Init {
+            referral_bonus: 10,
+            max_depth: 3,
+            min_claim: 0,
+            register_fee: 0,
+            max_reward_per_player: 0,
+            decay_bps_per_day: 0,
+            level_bps: DEFAULT_LEVEL_BPS,
+        }
+    );
+    // Before the register fee was added
+    assert_eq!(
+        Command::unpack(&[
+            INSTRUCTION_MAGIC,
+            0,
+            10,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            3,
+            50,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0
+        ])
+        .unwrap(),
+        Command::Init {
+            referral_bonus: 10,
+            max_depth: 3,
+            min_claim: 50,
+            register_fee: 0,
+            max_reward_per_player: 0,
+            decay_bps_per_day: 0,
+            level_bps: DEFAULT_LEVEL_BPS,
+        }
+    );
+    // Before the max reward per player was added
+    let mut data = vec![INSTRUCTION_MAGIC, 0, 10, 0, 0, 0, 0, 0, 0, 0, 3];
+    data.extend_from_slice(&50_u64.to_le_bytes());
+    data.extend_from_slice(&1_000_u64.to_le_bytes());
+    assert_eq!(
+        Command::unpack(&data).unwrap(),
+        Command::Init {
+            referral_bonus: 10,
+            max_depth: 3,
+            min_claim: 50,
+            register_fee: 1_000,
+            max_reward_per_player: 0,
+            decay_bps_per_day: 0,
+            level_bps: DEFAULT_LEVEL_BPS,
+        }
+    );
+    // Before the decay bps per day was added
+    data.extend_from_slice(&5_000_u64.to_le_bytes());
+    assert_eq!(
+        Command::unpack(&data).unwrap(),
+        Command::Init {
+            referral_bonus: 10,
+            max_depth: 3,
+            min_claim: 50,
+            register_fee: 1_000,
+            max_reward_per_player: 5_000,
+            decay_bps_per_day: 0,
+            level_bps: DEFAULT_LEVEL_BPS,
+        }
+    );
+    // Before the level bps were added
+    data.extend_from_slice(&100_u64.to_le_bytes());
+    assert_eq!(
+        Command::unpack(&data).unwrap(),
+        Command::Init {
+            referral_bonus: 10,
+            max_depth: 3,
+            min_claim: 50,
+            register_fee: 1_000,
+            max_reward_per_player: 5_000,
+            decay_bps_per_day: 100,
+            level_bps: DEFAULT_LEVEL_BPS,
+        }
+    );
+    // The level bps come all three at once
+    data.extend_from_slice(&5_000_u16.to_le_bytes());
+    assert_eq!(
+        Command::unpack(&data).unwrap_err(),
+        ProgramError::InvalidInstructionData
+    );
+}
+
+#[test]
+fn register_builder() {
+    let program_id = Pubkey::new_unique();
+    let holder = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let upline = Pubkey::new_unique();
+    let (player, _) = player_pda(&program_id, &holder);
+    let mut accounts = vec![
+        AccountMeta::new(holder, true),
+        AccountMeta::new(player, false),
+        AccountMeta::new(program_account, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+    ];
+
+    let instruction = instruction::register(&program_id, &holder, &program_account, None, false);
+    assert_eq!(instruction.accounts, accounts);
+    assert_eq!(instruction.data, [INSTRUCTION_MAGIC, 1]);
+    let instruction = instruction::register(&program_id, &holder, &program_account, None, true);
+    assert_eq!(instruction.data, [INSTRUCTION_MAGIC, 1, 1]);
+
+    // The upline is always the last account
+    let instruction =
+        instruction::register(&program_id, &holder, &program_account, Some(&upline), false);
+    accounts.push(AccountMeta::new(upline, false));
+    assert_eq!(instruction.accounts, accounts);
+
+    // The relayer signs and pays, the player is still derived from the holder
+    let relayer = Pubkey::new_unique();
+    let instruction = instruction::relay_register(
+        &program_id,
+        &relayer,
+        &holder,
+        &program_account,
+        Some(&upline),
+        false,
+    );
+    accounts[0] = AccountMeta::new(relayer, true);
+    assert_eq!(instruction.accounts, accounts);
+    let mut data = vec![INSTRUCTION_MAGIC, 1, 0];
+    data.extend_from_slice(holder.as_ref());
+    assert_eq!(instruction.data, data);
+}
+
+#[test]
+fn add_reward_builder() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let player = Pubkey::new_unique();
+    let uplines = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let instruction = instruction::add_reward(
+        &program_id,
+        &admin,
+        &program_account,
+        &player,
+        &uplines,
+        125,
+        false,
+    );
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new(program_account, false),
+            AccountMeta::new(player, false),
+            AccountMeta::new(uplines[0], false),
+            AccountMeta::new(uplines[1], false),
+        ]
+    );
+    assert_eq!(
+        Command::unpack(&instruction.data).unwrap(),
+        Command::AddReward {
+            reward_amount: 125,
+            saturate: false,
+        }
+    );
+}
+
+#[test]
+fn admin_set_reward_builder() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let player = Pubkey::new_unique();
+    let instruction =
+        instruction::admin_set_reward(&program_id, &admin, &program_account, &player, 70);
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new(program_account, false),
+            AccountMeta::new(player, false),
+        ]
+    );
+    assert_eq!(
+        Command::unpack(&instruction.data).unwrap(),
+        Command::AdminSetReward {
+            reward_to_claim: 70
+        }
+    );
+}
+
+#[test]
+fn claim_builder() {
+    let program_id = Pubkey::new_unique();
+    let holder = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let token_account = Pubkey::new_unique();
+    let player_token_account = Pubkey::new_unique();
+    let (player, _) = player_pda(&program_id, &holder);
+    let (pda, _) = vault_pda(&program_id);
+    let instruction = instruction::claim(
+        &program_id,
+        &holder,
+        &program_account,
+        &token_account,
+        &player_token_account,
+        None,
+    );
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new_readonly(holder, true),
+            AccountMeta::new(program_account, false),
+            AccountMeta::new(player, false),
+            AccountMeta::new(token_account, false),
+            AccountMeta::new_readonly(pda, false),
+            AccountMeta::new(player_token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ]
+    );
+    assert_eq!(instruction.data, [INSTRUCTION_MAGIC, 3]);
+
+    let instruction = instruction::claim(
+        &program_id,
+        &holder,
+        &program_account,
+        &token_account,
+        &player_token_account,
+        Some(40),
+    );
+    assert_eq!(
+        Command::unpack(&instruction.data).unwrap(),
+        Command::Claim { amount: Some(40) }
+    );
+
+    // With the Token-2022 program, only the token program account is swapped
+    let instruction = instruction::with_token_program(
+        instruction::claim(
+            &program_id,
+            &holder,
+            &program_account,
+            &token_account,
+            &player_token_account,
+            None,
+        ),
+        &spl_token_2022::id(),
+    );
+    assert_eq!(instruction.program_id, program_id);
+    assert_eq!(
+        instruction.accounts[6],
+        AccountMeta::new_readonly(spl_token_2022::id(), false)
+    );
+    assert_eq!(instruction.accounts[5].pubkey, player_token_account);
+
+    // With the memo, the memo program is passed last
+    let claim_instruction = instruction::claim(
+        &program_id,
+        &holder,
+        &program_account,
+        &token_account,
+        &player_token_account,
+        Some(40),
+    );
+    let instruction = instruction::claim_with_memo(
+        &program_id,
+        &holder,
+        &program_account,
+        &token_account,
+        &player_token_account,
+        Some(40),
+    );
+    assert_eq!(instruction.accounts[..8], claim_instruction.accounts[..]);
+    assert_eq!(
+        instruction.accounts[8],
+        AccountMeta::new_readonly(spl_memo::id(), false)
+    );
+    assert_eq!(instruction.data, claim_instruction.data);
+}
+
+#[test]
+fn player_builders() {
+    let program_id = Pubkey::new_unique();
+    let holder = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+    let (player, _) = player_pda(&program_id, &holder);
+    let accounts = vec![
+        AccountMeta::new_readonly(holder, true),
+        AccountMeta::new(player, false),
+        AccountMeta::new(other, false),
+    ];
+
+    let program_account = Pubkey::new_unique();
+    let instruction = instruction::close_account(&program_id, &holder, &program_account, &other);
+    assert_eq!(instruction.accounts[..3], accounts[..]);
+    assert_eq!(
+        instruction.accounts[3],
+        AccountMeta::new(program_account, false)
+    );
+    assert_eq!(instruction.data, [INSTRUCTION_MAGIC, 4]);
+
+    let instruction =
+        instruction::transfer_reward(&program_id, &holder, &program_account, &other, 40);
+    assert_eq!(instruction.accounts[..3], accounts[..]);
+    assert_eq!(
+        instruction.accounts[3],
+        AccountMeta::new(program_account, false)
+    );
+    assert_eq!(
+        Command::unpack(&instruction.data).unwrap(),
+        Command::TransferReward { amount: 40 }
+    );
+}
+
+#[test]
+fn close_account_with_downlines_builder() {
+    let program_id = Pubkey::new_unique();
+    let holder = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let destination = Pubkey::new_unique();
+    let upline = Pubkey::new_unique();
+    let downlines = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let (player, _) = player_pda(&program_id, &holder);
+
+    // The upline comes first, then the downlines to move to it
+    let instruction = instruction::close_account_with_downlines(
+        &program_id,
+        &holder,
+        &program_account,
+        &destination,
+        Some(&upline),
+        &downlines,
+    );
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new_readonly(holder, true),
+            AccountMeta::new(player, false),
+            AccountMeta::new(destination, false),
+            AccountMeta::new(program_account, false),
+            AccountMeta::new(upline, false),
+            AccountMeta::new(downlines[0], false),
+            AccountMeta::new(downlines[1], false),
+        ]
+    );
+    assert_eq!(instruction.data, [INSTRUCTION_MAGIC, 4]);
+
+    // Without upline and downlines, it is the plain close account
+    let instruction = instruction::close_account_with_downlines(
+        &program_id,
+        &holder,
+        &program_account,
+        &destination,
+        None,
+        &[],
+    );
+    assert_eq!(
+        instruction,
+        instruction::close_account(&program_id, &holder, &program_account, &destination)
+    );
+}
+
+#[test]
+fn set_upline_builder() {
+    let program_id = Pubkey::new_unique();
+    let holder = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let upline = Pubkey::new_unique();
+    let (player, _) = player_pda(&program_id, &holder);
+    let ancestor = Pubkey::new_unique();
+    let instruction =
+        instruction::set_upline(&program_id, &holder, &program_account, &upline, &[ancestor]);
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new_readonly(holder, true),
+            AccountMeta::new(player, false),
+            AccountMeta::new(program_account, false),
+            AccountMeta::new(upline, false),
+            AccountMeta::new_readonly(ancestor, false),
+        ]
+    );
+    assert_eq!(instruction.data, [INSTRUCTION_MAGIC, 11]);
+}
+
+#[test]
+fn set_vault_authority_builder() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let token_account = Pubkey::new_unique();
+    let new_authority = Pubkey::new_unique();
+    let (pda, _) = vault_pda(&program_id);
+    let instruction = instruction::set_vault_authority(
+        &program_id,
+        &admin,
+        &program_account,
+        &token_account,
+        &new_authority,
+    );
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new(program_account, false),
+            AccountMeta::new(token_account, false),
+            AccountMeta::new_readonly(pda, false),
+            AccountMeta::new_readonly(new_authority, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ]
+    );
+    assert_eq!(instruction.data, [INSTRUCTION_MAGIC, 12]);
+}
+
+#[test]
+fn sweep_dust_builder() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let token_account = Pubkey::new_unique();
+    let destination = Pubkey::new_unique();
+    let (pda, _) = vault_pda(&program_id);
+    let instruction = instruction::sweep_dust(
+        &program_id,
+        &admin,
+        &program_account,
+        &token_account,
+        &destination,
+    );
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new(program_account, false),
+            AccountMeta::new(token_account, false),
+            AccountMeta::new_readonly(pda, false),
+            AccountMeta::new(destination, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ]
+    );
+    assert_eq!(instruction.data, [INSTRUCTION_MAGIC, 22]);
+}
+
+#[test]
+fn set_tier_thresholds_builder() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let instruction =
+        instruction::set_tier_thresholds(&program_id, &admin, &program_account, [100, 250, 500]);
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new(program_account, false),
+        ]
+    );
+    assert_eq!(
+        Command::unpack(&instruction.data).unwrap(),
+        Command::SetTierThresholds {
+            tier_thresholds: [100, 250, 500]
+        }
+    );
+}
+
+#[test]
+fn set_referral_bonus_builder() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let instruction = instruction::set_referral_bonus(&program_id, &admin, &program_account, 50);
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new(program_account, false),
+        ]
+    );
+    assert_eq!(
+        Command::unpack(&instruction.data).unwrap(),
+        Command::SetReferralBonus { referral_bonus: 50 }
+    );
+}
+
+#[test]
+fn set_vest_duration_builder() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let instruction = instruction::set_vest_duration(&program_id, &admin, &program_account, 86_400);
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new(program_account, false),
+        ]
+    );
+    assert_eq!(
+        Command::unpack(&instruction.data).unwrap(),
+        Command::SetVestDuration {
+            vest_duration: 86_400
+        }
+    );
+}
+
+#[test]
+fn set_reward_multiplier_builder() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let instruction =
+        instruction::set_reward_multiplier(&program_id, &admin, &program_account, 20_000);
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new(program_account, false),
+        ]
+    );
+    assert_eq!(
+        Command::unpack(&instruction.data).unwrap(),
+        Command::SetRewardMultiplier {
+            reward_multiplier_bps: 20_000
+        }
+    );
+}
+
+#[test]
+fn admin_claim_batch_builder() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let token_account = Pubkey::new_unique();
+    let players = [
+        (Pubkey::new_unique(), Pubkey::new_unique()),
+        (Pubkey::new_unique(), Pubkey::new_unique()),
+    ];
+    let (pda, _) = vault_pda(&program_id);
+    let instruction = instruction::admin_claim_batch(
+        &program_id,
+        &admin,
+        &program_account,
+        &token_account,
+        &players,
+    );
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new(program_account, false),
+            AccountMeta::new(token_account, false),
+            AccountMeta::new_readonly(pda, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new(players[0].0, false),
+            AccountMeta::new(players[0].1, false),
+            AccountMeta::new(players[1].0, false),
+            AccountMeta::new(players[1].1, false),
+        ]
+    );
+    assert_eq!(instruction.data, [INSTRUCTION_MAGIC, 23]);
+}
+
+#[test]
+fn migrate_player_builder() {
+    let program_id = Pubkey::new_unique();
+    let holder = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let (player, _) = player_pda(&program_id, &holder);
+    let instruction = instruction::migrate_player(&program_id, &holder, &program_account, None);
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new(holder, true),
+            AccountMeta::new(player, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(program_account, false),
+        ]
+    );
+    assert_eq!(instruction.data, [INSTRUCTION_MAGIC, 13]);
+
+    // The upline goes last, read only
+    let upline = Pubkey::new_unique();
+    let instruction =
+        instruction::migrate_player(&program_id, &holder, &program_account, Some(&upline));
+    assert_eq!(
+        instruction.accounts[4],
+        AccountMeta::new_readonly(upline, false)
+    );
+}
+
+#[test]
+fn add_remove_admin_builders() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let other_admin = Pubkey::new_unique();
+    let accounts = vec![
+        AccountMeta::new_readonly(admin, true),
+        AccountMeta::new(program_account, false),
+        AccountMeta::new_readonly(other_admin, false),
+    ];
+    let instruction = instruction::add_admin(&program_id, &admin, &program_account, &other_admin);
+    assert_eq!(instruction.accounts, accounts);
+    assert_eq!(instruction.data, [INSTRUCTION_MAGIC, 14]);
+    let instruction =
+        instruction::remove_admin(&program_id, &admin, &program_account, &other_admin);
+    assert_eq!(instruction.accounts, accounts);
+    assert_eq!(instruction.data, [INSTRUCTION_MAGIC, 15]);
+}
+
+#[test]
+fn slash_reward_builder() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let player = Pubkey::new_unique();
+    let instruction = instruction::slash_reward(&program_id, &admin, &program_account, &player, 30);
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new(program_account, false),
+            AccountMeta::new(player, false),
+        ]
+    );
+    let mut data = vec![INSTRUCTION_MAGIC, 16];
+    data.extend_from_slice(&30_u64.to_le_bytes());
+    assert_eq!(instruction.data, data);
+}
+
+#[test]
+fn set_relayer_builder() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let relayer = Pubkey::new_unique();
+    let instruction = instruction::set_relayer(&program_id, &admin, &program_account, &relayer);
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new(program_account, false),
+            AccountMeta::new_readonly(relayer, false),
+        ]
+    );
+    assert_eq!(instruction.data, [INSTRUCTION_MAGIC, 19]);
+}
+
+#[test]
+fn freeze_player_builder() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let player = Pubkey::new_unique();
+    let instruction =
+        instruction::freeze_player(&program_id, &admin, &program_account, &player, true);
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new(program_account, false),
+            AccountMeta::new(player, false),
+        ]
+    );
+    assert_eq!(instruction.data, [INSTRUCTION_MAGIC, 20, 1]);
+}
+
+#[test]
+fn fund_pool_builder() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let instruction = instruction::fund_pool(&program_id, &admin, &program_account, 500);
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new(program_account, false),
+        ]
+    );
+    let mut data = vec![INSTRUCTION_MAGIC, 18];
+    data.extend_from_slice(&500_u64.to_le_bytes());
+    assert_eq!(instruction.data, data);
+}
+
+#[test]
+fn get_reward_builder() {
+    let program_id = Pubkey::new_unique();
+    let player = Pubkey::new_unique();
+    let instruction = instruction::get_reward(&program_id, &player);
+    assert_eq!(
+        instruction.accounts,
+        vec![AccountMeta::new_readonly(player, false)]
+    );
+    assert_eq!(instruction.data, [INSTRUCTION_MAGIC, 17]);
+}
+
+#[test]
+fn export_player_builder() {
+    let program_id = Pubkey::new_unique();
+    let player = Pubkey::new_unique();
+    let instruction = instruction::export_player(&program_id, &player);
+    assert_eq!(
+        instruction.accounts,
+        vec![AccountMeta::new_readonly(player, false)]
+    );
+    assert_eq!(instruction.data, [INSTRUCTION_MAGIC, 21]);
+}
+
+#[test]
+fn set_admin_builder() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let new_admin = Pubkey::new_unique();
+    let instruction = instruction::set_admin(&program_id, &admin, &program_account, &new_admin);
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new(program_account, false),
+            AccountMeta::new_readonly(new_admin, false),
+        ]
+    );
+    assert_eq!(instruction.data, [INSTRUCTION_MAGIC, 5]);
+
+    let instruction = instruction::set_paused(&program_id, &admin, &program_account, true);
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new(program_account, false),
+        ]
+    );
+    assert_eq!(instruction.data, [INSTRUCTION_MAGIC, 8, 1]);
+
+    let instruction = instruction::sweep_fees(&program_id, &admin, &program_account, &new_admin);
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new(program_account, false),
+            AccountMeta::new(new_admin, false),
+        ]
+    );
+    assert_eq!(instruction.data, [INSTRUCTION_MAGIC, 9]);
+}
+
+#[test]
+fn batch_register_builder() {
+    let program_id = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let upline = Pubkey::new_unique();
+    let holders = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let instruction = instruction::batch_register(&program_id, &program_account, &upline, &holders);
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new(program_account, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new(upline, false),
+            AccountMeta::new(holders[0], true),
+            AccountMeta::new(player_pda(&program_id, &holders[0]).0, false),
+            AccountMeta::new(holders[1], true),
+            AccountMeta::new(player_pda(&program_id, &holders[1]).0, false),
+        ]
+    );
+    assert_eq!(instruction.data, [INSTRUCTION_MAGIC, 7]);
+}
@@ -0,0 +1,500 @@
+use learn_solana::{
+    instruction::Command,
+    processor::Processor,
+    state::{GameInfo, Player, Vesting, CURRENT_VERSION, LEGACY_PLAYER_LENS},
+};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::SUCCESS,
+    program_error::ProgramError,
+    program_option::COption,
+    program_pack::Pack,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_program,
+};
+use std::sync::Once;
+
+// The processor is called directly, without the runtime, so the rent sysvar is stubbed
+struct RentSyscallStubs;
+
+impl SyscallStubs for RentSyscallStubs {
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe {
+            *(var_addr as *mut Rent) = Rent::default();
+        }
+        SUCCESS
+    }
+}
+
+static INIT_SYSCALL_STUBS: Once = Once::new();
+
+// The program account every registered player belongs to
+const PROGRAM_ACCOUNT: Pubkey = Pubkey::new_from_array([9; 32]);
+// The upline of every registered player
+const UPLINE: Pubkey = Pubkey::new_from_array([8; 32]);
+
+fn registered_player(holder: &Pubkey) -> Player {
+    Player {
+        is_initialized: true,
+        owner: *holder,
+        reward_to_claim: 70,
+        program_account: PROGRAM_ACCOUNT,
+        upline: COption::Some(UPLINE),
+        downline_count: 2,
+        registered_at: 1_000,
+        last_claim_at: 2_000,
+        total_earned: 90,
+        referral_code: [1; 8],
+        depth: 1,
+        last_credit_at: 3_000,
+        frozen: true,
+        tier: 2,
+        vesting: Vesting {
+            amount: 40,
+            start: 3_000,
+            duration: 600,
+        },
+    }
+}
+
+// Account data laid out the way the runtime serializes it for the program: the data length right before the data,
+// and spare room after the data, which realloc relies on
+fn legacy_player_buffer(player: &Player, legacy_len: usize) -> Vec<u8> {
+    let mut current = vec![0; Player::LEN];
+    Player::pack(player.clone(), &mut current).unwrap();
+    let mut buffer = vec![0; 8 + 2 * Player::LEN];
+    buffer[..8].copy_from_slice(&(legacy_len as u64).to_le_bytes());
+    buffer[8..8 + legacy_len].copy_from_slice(&current[..legacy_len]);
+    buffer
+}
+
+// Run migrate player on the buffer with the upline of the current layout at depth 1
+fn migrate_player(
+    program_id: &Pubkey,
+    holder: &Pubkey,
+    player: &Pubkey,
+    owner: &Pubkey,
+    buffer: &mut [u8],
+) -> Result<Vec<u8>, ProgramError> {
+    let mut upline_data = vec![0; Player::LEN];
+    let upline = Player {
+        upline: COption::None,
+        depth: 1,
+        ..registered_player(&Pubkey::new_unique())
+    };
+    Player::pack(upline, &mut upline_data).unwrap();
+    migrate_player_with_upline(
+        program_id,
+        holder,
+        player,
+        owner,
+        buffer,
+        Some(&mut upline_data),
+    )
+}
+
+// Run migrate player on the buffer, returning the player account data afterward
+// A migration also takes the next nonce of the program account
+fn migrate_player_with_upline(
+    program_id: &Pubkey,
+    holder: &Pubkey,
+    player: &Pubkey,
+    owner: &Pubkey,
+    buffer: &mut [u8],
+    upline_data: Option<&mut [u8]>,
+) -> Result<Vec<u8>, ProgramError> {
+    INIT_SYSCALL_STUBS.call_once(|| {
+        set_syscall_stubs(Box::new(RentSyscallStubs));
+    });
+
+    let (len, data) = buffer.split_at_mut(8);
+    let data_len = u64::from_le_bytes(len.try_into().unwrap()) as usize;
+    let system_program_id = system_program::id();
+    let mut holder_lamports = 0;
+    // Already rent exempt for the current layout, so no top up is needed
+    let mut player_lamports = Rent::default().minimum_balance(Player::LEN);
+    let mut system_program_lamports = 0;
+    let mut program_account_lamports = Rent::default().minimum_balance(GameInfo::LEN);
+    let mut upline_lamports = Rent::default().minimum_balance(Player::LEN);
+    let mut holder_data = [];
+    let mut system_program_data = [];
+    let mut game_info = GameInfo::unpack_unchecked(&[0; GameInfo::LEN]).unwrap();
+    game_info.version = CURRENT_VERSION;
+    game_info.is_initialized = true;
+    game_info.nonce = 5;
+    let mut program_account_data = vec![0; GameInfo::LEN];
+    GameInfo::pack(game_info, &mut program_account_data).unwrap();
+    let mut accounts = vec![
+        AccountInfo::new(
+            holder,
+            true,
+            true,
+            &mut holder_lamports,
+            &mut holder_data,
+            &system_program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            player,
+            false,
+            true,
+            &mut player_lamports,
+            &mut data[..data_len],
+            owner,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &system_program_id,
+            false,
+            false,
+            &mut system_program_lamports,
+            &mut system_program_data,
+            &system_program_id,
+            true,
+            0,
+        ),
+        AccountInfo::new(
+            &PROGRAM_ACCOUNT,
+            false,
+            true,
+            &mut program_account_lamports,
+            &mut program_account_data,
+            program_id,
+            false,
+            0,
+        ),
+    ];
+    if let Some(upline_data) = upline_data {
+        accounts.push(AccountInfo::new(
+            &UPLINE,
+            false,
+            false,
+            &mut upline_lamports,
+            upline_data,
+            program_id,
+            false,
+            0,
+        ));
+    }
+    Processor::process(program_id, &accounts, &Command::MigratePlayer.pack())?;
+    let game_info = GameInfo::unpack(&accounts[3].data.borrow()).unwrap();
+    assert_eq!(game_info.nonce, 6);
+    let migrated = accounts[1].data.borrow().to_vec();
+    Ok(migrated)
+}
+
+#[test]
+fn migrate_player_previous_layouts() {
+    let program_id = Pubkey::new_unique();
+    let holder = Pubkey::new_unique();
+    let player_account = Pubkey::new_unique();
+    let player = registered_player(&holder);
+
+    for legacy_len in LEGACY_PLAYER_LENS {
+        let mut buffer = legacy_player_buffer(&player, legacy_len);
+        let migrated = migrate_player(
+            &program_id,
+            &holder,
+            &player_account,
+            &program_id,
+            &mut buffer,
+        )
+        .unwrap();
+        assert_eq!(migrated.len(), Player::LEN);
+
+        // Fields of the oldest layout are always preserved
+        let migrated = Player::unpack(&migrated).unwrap();
+        assert!(migrated.is_initialized);
+        assert_eq!(migrated.owner, player.owner);
+        assert_eq!(migrated.reward_to_claim, player.reward_to_claim);
+        assert_eq!(migrated.program_account, player.program_account);
+        assert_eq!(migrated.upline, player.upline);
+        // None of them had the vesting, only the last one had the tier and the last two the frozen flag
+        assert_eq!(migrated.vesting, Vesting::default());
+        assert_eq!(
+            migrated.tier,
+            if legacy_len == 156 { player.tier } else { 0 }
+        );
+        assert_eq!(migrated.frozen, legacy_len >= 155 && player.frozen);
+    }
+}
+
+#[test]
+fn migrate_player_defaults() {
+    let program_id = Pubkey::new_unique();
+    let holder = Pubkey::new_unique();
+    let player_account = Pubkey::new_unique();
+    let player = registered_player(&holder);
+
+    // Before downline_count was added, every later field gets its default
+    let mut buffer = legacy_player_buffer(&player, 109);
+    let migrated = migrate_player(
+        &program_id,
+        &holder,
+        &player_account,
+        &program_id,
+        &mut buffer,
+    )
+    .unwrap();
+    assert_eq!(
+        Player::unpack(&migrated).unwrap(),
+        Player {
+            downline_count: 0,
+            registered_at: 0,
+            last_claim_at: 0,
+            total_earned: 0,
+            referral_code: Player::referral_code_of(&player_account),
+            depth: 2,
+            last_credit_at: 0,
+            frozen: false,
+            tier: 0,
+            vesting: Vesting::default(),
+            ..player
+        }
+    );
+
+    // Before depth was added, only the depth and the last credit are new, the depth is one below the upline
+    let mut buffer = legacy_player_buffer(&player, 145);
+    let migrated = migrate_player(
+        &program_id,
+        &holder,
+        &player_account,
+        &program_id,
+        &mut buffer,
+    )
+    .unwrap();
+    assert_eq!(
+        Player::unpack(&migrated).unwrap(),
+        Player {
+            depth: 2,
+            last_credit_at: 0,
+            frozen: false,
+            tier: 0,
+            vesting: Vesting::default(),
+            ..player
+        }
+    );
+
+    // Before last_credit_at was added
+    let mut buffer = legacy_player_buffer(&player, 146);
+    let migrated = migrate_player(
+        &program_id,
+        &holder,
+        &player_account,
+        &program_id,
+        &mut buffer,
+    )
+    .unwrap();
+    assert_eq!(
+        Player::unpack(&migrated).unwrap(),
+        Player {
+            last_credit_at: 0,
+            frozen: false,
+            tier: 0,
+            vesting: Vesting::default(),
+            ..player
+        }
+    );
+
+    // Before frozen was added
+    let mut buffer = legacy_player_buffer(&player, 154);
+    let migrated = migrate_player(
+        &program_id,
+        &holder,
+        &player_account,
+        &program_id,
+        &mut buffer,
+    )
+    .unwrap();
+    assert_eq!(
+        Player::unpack(&migrated).unwrap(),
+        Player {
+            frozen: false,
+            tier: 0,
+            vesting: Vesting::default(),
+            ..player
+        }
+    );
+
+    // Before tier was added, the tier is recomputed on the next credit
+    let mut buffer = legacy_player_buffer(&player, 155);
+    let migrated = migrate_player(
+        &program_id,
+        &holder,
+        &player_account,
+        &program_id,
+        &mut buffer,
+    )
+    .unwrap();
+    assert_eq!(
+        Player::unpack(&migrated).unwrap(),
+        Player {
+            tier: 0,
+            vesting: Vesting::default(),
+            ..player
+        }
+    );
+
+    // Before vesting was added, nothing is locked
+    let mut buffer = legacy_player_buffer(&player, 156);
+    let migrated = migrate_player(
+        &program_id,
+        &holder,
+        &player_account,
+        &program_id,
+        &mut buffer,
+    )
+    .unwrap();
+    assert_eq!(
+        Player::unpack(&migrated).unwrap(),
+        Player {
+            vesting: Vesting::default(),
+            ..player
+        }
+    );
+
+    // The account now has the current layout, it cannot be migrated twice
+    assert_eq!(
+        migrate_player(
+            &program_id,
+            &holder,
+            &player_account,
+            &program_id,
+            &mut buffer
+        ),
+        Err(ProgramError::Custom(21))
+    );
+}
+
+#[test]
+fn migrate_player_invalid_accounts() {
+    let program_id = Pubkey::new_unique();
+    let holder = Pubkey::new_unique();
+    let player_account = Pubkey::new_unique();
+    let player = registered_player(&holder);
+
+    let mut buffer = legacy_player_buffer(&player, 145);
+    assert_eq!(
+        migrate_player(
+            &program_id,
+            &Pubkey::new_unique(),
+            &player_account,
+            &program_id,
+            &mut buffer,
+        ),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+
+    assert_eq!(
+        migrate_player(
+            &program_id,
+            &holder,
+            &player_account,
+            &Pubkey::new_unique(),
+            &mut buffer,
+        ),
+        Err(ProgramError::IncorrectProgramId)
+    );
+
+    // Not the size of any layout
+    let mut buffer = legacy_player_buffer(&player, 100);
+    assert_eq!(
+        migrate_player(
+            &program_id,
+            &holder,
+            &player_account,
+            &program_id,
+            &mut buffer
+        ),
+        Err(ProgramError::InvalidAccountData)
+    );
+}
+
+#[test]
+fn migrate_player_depth() {
+    let program_id = Pubkey::new_unique();
+    let holder = Pubkey::new_unique();
+    let player_account = Pubkey::new_unique();
+    let player = registered_player(&holder);
+    let migrated_depth = |migrated: Vec<u8>| Player::unpack(&migrated).unwrap().depth;
+
+    // A player at the top of the chain stays at depth 0, without any upline account
+    let top_player = Player {
+        upline: COption::None,
+        ..player
+    };
+    let mut buffer = legacy_player_buffer(&top_player, 145);
+    let migrated = migrate_player_with_upline(
+        &program_id,
+        &holder,
+        &player_account,
+        &program_id,
+        &mut buffer,
+        None,
+    )
+    .unwrap();
+    assert_eq!(migrated_depth(migrated), 0);
+
+    // A player with upline needs the upline account
+    let mut buffer = legacy_player_buffer(&player, 145);
+    assert_eq!(
+        migrate_player_with_upline(
+            &program_id,
+            &holder,
+            &player_account,
+            &program_id,
+            &mut buffer,
+            None,
+        ),
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+
+    // The account passed must be the player upline
+    let other_upline_player = Player {
+        upline: COption::Some(Pubkey::new_unique()),
+        ..player
+    };
+    let mut buffer = legacy_player_buffer(&other_upline_player, 145);
+    assert_eq!(
+        migrate_player(
+            &program_id,
+            &holder,
+            &player_account,
+            &program_id,
+            &mut buffer
+        ),
+        Err(ProgramError::Custom(3))
+    );
+
+    // An upline without depth which has an upline of its own does not know its depth yet
+    let mut upline_data = legacy_player_buffer(&player, 145)[8..8 + 145].to_vec();
+    let mut buffer = legacy_player_buffer(&player, 145);
+    assert_eq!(
+        migrate_player_with_upline(
+            &program_id,
+            &holder,
+            &player_account,
+            &program_id,
+            &mut buffer,
+            Some(&mut upline_data),
+        ),
+        Err(ProgramError::Custom(3))
+    );
+
+    // One at the top of the chain is at depth 0
+    let mut upline_data = legacy_player_buffer(&top_player, 145)[8..8 + 145].to_vec();
+    let migrated = migrate_player_with_upline(
+        &program_id,
+        &holder,
+        &player_account,
+        &program_id,
+        &mut buffer,
+        Some(&mut upline_data),
+    )
+    .unwrap();
+    assert_eq!(migrated_depth(migrated), 1);
+}
@@ -0,0 +1,1078 @@
+use learn_solana::{
+    error::GameError,
+    instruction::Command,
+    processor::{distribute, game_info_pda, player_pda, require_admin, Processor},
+    state::{GameInfo, Player, Vesting, CURRENT_VERSION},
+};
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::{ProgramResult, SUCCESS},
+    instruction::Instruction,
+    native_token::LAMPORTS_PER_SOL,
+    program::get_return_data,
+    program_error::ProgramError,
+    program_option::COption,
+    program_pack::Pack,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction::SystemInstruction,
+    system_program,
+    sysvar::{self, Sysvar},
+};
+use solana_sdk::program_utils::limited_deserialize;
+use std::sync::Once;
+
+// Program test of this version drops the return data and cannot grow an account created through CPI in native mode,
+// so the processor is called directly and the syscall stubs stand in for the runtime, as the program calling them
+struct ProcessorSyscallStubs {
+    program_id: Pubkey,
+    return_data: Vec<u8>,
+}
+
+impl SyscallStubs for ProcessorSyscallStubs {
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        Some((self.program_id, self.return_data.clone()))
+    }
+    fn sol_set_return_data(&mut self, data: &[u8]) {
+        self.return_data = data.to_vec();
+    }
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe {
+            *(var_addr as *mut Rent) = Rent::default();
+        }
+        SUCCESS
+    }
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe {
+            *(var_addr as *mut Clock) = Clock::default();
+        }
+        SUCCESS
+    }
+    // Only the system program create account, transfer, allocate and assign are supported, checked the way the runtime does
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        assert_eq!(instruction.program_id, system_program::id());
+        let account_info = |index: usize| {
+            account_infos
+                .iter()
+                .find(|account_info| *account_info.key == instruction.accounts[index].pubkey)
+                .unwrap()
+        };
+        // An account signs itself, or through the seeds of the calling program
+        let signed = |account: &AccountInfo| {
+            account.is_signer
+                || signers_seeds.iter().any(|seeds| {
+                    Pubkey::create_program_address(seeds, &self.program_id) == Ok(*account.key)
+                })
+        };
+        // Only an empty system account can be allocated and assigned
+        let check_unallocated = |account: &AccountInfo| {
+            if account.data_len() != 0 || !system_program::check_id(account.owner) {
+                return Err(ProgramError::AccountAlreadyInitialized);
+            }
+            Ok(())
+        };
+
+        match limited_deserialize(&instruction.data).unwrap() {
+            SystemInstruction::CreateAccount {
+                lamports,
+                space,
+                owner,
+            } => {
+                let (from, to) = (account_info(0), account_info(1));
+                if !from.is_signer || !signed(to) {
+                    return Err(ProgramError::MissingRequiredSignature);
+                }
+                if to.lamports() != 0 {
+                    return Err(ProgramError::AccountAlreadyInitialized);
+                }
+                check_unallocated(to)?;
+                **from.try_borrow_mut_lamports()? -= lamports;
+                **to.try_borrow_mut_lamports()? += lamports;
+                to.realloc(space as usize, true)?;
+                to.assign(&owner);
+            }
+            SystemInstruction::Transfer { lamports } => {
+                let (from, to) = (account_info(0), account_info(1));
+                if !from.is_signer {
+                    return Err(ProgramError::MissingRequiredSignature);
+                }
+                **from.try_borrow_mut_lamports()? -= lamports;
+                **to.try_borrow_mut_lamports()? += lamports;
+            }
+            SystemInstruction::Allocate { space } => {
+                let account = account_info(0);
+                if !signed(account) {
+                    return Err(ProgramError::MissingRequiredSignature);
+                }
+                check_unallocated(account)?;
+                account.realloc(space as usize, true)?;
+            }
+            SystemInstruction::Assign { owner } => {
+                let account = account_info(0);
+                if !signed(account) {
+                    return Err(ProgramError::MissingRequiredSignature);
+                }
+                account.assign(&owner);
+            }
+            other => panic!("Unsupported system instruction {:?}", other),
+        }
+        Ok(())
+    }
+}
+
+// Every test of the binary shares the syscall stubs, which run as this program id
+const STUB_PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+static INIT_SYSCALL_STUBS: Once = Once::new();
+
+fn init_syscall_stubs() {
+    INIT_SYSCALL_STUBS.call_once(|| {
+        set_syscall_stubs(Box::new(ProcessorSyscallStubs {
+            program_id: STUB_PROGRAM_ID,
+            return_data: vec![],
+        }));
+    });
+}
+
+// Program account data of an initialized game, administrated by admin
+fn game_info_data(admin: &Pubkey) -> Vec<u8> {
+    let game_info = GameInfo {
+        version: CURRENT_VERSION,
+        is_initialized: true,
+        admins: [*admin, Pubkey::default(), Pubkey::default()],
+        spl_token_account: Pubkey::new_unique(),
+        total_players: 0,
+        referral_bonus: 0,
+        paused: false,
+        max_depth: 0,
+        mint: Pubkey::new_unique(),
+        min_claim: 0,
+        register_fee: 0,
+        max_reward_per_player: 0,
+        nonce: 0,
+        decay_bps_per_day: 0,
+        matching_pool: 0,
+        relayer: Pubkey::default(),
+        vault_bump: 0,
+        level_bps: [0; 3],
+        dust: 0,
+        tier_thresholds: [0; 3],
+        vest_duration: 0,
+        reward_multiplier_bps: 10_000,
+        token_program: spl_token::id(),
+    };
+    let mut data = vec![0; GameInfo::LEN];
+    GameInfo::pack(game_info, &mut data).unwrap();
+    data
+}
+
+// Player account data of a registered player of the program account, with no reward yet
+fn player_data(program_account: &Pubkey, upline: COption<Pubkey>) -> Vec<u8> {
+    let player = Player {
+        is_initialized: true,
+        owner: Pubkey::new_unique(),
+        reward_to_claim: 0,
+        program_account: *program_account,
+        upline,
+        downline_count: 0,
+        registered_at: 0,
+        last_claim_at: 0,
+        total_earned: 0,
+        referral_code: [0; 8],
+        depth: 0,
+        last_credit_at: 0,
+        frozen: false,
+        tier: 0,
+        vesting: Vesting::default(),
+    };
+    let mut data = vec![0; Player::LEN];
+    Player::pack(player, &mut data).unwrap();
+    data
+}
+
+// The processor methods only need the accounts, so they run without the program test runtime
+#[test]
+fn process_set_paused() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let system_program_id = system_program::id();
+    let (mut admin_lamports, mut program_account_lamports) = (0, 0);
+    let mut admin_data = [];
+    let mut program_account_data = game_info_data(&admin);
+    let accounts = [
+        AccountInfo::new(
+            &admin,
+            true,
+            false,
+            &mut admin_lamports,
+            &mut admin_data,
+            &system_program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &program_account,
+            false,
+            true,
+            &mut program_account_lamports,
+            &mut program_account_data,
+            &program_id,
+            false,
+            0,
+        ),
+    ];
+
+    Processor::process_set_paused(&program_id, &accounts, true).unwrap();
+    let game_info = GameInfo::unpack(&accounts[1].data.borrow()).unwrap();
+    assert!(game_info.paused);
+
+    Processor::process_set_paused(&program_id, &accounts, false).unwrap();
+    let game_info = GameInfo::unpack(&accounts[1].data.borrow()).unwrap();
+    assert!(!game_info.paused);
+
+    // Another program owning the program account
+    let other_program_id = Pubkey::new_unique();
+    assert_eq!(
+        Processor::process_set_paused(&other_program_id, &accounts, true),
+        Err(ProgramError::IncorrectProgramId)
+    );
+}
+
+#[test]
+fn process_set_paused_not_admin() {
+    let program_id = Pubkey::new_unique();
+    let holder = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let system_program_id = system_program::id();
+    let (mut holder_lamports, mut program_account_lamports) = (0, 0);
+    let mut holder_data = [];
+    let mut program_account_data = game_info_data(&Pubkey::new_unique());
+    let accounts = [
+        AccountInfo::new(
+            &holder,
+            true,
+            false,
+            &mut holder_lamports,
+            &mut holder_data,
+            &system_program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &program_account,
+            false,
+            true,
+            &mut program_account_lamports,
+            &mut program_account_data,
+            &program_id,
+            false,
+            0,
+        ),
+    ];
+
+    assert_eq!(
+        Processor::process_set_paused(&program_id, &accounts, true),
+        Err(ProgramError::Custom(6)) // GameError::InvalidAdmin
+    );
+    let game_info = GameInfo::unpack(&accounts[1].data.borrow()).unwrap();
+    assert!(!game_info.paused);
+}
+
+#[test]
+fn require_admin_signer_and_key() {
+    let admin = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+    let game_info = GameInfo::unpack(&game_info_data(&admin)).unwrap();
+    let system_program_id = system_program::id();
+    for (key, is_signer, expected) in [
+        (admin, true, Ok(())),
+        (admin, false, Err(GameError::InvalidAdmin.into())),
+        (other, true, Err(GameError::InvalidAdmin.into())),
+        // The free admin slots hold the default pubkey, which is never an admin
+        (Pubkey::default(), true, Err(GameError::InvalidAdmin.into())),
+    ] {
+        let (mut lamports, mut data) = (0, []);
+        let account = AccountInfo::new(
+            &key,
+            is_signer,
+            false,
+            &mut lamports,
+            &mut data,
+            &system_program_id,
+            false,
+            0,
+        );
+        assert_eq!(require_admin(&account, &game_info), expected, "{}", key);
+    }
+}
+
+#[test]
+fn distribute_zero_amount() {
+    assert_eq!(distribute(0, &[]), vec![0]);
+    assert_eq!(distribute(0, &[1000, 500, 250]), vec![0, 0, 0, 0]);
+}
+
+#[test]
+fn distribute_single_level() {
+    // Without upline the player gets everything
+    assert_eq!(distribute(1000, &[]), vec![1000]);
+    assert_eq!(distribute(1000, &[2500]), vec![250, 750]);
+    // A level can take the whole amount, never more
+    assert_eq!(distribute(1000, &[10_000]), vec![1000, 0]);
+    assert_eq!(distribute(1000, &[u16::MAX]), vec![1000, 0]);
+    assert_eq!(distribute(u64::MAX, &[10_000]), vec![u64::MAX, 0]);
+}
+
+#[test]
+fn distribute_rounding() {
+    // Every share is rounded down, the rest is the dust
+    for (amount, level_bps, expected, dust) in [
+        (999, vec![1000, 500, 250], vec![99, 49, 24, 824], 3),
+        (7, vec![3333, 3333, 3333], vec![2, 2, 2, 0], 1),
+        (1, vec![5000], vec![0, 0], 1),
+        (10_000, vec![1000, 500, 250], vec![1000, 500, 250, 8250], 0),
+        // The last level only takes what is left of the denominator
+        (100, vec![6000, 6000, 6000], vec![60, 40, 0, 0], 0),
+    ] {
+        let shares = distribute(amount, &level_bps);
+        assert_eq!(shares, expected, "{} {:?}", amount, level_bps);
+        assert_eq!(amount - shares.iter().sum::<u64>(), dust);
+    }
+}
+
+#[test]
+fn process_wrong_magic() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let system_program_id = system_program::id();
+    let (mut admin_lamports, mut program_account_lamports) = (0, 0);
+    let mut admin_data = [];
+    let mut program_account_data = game_info_data(&admin);
+    let accounts = [
+        AccountInfo::new(
+            &admin,
+            true,
+            false,
+            &mut admin_lamports,
+            &mut admin_data,
+            &system_program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &program_account,
+            false,
+            true,
+            &mut program_account_lamports,
+            &mut program_account_data,
+            &program_id,
+            false,
+            0,
+        ),
+    ];
+
+    // The instruction is rejected before it is dispatched, the program account is left untouched
+    let mut instruction_data = Command::SetPaused { paused: true }.pack();
+    instruction_data[0] ^= 0xff;
+    assert_eq!(
+        Processor::process(&program_id, &accounts, &instruction_data),
+        Err(GameError::UnsupportedVersion.into())
+    );
+    assert!(!GameInfo::unpack(&accounts[1].data.borrow()).unwrap().paused);
+
+    instruction_data[0] ^= 0xff;
+    Processor::process(&program_id, &accounts, &instruction_data).unwrap();
+    assert!(GameInfo::unpack(&accounts[1].data.borrow()).unwrap().paused);
+}
+
+#[test]
+fn process_get_reward() {
+    init_syscall_stubs();
+
+    let program_id = STUB_PROGRAM_ID;
+    let player = Pubkey::new_unique();
+    let mut player_lamports = 0;
+    let mut player_data = vec![0; Player::LEN];
+    Player::pack(
+        Player {
+            is_initialized: true,
+            owner: Pubkey::new_unique(),
+            reward_to_claim: 1_234,
+            program_account: Pubkey::new_unique(),
+            upline: COption::None,
+            downline_count: 0,
+            registered_at: 0,
+            last_claim_at: 0,
+            total_earned: 1_234,
+            referral_code: [0; 8],
+            depth: 0,
+            last_credit_at: 0,
+            frozen: false,
+            tier: 0,
+            vesting: Vesting::default(),
+        },
+        &mut player_data,
+    )
+    .unwrap();
+    let accounts = [AccountInfo::new(
+        &player,
+        false,
+        false,
+        &mut player_lamports,
+        &mut player_data,
+        &program_id,
+        false,
+        0,
+    )];
+
+    Processor::process(&program_id, &accounts, &Command::GetReward.pack()).unwrap();
+    assert_eq!(
+        get_return_data(),
+        Some((program_id, 1_234_u64.to_le_bytes().to_vec()))
+    );
+    // Read only, the player account is left as is
+    let player_state = Player::unpack(&accounts[0].data.borrow()).unwrap();
+    assert_eq!(player_state.reward_to_claim, 1_234);
+
+    // A player account of another program
+    assert_eq!(
+        Processor::process(&Pubkey::new_unique(), &accounts, &Command::GetReward.pack()),
+        Err(ProgramError::IncorrectProgramId)
+    );
+}
+
+#[test]
+fn process_register_creates_player_account() {
+    init_syscall_stubs();
+
+    let program_id = STUB_PROGRAM_ID;
+    let holder = Pubkey::new_unique();
+    let (player, _) = player_pda(&program_id, &holder);
+    let program_account = Pubkey::new_unique();
+    let system_program_id = system_program::id();
+    let (clock_id, sysvar_id) = (sysvar::clock::id(), sysvar::id());
+    // Owner of the player account, which the system program assigns to the program
+    let player_owner = system_program::id();
+    let (mut holder_lamports, mut player_lamports, mut program_account_lamports) =
+        (LAMPORTS_PER_SOL, 0, 0);
+    let (mut system_program_lamports, mut clock_lamports) = (0, 0);
+    let mut holder_data = [];
+    // The player account does not exist, only the length right before its data and room to grow are laid out,
+    // the way the runtime serializes the accounts for the program
+    let mut player_buffer = vec![0; 8 + Player::LEN];
+    let mut program_account_data = game_info_data(&Pubkey::new_unique());
+    let mut system_program_data = [];
+    let mut clock_data = vec![0; Clock::size_of()];
+    let mut accounts = [
+        AccountInfo::new(
+            &holder,
+            true,
+            true,
+            &mut holder_lamports,
+            &mut holder_data,
+            &system_program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &player,
+            false,
+            true,
+            &mut player_lamports,
+            &mut player_buffer[8..8],
+            &player_owner,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &program_account,
+            false,
+            true,
+            &mut program_account_lamports,
+            &mut program_account_data,
+            &program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &system_program_id,
+            false,
+            false,
+            &mut system_program_lamports,
+            &mut system_program_data,
+            &system_program_id,
+            true,
+            0,
+        ),
+        AccountInfo::new(
+            &clock_id,
+            false,
+            false,
+            &mut clock_lamports,
+            &mut clock_data,
+            &sysvar_id,
+            false,
+            0,
+        ),
+    ];
+    Clock {
+        unix_timestamp: 1_650_000_000,
+        ..Clock::default()
+    }
+    .to_account_info(&mut accounts[4])
+    .unwrap();
+
+    let register = Command::Register {
+        idempotent: false,
+        holder: None,
+    }
+    .pack();
+    Processor::process(&program_id, &accounts, &register).unwrap();
+
+    // Created with the player size, owned by the program and made rent exempt by the holder
+    let rent_exempt_minimum = Rent::default().minimum_balance(Player::LEN);
+    assert_eq!(accounts[1].data_len(), Player::LEN);
+    assert_eq!(*accounts[1].owner, program_id);
+    assert_eq!(accounts[1].lamports(), rent_exempt_minimum);
+    assert_eq!(
+        accounts[0].lamports(),
+        LAMPORTS_PER_SOL - rent_exempt_minimum
+    );
+    let player_state = Player::unpack(&accounts[1].data.borrow()).unwrap();
+    assert_eq!(player_state.owner, holder);
+    assert_eq!(player_state.program_account, program_account);
+    assert_eq!(player_state.registered_at, 1_650_000_000);
+
+    // Once created and registered, it is not created again
+    assert_eq!(
+        Processor::process(&program_id, &accounts, &register),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+}
+
+#[test]
+fn process_register_prefunded_player_account() {
+    init_syscall_stubs();
+
+    let program_id = STUB_PROGRAM_ID;
+    let holder = Pubkey::new_unique();
+    let (player, _) = player_pda(&program_id, &holder);
+    let program_account = Pubkey::new_unique();
+    let system_program_id = system_program::id();
+    let (clock_id, sysvar_id) = (sysvar::clock::id(), sysvar::id());
+    let player_owner = system_program::id();
+    // Someone sent a lamport to the player PDA before the holder registered
+    let (mut holder_lamports, mut player_lamports, mut program_account_lamports) =
+        (LAMPORTS_PER_SOL, 1, 0);
+    let (mut system_program_lamports, mut clock_lamports) = (0, 0);
+    let mut holder_data = [];
+    let mut player_buffer = vec![0; 8 + Player::LEN];
+    let mut program_account_data = game_info_data(&Pubkey::new_unique());
+    let mut system_program_data = [];
+    let mut clock_data = vec![0; Clock::size_of()];
+    let accounts = [
+        AccountInfo::new(
+            &holder,
+            true,
+            true,
+            &mut holder_lamports,
+            &mut holder_data,
+            &system_program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &player,
+            false,
+            true,
+            &mut player_lamports,
+            &mut player_buffer[8..8],
+            &player_owner,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &program_account,
+            false,
+            true,
+            &mut program_account_lamports,
+            &mut program_account_data,
+            &program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &system_program_id,
+            false,
+            false,
+            &mut system_program_lamports,
+            &mut system_program_data,
+            &system_program_id,
+            true,
+            0,
+        ),
+        AccountInfo::new(
+            &clock_id,
+            false,
+            false,
+            &mut clock_lamports,
+            &mut clock_data,
+            &sysvar_id,
+            false,
+            0,
+        ),
+    ];
+
+    let register = Command::Register {
+        idempotent: false,
+        holder: None,
+    }
+    .pack();
+    Processor::process(&program_id, &accounts, &register).unwrap();
+
+    // The holder only tops the lamport up to the rent exempt minimum
+    let rent_exempt_minimum = Rent::default().minimum_balance(Player::LEN);
+    assert_eq!(accounts[1].data_len(), Player::LEN);
+    assert_eq!(*accounts[1].owner, program_id);
+    assert_eq!(accounts[1].lamports(), rent_exempt_minimum);
+    assert_eq!(
+        accounts[0].lamports(),
+        LAMPORTS_PER_SOL - (rent_exempt_minimum - 1)
+    );
+    let player_state = Player::unpack(&accounts[1].data.borrow()).unwrap();
+    assert_eq!(player_state.owner, holder);
+}
+
+#[test]
+fn process_init_prefunded_game_info_account() {
+    init_syscall_stubs();
+
+    let program_id = STUB_PROGRAM_ID;
+    let admin = Pubkey::new_unique();
+    let (game_info, _) = game_info_pda(&program_id);
+    let token_account = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let system_program_id = system_program::id();
+    let token_program_id = spl_token::id();
+    let game_info_owner = system_program::id();
+    // The token account is not owned by the token program, so the init stops right after creating the game info
+    let token_account_owner = Pubkey::new_unique();
+    // Someone sent a lamport to the game info PDA before the admin initialized the game
+    let (mut admin_lamports, mut game_info_lamports, mut system_program_lamports) =
+        (LAMPORTS_PER_SOL, 1, 0);
+    let (mut token_account_lamports, mut mint_lamports, mut token_program_lamports) = (0, 0, 0);
+    let mut admin_data = [];
+    let mut game_info_buffer = vec![0; 8 + GameInfo::LEN];
+    let mut system_program_data = [];
+    let mut token_account_data = [];
+    let mut mint_data = [];
+    let mut token_program_data = [];
+    let accounts = [
+        AccountInfo::new(
+            &admin,
+            true,
+            true,
+            &mut admin_lamports,
+            &mut admin_data,
+            &system_program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &game_info,
+            false,
+            true,
+            &mut game_info_lamports,
+            &mut game_info_buffer[8..8],
+            &game_info_owner,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &system_program_id,
+            false,
+            false,
+            &mut system_program_lamports,
+            &mut system_program_data,
+            &system_program_id,
+            true,
+            0,
+        ),
+        AccountInfo::new(
+            &token_account,
+            false,
+            true,
+            &mut token_account_lamports,
+            &mut token_account_data,
+            &token_account_owner,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &mint,
+            false,
+            false,
+            &mut mint_lamports,
+            &mut mint_data,
+            &token_program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &token_program_id,
+            false,
+            false,
+            &mut token_program_lamports,
+            &mut token_program_data,
+            &token_program_id,
+            true,
+            0,
+        ),
+    ];
+
+    let init = Command::Init {
+        referral_bonus: 0,
+        max_depth: 0,
+        min_claim: 0,
+        register_fee: 0,
+        max_reward_per_player: 0,
+        decay_bps_per_day: 0,
+        level_bps: [0; 3],
+    }
+    .pack();
+    assert_eq!(
+        Processor::process(&program_id, &accounts, &init),
+        Err(ProgramError::IllegalOwner)
+    );
+
+    // The admin only tops the lamport up to the rent exempt minimum
+    let rent_exempt_minimum = Rent::default().minimum_balance(GameInfo::LEN);
+    assert_eq!(accounts[1].data_len(), GameInfo::LEN);
+    assert_eq!(*accounts[1].owner, program_id);
+    assert_eq!(accounts[1].lamports(), rent_exempt_minimum);
+    assert_eq!(
+        accounts[0].lamports(),
+        LAMPORTS_PER_SOL - (rent_exempt_minimum - 1)
+    );
+}
+
+#[test]
+fn process_add_reward_split_rounding() {
+    init_syscall_stubs();
+
+    let program_id = STUB_PROGRAM_ID;
+    let admin = Pubkey::new_unique();
+    let program_account = Pubkey::new_unique();
+    let system_program_id = system_program::id();
+    // Chain of the player and its three uplines, from the player up
+    let keys = [
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    ];
+    let mut admin_lamports = 0;
+    let mut admin_data = [];
+    let mut program_account_lamports = 0;
+    let mut program_account_data = game_info_data(&admin);
+    let mut game_info = GameInfo::unpack(&program_account_data).unwrap();
+    game_info.level_bps = [2_500, 1_500, 1_000];
+    GameInfo::pack(game_info, &mut program_account_data).unwrap();
+    let mut player_lamports = [0; 4];
+    let mut player_datas: Vec<Vec<u8>> = (0..4)
+        .map(|level| {
+            let upline = keys
+                .get(level + 1)
+                .copied()
+                .map_or(COption::None, COption::Some);
+            player_data(&program_account, upline)
+        })
+        .collect();
+
+    let mut accounts = vec![
+        AccountInfo::new(
+            &admin,
+            true,
+            false,
+            &mut admin_lamports,
+            &mut admin_data,
+            &system_program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &program_account,
+            false,
+            true,
+            &mut program_account_lamports,
+            &mut program_account_data,
+            &program_id,
+            false,
+            0,
+        ),
+    ];
+    for ((key, lamports), data) in keys
+        .iter()
+        .zip(player_lamports.iter_mut())
+        .zip(player_datas.iter_mut())
+    {
+        accounts.push(AccountInfo::new(
+            key,
+            false,
+            true,
+            lamports,
+            data,
+            &program_id,
+            false,
+            0,
+        ));
+    }
+
+    let rewards = |accounts: &[AccountInfo]| -> Vec<u64> {
+        accounts[2..]
+            .iter()
+            .map(|account| {
+                Player::unpack(&account.data.borrow())
+                    .unwrap()
+                    .reward_to_claim
+            })
+            .collect()
+    };
+    let dust = |accounts: &[AccountInfo]| -> u64 {
+        GameInfo::unpack(&accounts[1].data.borrow()).unwrap().dust
+    };
+
+    // Odd amounts, where every level share rounds down
+    let mut rounded_off = 0;
+    let odd_amounts = (1..200).step_by(2).chain([999, 10_001, 123_456_789]);
+    for reward_amount in odd_amounts {
+        let before = rewards(&accounts);
+        let dust_before = dust(&accounts);
+        Processor::process(
+            &program_id,
+            &accounts,
+            &Command::AddReward {
+                reward_amount,
+                saturate: false,
+            }
+            .pack(),
+        )
+        .unwrap();
+        let increments: Vec<u64> = rewards(&accounts)
+            .iter()
+            .zip(before)
+            .map(|(after, before)| after - before)
+            .collect();
+
+        // Every level, the player's included, gets its share rounded down
+        let shares: Vec<u64> = [5_000, 2_500, 1_500, 1_000]
+            .iter()
+            .map(|bps| reward_amount * bps / 10_000)
+            .collect();
+        assert_eq!(increments, shares, "{}", reward_amount);
+        // Nothing is created nor lost by the split, what is rounded off goes to the dust
+        let reward_dust = dust(&accounts) - dust_before;
+        assert_eq!(
+            increments.iter().sum::<u64>() + reward_dust,
+            reward_amount,
+            "{}",
+            reward_amount
+        );
+        rounded_off += reward_amount - shares.iter().sum::<u64>();
+    }
+    assert_eq!(dust(&accounts), rounded_off);
+
+    // 999 split as 499, 249, 149 and 99, with 3 left as dust
+    let before = rewards(&accounts);
+    Processor::process(
+        &program_id,
+        &accounts,
+        &Command::AddReward {
+            reward_amount: 999,
+            saturate: false,
+        }
+        .pack(),
+    )
+    .unwrap();
+    let increments: Vec<u64> = rewards(&accounts)
+        .iter()
+        .zip(before)
+        .map(|(after, before)| after - before)
+        .collect();
+    assert_eq!(increments, vec![499, 249, 149, 99]);
+    assert_eq!(dust(&accounts), rounded_off + 3);
+}
+
+#[test]
+fn process_close_account_total_players_underflow() {
+    let program_id = Pubkey::new_unique();
+    let holder = Pubkey::new_unique();
+    let (player, _) = player_pda(&program_id, &holder);
+    let program_account = Pubkey::new_unique();
+    let destination = Pubkey::new_unique();
+    let system_program_id = system_program::id();
+    let (mut holder_lamports, mut player_lamports) = (0, LAMPORTS_PER_SOL);
+    let (mut destination_lamports, mut program_account_lamports) = (0, 0);
+    let (mut holder_data, mut destination_data) = ([], []);
+    let mut player_account_data = player_data(&program_account, COption::None);
+    let mut player_state = Player::unpack(&player_account_data).unwrap();
+    player_state.owner = holder;
+    Player::pack(player_state, &mut player_account_data).unwrap();
+    // A program account which already counts no player, the counter has drifted
+    let mut program_account_data = game_info_data(&Pubkey::new_unique());
+    let accounts = [
+        AccountInfo::new(
+            &holder,
+            true,
+            false,
+            &mut holder_lamports,
+            &mut holder_data,
+            &system_program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &player,
+            false,
+            true,
+            &mut player_lamports,
+            &mut player_account_data,
+            &program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &destination,
+            false,
+            true,
+            &mut destination_lamports,
+            &mut destination_data,
+            &system_program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &program_account,
+            false,
+            true,
+            &mut program_account_lamports,
+            &mut program_account_data,
+            &program_id,
+            false,
+            0,
+        ),
+    ];
+
+    // The counter is not wrapped around, and the player account is left open
+    assert_eq!(
+        Processor::process(&program_id, &accounts, &Command::CloseAccount.pack()),
+        Err(GameError::RewardAmountOverflow.into())
+    );
+    assert_eq!(accounts[1].lamports(), LAMPORTS_PER_SOL);
+    assert_eq!(accounts[2].lamports(), 0);
+    let game_info = GameInfo::unpack(&accounts[3].data.borrow()).unwrap();
+    assert_eq!(game_info.total_players, 0);
+}
+
+#[test]
+fn process_set_upline_requires_full_chain() {
+    let program_id = Pubkey::new_unique();
+    let holder = Pubkey::new_unique();
+    let (player, _) = player_pda(&program_id, &holder);
+    let program_account = Pubkey::new_unique();
+    let (upline, ancestor) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let system_program_id = system_program::id();
+    let (mut holder_lamports, mut holder_data) = (0, []);
+    let mut lamports = [0; 4];
+    let mut player_account_data = player_data(&program_account, COption::None);
+    let mut player_state = Player::unpack(&player_account_data).unwrap();
+    // Like migrated legacy players, the player and its upline both have no registration time
+    player_state.owner = holder;
+    player_state.registered_at = 0;
+    Player::pack(player_state, &mut player_account_data).unwrap();
+    let mut program_account_data = game_info_data(&Pubkey::new_unique());
+    let mut game_info = GameInfo::unpack(&program_account_data).unwrap();
+    game_info.max_depth = 3;
+    GameInfo::pack(game_info, &mut program_account_data).unwrap();
+    // The upline has an upline of its own, at the top of the chain
+    let mut upline_data = player_data(&program_account, COption::Some(ancestor));
+    let mut ancestor_data = player_data(&program_account, COption::None);
+    let [player_lamports, program_account_lamports, upline_lamports, ancestor_lamports] =
+        &mut lamports;
+    let mut accounts = vec![
+        AccountInfo::new(
+            &holder,
+            true,
+            false,
+            &mut holder_lamports,
+            &mut holder_data,
+            &system_program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &player,
+            false,
+            true,
+            player_lamports,
+            &mut player_account_data,
+            &program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &program_account,
+            false,
+            true,
+            program_account_lamports,
+            &mut program_account_data,
+            &program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &upline,
+            false,
+            true,
+            upline_lamports,
+            &mut upline_data,
+            &program_id,
+            false,
+            0,
+        ),
+    ];
+    let ancestor_account = AccountInfo::new(
+        &ancestor,
+        false,
+        false,
+        ancestor_lamports,
+        &mut ancestor_data,
+        &program_id,
+        false,
+        0,
+    );
+
+    // Without the account above the upline the cycle walk cannot be skipped
+    assert_eq!(
+        Processor::process(&program_id, &accounts, &Command::SetUpline.pack()),
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+    assert_eq!(
+        Player::unpack(&accounts[1].data.borrow()).unwrap().upline,
+        COption::None
+    );
+
+    accounts.push(ancestor_account);
+    Processor::process(&program_id, &accounts, &Command::SetUpline.pack()).unwrap();
+    let player_state = Player::unpack(&accounts[1].data.borrow()).unwrap();
+    assert_eq!(player_state.upline, COption::Some(upline));
+    assert_eq!(player_state.depth, 1);
+}
@@ -0,0 +1,534 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use learn_solana::{
+    error::GameError,
+    state::{GameInfo, Player, Vesting, CURRENT_VERSION, DECAY_PERIOD_SECS, LEGACY_PLAYER_LENS},
+};
+use solana_program::{
+    program_error::ProgramError, program_option::COption, program_pack::Pack, pubkey::Pubkey,
+};
+
+// Number of random states generated per struct
+const ITERATIONS: usize = 1000;
+
+// Small xorshift generator, seeded so that a failure can be reproduced
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn next_bool(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+
+    fn next_pubkey(&mut self) -> Pubkey {
+        let mut bytes = [0; 32];
+        for chunk in bytes.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next_u64().to_le_bytes());
+        }
+        Pubkey::new_from_array(bytes)
+    }
+
+    // Mostly random values, with the boundaries showing up regularly
+    fn next_amount(&mut self) -> u64 {
+        match self.next_u64() % 4 {
+            0 => 0,
+            1 => u64::MAX,
+            _ => self.next_u64(),
+        }
+    }
+}
+
+fn random_game_info(rng: &mut Rng) -> GameInfo {
+    GameInfo {
+        version: (rng.next_u64() % (CURRENT_VERSION as u64 + 1)) as u8,
+        is_initialized: rng.next_bool(),
+        admins: [rng.next_pubkey(), rng.next_pubkey(), rng.next_pubkey()],
+        spl_token_account: rng.next_pubkey(),
+        total_players: rng.next_amount(),
+        referral_bonus: rng.next_amount(),
+        paused: rng.next_bool(),
+        max_depth: rng.next_u64() as u8,
+        mint: rng.next_pubkey(),
+        min_claim: rng.next_amount(),
+        register_fee: rng.next_amount(),
+        max_reward_per_player: rng.next_amount(),
+        nonce: rng.next_u64(),
+        decay_bps_per_day: rng.next_amount(),
+        matching_pool: rng.next_amount(),
+        relayer: rng.next_pubkey(),
+        vault_bump: rng.next_u64() as u8,
+        level_bps: [(); 3].map(|_| rng.next_u64() as u16),
+        dust: rng.next_u64(),
+        tier_thresholds: [(); 3].map(|_| rng.next_u64()),
+        vest_duration: rng.next_u64(),
+        reward_multiplier_bps: rng.next_u64() as u16,
+        token_program: rng.next_pubkey(),
+    }
+}
+
+fn random_player(rng: &mut Rng) -> Player {
+    Player {
+        is_initialized: rng.next_bool(),
+        owner: rng.next_pubkey(),
+        reward_to_claim: rng.next_amount(),
+        program_account: rng.next_pubkey(),
+        upline: if rng.next_bool() {
+            COption::Some(rng.next_pubkey())
+        } else {
+            COption::None
+        },
+        downline_count: rng.next_amount() as u32,
+        registered_at: rng.next_amount() as i64,
+        last_claim_at: rng.next_amount() as i64,
+        total_earned: rng.next_amount(),
+        referral_code: rng.next_amount().to_le_bytes(),
+        depth: rng.next_u64() as u8,
+        last_credit_at: rng.next_amount() as i64,
+        frozen: rng.next_bool(),
+        tier: rng.next_u64() as u8,
+        vesting: Vesting {
+            amount: rng.next_amount(),
+            start: rng.next_amount() as i64,
+            duration: rng.next_u64(),
+        },
+    }
+}
+
+#[test]
+fn game_info_pack_unpack_round_trip() {
+    let mut rng = Rng(0x5eed_0001);
+    for _ in 0..ITERATIONS {
+        let game_info = random_game_info(&mut rng);
+        // Start from a dirty buffer, pack must overwrite every byte
+        let mut data = vec![0xff; GameInfo::LEN];
+        game_info.pack_into_slice(&mut data);
+        assert_eq!(GameInfo::unpack_unchecked(&data).unwrap(), game_info);
+    }
+}
+
+#[test]
+fn player_pack_unpack_round_trip() {
+    let mut rng = Rng(0x5eed_0002);
+    for _ in 0..ITERATIONS {
+        let player = random_player(&mut rng);
+        let mut data = vec![0; Player::LEN];
+        player.pack_into_slice(&mut data);
+        assert_eq!(Player::unpack_unchecked(&data).unwrap(), player);
+    }
+}
+
+#[test]
+fn player_upline_edge_cases() {
+    let mut rng = Rng(0x5eed_0003);
+    let player = random_player(&mut rng);
+    for upline in [
+        COption::None,
+        COption::Some(Pubkey::default()),
+        COption::Some(Pubkey::new_from_array([0xff; 32])),
+    ] {
+        // Pack None after Some into the same buffer, the stale upline bytes must not leak back
+        let mut data = vec![0; Player::LEN];
+        Player {
+            upline: COption::Some(rng.next_pubkey()),
+            ..player.clone()
+        }
+        .pack_into_slice(&mut data);
+        let player = Player {
+            upline,
+            ..player.clone()
+        };
+        player.pack_into_slice(&mut data);
+        assert_eq!(Player::unpack_unchecked(&data).unwrap(), player);
+    }
+}
+
+#[test]
+fn player_invalid_upline_tag() {
+    let mut rng = Rng(0x5eed_0005);
+    let player = Player {
+        upline: COption::Some(rng.next_pubkey()),
+        ..random_player(&mut rng)
+    };
+    // The 4 byte tag of the upline follows is_initialized, owner, reward_to_claim and program_account
+    let tag_offset = 1 + 32 + 8 + 32;
+    for tag in [[2, 0, 0, 0], [0, 1, 0, 0], [1, 0, 0, 1], [0xff; 4]] {
+        let mut data = vec![0; Player::LEN];
+        player.pack_into_slice(&mut data);
+        data[tag_offset..tag_offset + 4].copy_from_slice(&tag);
+        assert_eq!(
+            Player::unpack_unchecked(&data).unwrap_err(),
+            ProgramError::InvalidAccountData
+        );
+    }
+}
+
+#[test]
+fn packed_len_matches() {
+    // Every field away from its default, with the upline set, so each byte of the layout is used
+    let player = Player {
+        is_initialized: true,
+        owner: Pubkey::new_from_array([1; 32]),
+        reward_to_claim: u64::MAX,
+        program_account: Pubkey::new_from_array([2; 32]),
+        upline: COption::Some(Pubkey::new_from_array([3; 32])),
+        downline_count: u32::MAX,
+        registered_at: i64::MAX,
+        last_claim_at: i64::MIN,
+        total_earned: u64::MAX,
+        referral_code: [4; 8],
+        depth: u8::MAX,
+        last_credit_at: i64::MAX,
+        frozen: true,
+        tier: u8::MAX,
+        vesting: Vesting {
+            amount: u64::MAX,
+            start: i64::MIN,
+            duration: u64::MAX,
+        },
+    };
+    let mut data = vec![0u8; Player::LEN];
+    Player::pack(player.clone(), &mut data).unwrap();
+    assert_eq!(Player::unpack_unchecked(&data).unwrap(), player);
+
+    let game_info = GameInfo {
+        version: CURRENT_VERSION,
+        is_initialized: true,
+        admins: [
+            Pubkey::new_from_array([1; 32]),
+            Pubkey::new_from_array([2; 32]),
+            Pubkey::new_from_array([3; 32]),
+        ],
+        spl_token_account: Pubkey::new_from_array([4; 32]),
+        total_players: u64::MAX,
+        referral_bonus: u64::MAX,
+        paused: true,
+        max_depth: u8::MAX,
+        mint: Pubkey::new_from_array([5; 32]),
+        min_claim: u64::MAX,
+        register_fee: u64::MAX,
+        max_reward_per_player: u64::MAX,
+        nonce: u64::MAX,
+        decay_bps_per_day: u64::MAX,
+        matching_pool: u64::MAX,
+        relayer: Pubkey::new_from_array([6; 32]),
+        vault_bump: u8::MAX,
+        level_bps: [u16::MAX; 3],
+        dust: u64::MAX,
+        tier_thresholds: [u64::MAX; 3],
+        vest_duration: u64::MAX,
+        reward_multiplier_bps: u16::MAX,
+        token_program: Pubkey::new_from_array([7; 32]),
+    };
+    // The Borsh layout must fill LEN exactly, neither shorter nor longer
+    assert_eq!(game_info.try_to_vec().unwrap().len(), GameInfo::LEN);
+    let mut data = vec![0u8; GameInfo::LEN];
+    GameInfo::pack(game_info.clone(), &mut data).unwrap();
+    assert_eq!(GameInfo::unpack(&data).unwrap(), game_info);
+}
+
+#[test]
+fn unpack_wrong_size() {
+    // Only the sizes of the previous layouts are accepted besides the current one
+    for len in [0, LEGACY_PLAYER_LENS[0] - 1, 150, Player::LEN + 1] {
+        assert_eq!(
+            Player::unpack_unchecked(&vec![0; len]).unwrap_err(),
+            ProgramError::InvalidAccountData
+        );
+    }
+    for len in [0, GameInfo::LEN - 1, GameInfo::LEN + 1] {
+        assert_eq!(
+            GameInfo::unpack_unchecked(&vec![0; len]).unwrap_err(),
+            ProgramError::InvalidAccountData
+        );
+    }
+}
+
+#[test]
+fn player_unpack_previous_layouts() {
+    let mut rng = Rng(0x5eed_0006);
+    let player = Player {
+        is_initialized: true,
+        ..random_player(&mut rng)
+    };
+    let mut data = vec![0; Player::LEN];
+    Player::pack(player.clone(), &mut data).unwrap();
+
+    // The current layout reads every field
+    assert_eq!(Player::unpack(&data).unwrap(), player);
+
+    // A previous layout reads the fields it has, the ones appended since get their default
+    assert_eq!(
+        Player::unpack(&data[..156]).unwrap(),
+        Player {
+            vesting: Vesting::default(),
+            ..player
+        }
+    );
+    assert_eq!(
+        Player::unpack(&data[..155]).unwrap(),
+        Player {
+            tier: 0,
+            vesting: Vesting::default(),
+            ..player
+        }
+    );
+    assert_eq!(
+        Player::unpack(&data[..154]).unwrap(),
+        Player {
+            frozen: false,
+            tier: 0,
+            vesting: Vesting::default(),
+            ..player
+        }
+    );
+    for legacy_len in LEGACY_PLAYER_LENS {
+        let unpacked = Player::unpack(&data[..legacy_len]).unwrap();
+        assert_eq!(unpacked.owner, player.owner);
+        assert_eq!(unpacked.reward_to_claim, player.reward_to_claim);
+        assert_eq!(unpacked.upline, player.upline);
+        assert_eq!(unpacked.vesting, Vesting::default());
+    }
+
+    // Packing still needs the current layout, the account has to be migrated first
+    assert_eq!(
+        Player::pack(player, &mut data[..155]).unwrap_err(),
+        ProgramError::InvalidAccountData
+    );
+}
+
+#[test]
+fn player_referral_code() {
+    // The code is part of what players share, it must never change for a given player account
+    let player = Pubkey::new_from_array([1; 32]);
+    assert_eq!(
+        Player::referral_code_of(&player),
+        [114, 205, 110, 132, 34, 196, 7, 251]
+    );
+    assert_ne!(
+        Player::referral_code_of(&player),
+        Player::referral_code_of(&Pubkey::new_from_array([2; 32]))
+    );
+}
+
+#[test]
+fn player_accrued_decay() {
+    let mut rng = Rng(0x5eed_0004);
+    let player = Player {
+        reward_to_claim: 1_000,
+        last_credit_at: 1_000_000,
+        ..random_player(&mut rng)
+    };
+    let credited_at = player.last_credit_at;
+
+    // 5% per whole day, a started day does not count
+    for (elapsed_secs, decay) in [
+        (0, 0),
+        (DECAY_PERIOD_SECS - 1, 0),
+        (DECAY_PERIOD_SECS, 50),
+        (3 * DECAY_PERIOD_SECS + 1, 150),
+        (20 * DECAY_PERIOD_SECS, 1_000),
+        (100 * DECAY_PERIOD_SECS, 1_000),
+    ] {
+        assert_eq!(player.accrued_decay(500, credited_at + elapsed_secs), decay);
+    }
+    assert_eq!(player.accrued_decay(0, credited_at + DECAY_PERIOD_SECS), 0);
+    // A clock behind the last credit does not decay
+    assert_eq!(
+        player.accrued_decay(500, credited_at - DECAY_PERIOD_SECS),
+        0
+    );
+
+    // Large values saturate at the reward to claim instead of overflowing
+    let player = Player {
+        reward_to_claim: u64::MAX,
+        last_credit_at: 1,
+        ..player
+    };
+    assert_eq!(player.accrued_decay(u64::MAX, i64::MAX), u64::MAX);
+    assert_eq!(player.accrued_decay(u64::MAX, i64::MIN), 0);
+
+    // Never credited since last_credit_at was added
+    let player = Player {
+        last_credit_at: 0,
+        ..player
+    };
+    assert_eq!(player.accrued_decay(u64::MAX, i64::MAX), 0);
+}
+
+#[test]
+fn player_update_tier() {
+    let mut rng = Rng(0x5eed_0005);
+    let mut player = random_player(&mut rng);
+    for (total_earned, tier) in [(0, 0), (99, 0), (100, 1), (249, 1), (250, 2), (u64::MAX, 3)] {
+        player.total_earned = total_earned;
+        player.update_tier(&[100, 250, 500]);
+        assert_eq!(player.tier, tier, "{}", total_earned);
+    }
+
+    // A disabled tier is never reached, nor the ones after it
+    player.update_tier(&[100, 0, 0]);
+    assert_eq!(player.tier, 1);
+    player.update_tier(&[0; 3]);
+    assert_eq!(player.tier, 0);
+}
+
+#[test]
+fn player_credit_reward_saturating() {
+    let mut rng = Rng(0x5eed_0008);
+    let mut player = Player {
+        reward_to_claim: 900,
+        total_earned: 900,
+        ..random_player(&mut rng)
+    };
+
+    // Above the cap credit_reward rejects the whole amount, the saturating credit keeps the part below it
+    assert_eq!(
+        player.credit_reward(200, 1_000, 5).unwrap_err(),
+        GameError::RewardCapExceeded
+    );
+    assert_eq!(player.credit_reward_saturating(200, 1_000, 5).unwrap(), 100);
+    assert_eq!(player.reward_to_claim, 1_000);
+    assert_eq!(player.total_earned, 1_000);
+    assert_eq!(player.last_credit_at, 5);
+
+    // Nothing more fits at the cap, or above it after the cap was lowered
+    assert_eq!(player.credit_reward_saturating(50, 1_000, 6).unwrap(), 0);
+    assert_eq!(player.credit_reward_saturating(50, 800, 6).unwrap(), 0);
+    assert_eq!(player.reward_to_claim, 1_000);
+
+    // Without a cap the whole amount is credited
+    assert_eq!(player.credit_reward_saturating(50, 0, 7).unwrap(), 50);
+    assert_eq!(player.reward_to_claim, 1_050);
+    player.reward_to_claim = u64::MAX;
+    assert_eq!(
+        player.credit_reward_saturating(1, 0, 8).unwrap_err(),
+        GameError::RewardAmountOverflow
+    );
+}
+
+#[test]
+fn game_info_multiply_reward() {
+    let mut rng = Rng(0x5eed_0009);
+    let mut game_info = random_game_info(&mut rng);
+    for (reward_multiplier_bps, amount, multiplied_amount) in [
+        (10_000, 1_234, 1_234),
+        (20_000, 1_234, 2_468),
+        (15_000, 3, 4),
+        (5_000, 1, 0),
+        (10_000, u64::MAX, u64::MAX),
+        (u16::MAX, 0, 0),
+    ] {
+        game_info.reward_multiplier_bps = reward_multiplier_bps;
+        assert_eq!(
+            game_info.multiply_reward(amount).unwrap(),
+            multiplied_amount,
+            "{} {}",
+            reward_multiplier_bps,
+            amount
+        );
+    }
+    // A boosted amount which does not fit a u64 is rejected
+    game_info.reward_multiplier_bps = 20_000;
+    assert_eq!(
+        game_info.multiply_reward(u64::MAX / 2 + 1).unwrap_err(),
+        GameError::RewardAmountOverflow
+    );
+}
+
+#[test]
+fn vesting_locked() {
+    let vesting = Vesting {
+        amount: 1_000,
+        start: 10_000,
+        duration: 400,
+    };
+    for (now, locked) in [
+        // A clock behind the start has nothing vested yet
+        (0, 1_000),
+        (10_000, 1_000),
+        (10_001, 998),
+        (10_100, 750),
+        (10_200, 500),
+        (10_399, 3),
+        (10_400, 0),
+        (i64::MAX, 0),
+    ] {
+        assert_eq!(vesting.locked(now), locked, "{}", now);
+    }
+    // Nothing is locked without a duration, or for the largest amount once elapsed
+    assert_eq!(Vesting::default().locked(0), 0);
+    let vesting = Vesting {
+        amount: u64::MAX,
+        start: 0,
+        duration: u64::MAX,
+    };
+    assert_eq!(vesting.locked(i64::MAX), u64::MAX - u64::MAX / 2);
+}
+
+#[test]
+fn player_vest_and_claimable() {
+    let mut rng = Rng(0x5eed_0007);
+    let mut player = Player {
+        reward_to_claim: 0,
+        vesting: Vesting::default(),
+        ..random_player(&mut rng)
+    };
+
+    // Without a vest duration the reward is claimable right away
+    player.reward_to_claim = 100;
+    player.vest(100, 0, 1_000).unwrap();
+    assert_eq!(player.vesting, Vesting::default());
+    assert_eq!(player.claimable(1_000), 100);
+
+    // The next reward unlocks over 400 seconds
+    player.reward_to_claim += 400;
+    player.vest(400, 400, 1_000).unwrap();
+    assert_eq!(player.claimable(1_000), 100);
+    assert_eq!(player.claimable(1_100), 200);
+    assert_eq!(player.claimable(1_400), 500);
+
+    // Halfway through, a credit restarts the 200 still locked together with it
+    player.reward_to_claim += 200;
+    player.vest(200, 400, 1_200).unwrap();
+    assert_eq!(
+        player.vesting,
+        Vesting {
+            amount: 400,
+            start: 1_200,
+            duration: 400,
+        }
+    );
+    assert_eq!(player.claimable(1_200), 300);
+    assert_eq!(player.claimable(1_400), 500);
+    assert_eq!(player.claimable(1_600), 700);
+
+    // A decayed reward below the locked amount has nothing claimable
+    player.reward_to_claim = 100;
+    assert_eq!(player.claimable(1_200), 0);
+}
+
+#[test]
+fn game_info_borsh_layout() {
+    // Pack is a wrapper around Borsh, both must produce and accept the same bytes
+    let mut rng = Rng(0x5eed_0003);
+    for _ in 0..ITERATIONS {
+        let game_info = random_game_info(&mut rng);
+        let mut data = vec![0; GameInfo::LEN];
+        GameInfo::pack(game_info.clone(), &mut data).unwrap();
+        assert_eq!(game_info.try_to_vec().unwrap(), data);
+        assert_eq!(GameInfo::try_from_slice(&data).unwrap(), game_info);
+    }
+
+    // Booleans other than 0 or 1 are still rejected
+    let mut data = vec![0; GameInfo::LEN];
+    data[1] = 2;
+    assert_eq!(
+        GameInfo::unpack_unchecked(&data).unwrap_err(),
+        ProgramError::InvalidAccountData
+    );
+}
@@ -0,0 +1,387 @@
+// Golden vectors of the instruction data and account layouts, the clients encode and decode the same bytes
+// A failure here means the wire format changed, which breaks every deployed client, update them together
+use learn_solana::{
+    instruction::{self, Command},
+    state::{GameInfo, Player, Vesting},
+};
+use solana_program::{
+    instruction::AccountMeta, program_option::COption, program_pack::Pack, pubkey::Pubkey,
+    system_program, sysvar,
+};
+use std::str::FromStr;
+
+fn hex(input: &str) -> Vec<u8> {
+    (0..input.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&input[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn key(byte: u8) -> Pubkey {
+    Pubkey::new_from_array([byte; 32])
+}
+
+fn key_hex(byte: u8) -> String {
+    format!("{:02x}", byte).repeat(32)
+}
+
+#[test]
+fn command_vectors() {
+    let vectors = [
+        (
+            Command::Init {
+                referral_bonus: 10,
+                max_depth: 3,
+                min_claim: 50,
+                register_fee: 1_000,
+                max_reward_per_player: 500,
+                decay_bps_per_day: 100,
+                level_bps: [2_500, 1_500, 1_000],
+            },
+            [
+                "00",
+                "0a00000000000000", // referral_bonus
+                "03",               // max_depth
+                "3200000000000000", // min_claim
+                "e803000000000000", // register_fee
+                "f401000000000000", // max_reward_per_player
+                "6400000000000000", // decay_bps_per_day
+                "c409dc05e803",     // level_bps
+            ]
+            .concat(),
+        ),
+        (
+            Command::Register {
+                idempotent: false,
+                holder: None,
+            },
+            "01".to_string(),
+        ),
+        (
+            Command::Register {
+                idempotent: true,
+                holder: None,
+            },
+            "0101".to_string(),
+        ),
+        (
+            Command::Register {
+                idempotent: false,
+                holder: Some(key(2)),
+            },
+            ["0100", &key_hex(2)].concat(),
+        ),
+        (
+            Command::AddReward {
+                reward_amount: 100,
+                saturate: false,
+            },
+            "026400000000000000".to_string(),
+        ),
+        (
+            Command::AddReward {
+                reward_amount: 100,
+                saturate: true,
+            },
+            "02640000000000000001".to_string(),
+        ),
+        (Command::Claim { amount: None }, "03".to_string()),
+        (
+            Command::Claim { amount: Some(70) },
+            "034600000000000000".to_string(),
+        ),
+        (Command::CloseAccount, "04".to_string()),
+        (Command::SetAdmin, "05".to_string()),
+        (
+            Command::TransferReward { amount: 25 },
+            "061900000000000000".to_string(),
+        ),
+        (Command::BatchRegister, "07".to_string()),
+        (Command::SetPaused { paused: true }, "0801".to_string()),
+        (Command::SweepFees, "09".to_string()),
+        (
+            Command::AdminSetReward {
+                reward_to_claim: 500,
+            },
+            "0af401000000000000".to_string(),
+        ),
+        (Command::SetUpline, "0b".to_string()),
+        (Command::SetVaultAuthority, "0c".to_string()),
+        (Command::MigratePlayer, "0d".to_string()),
+        (Command::AddAdmin, "0e".to_string()),
+        (Command::RemoveAdmin, "0f".to_string()),
+        (
+            Command::SlashReward { amount: 30 },
+            "101e00000000000000".to_string(),
+        ),
+        (Command::GetReward, "11".to_string()),
+        (
+            Command::FundPool { amount: 1_000 },
+            "12e803000000000000".to_string(),
+        ),
+        (Command::SetRelayer, "13".to_string()),
+        (Command::FreezePlayer { frozen: true }, "1401".to_string()),
+        (Command::ExportPlayer, "15".to_string()),
+        (Command::SweepDust, "16".to_string()),
+        (Command::AdminClaimBatch, "17".to_string()),
+        (
+            Command::SetTierThresholds {
+                tier_thresholds: [100, 1_000, 10_000],
+            },
+            [
+                "18",
+                "6400000000000000", // bronze
+                "e803000000000000", // silver
+                "1027000000000000", // gold
+            ]
+            .concat(),
+        ),
+        (
+            Command::SetReferralBonus { referral_bonus: 50 },
+            "193200000000000000".to_string(),
+        ),
+        (
+            Command::SetVestDuration {
+                vest_duration: 86_400,
+            },
+            "1a8051010000000000".to_string(),
+        ),
+        (
+            Command::SetRewardMultiplier {
+                reward_multiplier_bps: 20_000,
+            },
+            "1b204e".to_string(),
+        ),
+    ];
+    for (command, expected) in vectors {
+        // Every instruction starts with the magic
+        let expected = hex(&["4c", &expected].concat());
+        assert_eq!(command.pack(), expected, "{:?}", command);
+        assert_eq!(Command::unpack(&expected).unwrap(), command);
+    }
+}
+
+// Program id and holder of the builder vectors, the derived addresses below are fixed by them
+fn builder_keys() -> (Pubkey, Pubkey) {
+    (key(1), key(2))
+}
+
+#[test]
+fn init_vector() {
+    let (program_id, admin) = builder_keys();
+    let program_account = Pubkey::from_str("gNds7BMTu9de96Ey9sB1nLq2EZVkvMKUavWG8XWCuZa").unwrap();
+    let instruction = instruction::init(
+        &program_id,
+        &admin,
+        &program_account,
+        &key(3),
+        &key(4),
+        10,
+        3,
+        50,
+        1_000,
+        500,
+        100,
+        [2_500, 1_500, 1_000],
+    );
+    assert_eq!(
+        instruction.data,
+        hex(&[
+            "4c",
+            "00",
+            "0a00000000000000",
+            "03",
+            "3200000000000000",
+            "e803000000000000",
+            "f401000000000000",
+            "6400000000000000",
+            "c409dc05e803",
+        ]
+        .concat())
+    );
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new(admin, true),
+            AccountMeta::new(program_account, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(key(3), false),
+            AccountMeta::new_readonly(key(4), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ]
+    );
+}
+
+#[test]
+fn register_vectors() {
+    let (program_id, holder) = builder_keys();
+    let player = Pubkey::from_str("FKPP8dqoF4ZXhTfgFX9PuRp9v95FWorjwXmB4f7PHbJL").unwrap();
+    let mut accounts = vec![
+        AccountMeta::new(holder, true),
+        AccountMeta::new(player, false),
+        AccountMeta::new(key(3), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+    ];
+
+    // Without upline
+    let instruction = instruction::register(&program_id, &holder, &key(3), None, false);
+    assert_eq!(instruction.data, hex("4c01"));
+    assert_eq!(instruction.accounts, accounts);
+
+    // With upline, passed last
+    let instruction = instruction::register(&program_id, &holder, &key(3), Some(&key(4)), false);
+    accounts.push(AccountMeta::new(key(4), false));
+    assert_eq!(instruction.data, hex("4c01"));
+    assert_eq!(instruction.accounts, accounts);
+}
+
+#[test]
+fn claim_vectors() {
+    let (program_id, holder) = builder_keys();
+    let player = Pubkey::from_str("FKPP8dqoF4ZXhTfgFX9PuRp9v95FWorjwXmB4f7PHbJL").unwrap();
+    let vault = Pubkey::from_str("EiNMjB6bUHkoFq8JjA8RKyAXjkNyuNt7PqnLME2u7ptj").unwrap();
+    let accounts = vec![
+        AccountMeta::new_readonly(holder, true),
+        AccountMeta::new(key(3), false),
+        AccountMeta::new(player, false),
+        AccountMeta::new(key(4), false),
+        AccountMeta::new_readonly(vault, false),
+        AccountMeta::new(key(5), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+    ];
+
+    let instruction = instruction::claim(&program_id, &holder, &key(3), &key(4), &key(5), None);
+    assert_eq!(instruction.data, hex("4c03"));
+    assert_eq!(instruction.accounts, accounts);
+
+    let instruction = instruction::claim(&program_id, &holder, &key(3), &key(4), &key(5), Some(70));
+    assert_eq!(instruction.data, hex("4c034600000000000000"));
+    assert_eq!(instruction.accounts, accounts);
+}
+
+#[test]
+fn player_vector() {
+    let player = Player {
+        is_initialized: true,
+        owner: key(2),
+        reward_to_claim: 125,
+        program_account: key(3),
+        upline: COption::Some(key(4)),
+        downline_count: 2,
+        registered_at: 1_650_000_000,
+        last_claim_at: 0,
+        total_earned: 250,
+        referral_code: [1, 2, 3, 4, 5, 6, 7, 8],
+        depth: 1,
+        last_credit_at: 1_650_000_000,
+        frozen: false,
+        tier: 1,
+        vesting: Vesting {
+            amount: 100,
+            start: 1_650_000_000,
+            duration: 86_400,
+        },
+    };
+    let expected = hex(&[
+        "01",               // is_initialized
+        &key_hex(2),        // owner
+        "7d00000000000000", // reward_to_claim
+        &key_hex(3),        // program_account
+        "01000000",         // upline tag
+        &key_hex(4),        // upline
+        "02000000",         // downline_count
+        "8000596200000000", // registered_at
+        "0000000000000000", // last_claim_at
+        "fa00000000000000", // total_earned
+        "0102030405060708", // referral_code
+        "01",               // depth
+        "8000596200000000", // last_credit_at
+        "00",               // frozen
+        "01",               // tier
+        "6400000000000000", // vesting amount
+        "8000596200000000", // vesting start
+        "8051010000000000", // vesting duration
+    ]
+    .concat());
+    let mut data = vec![0; Player::LEN];
+    Player::pack(player.clone(), &mut data).unwrap();
+    assert_eq!(data, expected);
+    assert_eq!(Player::unpack(&expected).unwrap(), player);
+
+    // Without upline the tag is 0 and the pubkey bytes are zero
+    let mut expected = expected;
+    expected[73..109].fill(0);
+    let player = Player {
+        upline: COption::None,
+        ..player
+    };
+    let mut data = vec![0; Player::LEN];
+    Player::pack(player.clone(), &mut data).unwrap();
+    assert_eq!(data, expected);
+    assert_eq!(Player::unpack(&expected).unwrap(), player);
+}
+
+#[test]
+fn game_info_vector() {
+    let game_info = GameInfo {
+        version: 19,
+        is_initialized: true,
+        admins: [key(5), key(6), Pubkey::default()],
+        spl_token_account: key(7),
+        total_players: 3,
+        referral_bonus: 10,
+        paused: false,
+        max_depth: 3,
+        mint: key(8),
+        min_claim: 50,
+        register_fee: 1_000,
+        max_reward_per_player: 500,
+        nonce: 7,
+        decay_bps_per_day: 100,
+        matching_pool: 40,
+        relayer: key(9),
+        vault_bump: 255,
+        level_bps: [2_500, 1_500, 1_000],
+        dust: 3,
+        tier_thresholds: [100, 1_000, 10_000],
+        vest_duration: 86_400,
+        reward_multiplier_bps: 20_000,
+        token_program: key(10),
+    };
+    let expected = hex(&[
+        "13",               // version
+        "01",               // is_initialized
+        &key_hex(5),        // admins
+        &key_hex(6),        //
+        &key_hex(0),        //
+        &key_hex(7),        // spl_token_account
+        "0300000000000000", // total_players
+        "0a00000000000000", // referral_bonus
+        "00",               // paused
+        "03",               // max_depth
+        &key_hex(8),        // mint
+        "3200000000000000", // min_claim
+        "e803000000000000", // register_fee
+        "f401000000000000", // max_reward_per_player
+        "0700000000000000", // nonce
+        "6400000000000000", // decay_bps_per_day
+        "2800000000000000", // matching_pool
+        &key_hex(9),        // relayer
+        "ff",               // vault_bump
+        "c409dc05e803",     // level_bps
+        "0300000000000000", // dust
+        "6400000000000000", // tier_thresholds
+        "e803000000000000", //
+        "1027000000000000", //
+        "8051010000000000", // vest_duration
+        "204e",             // reward_multiplier_bps
+        &key_hex(10),       // token_program
+    ]
+    .concat());
+    let mut data = vec![0; GameInfo::LEN];
+    GameInfo::pack(game_info.clone(), &mut data).unwrap();
+    assert_eq!(data, expected);
+    assert_eq!(GameInfo::unpack(&expected).unwrap(), game_info);
+}